@@ -339,6 +339,14 @@ pub fn path_prebaked_results(name: &MapName, scenario_name: &str) -> String {
     ))
 }
 
+/// Trip times from simulating the scenario with several different RNG seeds
+pub fn path_prebaked_multi_seed_results(name: &MapName, scenario_name: &str) -> String {
+    path(format!(
+        "system/{}/{}/prebaked_results/{}/{}_multi_seed.bin",
+        name.city.country, name.city.city, name.map, scenario_name
+    ))
+}
+
 pub fn path_scenario(name: &MapName, scenario_name: &str) -> String {
     // TODO Getting complicated. Sometimes we're trying to load, so we should look for .bin, then
     // .json. But when we're writing a custom scenario, we actually want to write a .bin.
@@ -15,7 +15,7 @@ use map_gui::tools::CameraState;
 use map_gui::ID;
 use map_model::AreaType;
 use map_model::{BufferType, IntersectionID, LaneType, Map, Traversable};
-use sim::{AgentID, Analytics, Sim, SimCallback, SimFlags, VehicleType};
+use sim::{AgentID, Analytics, MultiSeedResults, Sim, SimCallback, SimFlags, VehicleType};
 use synthpop::Scenario;
 use widgetry::mapspace::ToggleZoomed;
 use widgetry::{Cached, Canvas, EventCtx, GfxCtx, Prerender, SharedAppState, State};
@@ -58,8 +58,18 @@ impl App {
     pub fn prebaked(&self) -> &Analytics {
         &self.primary.prebaked.as_ref().unwrap().2
     }
+    /// Trip times from running the prebaked scenario with several RNG seeds. This is optional even
+    /// when regular prebaked results exist.
+    pub fn prebaked_seeds(&self) -> Option<&MultiSeedResults> {
+        self.primary.prebaked_seeds.as_ref()
+    }
+    /// Only call after `set_prebaked`, for the same map and scenario.
+    pub fn set_prebaked_seeds(&mut self, results: Option<MultiSeedResults>) {
+        self.primary.prebaked_seeds = results;
+    }
     pub fn set_prebaked(&mut self, prebaked: Option<(MapName, String, Analytics)>) {
         self.primary.prebaked = prebaked;
+        self.primary.prebaked_seeds = None;
 
         if false {
             if let Some((_, _, ref a)) = self.primary.prebaked {
@@ -645,6 +655,8 @@ pub struct PerMap {
     /// scenario name too.
     // TODO Embed that in Analytics directly instead.
     prebaked: Option<(MapName, String, Analytics)>,
+    /// Matches the map and scenario of `prebaked`
+    prebaked_seeds: Option<MultiSeedResults>,
     /// The most recent Scenario loaded from a file. Don't depend on it always matching the current
     /// gameplay mode; always verify the name matches what's needed.
     ///
@@ -691,6 +703,7 @@ impl PerMap {
             layer: None,
             suspended_sim: None,
             prebaked: None,
+            prebaked_seeds: None,
             scenario: None,
             is_secondary: false,
         }
@@ -855,11 +868,15 @@ impl SharedAppState for App {
         if self.primary.map.get_edits().commands.is_empty() {
             println!("No edits");
         } else {
-            abstio::write_json(
-                "edits_during_crash.json".to_string(),
-                &self.primary.map.get_edits().to_permanent(&self.primary.map),
-            );
-            println!("Please include edits_during_crash.json in your bug report.");
+            match self.primary.map.get_edits().to_permanent(&self.primary.map) {
+                Ok(perma) => {
+                    abstio::write_json("edits_during_crash.json".to_string(), &perma);
+                    println!("Please include edits_during_crash.json in your bug report.");
+                }
+                Err(err) => {
+                    println!("The edits couldn't be saved: {}", err);
+                }
+            }
         }
 
         // Repeat, because it can be hard to see the top of the report if it's long
@@ -22,7 +22,13 @@ impl ShareProposal {
     /// This will point to a URL with the new edits and the current map, but the caller needs to
     /// indicate a flag to reach the proper mode of A/B Street.
     pub fn new_state(ctx: &mut EventCtx, app: &App, url_flag: &'static str) -> Box<dyn State<App>> {
-        let checksum = app.primary.map.get_edits().get_checksum(&app.primary.map);
+        // If the edits can't be encoded, they can't have been uploaded
+        let checksum = app
+            .primary
+            .map
+            .get_edits()
+            .get_checksum(&app.primary.map)
+            .unwrap_or_default();
         let mut url = None;
         let mut col = vec![Widget::row(vec![
             Line("Share this proposal").small_heading().into_widget(ctx),
@@ -103,8 +109,16 @@ impl SimpleState<App> for ShareProposal {
             "Upload" => {
                 let (_, outer_progress_rx) = futures_channel::mpsc::channel(1);
                 let (_, inner_progress_rx) = futures_channel::mpsc::channel(1);
-                let edits_json =
-                    abstutil::to_json(&app.primary.map.get_edits().to_permanent(&app.primary.map));
+                let edits_json = match app.primary.map.get_edits().to_permanent(&app.primary.map) {
+                    Ok(perma) => abstutil::to_json(&perma),
+                    Err(err) => {
+                        return Transition::Push(PopupMsg::new_state(
+                            ctx,
+                            "Can't upload this proposal",
+                            vec![err.to_string()],
+                        ));
+                    }
+                };
                 let url_flag = self.url_flag;
                 return Transition::Replace(FutureLoader::<App, String>::new_state(
                     ctx,
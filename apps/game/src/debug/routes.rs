@@ -13,7 +13,7 @@ use synthpop::{TripEndpoint, TripMode};
 use widgetry::mapspace::ToggleZoomed;
 use widgetry::{
     Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
-    RoundedF64, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
+    RoundedF64, Spinner, State, Text, TextExt, Toggle, VerticalAlignment, Widget,
 };
 
 use crate::app::{App, Transition};
@@ -278,6 +278,14 @@ fn params_to_controls(ctx: &mut EventCtx, mode: TripMode, params: &RoutingParams
             ),
         ]));
     }
+    if mode == TripMode::Walk {
+        rows.push(Toggle::checkbox(
+            ctx,
+            "jaywalk across slow local roads",
+            None,
+            params.jaywalking,
+        ));
+    }
     Widget::col(rows)
 }
 
@@ -288,6 +296,7 @@ fn controls_to_params(panel: &Panel) -> (TripMode, RoutingParams) {
         return (TripMode::Drive, params);
     }
     if !panel.is_button_enabled("pedestrians") {
+        params.jaywalking = panel.is_checked("jaywalk across slow local roads");
         return (TripMode::Walk, params);
     }
     params.unprotected_turn_penalty = panel.spinner("unprotected_turn_penalty");
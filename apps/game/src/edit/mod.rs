@@ -2,11 +2,12 @@ use maplit::btreeset;
 
 use abstutil::{prettyprint_usize, Timer};
 use geom::Speed;
+use map_gui::colors::ColorScheme;
 use map_gui::options::OptionsPanel;
 use map_gui::render::DrawMap;
 use map_gui::tools::{grey_out_map, ChooseSomething, ColorLegend};
 use map_gui::ID;
-use map_model::{EditCmd, IntersectionID, LaneID, MapEdits};
+use map_model::{EditCmd, EditEffects, IntersectionID, LaneID, MapEdits};
 use widgetry::mapspace::ToggleZoomed;
 use widgetry::tools::PopupMsg;
 use widgetry::{
@@ -19,7 +20,7 @@ pub use self::routes::RouteEditor;
 pub use self::stop_signs::StopSignEditor;
 pub use self::traffic_signals::TrafficSignalEditor;
 pub use self::validate::{check_blackholes, check_sidewalk_connectivity};
-use crate::app::{App, Transition};
+use crate::app::{App, PerMap, Transition};
 use crate::common::{tool_panel, CommonState, Warping};
 use crate::debug::DebugMode;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
@@ -213,6 +214,7 @@ impl State<App> for EditMode {
                             Choice::string("open a saved proposal").multikey(lctrl(Key::L)),
                             Choice::string("create a blank proposal"),
                             Choice::string("save this proposal as..."),
+                            Choice::string("combine with another proposal"),
                             // TODO Disable if empty edits
                             Choice::string("share proposal"),
                             Choice::string("delete this proposal and remove all edits")
@@ -285,6 +287,25 @@ impl State<App> for EditMode {
                                     Box::new(|_, _| {}),
                                 ))
                             }
+                            "combine with another proposal" => {
+                                let current = app.primary.map.get_edits().edits_name.clone();
+                                let choices = Choice::strings(
+                                    abstio::list_all_objects(abstio::path_all_edits(
+                                        app.primary.map.get_name(),
+                                    ))
+                                    .into_iter()
+                                    .filter(|name| name != &current)
+                                    .collect(),
+                                );
+                                Transition::Replace(ChooseSomething::new_state(
+                                    ctx,
+                                    "Combine with which proposal?",
+                                    choices,
+                                    Box::new(move |name, ctx, app| {
+                                        combine_proposals(ctx, app, &mode, name)
+                                    }),
+                                ))
+                            }
                             "share proposal" => {
                                 // TODO This'll always set or share a URL with the map, losing any
                                 // info about the current scenario.
@@ -692,6 +713,58 @@ impl State<App> for LoadEdits {
     }
 }
 
+/// Merge a saved proposal into the current one, assuming both started from the unedited map.
+fn combine_proposals(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    mode: &GameplayMode,
+    name: String,
+) -> Transition {
+    let map = &app.primary.map;
+    let result = MapEdits::load_from_file(
+        map,
+        abstio::path_edits(map.get_name(), &name),
+        &mut Timer::throwaway(),
+    )
+    .map(|theirs| MapEdits::merge(&map.new_edits(), map.get_edits(), &theirs, map))
+    .and_then(|merged| {
+        if mode.allows(&merged.edits) {
+            Ok(merged)
+        } else {
+            Err(anyhow!(
+                "The current gameplay mode restricts edits. The combined proposal has a banned \
+                 command."
+            ))
+        }
+    });
+    match result {
+        Ok(mut merged) => {
+            merged.edits.edits_name = map.get_edits().edits_name.clone();
+            apply_map_edits(ctx, app, merged.edits);
+            app.primary
+                .sim
+                .handle_live_edited_traffic_signals(&app.primary.map);
+            if merged.conflicts.is_empty() {
+                return Transition::Pop;
+            }
+            let mut lines = vec![
+                "Both proposals changed these differently. The current proposal's version was \
+                 kept:"
+                    .to_string(),
+            ];
+            for obj in merged.conflicts {
+                lines.push(format!("- {}", obj));
+            }
+            Transition::Replace(PopupMsg::new_state(ctx, "Conflicts", lines))
+        }
+        Err(err) => Transition::Replace(PopupMsg::new_state(
+            ctx,
+            "Error",
+            vec![format!("Can't combine with {}", name), err.to_string()],
+        )),
+    }
+}
+
 fn make_topcenter(ctx: &mut EventCtx, app: &App) -> Panel {
     Panel::new_builder(Widget::col(vec![
         Line("Editing map")
@@ -758,27 +831,7 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
         let effects = app.primary.map.must_apply_edits(edits, timer);
         timer.stop("edit map");
 
-        if !effects.changed_roads.is_empty() || !effects.changed_intersections.is_empty() {
-            app.primary
-                .draw_map
-                .draw_all_unzoomed_roads_and_intersections =
-                DrawMap::regenerate_unzoomed_layer(&app.primary.map, &app.cs, ctx, timer);
-        }
-
-        for r in effects.changed_roads {
-            let road = app.primary.map.get_r(r);
-            app.primary.draw_map.recreate_road(road, &app.primary.map);
-        }
-
-        for i in effects.changed_intersections {
-            app.primary
-                .draw_map
-                .recreate_intersection(i, &app.primary.map);
-        }
-
-        for pl in effects.changed_parking_lots {
-            app.primary.draw_map.get_pl(pl).clear_rendering();
-        }
+        redraw_edited_map(ctx, &mut app.primary, &app.cs, effects, timer);
 
         if app.primary.layer.as_ref().and_then(|l| l.name()) == Some("map edits") {
             app.primary.layer = Some(Box::new(crate::layer::map::Static::edits(ctx, app)));
@@ -791,6 +844,63 @@ pub fn apply_map_edits(ctx: &mut EventCtx, app: &mut App, edits: MapEdits) {
     });
 }
 
+/// If an incident in the simulation just started or ended, temporarily edit the map. Call this
+/// after stepping the simulation forwards.
+pub fn apply_incidents(ctx: &mut EventCtx, app: &mut App) {
+    if !app.primary.sim.has_pending_incidents() {
+        return;
+    }
+    ctx.loading_screen("apply incidents", |ctx, timer| {
+        if let Some(effects) = app.primary.sim.apply_incidents(&mut app.primary.map, timer) {
+            redraw_edited_map(ctx, &mut app.primary, &app.cs, effects, timer);
+        }
+    });
+}
+
+/// Update the drawn map after edits.
+pub fn redraw_edited_map(
+    ctx: &mut EventCtx,
+    per_map: &mut PerMap,
+    cs: &ColorScheme,
+    effects: EditEffects,
+    timer: &mut Timer,
+) {
+    if !effects.changed_roads.is_empty()
+        || !effects.changed_intersections.is_empty()
+        || !effects.deleted_roads.is_empty()
+    {
+        per_map.draw_map.draw_all_unzoomed_roads_and_intersections =
+            DrawMap::regenerate_unzoomed_layer(&per_map.map, cs, ctx, timer);
+    }
+
+    // Undoing edits that created roads or intersections removes them, newest first
+    for r in effects.deleted_roads.iter().rev() {
+        per_map.draw_map.delete_road(*r);
+    }
+    for i in effects.deleted_intersections.iter().rev() {
+        per_map.draw_map.delete_intersection(*i);
+    }
+
+    for r in effects.changed_roads {
+        let road = per_map.map.get_r(r);
+        per_map.draw_map.recreate_road(road, &per_map.map);
+    }
+
+    for i in effects.changed_intersections {
+        per_map.draw_map.recreate_intersection(i, &per_map.map);
+    }
+
+    for pl in effects.changed_parking_lots {
+        per_map.draw_map.get_pl(pl).clear_rendering();
+    }
+
+    for ts in effects.changed_transit_stops {
+        per_map
+            .draw_map
+            .recreate_transit_stop(ctx, ts, &per_map.map, cs);
+    }
+}
+
 pub fn can_edit_lane(app: &App, l: LaneID) -> bool {
     let map = &app.primary.map;
     if map.get_l(l).is_light_rail() {
@@ -850,14 +960,15 @@ pub fn maybe_edit_intersection(
     if app.primary.map.get_i(id).is_closed()
         && app.per_obj.left_click(ctx, "re-open closed intersection")
     {
-        // This resets to the original state; it doesn't undo the closure to the last
-        // state. Seems reasonable to me.
+        // This resets to the original control; it doesn't undo the closure to the last
+        // state. Seems reasonable to me. Banned movements are kept.
         let mut edits = app.primary.map.get_edits().clone();
-        edits.commands.push(EditCmd::ChangeIntersection {
-            i: id,
-            old: app.primary.map.get_i_edit(id),
-            new: edits.original_intersections[&id].clone(),
-        });
+        let original = edits.original_intersections[&id].control.clone();
+        edits
+            .commands
+            .push(app.primary.map.edit_intersection_cmd(id, |new| {
+                new.control = original.clone();
+            }));
         apply_map_edits(ctx, app, edits);
     }
 
@@ -935,7 +1046,10 @@ fn cmd_to_id(cmd: &EditCmd) -> Option<ID> {
         EditCmd::ChangeRoad { r, .. } => Some(ID::Road(*r)),
         EditCmd::ChangeIntersection { i, .. } => Some(ID::Intersection(*i)),
         EditCmd::ChangeCrosswalks { i, .. } => Some(ID::Intersection(*i)),
-        EditCmd::ChangeRouteSchedule { .. } => None,
+        EditCmd::ChangeTransitStop { id, new, .. } => new.as_ref().map(|_| ID::TransitStop(*id)),
+        EditCmd::ChangeRouteSchedule { .. } | EditCmd::ChangeTransitRoute { .. } => None,
+        EditCmd::AddRoad { r, new, .. } => new.as_ref().map(|_| ID::Road(*r)),
+        EditCmd::SplitRoad { new, .. } => new.as_ref().map(|split| ID::Intersection(split.new_i)),
     }
 }
 
@@ -15,6 +15,7 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::common::Warping;
+use crate::edit::crosswalks::CrosswalkEditor;
 use crate::edit::heuristics::add_new_lane;
 use crate::edit::zones::ZoneEditor;
 use crate::edit::{apply_map_edits, can_edit_lane, speed_limit_choices};
@@ -268,6 +269,33 @@ impl State<App> for RoadEditor {
                         ),
                     );
                 }
+                "Add mid-block crossing" => {
+                    let map = &app.primary.map;
+                    return match map
+                        .mid_block_crossing_cmd(self.r, map.get_r(self.r).length() / 2.0)
+                    {
+                        Ok(cmd) => {
+                            let new_i = match cmd {
+                                EditCmd::SplitRoad {
+                                    new: Some(ref split),
+                                    ..
+                                } => split.new_i,
+                                _ => unreachable!(),
+                            };
+                            let mut edits = self
+                                .compress_edits(app)
+                                .unwrap_or_else(|| app.primary.map.get_edits().clone());
+                            edits.commands.push(cmd);
+                            apply_map_edits(ctx, app, edits);
+                            Transition::Replace(CrosswalkEditor::new_state(ctx, app, new_i))
+                        }
+                        Err(err) => Transition::Push(PopupMsg::new_state(
+                            ctx,
+                            "Can't add a crossing here",
+                            vec![err.to_string()],
+                        )),
+                    };
+                }
                 _ => unreachable!(),
             }
         }
@@ -518,6 +546,11 @@ fn make_top_panel(
                 .disabled(current_state == orig_road_state)
                 .disabled_tooltip("You have to edit one road segment first, then you can apply the changes to more segments.")
                 .build_widget(ctx, "Apply to multiple road segments"),
+            ctx.style()
+                .btn_plain
+                .text("+ Add crossing")
+                .label_color(Color::hex("#4CA7E9"), ControlState::Default)
+                .build_widget(ctx, "Add mid-block crossing"),
         ]),
         Widget::row(vec![
             ctx.style()
@@ -5,7 +5,7 @@ use maplit::btreeset;
 use geom::Polygon;
 use map_gui::render::DrawIntersection;
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, RoadID,
+    ControlStopSign, ControlTrafficSignal, EditIntersectionControl, IntersectionID, RoadID,
 };
 use widgetry::{
     EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Panel, SimpleState, State, Text,
@@ -80,6 +80,17 @@ impl StopSignEditor {
                     .btn_outline
                     .text("convert to traffic signal")
                     .build_def(ctx),
+                if app.primary.map.get_stop_sign(id).yield_on_entry {
+                    ctx.style()
+                        .btn_outline
+                        .text("convert to stop signs")
+                        .build_def(ctx)
+                } else {
+                    ctx.style()
+                        .btn_outline
+                        .text("convert to roundabout / all-way yield")
+                        .build_def(ctx)
+                },
             ]),
         ]))
         .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
@@ -109,14 +120,14 @@ impl SimpleState<App> for StopSignEditor {
             "Finish" => Transition::Pop,
             "reset to default" => {
                 let mut edits = app.primary.map.get_edits().clone();
-                edits.commands.push(EditCmd::ChangeIntersection {
-                    i: self.id,
-                    old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::StopSign(ControlStopSign::new(
-                        &app.primary.map,
-                        self.id,
-                    )),
-                });
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.control = EditIntersectionControl::StopSign(ControlStopSign::new(
+                            &app.primary.map,
+                            self.id,
+                        ));
+                    }));
                 apply_map_edits(ctx, app, edits);
                 Transition::Replace(StopSignEditor::new_state(
                     ctx,
@@ -126,11 +137,9 @@ impl SimpleState<App> for StopSignEditor {
                 ))
             }
             "close intersection for construction" => {
-                let cmd = EditCmd::ChangeIntersection {
-                    i: self.id,
-                    old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::Closed,
-                };
+                let cmd = app.primary.map.edit_intersection_cmd(self.id, |new| {
+                    new.control = EditIntersectionControl::Closed;
+                });
                 if let Some(err) = check_sidewalk_connectivity(ctx, app, cmd.clone()) {
                     Transition::Push(err)
                 } else {
@@ -143,14 +152,14 @@ impl SimpleState<App> for StopSignEditor {
             }
             "convert to traffic signal" => {
                 let mut edits = app.primary.map.get_edits().clone();
-                edits.commands.push(EditCmd::ChangeIntersection {
-                    i: self.id,
-                    old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::TrafficSignal(
-                        ControlTrafficSignal::new(&app.primary.map, self.id)
-                            .export(&app.primary.map),
-                    ),
-                });
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.control = EditIntersectionControl::TrafficSignal(
+                            ControlTrafficSignal::new(&app.primary.map, self.id)
+                                .export(&app.primary.map),
+                        );
+                    }));
                 apply_map_edits(ctx, app, edits);
                 app.primary
                     .sim
@@ -162,6 +171,28 @@ impl SimpleState<App> for StopSignEditor {
                     self.mode.clone(),
                 ))
             }
+            "convert to roundabout / all-way yield" | "convert to stop signs" => {
+                let mut sign = app.primary.map.get_stop_sign(self.id).clone();
+                if sign.yield_on_entry {
+                    sign.yield_on_entry = false;
+                } else {
+                    sign.convert_to_yield(&app.primary.map);
+                }
+
+                let mut edits = app.primary.map.get_edits().clone();
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.control = EditIntersectionControl::StopSign(sign.clone());
+                    }));
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
             "Change crosswalks" => Transition::Replace(
                 super::crosswalks::CrosswalkEditor::new_state(ctx, app, self.id),
             ),
@@ -195,11 +226,11 @@ impl SimpleState<App> for StopSignEditor {
                 sign.flip_sign(r);
 
                 let mut edits = app.primary.map.get_edits().clone();
-                edits.commands.push(EditCmd::ChangeIntersection {
-                    i: self.id,
-                    old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::StopSign(sign),
-                });
+                edits
+                    .commands
+                    .push(app.primary.map.edit_intersection_cmd(self.id, |new| {
+                        new.control = EditIntersectionControl::StopSign(sign.clone());
+                    }));
                 apply_map_edits(ctx, app, edits);
                 return Transition::Replace(StopSignEditor::new_state(
                     ctx,
@@ -4,7 +4,7 @@ use geom::{Angle, Circle, Distance, Speed, Time};
 use map_gui::render::DrawPedestrian;
 use map_model::{BuildingID, LaneID, OffstreetParking, Traversable, SIDEWALK_THICKNESS};
 use sim::{DrawPedestrianInput, PedestrianID, PersonID, TripResult, VehicleType};
-use synthpop::TripMode;
+use synthpop::{NoiseModel, TripMode};
 use widgetry::{Color, EventCtx, Line, Text, TextExt, Widget};
 
 use crate::app::App;
@@ -109,6 +109,21 @@ fn info_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingI
         }
     }
 
+    let map = &app.primary.map;
+    let now = app.primary.sim.time();
+    let noise = NoiseModel::new(map, &app.primary.sim.get_analytics().road_flows(now));
+    if let Some(db) = noise.building_lden(map, id) {
+        txt.add_line("");
+        txt.add_line(format!("Traffic noise so far: {:.0} dB (Lden)", db));
+        if app.has_prebaked().is_some() {
+            if let Some(before) =
+                NoiseModel::new(map, &app.prebaked().road_flows(now)).building_lden(map, id)
+            {
+                txt.append(Line(format!(", {:.0} dB before edits", before)).secondary());
+            }
+        }
+    }
+
     if !txt.is_empty() {
         rows.push(txt.into_widget(ctx))
     }
@@ -143,7 +143,7 @@ fn delay_body(
     let mut rows = vec![];
     let i = app.primary.map.get_i(id);
 
-    assert!(i.is_traffic_signal());
+    assert!(app.primary.map.is_signal_or_yield(i.id));
     rows.push(opts.to_controls(ctx, app));
     rows.push(Toggle::choice(
         ctx,
@@ -443,7 +443,13 @@ fn header(
     let i = app.primary.map.get_i(id);
 
     let label = match i.intersection_type {
-        IntersectionType::StopSign => format!("{} (Stop signs)", id),
+        IntersectionType::StopSign => {
+            if app.primary.map.get_stop_sign(id).yield_on_entry {
+                format!("{} (Yield)", id)
+            } else {
+                format!("{} (Stop signs)", id)
+            }
+        }
         IntersectionType::TrafficSignal => format!("{} (Traffic signals)", id),
         IntersectionType::Border => format!("Border #{}", id.0),
         IntersectionType::Construction => format!("{} (under construction)", id),
@@ -458,12 +464,14 @@ fn header(
             ("Info", Tab::IntersectionInfo(id)),
             ("Traffic", Tab::IntersectionTraffic(id, DataOptions::new())),
         ];
-        if i.is_traffic_signal() {
+        if app.primary.map.is_signal_or_yield(id) {
             tabs.push((
                 "Delay",
                 Tab::IntersectionDelay(id, DataOptions::new(), false),
             ));
             tabs.push(("Current demand", Tab::IntersectionDemand(id)));
+        }
+        if i.is_traffic_signal() {
             tabs.push(("Signal", Tab::IntersectionTrafficSignal(id)));
         }
         if i.is_incoming_border() {
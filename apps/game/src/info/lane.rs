@@ -41,7 +41,10 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
             "Parking",
             format!(
                 "{} / {} spots available",
-                app.primary.sim.get_free_onstreet_spots(l.id).len(),
+                app.primary
+                    .sim
+                    .get_free_onstreet_spots(l.id, &app.primary.map)
+                    .len(),
                 l.number_parking_spots(app.primary.map.get_config())
             ),
         ));
@@ -102,14 +102,14 @@ impl Tab {
                 "info" => Tab::IntersectionInfo(i),
                 "traffic" => Tab::IntersectionTraffic(i, DataOptions::new()),
                 "delay" => {
-                    if app.primary.map.get_i(i).is_traffic_signal() {
+                    if app.primary.map.is_signal_or_yield(i) {
                         Tab::IntersectionDelay(i, DataOptions::new(), false)
                     } else {
                         Tab::IntersectionInfo(i)
                     }
                 }
                 "demand" => {
-                    if app.primary.map.get_i(i).is_traffic_signal() {
+                    if app.primary.map.is_signal_or_yield(i) {
                         Tab::IntersectionDemand(i)
                     } else {
                         Tab::IntersectionInfo(i)
@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use maplit::btreemap;
 
-use geom::{Distance, Duration, Percent, Polygon, Pt2D, UnitFmt};
+use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D, UnitFmt};
 use map_gui::ID;
 use map_model::{Map, Path, PathStep, Traversable};
 use sim::{AgentID, Analytics, PersonID, Problem, TripID, TripInfo, TripPhase, TripPhaseType};
@@ -401,6 +401,9 @@ fn describe_problems(
         TripMode::Walk => {
             let mut arterial_intersection_crossings = 0;
             let mut overcrowding = 0;
+            let mut near_misses = 0;
+            let mut mid_block_crossings = 0;
+            let mut jaywalking = 0;
             let empty = Vec::new();
             for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
                 match problem {
@@ -410,6 +413,15 @@ fn describe_problems(
                     Problem::PedestrianOvercrowding(_) => {
                         overcrowding += 1;
                     }
+                    Problem::NearMiss(_) => {
+                        near_misses += 1;
+                    }
+                    Problem::MidBlockCrossing(_) => {
+                        mid_block_crossings += 1;
+                    }
+                    Problem::Jaywalking(_) => {
+                        jaywalking += 1;
+                    }
                     _ => {}
                 }
             }
@@ -424,6 +436,11 @@ fn describe_problems(
                 .secondary(),
             ]);
             txt.add_line(Line(format!("{overcrowding} overcrowded sidewalks crossed")).secondary());
+            txt.add_line(Line(format!("{near_misses} near misses with vehicles")).secondary());
+            txt.add_line(Line(format!("{mid_block_crossings} mid-block crossings")).secondary());
+            txt.add_line(
+                Line(format!("{jaywalking} times crossing against the light")).secondary(),
+            );
 
             Widget::custom_row(vec![
                 Line("Risk Exposure")
@@ -437,6 +454,8 @@ fn describe_problems(
         TripMode::Bike => {
             let mut count_complex_intersections = 0;
             let mut count_overtakes = 0;
+            let mut count_close_passes = 0;
+            let mut count_near_misses = 0;
             let empty = Vec::new();
             for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
                 match problem {
@@ -446,6 +465,12 @@ fn describe_problems(
                     Problem::OvertakeDesired(_) => {
                         count_overtakes += 1;
                     }
+                    Problem::ClosePass(_, _) => {
+                        count_close_passes += 1;
+                    }
+                    Problem::NearMiss(_) => {
+                        count_near_misses += 1;
+                    }
                     _ => {}
                 }
             }
@@ -468,6 +493,24 @@ fn describe_problems(
                 }
                 .secondary(),
             ]);
+            txt.add_appended(vec![
+                Line(count_close_passes.to_string()),
+                if count_close_passes == 1 {
+                    Line(" vehicle passed too closely")
+                } else {
+                    Line(" vehicles passed too closely")
+                }
+                .secondary(),
+            ]);
+            txt.add_appended(vec![
+                Line(count_near_misses.to_string()),
+                if count_near_misses == 1 {
+                    Line(" near miss")
+                } else {
+                    Line(" near misses")
+                }
+                .secondary(),
+            ]);
 
             Widget::custom_row(vec![
                 Line("Risk Exposure")
@@ -573,6 +616,31 @@ fn draw_problems(
                     (id, *time),
                 ));
             }
+            Problem::ClosePass(on, clearance) => {
+                let pt = on.get_polyline(map).middle();
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    match on {
+                        Traversable::Lane(l) => map.get_parent(*l).get_thick_polygon(),
+                        Traversable::Turn(t) => map.get_i(t.parent).polygon.clone(),
+                    },
+                    Text::from(format!(
+                        "A vehicle passed this cyclist near here, leaving only {}",
+                        clearance.to_string(&app.opts.units)
+                    )),
+                    (id, *time),
+                ));
+            }
             Problem::ArterialIntersectionCrossing(t) => {
                 let t = map.get_t(*t);
 
@@ -597,6 +665,31 @@ fn draw_problems(
                     (id, *time)
                 ));
             }
+            Problem::MidBlockCrossing(t) | Problem::Jaywalking(t) => {
+                let t = map.get_t(*t);
+
+                let geom = t.geom.make_polygons(Distance::meters(10.0));
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(geom.center())
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(geom.center()),
+                );
+                details.tooltips.push((
+                    geom,
+                    Text::from(if matches!(problem, Problem::Jaywalking(_)) {
+                        "This pedestrian crossed against the light, waiting for a gap in traffic."
+                    } else {
+                        "Drivers may not expect pedestrians crossing in the middle of a block."
+                    }),
+                    (id, *time),
+                ));
+            }
             Problem::PedestrianOvercrowding(on) => {
                 let pt = on.get_polyline(map).middle();
                 details.draw_extra.unzoomed.append(
@@ -619,6 +712,39 @@ fn draw_problems(
                     (id, *time),
                 ));
             }
+            Problem::NearMiss(conflict) => {
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(conflict.pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(conflict.pt),
+                );
+                let mut lines = vec![
+                    Line(format!(
+                        "{:?} near miss between a {} and a {}",
+                        conflict.severity,
+                        conflict.agent_types.0.noun().to_lowercase(),
+                        conflict.agent_types.1.noun().to_lowercase()
+                    )),
+                    Line(format!(
+                        "Post-encroachment time: {}",
+                        conflict.post_encroachment_time
+                    )),
+                ];
+                if let Some(ttc) = conflict.time_to_collision {
+                    lines.push(Line(format!("Time to collision: {}", ttc)));
+                }
+                details.tooltips.push((
+                    Circle::new(conflict.pt, Distance::meters(5.0)).to_polygon(),
+                    Text::from_multiline(lines),
+                    (id, *time),
+                ));
+            }
         }
     }
 }
@@ -0,0 +1,169 @@
+use abstutil::prettyprint_usize;
+use geom::Time;
+use map_gui::tools::{ColorLegend, ColorNetwork};
+use map_gui::ID;
+use sim::Emissions;
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::{Choice, EventCtx, GfxCtx, Line, Outcome, Panel, Text, TextExt, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+pub struct EmissionsLayer {
+    time: Time,
+    pollutant: Pollutant,
+    tooltip: Option<Text>,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pollutant {
+    Co2,
+    Nox,
+    ParticulateMatter,
+    Electricity,
+}
+
+impl Pollutant {
+    fn amount(self, emissions: &Emissions) -> f64 {
+        match self {
+            Pollutant::Co2 => emissions.co2_grams,
+            Pollutant::Nox => emissions.nox_grams,
+            Pollutant::ParticulateMatter => emissions.pm_grams,
+            Pollutant::Electricity => emissions.electricity_kwh,
+        }
+    }
+}
+
+impl Layer for EmissionsLayer {
+    fn name(&self) -> Option<&'static str> {
+        Some("emissions")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        let mut recalc_tooltip = false;
+        if app.primary.sim.time() != self.time {
+            *self = EmissionsLayer::new(ctx, app, self.pollutant);
+            recalc_tooltip = true;
+        }
+
+        if ctx.canvas.is_unzoomed() {
+            if ctx.redo_mouseover() || recalc_tooltip {
+                self.tooltip = None;
+                if let Some(ID::Road(r)) = app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                    if let Some(emissions) =
+                        app.primary.sim.get_analytics().emissions_per_road.get(&r)
+                    {
+                        self.tooltip = Some(describe(emissions));
+                    }
+                }
+            }
+        } else {
+            self.tooltip = None;
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => {
+                return Some(LayerOutcome::Replace(Box::new(EmissionsLayer::new(
+                    ctx,
+                    app,
+                    self.panel.dropdown_value("pollutant"),
+                ))));
+            }
+            _ => {}
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl EmissionsLayer {
+    pub fn new(ctx: &mut EventCtx, app: &App, pollutant: Pollutant) -> EmissionsLayer {
+        let analytics = app.primary.sim.get_analytics();
+
+        let mut colorer = ColorNetwork::new(app);
+        let max = analytics
+            .emissions_per_road
+            .values()
+            .map(|e| pollutant.amount(e))
+            .fold(0.0, f64::max);
+        if max > 0.0 {
+            for (r, emissions) in &analytics.emissions_per_road {
+                colorer.add_r(
+                    *r,
+                    app.cs
+                        .good_to_bad_red
+                        .eval(pollutant.amount(emissions) / max),
+                );
+            }
+        }
+
+        let total = &analytics.total_emissions;
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Emissions"),
+            Text::from(
+                Line("Estimated from how vehicles have driven so far, over the whole map")
+                    .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            describe(total).into_widget(ctx),
+            Widget::row(vec![
+                "Show:".text_widget(ctx),
+                Widget::dropdown(
+                    ctx,
+                    "pollutant",
+                    pollutant,
+                    vec![
+                        Choice::new("CO2", Pollutant::Co2),
+                        Choice::new("NOx", Pollutant::Nox),
+                        Choice::new("particulate matter", Pollutant::ParticulateMatter),
+                        Choice::new("electricity", Pollutant::Electricity),
+                    ],
+                ),
+            ]),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["lowest", "highest"]),
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        EmissionsLayer {
+            time: app.primary.sim.time(),
+            pollutant,
+            tooltip: None,
+            draw: colorer.build(ctx),
+            panel,
+        }
+    }
+}
+
+fn describe(emissions: &Emissions) -> Text {
+    Text::from_multiline(vec![
+        Line(format!(
+            "{} kg CO2",
+            prettyprint_usize((emissions.co2_grams / 1000.0).round() as usize)
+        )),
+        Line(format!("{:.1} g NOx", emissions.nox_grams)),
+        Line(format!("{:.1} g particulate matter", emissions.pm_grams)),
+        Line(format!("{:.1} liters of fuel", emissions.fuel_liters)),
+        Line(format!(
+            "{:.1} kWh of electricity",
+            emissions.electricity_kwh
+        )),
+    ])
+}
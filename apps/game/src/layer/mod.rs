@@ -9,8 +9,10 @@ use crate::app::{App, Transition};
 use crate::sandbox::dashboards;
 
 pub mod elevation;
+mod emissions;
 pub mod favorites;
 pub mod map;
+mod noise;
 mod pandemic;
 mod parking;
 mod population;
@@ -109,6 +111,8 @@ impl PickLayer {
                     btn("throughput", Key::T),
                     btn("traffic jams", Key::J),
                     btn("cycling activity", Key::B),
+                    btn("emissions", Key::C),
+                    btn("traffic noise", Key::W),
                 ]),
                 Widget::col(vec![
                     "Map".text_widget(ctx),
@@ -178,6 +182,13 @@ impl State<App> for PickLayer {
                 "delay" => {
                     app.primary.layer = Some(Box::new(traffic::Delay::new(ctx, app)));
                 }
+                "emissions" => {
+                    app.primary.layer = Some(Box::new(emissions::EmissionsLayer::new(
+                        ctx,
+                        app,
+                        emissions::Pollutant::Co2,
+                    )));
+                }
                 "steep streets" => {
                     app.primary.layer = Some(Box::new(elevation::SteepStreets::new(ctx, app)));
                 }
@@ -240,6 +251,9 @@ impl State<App> for PickLayer {
                         AgentType::all().into_iter().collect(),
                     )));
                 }
+                "traffic noise" => {
+                    app.primary.layer = Some(Box::new(noise::TrafficNoise::new(ctx, app)));
+                }
                 "traffic jams" => {
                     app.primary.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
                 }
@@ -0,0 +1,92 @@
+use abstutil::prettyprint_usize;
+use map_gui::tools::ColorDiscrete;
+use synthpop::{lden_band, NoiseModel, LDEN_BANDS};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::{Color, EventCtx, GfxCtx, Line, Panel, Text, TextExt, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+pub struct TrafficNoise {
+    // Estimating noise at every building is slow, so only recalculate hourly
+    hour: usize,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+impl Layer for TrafficNoise {
+    fn name(&self) -> Option<&'static str> {
+        Some("traffic noise")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        if app.primary.sim.time().get_hours() != self.hour {
+            *self = TrafficNoise::new(ctx, app);
+        }
+
+        <dyn Layer>::simple_event(ctx, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl TrafficNoise {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> TrafficNoise {
+        let map = &app.primary.map;
+        let now = app.primary.sim.time();
+        let model = NoiseModel::new(map, &app.primary.sim.get_analytics().road_flows(now));
+
+        let mut colorer = ColorDiscrete::new(app, LDEN_BANDS.into_iter().zip(colors()).collect());
+        let mut loud = 0;
+        for (b, db) in model.all_buildings(map) {
+            colorer.add_b(b, lden_band(db));
+            if db >= 65.0 {
+                loud += 1;
+            }
+        }
+        let (draw, legend) = colorer.build(ctx);
+
+        let panel = Panel::new_builder(Widget::col(vec![
+            header(ctx, "Traffic noise"),
+            Text::from(
+                Line(
+                    "Day-evening-night level (Lden) at the loudest side of each building, from \
+                     traffic so far today",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            format!(
+                "{} buildings exposed to 65 dB or more",
+                prettyprint_usize(loud)
+            )
+            .text_widget(ctx),
+            legend,
+        ]))
+        .aligned_pair(PANEL_PLACEMENT)
+        .build(ctx);
+
+        TrafficNoise {
+            hour: now.get_hours(),
+            draw,
+            panel,
+        }
+    }
+}
+
+// Matching LDEN_BANDS
+fn colors() -> Vec<Color> {
+    vec![
+        Color::hex("#A0BABF"),
+        Color::hex("#B8D6D1"),
+        Color::hex("#FFDA8A"),
+        Color::hex("#F2A64D"),
+        Color::hex("#E0584A"),
+        Color::hex("#A1273A"),
+    ]
+}
@@ -136,12 +136,20 @@ impl RelativeProblemMap {
             show_overtakes: self
                 .panel
                 .is_checked("show where cars want to overtake cyclists"),
+            show_close_passes: self
+                .panel
+                .is_checked("show where cars pass cyclists too closely"),
             show_arterial_crossings: self
                 .panel
                 .is_checked("show where pedestrians cross arterial intersections"),
             show_overcrowding: self
                 .panel
                 .is_checked("show where pedestrians are over-crowded"),
+            show_near_misses: self.panel.is_checked("show near misses"),
+            show_mid_block_crossings: self
+                .panel
+                .is_checked("show where pedestrians cross mid-block"),
+            show_jaywalking: self.panel.is_checked("show where pedestrians jaywalk"),
         }
     }
 }
@@ -151,8 +159,12 @@ pub struct Options {
     show_delays: bool,
     show_complex_crossings: bool,
     show_overtakes: bool,
+    show_close_passes: bool,
     show_arterial_crossings: bool,
     show_overcrowding: bool,
+    show_near_misses: bool,
+    show_mid_block_crossings: bool,
+    show_jaywalking: bool,
 }
 
 impl Options {
@@ -161,8 +173,12 @@ impl Options {
             show_delays: true,
             show_complex_crossings: true,
             show_overtakes: true,
+            show_close_passes: true,
             show_arterial_crossings: true,
             show_overcrowding: true,
+            show_near_misses: true,
+            show_mid_block_crossings: true,
+            show_jaywalking: true,
         }
     }
 
@@ -171,8 +187,12 @@ impl Options {
             Problem::IntersectionDelay(_, _) => self.show_delays,
             Problem::ComplexIntersectionCrossing(_) => self.show_complex_crossings,
             Problem::OvertakeDesired(_) => self.show_overtakes,
+            Problem::ClosePass(_, _) => self.show_close_passes,
             Problem::ArterialIntersectionCrossing(_) => self.show_arterial_crossings,
             Problem::PedestrianOvercrowding(_) => self.show_overcrowding,
+            Problem::NearMiss(_) => self.show_near_misses,
+            Problem::MidBlockCrossing(_) => self.show_mid_block_crossings,
+            Problem::Jaywalking(_) => self.show_jaywalking,
         }
     }
 }
@@ -196,6 +216,12 @@ fn make_controls(ctx: &mut EventCtx, opts: &Options) -> Panel {
         None,
         opts.show_overtakes,
     ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where cars pass cyclists too closely",
+        None,
+        opts.show_close_passes,
+    ));
     col.push(Toggle::checkbox(
         ctx,
         "show where pedestrians cross arterial intersections",
@@ -208,6 +234,24 @@ fn make_controls(ctx: &mut EventCtx, opts: &Options) -> Panel {
         None,
         opts.show_overcrowding,
     ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show near misses",
+        None,
+        opts.show_near_misses,
+    ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where pedestrians cross mid-block",
+        None,
+        opts.show_mid_block_crossings,
+    ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where pedestrians jaywalk",
+        None,
+        opts.show_jaywalking,
+    ));
 
     Panel::new_builder(Widget::col(col))
         .aligned_pair(PANEL_PLACEMENT)
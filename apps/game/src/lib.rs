@@ -535,12 +535,29 @@ fn finish_app_setup(
                 // same time yet. Just handle this one startup case, so we can switch maps without
                 // constantly flopping day/night mode.
                 if let Some(ref mut secondary) = app.secondary {
-                    secondary.sim.timed_step(
-                        &secondary.map,
-                        start_time,
-                        &mut None,
-                        &mut Timer::throwaway(),
-                    );
+                    let mut timer = Timer::throwaway();
+                    let end_time = secondary.sim.time() + start_time;
+                    while secondary.sim.time() < end_time {
+                        secondary.sim.timed_step(
+                            &secondary.map,
+                            end_time - secondary.sim.time(),
+                            &mut None,
+                            &mut timer,
+                        );
+                        match secondary
+                            .sim
+                            .apply_incidents(&mut secondary.map, &mut timer)
+                        {
+                            Some(effects) => {
+                                crate::edit::redraw_edited_map(
+                                    ctx, secondary, &app.cs, effects, &mut timer,
+                                );
+                            }
+                            None => {
+                                break;
+                            }
+                        }
+                    }
                 }
 
                 // We got here by just passing --dev and a map as flags; we're just looking at an
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use abstutil::Counter;
-use geom::{Distance, Duration};
+use geom::{Distance, Duration, SampleSummary};
 use map_gui::tools::ColorNetwork;
 use map_model::PathStepV2;
 use sim::TripID;
@@ -104,8 +104,9 @@ impl State<App> for ModeShift {
 struct Entry {
     trip: TripID,
     estimated_driving_time: Duration,
-    // Only when we prebaked data?
-    //actual_driving_time: Duration,
+    /// How long the trip took in the baseline, across runs with different RNG seeds. Only when
+    /// there are prebaked results from several runs.
+    actual_driving_time: Option<SampleSummary>,
     estimated_biking_time: Duration,
     distance: Distance,
     total_elevation_gain: Distance,
@@ -121,6 +122,7 @@ struct Filters {
 
 fn produce_raw_data(ctx: &mut EventCtx, app: &App) -> Vec<Entry> {
     let map = &app.primary.map;
+    let seeds = app.prebaked_seeds();
     ctx.loading_screen("shift modes", |_, timer| {
         timer.parallelize(
             "analyze trips",
@@ -152,6 +154,7 @@ fn produce_raw_data(ctx: &mut EventCtx, app: &App) -> Vec<Entry> {
                     Some(Entry {
                         trip: id,
                         estimated_driving_time: driving_path.estimate_duration(map, None),
+                        actual_driving_time: seeds.and_then(|seeds| seeds.trip_duration(id)),
                         estimated_biking_time: biking_path
                             .estimate_duration(map, Some(map_model::MAX_BIKE_SPEED)),
                         // TODO The distance (and elevation change) might differ between the two
@@ -257,6 +260,33 @@ fn make_table(ctx: &mut EventCtx, app: &App) -> Table<App, Entry, Filters> {
             rows.sort_by_key(|x| x.estimated_driving_time)
         })),
     );
+    if app.prebaked_seeds().is_some() {
+        table.column(
+            "Driving time across runs",
+            Box::new(|ctx, app, x| {
+                let mut txt = Text::new();
+                if let Some(summary) = x.actual_driving_time {
+                    txt.add_line(format!(
+                        "{} ± {}",
+                        Duration::seconds(summary.mean).to_string(&app.opts.units),
+                        Duration::seconds(summary.confidence_margin()).to_string(&app.opts.units)
+                    ));
+                    // Is biking really faster or slower, or is the difference just noise?
+                    if !summary.is_significant(x.estimated_biking_time.inner_seconds()) {
+                        txt.append(Line(" (similar to biking)").secondary());
+                    }
+                }
+                txt.render(ctx)
+            }),
+            Col::Sortable(Box::new(|rows| {
+                rows.sort_by_key(|x| {
+                    x.actual_driving_time
+                        .map(|summary| Duration::seconds(summary.mean))
+                        .unwrap_or(Duration::ZERO)
+                })
+            })),
+        );
+    }
     table.column(
         "Estimated biking time",
         Box::new(|ctx, app, x| {
@@ -35,6 +35,11 @@ impl RiskSummaries {
             include_no_changes,
         };
 
+        let driver_filter = Filter {
+            modes: maplit::btreeset! { TripMode::Drive },
+            include_no_changes,
+        };
+
         Box::new(RiskSummaries {
             panel: Panel::new_builder(Widget::col(vec![
                 DashTab::RiskSummaries.picker(ctx, app),
@@ -90,6 +95,31 @@ impl RiskSummaries {
                             ),
                         ])
                         .section(ctx),
+                        Widget::col(vec![
+                            Line("Mid-block crossings")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                ped_filter.trip_problems(app, ProblemType::MidBlockCrossing),
+                            ),
+                        ])
+                        .section(ctx),
+                        Widget::col(vec![
+                            Line("Crossing against the light")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                ped_filter.trip_problems(app, ProblemType::Jaywalking),
+                            ),
+                        ])
+                        .section(ctx),
+                        near_miss_matrix(ctx, app, &ped_filter),
                     ],
                 )
                 .margin_above(30),
@@ -135,9 +165,38 @@ impl RiskSummaries {
                             ),
                         ])
                         .section(ctx),
+                        Widget::col(vec![
+                            Line("Cars passing cyclists too closely")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                bike_filter.trip_problems(app, ProblemType::ClosePass),
+                            ),
+                        ])
+                        .section(ctx),
+                        near_miss_matrix(ctx, app, &bike_filter),
                     ],
                 )
                 .margin_above(30),
+                Widget::row(vec![
+                    Image::from_path("system/assets/meters/car.svg")
+                        .dims(36.0)
+                        .into_widget(ctx)
+                        .centered_vert(),
+                    Line(format!(
+                        "Driver Risks - {} Finished Trips",
+                        prettyprint_usize(driver_filter.finished_trip_count(app))
+                    ))
+                    .big_heading_plain()
+                    .into_widget(ctx)
+                    .centered_vert(),
+                ])
+                .margin_above(30),
+                Widget::evenly_spaced_row(32, vec![near_miss_matrix(ctx, app, &driver_filter)])
+                    .margin_above(30),
                 ctx.style().btn_plain.text("Export to CSV").build_def(ctx),
             ]))
             .exact_size_percent(90, 90)
@@ -187,6 +246,19 @@ impl State<App> for RiskSummaries {
     }
 }
 
+/// Near misses are simulated, so unlike the other risks, they predict how edits change safety
+/// beyond the design of the intersections a trip passes through.
+fn near_miss_matrix(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
+    Widget::col(vec![
+        Line("Near misses at intersections")
+            .small_heading()
+            .into_widget(ctx)
+            .centered_horiz(),
+        problem_matrix(ctx, app, filter.trip_problems(app, ProblemType::NearMiss)),
+    ])
+    .section(ctx)
+}
+
 pub struct Filter {
     modes: BTreeSet<TripMode>,
     include_no_changes: bool,
@@ -173,7 +173,7 @@ impl Demand {
 
         let mut all_demand = HashMap::new();
         for i in map.all_intersections() {
-            if i.is_traffic_signal() {
+            if map.is_signal_or_yield(i.id) {
                 all_demand.insert(i.id, Demand { raw: Vec::new() });
             }
         }
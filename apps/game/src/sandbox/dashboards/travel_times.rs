@@ -167,12 +167,16 @@ impl State<App> for TravelTimes {
 }
 
 fn summary_boxes(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
+    let seeds = app.prebaked_seeds();
     let mut num_same = 0;
+    let mut num_within_noise = 0;
     let mut num_faster = 0;
     let mut num_slower = 0;
     let mut sum_faster = Duration::ZERO;
     let mut sum_slower = Duration::ZERO;
-    for (_, b, a, mode) in app
+    let mut trips = Vec::new();
+    let mut sum_after = Duration::ZERO;
+    for (id, b, a, mode) in app
         .primary
         .sim
         .get_analytics()
@@ -181,11 +185,24 @@ fn summary_boxes(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
         if !filter.modes.contains(&mode) {
             continue;
         }
-        let same = if let Some(pct) = filter.changes_pct {
+        trips.push(id);
+        sum_after += a;
+        let mut same = if let Some(pct) = filter.changes_pct {
             pct_diff(a, b) <= pct
         } else {
             a == b
         };
+        // If the trip's time varies this much between runs without any edits, the change
+        // doesn't mean anything
+        if !same
+            && seeds
+                .and_then(|seeds| seeds.trip_duration(id))
+                .map(|summary| !summary.is_significant(a.inner_seconds()))
+                .unwrap_or(false)
+        {
+            same = true;
+            num_within_noise += 1;
+        }
 
         if same {
             num_same += 1;
@@ -199,7 +216,26 @@ fn summary_boxes(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
     }
     let num_total = (num_faster + num_slower + num_same) as f64;
 
-    Widget::row(vec![
+    let mut unchanged = vec![
+        Line(format!("Unchanged: {}", prettyprint_usize(num_same))).big_heading_plain(),
+        Line(format!(
+            "{:.2}% of finished trips",
+            100.0 * (num_same as f64) / num_total
+        ))
+        .small(),
+    ];
+    if let Some(seeds) = seeds {
+        unchanged.push(
+            Line(format!(
+                "Including {} changes within the noise between {} runs",
+                prettyprint_usize(num_within_noise),
+                seeds.seeds.len()
+            ))
+            .small(),
+        );
+    }
+
+    let boxes = Widget::row(vec![
         Text::from_multiline(vec![
             Line(format!("Faster Trips: {}", prettyprint_usize(num_faster))).big_heading_plain(),
             Line(format!(
@@ -250,21 +286,42 @@ fn summary_boxes(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
         .padding(20)
         .bg(app.cs.signal_banned_turn.alpha(0.5))
         .outline(ctx.style().section_outline),
-        Text::from_multiline(vec![
-            Line(format!("Unchanged: {}", prettyprint_usize(num_same))).big_heading_plain(),
-            Line(format!(
-                "{:.2}% of finished trips",
-                100.0 * (num_same as f64) / num_total
-            ))
-            .small(),
-        ])
-        .into_widget(ctx)
-        .container()
-        .padding(20)
-        .bg(Color::hex("#F4DA22").alpha(0.5))
-        .outline(ctx.style().section_outline),
+        Text::from_multiline(unchanged)
+            .into_widget(ctx)
+            .container()
+            .padding(20)
+            .bg(Color::hex("#F4DA22").alpha(0.5))
+            .outline(ctx.style().section_outline),
     ])
-    .evenly_spaced()
+    .evenly_spaced();
+
+    // Compare the average over all of these trips to how much it varies between runs
+    let baseline = if trips.is_empty() {
+        None
+    } else {
+        seeds.and_then(|seeds| seeds.mean_duration(trips.iter().cloned()))
+    };
+    if let Some(baseline) = baseline {
+        let after = sum_after / (trips.len() as f64);
+        Widget::col(vec![
+            boxes,
+            Text::from(Line(format!(
+                "Average trip time: {} now, {} ± {} before across {} runs ({})",
+                after,
+                Duration::seconds(baseline.mean),
+                Duration::seconds(baseline.confidence_margin()),
+                seeds.unwrap().seeds.len(),
+                if baseline.is_significant(after.inner_seconds()) {
+                    "a significant difference"
+                } else {
+                    "not significant, within noise"
+                }
+            )))
+            .into_widget(ctx),
+        ])
+    } else {
+        boxes
+    }
 }
 
 fn scatter_plot(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
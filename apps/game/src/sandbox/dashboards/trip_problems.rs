@@ -17,8 +17,12 @@ pub enum ProblemType {
     IntersectionDelay,
     ComplexIntersectionCrossing,
     OvertakeDesired,
+    ClosePass,
     ArterialIntersectionCrossing,
     PedestrianOvercrowding,
+    NearMiss,
+    MidBlockCrossing,
+    Jaywalking,
 }
 
 impl From<&Problem> for ProblemType {
@@ -27,8 +31,12 @@ impl From<&Problem> for ProblemType {
             Problem::IntersectionDelay(_, _) => Self::IntersectionDelay,
             Problem::ComplexIntersectionCrossing(_) => Self::ComplexIntersectionCrossing,
             Problem::OvertakeDesired(_) => Self::OvertakeDesired,
+            Problem::ClosePass(_, _) => Self::ClosePass,
             Problem::ArterialIntersectionCrossing(_) => Self::ArterialIntersectionCrossing,
             Problem::PedestrianOvercrowding(_) => Self::PedestrianOvercrowding,
+            Problem::NearMiss(_) => Self::NearMiss,
+            Problem::MidBlockCrossing(_) => Self::MidBlockCrossing,
+            Problem::Jaywalking(_) => Self::Jaywalking,
         }
     }
 }
@@ -49,8 +57,12 @@ impl ProblemType {
             ProblemType::IntersectionDelay,
             ProblemType::ComplexIntersectionCrossing,
             ProblemType::OvertakeDesired,
+            ProblemType::ClosePass,
             ProblemType::ArterialIntersectionCrossing,
             ProblemType::PedestrianOvercrowding,
+            ProblemType::NearMiss,
+            ProblemType::MidBlockCrossing,
+            ProblemType::Jaywalking,
         ]
     }
 }
@@ -380,6 +380,7 @@ pub fn spawn_agents_around(i: IntersectionID, app: &mut App) {
                         TripEndpoint::Building(map.all_buildings().choose(&mut rng).unwrap().id),
                         mode,
                     )],
+                    behavior: None,
                 });
             }
         } else if lane.is_walkable() {
@@ -396,6 +397,7 @@ pub fn spawn_agents_around(i: IntersectionID, app: &mut App) {
                         TripEndpoint::Building(map.all_buildings().choose(&mut rng).unwrap().id),
                         TripMode::Walk,
                     )],
+                    behavior: None,
                 });
             }
         }
@@ -109,6 +109,7 @@ impl State<App> for AgentSpawner {
                                 to,
                                 self.panel.dropdown_value("mode"),
                             )],
+                            behavior: None,
                         });
                     }
                     let mut rng = app.primary.current_flags.sim_flags.make_rng();
@@ -7,7 +7,7 @@ use rand_xorshift::XorShiftRng;
 use abstio::MapName;
 use abstutil::Timer;
 use geom::Duration;
-use map_model::{EditCmd, EditIntersection, MapEdits};
+use map_model::{EditCmd, EditIntersectionControl, MapEdits};
 use sim::ScenarioGenerator;
 use synthpop::{OrigPersonID, Scenario, ScenarioModifier};
 use widgetry::{
@@ -177,27 +177,39 @@ impl GameplayMode {
     pub fn allows(&self, edits: &MapEdits) -> bool {
         for cmd in &edits.commands {
             match cmd {
-                EditCmd::ChangeRoad { .. } => {
+                EditCmd::ChangeRoad { .. }
+                | EditCmd::AddRoad { .. }
+                | EditCmd::SplitRoad { .. } => {
                     if !self.can_edit_roads() {
                         return false;
                     }
                 }
-                EditCmd::ChangeIntersection { ref new, .. } => match new {
-                    // TODO Conflating construction
-                    EditIntersection::StopSign(_) | EditIntersection::Closed => {
-                        if !self.can_edit_stop_signs() {
-                            return false;
+                EditCmd::ChangeIntersection {
+                    ref new, ref old, ..
+                } => {
+                    match new.control {
+                        // TODO Conflating construction
+                        EditIntersectionControl::StopSign(_) | EditIntersectionControl::Closed => {
+                            if !self.can_edit_stop_signs() {
+                                return false;
+                            }
                         }
+                        _ => {}
                     }
-                    _ => {}
-                },
+                    // Turn restrictions change the road network
+                    if new.banned_movements != old.banned_movements && !self.can_edit_roads() {
+                        return false;
+                    }
+                }
                 EditCmd::ChangeCrosswalks { .. } => {
                     // TODO Another hack to see if we can only edit signal timing
                     if !self.can_edit_stop_signs() {
                         return false;
                     }
                 }
-                EditCmd::ChangeRouteSchedule { .. } => {}
+                EditCmd::ChangeRouteSchedule { .. }
+                | EditCmd::ChangeTransitStop { .. }
+                | EditCmd::ChangeTransitRoute { .. } => {}
             }
         }
         true
@@ -1082,6 +1082,7 @@ impl TutorialState {
                             TripEndpoint::Building(goal_bldg),
                             TripMode::Drive,
                         )],
+                        behavior: None,
                     });
                     // Will definitely get there first
                     for _ in 0..map.get_b(goal_bldg).num_parking_spots() {
@@ -1097,6 +1098,7 @@ impl TutorialState {
                                 TripEndpoint::Building(goal_bldg),
                                 TripMode::Drive,
                             )],
+                            behavior: None,
                         });
                     }
                     let mut rng = app.primary.current_flags.sim_flags.make_rng();
@@ -1352,7 +1354,11 @@ pub fn execute(ctx: &mut EventCtx, app: &mut App, id: ID, action: &str) -> Trans
         (ID::Lane(l), "check the parking occupancy") => {
             let lane = app.primary.map.get_l(l);
             if lane.is_parking() {
-                let percent = (app.primary.sim.get_free_onstreet_spots(l).len() as f64)
+                let percent = (app
+                    .primary
+                    .sim
+                    .get_free_onstreet_spots(l, &app.primary.map)
+                    .len() as f64)
                     / (lane.number_parking_spots(app.primary.map.get_config()) as f64);
                 if percent > 0.1 {
                     PopupMsg::new_state(
@@ -8,7 +8,7 @@ use map_gui::options::OptionsPanel;
 use map_gui::render::{unzoomed_agent_radius, UnzoomedAgents};
 use map_gui::tools::{ChooseSomething, Minimap, TurnExplorer};
 use map_gui::{AppLike, ID};
-use sim::Analytics;
+use sim::{Analytics, MultiSeedResults};
 use synthpop::Scenario;
 use widgetry::tools::{FileLoader, FutureLoader, URLManager};
 use widgetry::{lctrl, Choice, EventCtx, GfxCtx, Key, Outcome, Panel, State, UpdateType};
@@ -439,6 +439,9 @@ enum LoadStage {
     LoadingPrebaked(String),
     // Scenario name, maybe prebaked data
     GotPrebaked(String, Result<Analytics>),
+    // Scenario name
+    LoadingPrebakedSeeds(String),
+    GotPrebakedSeeds(Result<MultiSeedResults>),
     Finalizing,
 }
 
@@ -597,9 +600,11 @@ impl State<App> for SandboxLoader {
                         Ok(prebaked) => {
                             app.set_prebaked(Some((
                                 app.primary.map.get_name().clone(),
-                                scenario_name,
+                                scenario_name.clone(),
                                 prebaked,
                             )));
+                            self.stage = Some(LoadStage::LoadingPrebakedSeeds(scenario_name));
+                            continue;
                         }
                         Err(err) => {
                             warn!(
@@ -616,6 +621,30 @@ impl State<App> for SandboxLoader {
                     self.stage = Some(LoadStage::Finalizing);
                     continue;
                 }
+                LoadStage::LoadingPrebakedSeeds(scenario_name) => {
+                    return Transition::Push(FileLoader::<App, MultiSeedResults>::new_state(
+                        ctx,
+                        abstio::path_prebaked_multi_seed_results(
+                            app.primary.map.get_name(),
+                            &scenario_name,
+                        ),
+                        Box::new(move |_, _, _, results| {
+                            Transition::Multi(vec![
+                                Transition::Pop,
+                                Transition::ModifyState(Box::new(move |state, _, _| {
+                                    let loader = state.downcast_mut::<SandboxLoader>().unwrap();
+                                    loader.stage = Some(LoadStage::GotPrebakedSeeds(results));
+                                })),
+                            ])
+                        }),
+                    ));
+                }
+                LoadStage::GotPrebakedSeeds(results) => {
+                    // Most maps only have results from a single run, so this isn't worth a warning
+                    app.set_prebaked_seeds(results.ok());
+                    self.stage = Some(LoadStage::Finalizing);
+                    continue;
+                }
                 LoadStage::Finalizing => {
                     let mut gameplay = self.mode.initialize(ctx, app);
                     gameplay.recreate_panels(ctx, app);
@@ -497,6 +497,9 @@ impl TimePanel {
             }
         }
 
+        // Anything stepping the sim might've halted for an incident, so handle that here
+        crate::edit::apply_incidents(ctx, app);
+
         // TODO Need to do this anywhere that steps the sim, like TimeWarpScreen.
         let alerts = app.primary.sim.clear_alerts();
         if !alerts.is_empty() {
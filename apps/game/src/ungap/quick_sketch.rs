@@ -95,11 +95,16 @@ impl QuickSketch {
 
         // Also manage the URL here, since this is called for every edit
         let map = &app.primary.map;
-        let checksum = map.get_edits().get_checksum(map);
-        if share::UploadedProposals::load().md5sums.contains(&checksum) {
-            URLManager::update_url_param("--edits".to_string(), format!("remote/{}", checksum));
-        } else {
-            URLManager::update_url_param("--edits".to_string(), map.get_edits().edits_name.clone());
+        match map.get_edits().get_checksum(map) {
+            Ok(checksum) if share::UploadedProposals::load().md5sums.contains(&checksum) => {
+                URLManager::update_url_param("--edits".to_string(), format!("remote/{}", checksum));
+            }
+            _ => {
+                URLManager::update_url_param(
+                    "--edits".to_string(),
+                    map.get_edits().edits_name.clone(),
+                );
+            }
         }
     }
 }
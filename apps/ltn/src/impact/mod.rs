@@ -1,13 +1,13 @@
 mod ui;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use abstio::MapName;
 use abstutil::Timer;
 use geom::{Duration, Time};
 use map_gui::tools::compare_counts::CompareCounts;
-use map_model::{Path, PathConstraints, PathRequest, Pathfinder, RoadID};
-use synthpop::{Scenario, TrafficCounts, TripEndpoint, TripMode};
+use map_model::{BuildingID, Path, PathConstraints, PathRequest, Pathfinder, RoadID};
+use synthpop::{NoiseModel, Scenario, TrafficCounts, TripEndpoint, TripMode};
 use widgetry::EventCtx;
 
 pub use self::ui::ShowResults;
@@ -23,6 +23,7 @@ use crate::App;
 // - all_trips and everything else depends just on the map (we only have one scenario per map now)
 // - filtered_trips depend on filters
 // - the 'b' and 'relative' parts of compare_counts depend on change_key (for when the map is edited)
+// - noise_before depends on filters, and noise_after also on change_key
 pub struct Impact {
     pub map: MapName,
     pub filters: Filters,
@@ -33,6 +34,10 @@ pub struct Impact {
 
     pub compare_counts: CompareCounts,
     pub change_key: ChangeKey,
+
+    /// Traffic noise (Lden in dB) at each building, from the counts before and after filters
+    pub noise_before: BTreeMap<BuildingID, f64>,
+    pub noise_after: BTreeMap<BuildingID, f64>,
 }
 
 #[derive(PartialEq)]
@@ -59,6 +64,9 @@ impl Impact {
 
             compare_counts: CompareCounts::empty(ctx),
             change_key: ChangeKey::default(),
+
+            noise_before: BTreeMap::new(),
+            noise_after: BTreeMap::new(),
         }
     }
 
@@ -113,6 +121,11 @@ impl Impact {
 
         let counts_b = self.counts_b(app, timer);
 
+        timer.start("estimate traffic noise");
+        self.noise_before = NoiseModel::from_counts(map, &counts_a).all_buildings(map);
+        self.noise_after = NoiseModel::from_counts(map, &counts_b).all_buildings(map);
+        timer.stop("estimate traffic noise");
+
         let clickable_roads = true;
         self.compare_counts = CompareCounts::new(
             ctx,
@@ -127,6 +140,9 @@ impl Impact {
     fn map_edits_changed(&mut self, ctx: &mut EventCtx, app: &App, timer: &mut Timer) {
         self.change_key = app.session.modal_filters.get_change_key();
         let counts_b = self.counts_b(app, timer);
+        timer.start("estimate traffic noise");
+        self.noise_after = NoiseModel::from_counts(&app.map, &counts_b).all_buildings(&app.map);
+        timer.stop("estimate traffic noise");
         self.compare_counts.recalculate_b(ctx, app, counts_b);
     }
 
@@ -1,8 +1,9 @@
 use std::collections::BTreeSet;
 
+use abstutil::{prettyprint_usize, Counter};
 use map_gui::tools::checkbox_per_mode;
 use map_model::{Path, NORMAL_LANE_THICKNESS};
-use synthpop::{Scenario, TripMode};
+use synthpop::{lden_band, Scenario, TripMode, LDEN_BANDS};
 use widgetry::tools::{FileLoader, PopupMsg};
 use widgetry::{
     Color, DrawBaselayer, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
@@ -63,6 +64,7 @@ impl ShowResults {
             // TODO Dropdown for the scenario, and explain its source/limitations
             app.session.impact.filters.to_panel(ctx, app),
             app.session.impact.compare_counts.get_panel_widget(ctx).named("compare counts"),
+            noise_summary(ctx, &app.session.impact),
             ctx.style().btn_outline.text("Save before/after counts to files").build_def(ctx),
         ]);
         let top_panel = crate::common::app_top_panel(ctx, app);
@@ -210,6 +212,51 @@ impl Filters {
     }
 }
 
+fn noise_summary(ctx: &mut EventCtx, impact: &Impact) -> Widget {
+    let mut before_per_band = Counter::new();
+    let mut after_per_band = Counter::new();
+    let mut louder = 0;
+    let mut quieter = 0;
+    let all_bldgs: BTreeSet<_> = impact
+        .noise_before
+        .keys()
+        .chain(impact.noise_after.keys())
+        .collect();
+    for b in all_bldgs {
+        // Buildings without traffic nearby are effectively silent
+        let before = impact.noise_before.get(b).cloned().unwrap_or(0.0);
+        let after = impact.noise_after.get(b).cloned().unwrap_or(0.0);
+        before_per_band.inc(lden_band(before));
+        after_per_band.inc(lden_band(after));
+        // About the smallest change people notice
+        if after - before >= 3.0 {
+            louder += 1;
+        } else if before - after >= 3.0 {
+            quieter += 1;
+        }
+    }
+
+    let mut txt = Text::from(Line("Traffic noise").small_heading());
+    txt.add_line(Line(
+        "Day-evening-night level at the loudest side of each building. Buildings before and \
+         after:",
+    ));
+    for band in LDEN_BANDS {
+        txt.add_line(format!(
+            "{}: {} -> {}",
+            band,
+            prettyprint_usize(before_per_band.get(band)),
+            prettyprint_usize(after_per_band.get(band))
+        ));
+    }
+    txt.add_line(Line(format!(
+        "{} buildings get noticeably louder, {} quieter",
+        prettyprint_usize(louder),
+        prettyprint_usize(quieter)
+    )));
+    txt.wrap_to_pct(ctx, 20).into_widget(ctx).section(ctx)
+}
+
 fn help() -> Vec<&'static str> {
     vec![
         "This tool is still experimental.",
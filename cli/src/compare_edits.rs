@@ -0,0 +1,290 @@
+//! Simulates a scenario once without edits and once per proposal, then writes a JSON report
+//! comparing each proposal against the baseline. This is meant for automated checks in
+//! repositories of proposals, so nobody has to click through the A/B testing dashboards.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use abstutil::Timer;
+use geom::Time;
+use map_model::{IntersectionID, Map, MapEdits};
+use sim::{Analytics, Emissions, Problem};
+use synthpop::{Scenario, TripMode};
+
+pub fn run(
+    map_path: String,
+    scenario_path: String,
+    edits_paths: Vec<String>,
+    output: String,
+) -> Result<()> {
+    let mut timer = Timer::new("compare edits");
+    let map = Map::load_synchronously(map_path, &mut timer);
+    let scenario: Scenario = abstio::must_read_object(scenario_path, &mut timer);
+
+    // Load everything up-front, so a broken file fails before spending time simulating
+    let mut requests = vec![None];
+    for path in edits_paths {
+        requests.push(Some(MapEdits::load_from_file(&map, path, &mut timer)?));
+    }
+
+    let map_ref = &map;
+    let scenario_ref = &scenario;
+    let mut results = timer.parallelize(
+        "simulate each proposal",
+        requests,
+        |maybe_edits: Option<MapEdits>| {
+            let mut timer = Timer::throwaway();
+            let mut map = map_ref.clone();
+            let name = maybe_edits
+                .as_ref()
+                .map(|edits| edits.edits_name.clone())
+                .unwrap_or_else(|| "baseline".to_string());
+            if let Some(edits) = maybe_edits {
+                map.must_apply_edits(edits, &mut timer);
+                map.recalculate_pathfinding_after_edits(&mut timer);
+            }
+            let sim = sim::prebake::simulate_full_day(&map, scenario_ref, &mut timer);
+            let intersections: Vec<i64> = map
+                .all_intersections()
+                .iter()
+                .map(|i| i.orig_id.0)
+                .collect();
+            (name, sim.get_analytics().clone(), intersections)
+        },
+    );
+    // parallelize preserves the order of requests
+    let (_, baseline, _) = results.remove(0);
+
+    let report = Report {
+        map: scenario.map_name.describe(),
+        scenario: scenario.scenario_name.clone(),
+        baseline: RunSummary::new(&baseline),
+        proposals: results
+            .into_iter()
+            .map(|(name, after, intersections)| {
+                ProposalReport::new(name, &baseline, &after, &intersections)
+            })
+            .collect(),
+    };
+    for proposal in &report.proposals {
+        println!(
+            "{}: {} trips faster, {} slower, {} cancelled (baseline {}), {:+.1} kg CO2",
+            proposal.edits_name,
+            proposal.trips_faster,
+            proposal.trips_slower,
+            proposal.summary.cancelled_trips,
+            report.baseline.cancelled_trips,
+            proposal.emissions_delta.co2_grams / 1000.0
+        );
+    }
+    abstio::write_json(output, &report);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Report {
+    map: String,
+    scenario: String,
+    baseline: RunSummary,
+    proposals: Vec<ProposalReport>,
+}
+
+#[derive(Serialize)]
+struct RunSummary {
+    finished_trips: usize,
+    cancelled_trips: usize,
+    // Use f64 seconds, since a serialized Duration has a low cap.
+    total_trip_duration_seconds: f64,
+    /// The number of problems encountered by all trips, per type of problem
+    problems: BTreeMap<&'static str, usize>,
+    /// Estimated for all vehicles over the whole day
+    emissions: Emissions,
+}
+
+impl RunSummary {
+    fn new(analytics: &Analytics) -> RunSummary {
+        let mut finished_trips = 0;
+        let mut cancelled_trips = 0;
+        let mut total_trip_duration_seconds = 0.0;
+        for (_, _, _, maybe_duration) in &analytics.finished_trips {
+            if let Some(dt) = maybe_duration {
+                finished_trips += 1;
+                total_trip_duration_seconds += dt.inner_seconds();
+            } else {
+                cancelled_trips += 1;
+            }
+        }
+
+        let mut problems = BTreeMap::new();
+        for list in analytics.problems_per_trip.values() {
+            for (_, problem) in list {
+                *problems.entry(problem_type(problem)).or_insert(0) += 1;
+            }
+        }
+
+        RunSummary {
+            finished_trips,
+            cancelled_trips,
+            total_trip_duration_seconds,
+            problems,
+            emissions: analytics.total_emissions,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProposalReport {
+    edits_name: String,
+    summary: RunSummary,
+    /// Only trips that finished both with and without the edits are compared.
+    trips_faster: usize,
+    trips_slower: usize,
+    trips_unchanged: usize,
+    /// Trip times summed per mode, before and after the edits
+    modes: BTreeMap<TripMode, ModeDelta>,
+    /// Only intersections where delays changed, with the biggest change in total delay first
+    intersection_delays: Vec<IntersectionDelayDelta>,
+    /// Negative means the proposal reduces emissions
+    emissions_delta: Emissions,
+}
+
+#[derive(Serialize, Default)]
+struct ModeDelta {
+    trips: usize,
+    before_seconds: f64,
+    after_seconds: f64,
+    /// Negative means the proposal saves time
+    delta_seconds: f64,
+}
+
+#[derive(Serialize)]
+struct IntersectionDelayDelta {
+    id: usize,
+    /// Intersections created by the edits have made-up negative IDs
+    osm_node_id: i64,
+    before: DelayStats,
+    after: DelayStats,
+}
+
+#[derive(Serialize, Default, PartialEq)]
+struct DelayStats {
+    /// How many agents were delayed here
+    count: usize,
+    total_seconds: f64,
+    max_seconds: f64,
+}
+
+impl ProposalReport {
+    fn new(
+        edits_name: String,
+        before: &Analytics,
+        after: &Analytics,
+        osm_node_ids: &[i64],
+    ) -> ProposalReport {
+        let mut trips_faster = 0;
+        let mut trips_slower = 0;
+        let mut trips_unchanged = 0;
+        let mut modes: BTreeMap<TripMode, ModeDelta> = BTreeMap::new();
+        // Both simulations ran to completion, so compare every trip that finished
+        let end = before
+            .finished_trips
+            .iter()
+            .chain(after.finished_trips.iter())
+            .map(|(t, _, _, _)| *t)
+            .max()
+            .unwrap_or(Time::START_OF_DAY);
+        for (_, b, a, mode) in after.both_finished_trips(end, before) {
+            if a < b {
+                trips_faster += 1;
+            } else if a > b {
+                trips_slower += 1;
+            } else {
+                trips_unchanged += 1;
+            }
+            let delta = modes.entry(mode).or_default();
+            delta.trips += 1;
+            delta.before_seconds += b.inner_seconds();
+            delta.after_seconds += a.inner_seconds();
+            delta.delta_seconds += (a - b).inner_seconds();
+        }
+
+        let before_delays = delay_stats(before);
+        let mut after_delays = delay_stats(after);
+        let mut intersection_delays = Vec::new();
+        for (i, before) in before_delays {
+            let after = after_delays.remove(&i).unwrap_or_default();
+            if before != after {
+                intersection_delays.push(IntersectionDelayDelta {
+                    id: i.0,
+                    osm_node_id: osm_node_ids[i.0],
+                    before,
+                    after,
+                });
+            }
+        }
+        for (i, after) in after_delays {
+            intersection_delays.push(IntersectionDelayDelta {
+                id: i.0,
+                osm_node_id: osm_node_ids[i.0],
+                before: DelayStats::default(),
+                after,
+            });
+        }
+        intersection_delays.sort_by(|x, y| {
+            let change =
+                |d: &IntersectionDelayDelta| (d.after.total_seconds - d.before.total_seconds).abs();
+            change(y).partial_cmp(&change(x)).unwrap()
+        });
+
+        ProposalReport {
+            edits_name,
+            summary: RunSummary::new(after),
+            trips_faster,
+            trips_slower,
+            trips_unchanged,
+            modes,
+            intersection_delays,
+            emissions_delta: emissions_delta(&before.total_emissions, &after.total_emissions),
+        }
+    }
+}
+
+fn delay_stats(analytics: &Analytics) -> BTreeMap<IntersectionID, DelayStats> {
+    let mut results = BTreeMap::new();
+    for (i, delays) in &analytics.intersection_delays {
+        let mut stats = DelayStats::default();
+        for (_, _, dt, _) in delays {
+            stats.count += 1;
+            stats.total_seconds += dt.inner_seconds();
+            stats.max_seconds = stats.max_seconds.max(dt.inner_seconds());
+        }
+        results.insert(*i, stats);
+    }
+    results
+}
+
+fn emissions_delta(before: &Emissions, after: &Emissions) -> Emissions {
+    Emissions {
+        co2_grams: after.co2_grams - before.co2_grams,
+        nox_grams: after.nox_grams - before.nox_grams,
+        pm_grams: after.pm_grams - before.pm_grams,
+        fuel_liters: after.fuel_liters - before.fuel_liters,
+        electricity_kwh: after.electricity_kwh - before.electricity_kwh,
+    }
+}
+
+fn problem_type(problem: &Problem) -> &'static str {
+    match problem {
+        Problem::IntersectionDelay(_, _) => "intersection_delay",
+        Problem::ComplexIntersectionCrossing(_) => "complex_intersection_crossing",
+        Problem::ArterialIntersectionCrossing(_) => "arterial_intersection_crossing",
+        Problem::OvertakeDesired(_) => "overtake_desired",
+        Problem::ClosePass(_, _) => "close_pass",
+        Problem::PedestrianOvercrowding(_) => "pedestrian_overcrowding",
+        Problem::NearMiss(_) => "near_miss",
+        Problem::MidBlockCrossing(_) => "mid_block_crossing",
+        Problem::Jaywalking(_) => "jaywalking",
+    }
+}
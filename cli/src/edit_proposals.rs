@@ -0,0 +1,61 @@
+//! Compare and combine proposals that different people made against the same map.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use map_model::{EditedObject, Map, MapEdits};
+
+pub fn diff(map_path: String, first: String, second: String) -> Result<()> {
+    let mut timer = Timer::new("diff proposals");
+    let map = Map::load_synchronously(map_path, &mut timer);
+    let first = MapEdits::load_from_file(&map, first, &mut timer)?;
+    let second = MapEdits::load_from_file(&map, second, &mut timer)?;
+    let diff = first.diff(&second, &map);
+
+    print_objects(&format!("Only in {}", first.edits_name), &diff.only_first);
+    print_objects(&format!("Only in {}", second.edits_name), &diff.only_second);
+    print_objects("Changed differently by both", &diff.different);
+    print_objects("Changed the same way by both", &diff.same);
+    Ok(())
+}
+
+pub fn merge(
+    map_path: String,
+    base: Option<String>,
+    ours: String,
+    theirs: String,
+    output: String,
+) -> Result<()> {
+    let mut timer = Timer::new("merge proposals");
+    let mut map = Map::load_synchronously(map_path, &mut timer);
+    // An empty proposal can't be loaded from a file, so a missing base means the basemap
+    let base = match base {
+        Some(path) => MapEdits::load_from_file(&map, path, &mut timer)?,
+        None => map.new_edits(),
+    };
+    let ours = MapEdits::load_from_file(&map, ours, &mut timer)?;
+    let theirs = MapEdits::load_from_file(&map, theirs, &mut timer)?;
+
+    let mut merged = MapEdits::merge(&base, &ours, &theirs, &map);
+    print_objects(
+        &format!("Conflicts, keeping the version in {}", ours.edits_name),
+        &merged.conflicts,
+    );
+
+    merged.edits.edits_name = abstutil::basename(&output);
+    map.must_apply_edits(merged.edits, &mut timer);
+    abstio::write_json(output, &map.get_edits().to_permanent(&map)?);
+    Ok(())
+}
+
+fn print_objects(title: &str, objects: &BTreeSet<EditedObject>) {
+    if objects.is_empty() {
+        return;
+    }
+    println!("{} ({}):", title, objects.len());
+    for obj in objects {
+        println!("  {}", obj);
+    }
+}
@@ -0,0 +1,480 @@
+//! Exports the lane-level geometry of a map to the Lanelet2 OSM format
+//! (https://github.com/fzi-forschungszentrum-informatik/Lanelet2/blob/master/lanelet2_core/doc/LaneletPrimitives.md),
+//! for use in driving simulators and autonomous vehicle tools.
+//!
+//! - Every lane that traffic moves along becomes a lanelet, bounded by line strings at the lane
+//!   edges. Adjacent lanes share these line strings, and the type of the line describes the
+//!   marking or barrier between the lanes.
+//! - Every turn becomes a lanelet inside the intersection, sharing endpoints with the lanes it
+//!   connects, so routing graphs built by Lanelet2 will follow the turns.
+//! - Traffic signals become regulatory elements. Lanelet2 has no notion of signal timing, so the
+//!   stages are described with extra tags prefixed by `abst:`.
+//!
+//! Lanes pointing backwards along a road reference the road's line strings in the opposite
+//! orientation; Lanelet2 inverts bounds as needed when loading.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use fs_err::File;
+
+use abstutil::Timer;
+use geom::{Distance, PolyLine, Pt2D};
+use map_model::{
+    BufferType, Direction, IntersectionID, Lane, LaneID, LaneType, Map, MovementID, TurnType,
+};
+
+pub fn run(map_path: String, output: String) -> Result<()> {
+    let map = Map::load_synchronously(map_path, &mut Timer::new("export to Lanelet2"));
+    let mut doc = Document::new();
+
+    // The left and right bound of each lane, in the direction of travel
+    let mut lane_bounds: HashMap<LaneID, (PolyLine, PolyLine)> = HashMap::new();
+    for road in map.all_roads() {
+        // Line string k runs along the left side of lane k, in the direction of the road
+        let mut edges = Vec::new();
+        let mut width_so_far = Distance::ZERO;
+        edges.push(road.shift_from_left_side(width_so_far));
+        for lane in &road.lanes {
+            width_so_far += lane.width;
+            edges.push(road.shift_from_left_side(width_so_far));
+        }
+        if edges.iter().any(|pl| pl.is_err()) {
+            warn!("Skipping {}; couldn't find the lane edges", road.id);
+            continue;
+        }
+        let edges: Vec<PolyLine> = edges.into_iter().map(|pl| pl.unwrap()).collect();
+
+        let mut edge_ways = BTreeMap::new();
+        for lane in &road.lanes {
+            if !exported(lane.lane_type) {
+                continue;
+            }
+            let idx = lane.id.offset;
+            for k in [idx, idx + 1] {
+                if edge_ways.contains_key(&k) {
+                    continue;
+                }
+                let left = if k == 0 {
+                    None
+                } else {
+                    Some(&road.lanes[k - 1])
+                };
+                let right = road.lanes.get(k);
+                let way = doc.add_way(edges[k].points(), marking(left, right));
+                edge_ways.insert(k, way);
+            }
+
+            let (left, right) = if lane.dir == Direction::Fwd {
+                (edge_ways[&idx], edge_ways[&(idx + 1)])
+            } else {
+                (edge_ways[&(idx + 1)], edge_ways[&idx])
+            };
+            let mut tags = lanelet_tags(lane);
+            tags.push((
+                "speed_limit",
+                format!("{:.0}", road.speed_limit.inner_meters_per_second() * 3.6),
+            ));
+            tags.push(("abst:lane", lane.id.encode_u32().to_string()));
+            let lanelet =
+                doc.add_relation(vec![("way", left, "left"), ("way", right, "right")], tags);
+            doc.lanelet_per_lane.insert(lane.id, lanelet);
+
+            lane_bounds.insert(
+                lane.id,
+                if lane.dir == Direction::Fwd {
+                    (edges[idx].clone(), edges[idx + 1].clone())
+                } else {
+                    (edges[idx + 1].reversed(), edges[idx].reversed())
+                },
+            );
+        }
+    }
+
+    // Which stages of a traffic signal is each movement protected or permitted during?
+    let mut signal_stages: HashMap<MovementID, (Vec<usize>, Vec<usize>)> = HashMap::new();
+    for i in map.all_intersections() {
+        if let Some(signal) = map.maybe_get_traffic_signal(i.id) {
+            for (idx, stage) in signal.stages.iter().enumerate() {
+                for m in &stage.protected_movements {
+                    signal_stages.entry(*m).or_default().0.push(idx);
+                }
+                for m in &stage.yield_movements {
+                    signal_stages.entry(*m).or_default().1.push(idx);
+                }
+            }
+        }
+    }
+
+    for turn in map.all_turns() {
+        if turn.turn_type == TurnType::SharedSidewalkCorner {
+            continue;
+        }
+        let (src_left, src_right) = match lane_bounds.get(&turn.id.src) {
+            Some(x) => x,
+            None => continue,
+        };
+        let (dst_left, dst_right) = match lane_bounds.get(&turn.id.dst) {
+            Some(x) => x,
+            None => continue,
+        };
+        let is_crossing =
+            turn.turn_type == TurnType::Crosswalk || turn.turn_type == TurnType::UnmarkedCrossing;
+        let half_width = map.get_l(turn.id.src).width / 2.0;
+
+        let (left_pts, right_pts) = if is_crossing {
+            // Crossings go between the ends of two sidewalks, which may face any direction
+            (
+                turn_bound(&turn.geom, half_width, true),
+                turn_bound(&turn.geom, half_width, false),
+            )
+        } else {
+            (
+                join_bound(
+                    src_left.last_pt(),
+                    turn_bound(&turn.geom, half_width, true),
+                    dst_left.first_pt(),
+                ),
+                join_bound(
+                    src_right.last_pt(),
+                    turn_bound(&turn.geom, half_width, false),
+                    dst_right.first_pt(),
+                ),
+            )
+        };
+        let bound_tags = if turn.turn_type == TurnType::Crosswalk {
+            vec![("type", "zebra_marking".to_string())]
+        } else {
+            vec![("type", "virtual".to_string())]
+        };
+        let left = doc.add_way(&left_pts, bound_tags.clone());
+        let right = doc.add_way(&right_pts, bound_tags);
+
+        let mut tags = vec![
+            ("type", "lanelet".to_string()),
+            ("location", "urban".to_string()),
+            ("abst:turn", turn.id.to_string()),
+        ];
+        if is_crossing {
+            tags.push(("subtype", "crosswalk".to_string()));
+            tags.push(("one_way", "no".to_string()));
+            tags.push(("participant:pedestrian", "yes".to_string()));
+        } else {
+            tags.push(("subtype", "road".to_string()));
+            tags.push(("one_way", "yes".to_string()));
+            if let Some(dir) = match turn.turn_type {
+                TurnType::Straight => Some("straight"),
+                TurnType::Left | TurnType::UTurn => Some("left"),
+                TurnType::Right => Some("right"),
+                _ => None,
+            } {
+                tags.push(("turn_direction", dir.to_string()));
+            }
+        }
+        if let Some((protected, permitted)) = signal_stages.get(&turn.id.to_movement(&map)) {
+            tags.push(("abst:protected_stages", join(protected)));
+            tags.push(("abst:permitted_stages", join(permitted)));
+        }
+        doc.add_relation(vec![("way", left, "left"), ("way", right, "right")], tags);
+    }
+
+    for i in map.all_intersections() {
+        if i.is_traffic_signal() {
+            add_traffic_signal(&mut doc, &map, i.id, &lane_bounds);
+        }
+    }
+
+    let mut f = BufWriter::new(File::create(&output)?);
+    doc.write(&mut f, &map)?;
+    println!("Wrote {}", output);
+    Ok(())
+}
+
+/// Every incoming lane controlled by the signal gets a regulatory element, with a stop line at the
+/// end of the lane.
+fn add_traffic_signal(
+    doc: &mut Document,
+    map: &Map,
+    i: IntersectionID,
+    lane_bounds: &HashMap<LaneID, (PolyLine, PolyLine)>,
+) {
+    let signal = map.get_traffic_signal(i);
+    let stage_durations: Vec<String> = signal
+        .stages
+        .iter()
+        .map(|stage| format!("{}", stage.stage_type.simple_duration().inner_seconds()))
+        .collect();
+
+    let incoming_lanes: BTreeSet<LaneID> = map
+        .get_i(i)
+        .turns
+        .iter()
+        .filter(|t| !t.between_sidewalks())
+        .map(|t| t.id.src)
+        .collect();
+    for lane in incoming_lanes {
+        let (left, right) = match lane_bounds.get(&lane) {
+            Some(x) => x,
+            None => continue,
+        };
+        let lanelet = match doc.lanelet_per_lane.get(&lane) {
+            Some(x) => *x,
+            None => continue,
+        };
+        let stop_line_pts = vec![left.last_pt(), right.last_pt()];
+        let stop_line = doc.add_way(&stop_line_pts, vec![("type", "stop_line".to_string())]);
+        let light = doc.add_way(
+            &stop_line_pts,
+            vec![
+                ("type", "traffic_light".to_string()),
+                ("subtype", "red_yellow_green".to_string()),
+            ],
+        );
+        let reg = doc.add_relation(
+            vec![("way", light, "refers"), ("way", stop_line, "ref_line")],
+            vec![
+                ("type", "regulatory_element".to_string()),
+                ("subtype", "traffic_light".to_string()),
+                ("abst:intersection", i.0.to_string()),
+                ("abst:offset", signal.offset.inner_seconds().to_string()),
+                ("abst:stage_durations", stage_durations.join(";")),
+            ],
+        );
+        doc.relations[lanelet]
+            .0
+            .push(("relation", reg, "regulatory_element"));
+    }
+}
+
+/// Which lanes get a lanelet? Parking lanes and light rail don't carry through traffic, and
+/// Lanelet2 has no subtype that'd keep tools from routing cars along them.
+fn exported(lt: LaneType) -> bool {
+    !matches!(
+        lt,
+        LaneType::Buffer(_) | LaneType::Construction | LaneType::Parking | LaneType::LightRail
+    )
+}
+
+fn lanelet_tags(lane: &Lane) -> Vec<(&'static str, String)> {
+    let (subtype, one_way) = match lane.lane_type {
+        LaneType::Sidewalk | LaneType::Shoulder => ("walkway", "no"),
+        LaneType::Biking => ("bicycle_lane", "yes"),
+        LaneType::Bus => ("bus_lane", "yes"),
+        LaneType::Driving => ("road", "yes"),
+        // Traffic from both directions turns out of the middle lane
+        LaneType::SharedLeftTurn => ("road", "no"),
+        LaneType::Parking | LaneType::LightRail | LaneType::Construction | LaneType::Buffer(_) => {
+            unreachable!("{:?} lanes aren't exported", lane.lane_type)
+        }
+    };
+    vec![
+        ("type", "lanelet".to_string()),
+        ("subtype", subtype.to_string()),
+        ("location", "urban".to_string()),
+        ("one_way", one_way.to_string()),
+        ("abst:lane_type", format!("{:?}", lane.lane_type)),
+    ]
+}
+
+/// Describes the line between two lanes of a road, either of which may be missing at the edges of
+/// the road.
+fn marking(left: Option<&Lane>, right: Option<&Lane>) -> Vec<(&'static str, String)> {
+    let (line_type, subtype) = match (left, right) {
+        (Some(l1), Some(l2)) => match (l1.lane_type, l2.lane_type) {
+            (LaneType::Buffer(buffer), _) | (_, LaneType::Buffer(buffer)) => match buffer {
+                BufferType::Stripes => ("line_thick", Some("solid")),
+                // Lanelet2 doesn't have anything for posts, so describe them with a tag below
+                BufferType::FlexPosts => ("line_thick", Some("solid")),
+                BufferType::Planters => ("fence", None),
+                BufferType::JerseyBarrier => ("guard_rail", None),
+                BufferType::Curb => ("curbstone", Some("high")),
+            },
+            (LaneType::Sidewalk, _) | (_, LaneType::Sidewalk) => {
+                if l1.lane_type == l2.lane_type {
+                    ("virtual", None)
+                } else {
+                    ("curbstone", Some("high"))
+                }
+            }
+            (lt1, lt2) if lt1 == lt2 && l1.dir == l2.dir => ("line_thin", Some("dashed")),
+            _ => ("line_thin", Some("solid")),
+        },
+        _ => ("road_border", None),
+    };
+    let mut tags = vec![("type", line_type.to_string())];
+    if let Some(subtype) = subtype {
+        tags.push(("subtype", subtype.to_string()));
+    }
+    for lane in [left, right].into_iter().flatten() {
+        if let LaneType::Buffer(buffer) = lane.lane_type {
+            tags.push(("abst:buffer", format!("{:?}", buffer)));
+            break;
+        }
+    }
+    tags
+}
+
+/// One side of a turn, shifted over from its center line.
+fn turn_bound(geom: &PolyLine, half_width: Distance, left: bool) -> Vec<Pt2D> {
+    let shifted = if left {
+        geom.shift_left(half_width)
+    } else {
+        geom.shift_right(half_width)
+    };
+    match shifted {
+        Ok(pl) => pl.into_points(),
+        Err(_) => vec![geom.first_pt(), geom.last_pt()],
+    }
+}
+
+/// Replace the ends of a turn's bound with the exact points where the lanes end, so the lanelets
+/// share nodes.
+fn join_bound(start: Pt2D, mut pts: Vec<Pt2D>, end: Pt2D) -> Vec<Pt2D> {
+    let len = pts.len();
+    if len >= 2 {
+        pts[0] = start;
+        pts[len - 1] = end;
+    } else {
+        pts = vec![start, end];
+    }
+    pts
+}
+
+fn join(list: &[usize]) -> String {
+    list.iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+type Tags = Vec<(&'static str, String)>;
+/// (member type, index, role)
+type Member = (&'static str, usize, &'static str);
+
+/// Accumulates OSM nodes, ways, and relations. Nodes at the same position are shared, which is how
+/// Lanelet2 figures out connectivity.
+struct Document {
+    nodes: Vec<Pt2D>,
+    node_per_pt: HashMap<(i64, i64), usize>,
+    ways: Vec<(Vec<usize>, Tags)>,
+    relations: Vec<(Vec<Member>, Tags)>,
+    lanelet_per_lane: HashMap<LaneID, usize>,
+}
+
+impl Document {
+    fn new() -> Document {
+        Document {
+            nodes: Vec::new(),
+            node_per_pt: HashMap::new(),
+            ways: Vec::new(),
+            relations: Vec::new(),
+            lanelet_per_lane: HashMap::new(),
+        }
+    }
+
+    fn add_node(&mut self, pt: Pt2D) -> usize {
+        // Round to centimeters
+        let key = (
+            (pt.x() * 100.0).round() as i64,
+            (pt.y() * 100.0).round() as i64,
+        );
+        if let Some(id) = self.node_per_pt.get(&key) {
+            return *id;
+        }
+        self.nodes.push(pt);
+        self.node_per_pt.insert(key, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn add_way(&mut self, pts: &[Pt2D], tags: Tags) -> usize {
+        let mut nodes: Vec<usize> = Vec::new();
+        for pt in pts {
+            let node = self.add_node(*pt);
+            if nodes.last() != Some(&node) {
+                nodes.push(node);
+            }
+        }
+        self.ways.push((nodes, tags));
+        self.ways.len() - 1
+    }
+
+    fn add_relation(&mut self, members: Vec<Member>, tags: Tags) -> usize {
+        self.relations.push((members, tags));
+        self.relations.len() - 1
+    }
+
+    fn write<W: Write>(&self, f: &mut W, map: &Map) -> Result<()> {
+        let gps_bounds = map.get_gps_bounds();
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(f, r#"<osm version="0.6" generator="abstreet">"#)?;
+        for (idx, pt) in self.nodes.iter().enumerate() {
+            let gps = pt.to_gps(gps_bounds);
+            writeln!(
+                f,
+                r#"  <node id="{}" lat="{}" lon="{}">"#,
+                idx + 1,
+                gps.y(),
+                gps.x()
+            )?;
+            // Lanelet2 can use these directly instead of projecting lat/lon
+            writeln!(f, r#"    <tag k="local_x" v="{}"/>"#, pt.x())?;
+            // Map-space grows down, but Lanelet2 expects y to grow up
+            writeln!(f, r#"    <tag k="local_y" v="{}"/>"#, -pt.y())?;
+            writeln!(f, r#"  </node>"#)?;
+        }
+        for (idx, (nodes, tags)) in self.ways.iter().enumerate() {
+            writeln!(f, r#"  <way id="{}">"#, idx + 1)?;
+            for node in nodes {
+                writeln!(f, r#"    <nd ref="{}"/>"#, node + 1)?;
+            }
+            write_tags(f, tags)?;
+            writeln!(f, r#"  </way>"#)?;
+        }
+        for (idx, (members, tags)) in self.relations.iter().enumerate() {
+            writeln!(f, r#"  <relation id="{}">"#, idx + 1)?;
+            for (member_type, id, role) in members {
+                writeln!(
+                    f,
+                    r#"    <member type="{}" ref="{}" role="{}"/>"#,
+                    member_type,
+                    id + 1,
+                    role
+                )?;
+            }
+            write_tags(f, tags)?;
+            writeln!(f, r#"  </relation>"#)?;
+        }
+        writeln!(f, "</osm>")?;
+        Ok(())
+    }
+}
+
+fn write_tags<W: Write>(f: &mut W, tags: &[(&'static str, String)]) -> Result<()> {
+    for (k, v) in tags {
+        writeln!(
+            f,
+            r#"    <tag k="{}" v="{}"/>"#,
+            escape_xml(k),
+            escape_xml(v)
+        )?;
+    }
+    Ok(())
+}
+
+// Tag values include things like `Debug` output of lane types, so don't trust any of them to be
+// safe inside an XML attribute.
+fn escape_xml(x: &str) -> String {
+    let mut result = String::with_capacity(x.len());
+    for c in x.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
@@ -6,6 +6,9 @@ extern crate log;
 
 mod augment_scenario;
 mod clip_osm;
+mod compare_edits;
+mod edit_proposals;
+mod export_lanelet2;
 mod generate_houses;
 mod geojson_to_osmosis;
 mod import_grid2demand;
@@ -14,8 +17,8 @@ mod one_step_import;
 
 use std::io::Write;
 
-use abstio::CityName;
-use anyhow::Result;
+use abstio::{CityName, MapName};
+use anyhow::{bail, Result};
 use fs_err::File;
 use importer::Job;
 use structopt::StructOpt;
@@ -64,6 +67,79 @@ enum Command {
         #[structopt(long, default_value = "42")]
         rng_seed: u64,
     },
+    /// Adds incidents, like crashes and road closures, to an existing scenario. The simulation
+    /// applies each as a temporary map edit while it lasts.
+    AddIncidents {
+        /// The path to a scenario. This will be modified in-place.
+        #[structopt(long)]
+        scenario: String,
+        /// The path to a JSON list of incidents, each with a start time, end time, and what
+        /// happens
+        #[structopt(long)]
+        input: String,
+    },
+    /// Exports the lanes, turns, lane markings, and traffic signals of a map to the Lanelet2 OSM
+    /// format, for use in driving simulators.
+    ExportLanelet2 {
+        /// The path to a map to export
+        #[structopt(long)]
+        map: String,
+        /// The path to the .osm file to write
+        #[structopt(long)]
+        output: String,
+    },
+    /// Simulates a scenario without edits and with each of several proposals, then writes a JSON
+    /// report comparing every proposal to the baseline: trips faster and slower, travel time per
+    /// mode, intersection delays, and problems encountered by trips. The simulations run in
+    /// parallel.
+    CompareEdits {
+        /// The path to a map
+        #[structopt(long)]
+        map: String,
+        /// The path to a scenario for the map
+        #[structopt(long)]
+        scenario: String,
+        /// The path to a proposal to compare. Repeat this for more proposals.
+        #[structopt(long)]
+        edits: Vec<String>,
+        /// The path to write the JSON report
+        #[structopt(long)]
+        output: String,
+    },
+    /// Compares two proposals made against the same map, listing the roads, intersections, and
+    /// transit changed by only one of them or by both.
+    DiffEdits {
+        /// The path to the map both proposals edit
+        #[structopt(long)]
+        map: String,
+        /// The path to the first proposal
+        #[structopt(long)]
+        first: String,
+        /// The path to the second proposal
+        #[structopt(long)]
+        second: String,
+    },
+    /// Combines two proposals that started from a common base. When both proposals change the
+    /// same road or intersection differently, the conflict is reported and the first proposal's
+    /// version is kept.
+    MergeEdits {
+        /// The path to the map all proposals edit
+        #[structopt(long)]
+        map: String,
+        /// The path to the proposal both sides started from. If omitted, they both started from
+        /// the unedited map.
+        #[structopt(long)]
+        base: Option<String>,
+        /// The path to the first proposal, which wins conflicts
+        #[structopt(long)]
+        ours: String,
+        /// The path to the second proposal
+        #[structopt(long)]
+        theirs: String,
+        /// The path to write the merged proposal
+        #[structopt(long)]
+        output: String,
+    },
     /// Clips an OSM file to a boundary. This is a simple Rust port of `osmconvert large_map.osm
     /// -B=clipping.poly --complete-ways -o=smaller_map.osm`.
     ClipOSM {
@@ -119,6 +195,17 @@ enum Command {
         #[structopt(long)]
         output: String,
     },
+    /// Imports curb regulations from a CurbLR or Curb Data Specification GeoJSON file into an
+    /// existing map. The simulation won't offer on-street parking spots when regulations say
+    /// they're used for something else.
+    ImportCurbRegulations {
+        /// The path to a GeoJSON file with curb regulations
+        #[structopt(long)]
+        input: String,
+        /// The path to the map to modify in-place
+        #[structopt(long)]
+        map: String,
+    },
     /// Removes nonessential parts of a Map, for the bike network tool.
     MinifyMap {
         /// The path to a map to shrink. The map is modified in-place.
@@ -195,6 +282,20 @@ enum Command {
         #[structopt(flatten)]
         opts: map_model::RawToMapOptions,
     },
+    /// Stitches several adjacent maps in the same city into one regional map. Roads cut by the
+    /// boundaries between the maps are joined back together, and every scenario from the input
+    /// maps is re-projected onto the new map.
+    StitchMaps {
+        /// The path to one of the maps to stitch together. Repeat this at least twice. The RawMap
+        /// for each of these must exist.
+        #[structopt(long)]
+        input: Vec<String>,
+        /// The name of the new map. It'll be in the same city as the inputs.
+        #[structopt(long)]
+        output: String,
+        #[structopt(flatten)]
+        opts: map_model::RawToMapOptions,
+    },
     /// Regenerate all maps and scenarios from scratch.
     RegenerateEverything {
         /// If this command is being run in the cloud, parallelize the jobs by specifying which
@@ -220,6 +321,10 @@ enum Command {
         /// The path to a scenario file
         #[structopt()]
         scenario_path: String,
+        /// Also simulate with this many different RNG seeds, and write the trip times from every
+        /// run. The dashboards use these to tell changes apart from noise.
+        #[structopt(long, default_value = "1")]
+        num_seeds: usize,
     },
 }
 
@@ -264,6 +369,22 @@ async fn main() -> Result<()> {
             scenario_modifiers,
             rng_seed,
         ),
+        Command::AddIncidents { scenario, input } => add_incidents(scenario, input)?,
+        Command::ExportLanelet2 { map, output } => export_lanelet2::run(map, output)?,
+        Command::CompareEdits {
+            map,
+            scenario,
+            edits,
+            output,
+        } => compare_edits::run(map, scenario, edits, output)?,
+        Command::DiffEdits { map, first, second } => edit_proposals::diff(map, first, second)?,
+        Command::MergeEdits {
+            map,
+            base,
+            ours,
+            theirs,
+            output,
+        } => edit_proposals::merge(map, base, ours, theirs, output)?,
         Command::ClipOSM {
             pbf_path,
             clip_path,
@@ -277,6 +398,7 @@ async fn main() -> Result<()> {
             skip_problems,
         } => import_scenario::run(input, map, skip_problems),
         Command::ImportJSONMap { input, output } => import_json_map(input, output),
+        Command::ImportCurbRegulations { input, map } => import_curb_regulations(input, map)?,
         Command::MinifyMap { map } => minify_map(map),
         Command::GenerateHouses {
             map,
@@ -323,17 +445,40 @@ async fn main() -> Result<()> {
             )
             .await
         }
+        Command::StitchMaps {
+            input,
+            output,
+            opts,
+        } => stitch_maps(input, output, opts)?,
         Command::RegenerateEverything {
             shard_num,
             num_shards,
-        } => importer::regenerate_everything(shard_num, num_shards).await,
+        } => importer::regenerate_everything(shard_num, num_shards).await?,
         Command::RegenerateEverythingExternally => regenerate_everything_externally()?,
-        Command::Import { job } => job.run(&mut Timer::new("import one city")).await,
-        Command::PrebakeScenario { scenario_path } => prebake_scenario(scenario_path),
+        Command::Import { job } => job.run(&mut Timer::new("import one city")).await?,
+        Command::PrebakeScenario {
+            scenario_path,
+            num_seeds,
+        } => prebake_scenario(scenario_path, num_seeds),
     }
     Ok(())
 }
 
+fn stitch_maps(input: Vec<String>, output: String, opts: map_model::RawToMapOptions) -> Result<()> {
+    let mut inputs = Vec::new();
+    for path in input {
+        match MapName::from_path(&path) {
+            Some(name) => inputs.push(name),
+            None => bail!("{} isn't a map path", path),
+        }
+    }
+    if inputs.len() < 2 {
+        bail!("Pass at least two --input maps");
+    }
+    let output = MapName::from_city(&inputs[0].city, &output);
+    importer::stitch_maps(inputs, output, opts, &mut Timer::new("stitch maps"))
+}
+
 fn dump_json(path: String) {
     // Just try to deserialize as different formats
     if let Ok(map) =
@@ -370,6 +515,24 @@ fn random_scenario(rng_seed: u64, map: String, scenario_name: String) {
     );
 }
 
+fn add_incidents(path: String, input: String) -> Result<()> {
+    let mut timer = Timer::new("add incidents");
+    let mut scenario: synthpop::Scenario = abstio::read_binary(path.clone(), &mut timer);
+    let map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    let incidents: Vec<synthpop::ScheduledIncident> = abstio::maybe_read_json(input, &mut timer)?;
+    for incident in &incidents {
+        incident.check(&map)?;
+    }
+    println!(
+        "Adding {} incidents to {}",
+        incidents.len(),
+        scenario.scenario_name
+    );
+    scenario.incidents.extend(incidents);
+    abstio::write_binary(path, &scenario);
+    Ok(())
+}
+
 fn import_json_map(input: String, output: String) {
     // TODO This can't handle the output of dump_map! What?!
     let mut map: map_model::Map = abstio::read_json(input, &mut Timer::throwaway());
@@ -377,6 +540,14 @@ fn import_json_map(input: String, output: String) {
     abstio::write_binary(output, &map);
 }
 
+fn import_curb_regulations(input: String, map: String) -> Result<()> {
+    let mut timer = Timer::new("import curb regulations");
+    let mut map = map_model::Map::load_synchronously(map, &mut timer);
+    importer::import_curb_regulations(&mut map, input, &mut timer)?;
+    map.save();
+    Ok(())
+}
+
 fn minify_map(path: String) {
     let mut timer = Timer::new("minify map");
     let mut map = map_model::Map::load_synchronously(path, &mut timer);
@@ -406,9 +577,12 @@ fn regenerate_everything_externally() -> Result<()> {
     Ok(())
 }
 
-fn prebake_scenario(path: String) {
+fn prebake_scenario(path: String, num_seeds: usize) {
     let mut timer = Timer::new("prebake scenario");
     let scenario: synthpop::Scenario = abstio::must_read_object(path, &mut timer);
     let map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    if num_seeds > 1 {
+        sim::prebake::prebake_multiple_seeds(&map, &scenario, num_seeds, &mut timer);
+    }
     sim::prebake::prebake(&map, scenario, &mut timer);
 }
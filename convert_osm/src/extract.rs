@@ -28,6 +28,8 @@ pub struct OsmExtract {
     pub amenities: Vec<(Pt2D, Amenity)>,
     /// Crosswalks located at these points, which should be on a RawRoad's center line
     pub crosswalks: HashSet<HashablePt2D>,
+    /// Sidewalks mapped as separate ways, if `Options::separate_sidewalks` is set
+    pub separate_sidewalks: Vec<(WayID, Vec<Pt2D>, Tags)>,
 }
 
 pub fn extract_osm(
@@ -66,6 +68,7 @@ pub fn extract_osm(
         complicated_turn_restrictions: Vec::new(),
         amenities: Vec::new(),
         crosswalks: HashSet::new(),
+        separate_sidewalks: Vec::new(),
     };
 
     timer.start_iter("processing OSM nodes", doc.nodes.len());
@@ -101,6 +104,22 @@ pub fn extract_osm(
 
         way.tags.insert(osm::OSM_WAY_ID, id.0.to_string());
 
+        if opts.separate_sidewalks && way.tags.is_any(osm::HIGHWAY, vec!["footway", "path"]) {
+            if way.tags.is("footway", "sidewalk") {
+                out.separate_sidewalks
+                    .push((id, way.pts.clone(), way.tags.clone()));
+                continue;
+            }
+            if way.tags.is("footway", "crossing") {
+                // A crossing way shares a node with the road it crosses. Treat that node like a
+                // highway=crossing node; points not on a road will be ignored later. The way is
+                // still imported like any other footway, so paths meeting it stay connected.
+                for pt in &way.pts {
+                    out.crosswalks.insert(pt.to_hashable());
+                }
+            }
+        }
+
         if is_road(&mut way.tags, opts, &map.name) {
             // TODO Hardcoding these overrides. OSM is correct, these don't have
             // sidewalks; there's a crosswalk mapped. But until we can snap sidewalks properly, do
@@ -20,6 +20,7 @@ mod gtfs;
 pub mod osm_geom;
 mod parking;
 pub mod reader;
+mod sidewalks;
 mod split_ways;
 
 /// Configures the creation of a RawMap from OSM and other input data.
@@ -36,8 +37,17 @@ pub struct Options {
     /// Only include highways and arterials. This may make sense for some region-wide maps for
     /// particular use cases.
     pub skip_local_roads: bool,
-    /// Only include crosswalks that match a `highway=crossing` OSM node.
+    /// Only include crosswalks that match a `highway=crossing` OSM node, or a node shared with a
+    /// `footway=crossing` way when `separate_sidewalks` is set.
     pub filter_crosswalks: bool,
+    /// Match sidewalks mapped as separate `footway=sidewalk` ways to the roads they run alongside,
+    /// instead of ignoring them or importing them as standalone paths. Footways that ended on one
+    /// of these sidewalks are extended to the road. `footway=crossing` ways are also used to find
+    /// crosswalks.
+    pub separate_sidewalks: bool,
+    /// Split roads at `highway=crossing` nodes that aren't at an intersection, so pedestrians can
+    /// cross mid-block. Otherwise, these crossings are snapped to the nearest intersection.
+    pub mid_block_crossings: bool,
     /// Configure public transit using this URL to a static GTFS feed in .zip format.
     pub gtfs_url: Option<String>,
     pub elevation: bool,
@@ -94,8 +104,13 @@ pub fn convert(
         map.gps_bounds = gps_bounds;
     }
 
-    let extract = extract::extract_osm(&mut map, &osm_input_path, clip_path, &opts, timer);
-    let split_output = split_ways::split_up_roads(&mut map, extract, timer);
+    let mut extract = extract::extract_osm(&mut map, &osm_input_path, clip_path, &opts, timer);
+    let separate_sidewalks = std::mem::take(&mut extract.separate_sidewalks);
+    if opts.separate_sidewalks {
+        sidewalks::connect_footways(&mut extract, &separate_sidewalks, &map.gps_bounds);
+    }
+    let split_output =
+        split_ways::split_up_roads(&mut map, extract, opts.mid_block_crossings, timer);
     clip::clip_map(&mut map, timer);
 
     // Need to do a first pass of removing cul-de-sacs here, or we wind up with loop PolyLines when
@@ -104,6 +119,10 @@ pub fn convert(
 
     use_amenities(&mut map, split_output.amenities, timer);
 
+    if opts.separate_sidewalks {
+        sidewalks::snap_separate_sidewalks(&mut map, separate_sidewalks, &opts.map_config, timer);
+    }
+
     parking::apply_parking(&mut map, &opts, timer);
 
     if opts.elevation {
@@ -185,10 +204,11 @@ fn filter_crosswalks(
     timer: &mut Timer,
 ) {
     // Normally we assume every road has a crosswalk, but since this map is configured to use OSM
-    // crossing nodes, let's reverse that assumption.
+    // crossing nodes, let's reverse that assumption. Roads split at a mid-block crossing are
+    // already marked.
     for road in map.roads.values_mut() {
-        road.crosswalk_forward = false;
-        road.crosswalk_backward = false;
+        road.crosswalk_forward = road.osm_tags.contains_key(osm::CROSSING_FWD);
+        road.crosswalk_backward = road.osm_tags.contains_key(osm::CROSSING_BACK);
     }
 
     // Match each crosswalk node to a road
@@ -205,9 +225,9 @@ fn filter_crosswalks(
                 if let Some((dist, _)) = pl.dist_along_of_point(pt.to_pt2d()) {
                     let pct = dist / pl.length();
                     // Don't throw away any crossings. If it occurs in the first half of the road,
-                    // snap to the first intersection. If there's a mid-block crossing mapped,
-                    // that'll likely not be correctly interpreted, unless an intersection is there
-                    // anyway.
+                    // snap to the first intersection. Mid-block crossings are only split into
+                    // their own intersection when mid_block_crossings is enabled and they're far
+                    // enough from other intersections.
                     if pct <= 0.5 {
                         road.crosswalk_backward = true;
                     } else {
@@ -0,0 +1,416 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use abstutil::{Counter, Tags, Timer};
+use geom::{Distance, FindClosest, GPSBounds, HashablePt2D, Line, PolyLine, Pt2D};
+use raw_map::{osm, MapConfig, OriginalRoad, RawMap};
+
+use crate::extract::OsmExtract;
+
+/// How far away from a road's center line can a separate sidewalk be and still belong to it?
+const MAX_DIST_FROM_CENTER: Distance = Distance::const_meters(20.0);
+/// Sample points along each sidewalk way this far apart.
+const STEP_SIZE: Distance = Distance::const_meters(2.0);
+/// The sidewalk has to run roughly alongside the road.
+const MAX_ANGLE_DIFF_DEGREES: f64 = 30.0;
+/// What fraction of a road side has to be matched to a separate sidewalk to count it?
+const MIN_PCT_MATCHED: f64 = 0.3;
+
+/// Ways tagged `footway=sidewalk` represent sidewalks that're mapped separately from the road they
+/// run alongside. Match each of them to the nearest parallel side of a road, then turn that into
+/// tags on the road, so that the road gets a sidewalk lane with the real width on that side.
+///
+/// Only roads without explicit sidewalk tagging (no tag at all, an inferred value, or
+/// `sidewalk=separate`) are changed. Once any separate sidewalk is matched to one of those roads,
+/// the separate data is trusted for both sides of it.
+pub fn snap_separate_sidewalks(
+    map: &mut RawMap,
+    sidewalks: Vec<(osm::WayID, Vec<Pt2D>, Tags)>,
+    cfg: &MapConfig,
+    timer: &mut Timer,
+) {
+    let mut closest: FindClosest<OriginalRoad> = FindClosest::new(&map.gps_bounds.to_bounds());
+    let mut center_lines: HashMap<OriginalRoad, PolyLine> = HashMap::new();
+    for (id, r) in &map.roads {
+        if r.is_light_rail() || r.is_footway() || !has_implicit_sidewalks(&r.osm_tags) {
+            continue;
+        }
+        if let Ok(pl) = PolyLine::new(r.osm_center_points.clone()) {
+            closest.add(*id, pl.points());
+            center_lines.insert(*id, pl);
+        }
+    }
+
+    // For each side of a road (true for forwards, the right side), how much of it is matched, and
+    // what's the widest sidewalk along it?
+    let mut matches: BTreeMap<(OriginalRoad, bool), (Distance, Option<Distance>)> = BTreeMap::new();
+    timer.start_iter("match separate sidewalks to roads", sidewalks.len());
+    for (id, pts, tags) in sidewalks {
+        timer.next();
+        let pl = match PolyLine::deduping_new(pts) {
+            Ok(pl) => pl,
+            Err(err) => {
+                warn!("Skipping separate sidewalk {}: {}", id, err);
+                continue;
+            }
+        };
+        let width = tags.get("width").and_then(|x| parse_width(x));
+
+        for (pt, angle) in pl.step_along(STEP_SIZE, Distance::ZERO) {
+            let mut best: Option<(OriginalRoad, bool, Distance)> = None;
+            for (road, road_pt, dist) in closest.all_close_pts(pt, MAX_DIST_FROM_CENTER) {
+                if best.map(|(_, _, d)| dist >= d).unwrap_or(false) {
+                    continue;
+                }
+                let center = &center_lines[&road];
+                let road_angle = match center.dist_along_of_point(road_pt) {
+                    Some((_, angle)) => angle,
+                    None => continue,
+                };
+                if !road_angle.approx_parallel(angle, MAX_ANGLE_DIFF_DEGREES) {
+                    continue;
+                }
+                // Which side of the road is the sample point on? Compare to the center line
+                // shifted over to each side.
+                let fwds = match (center.shift_right(dist), center.shift_left(dist)) {
+                    (Ok(right), Ok(left)) => {
+                        right.project_pt(pt).dist_to(pt) < left.project_pt(pt).dist_to(pt)
+                    }
+                    _ => continue,
+                };
+                best = Some((road, fwds, dist));
+            }
+
+            if let Some((road, fwds, _)) = best {
+                let entry = matches
+                    .entry((road, fwds))
+                    .or_insert((Distance::ZERO, None));
+                entry.0 += STEP_SIZE;
+                if let Some(w) = width {
+                    entry.1 = Some(entry.1.map(|x| x.max(w)).unwrap_or(w));
+                }
+            }
+        }
+    }
+
+    // Only keep road sides that're mostly matched
+    matches.retain(|(road, _), (matched_length, _)| {
+        *matched_length >= MIN_PCT_MATCHED * center_lines[road].length()
+    });
+    let roads: BTreeSet<OriginalRoad> = matches.keys().map(|(road, _)| *road).collect();
+
+    info!("Matched separate sidewalks to {} roads", roads.len());
+    for id in roads {
+        let right = matches.get(&(id, true)).map(|(_, width)| *width);
+        let left = matches.get(&(id, false)).map(|(_, width)| *width);
+
+        let road = map.roads.get_mut(&id).unwrap();
+        let tags = &mut road.osm_tags;
+        tags.remove(osm::INFERRED_SIDEWALKS);
+        tags.insert(
+            osm::SIDEWALK,
+            match (right.is_some(), left.is_some()) {
+                (true, true) => "both",
+                (true, false) => "right",
+                (false, true) => "left",
+                (false, false) => unreachable!(),
+            },
+        );
+        for (key, width) in [
+            ("sidewalk:right:width", right),
+            ("sidewalk:left:width", left),
+        ] {
+            if let Some(Some(w)) = width {
+                tags.insert(key, w.inner_meters().to_string());
+            } else {
+                tags.remove(key);
+            }
+        }
+        road.lane_specs_ltr = raw_map::get_lane_specs_ltr(&road.osm_tags, cfg);
+    }
+}
+
+/// Separate sidewalks don't become paths, so other footways that ended on one would be left
+/// dangling. Extend each of these to the closest point on a nearby road, which'll split the road
+/// there, so the footway meets the road's sidewalk lanes at an intersection. This has to happen
+/// before roads are split. Crossings aren't extended, since they already meet the road they cross.
+pub fn connect_footways(
+    extract: &mut OsmExtract,
+    sidewalks: &[(osm::WayID, Vec<Pt2D>, Tags)],
+    gps_bounds: &GPSBounds,
+) {
+    let sidewalk_pts: HashSet<HashablePt2D> = sidewalks
+        .iter()
+        .flat_map(|(_, pts, _)| pts.iter().map(|pt| pt.to_hashable()))
+        .collect();
+    let mut counts_per_pt = Counter::new();
+    for (_, r) in &extract.roads {
+        for pt in &r.osm_center_points {
+            counts_per_pt.inc(pt.to_hashable());
+        }
+    }
+
+    let mut closest: FindClosest<usize> = FindClosest::new(&gps_bounds.to_bounds());
+    for (idx, (_, r)) in extract.roads.iter().enumerate() {
+        if !r.is_footway() && !r.is_light_rail() {
+            closest.add(idx, &r.osm_center_points);
+        }
+    }
+
+    // (footway, extend the start or end, road, point on the road)
+    let mut connections: Vec<(usize, bool, usize, Pt2D)> = Vec::new();
+    for (idx, (_, r)) in extract.roads.iter().enumerate() {
+        if !r.is_footway() || r.osm_tags.is("footway", "crossing") {
+            continue;
+        }
+        let pts = &r.osm_center_points;
+        for (start, pt) in [(true, pts[0]), (false, *pts.last().unwrap())] {
+            // If something else meets the footway here, it's still connected
+            if !sidewalk_pts.contains(&pt.to_hashable()) || counts_per_pt.get(pt.to_hashable()) > 1
+            {
+                continue;
+            }
+            if let Some((road, road_pt, _)) = closest
+                .all_close_pts(pt, MAX_DIST_FROM_CENTER)
+                .into_iter()
+                .min_by_key(|(_, _, dist)| *dist)
+            {
+                connections.push((idx, start, road, road_pt));
+            }
+        }
+    }
+
+    info!(
+        "Extending {} footways that ended on separate sidewalks",
+        connections.len()
+    );
+    // Don't collide with real OSM node IDs
+    let mut next_id = extract
+        .osm_node_ids
+        .values()
+        .map(|id| id.0)
+        .min()
+        .unwrap_or(0)
+        .min(0)
+        - 1;
+    for (footway, start, road, pt) in connections {
+        let pt = insert_pt(&mut extract.roads[road].1.osm_center_points, pt);
+        if let Entry::Vacant(e) = extract.osm_node_ids.entry(pt.to_hashable()) {
+            e.insert(osm::NodeID(next_id));
+            next_id -= 1;
+        }
+        let pts = &mut extract.roads[footway].1.osm_center_points;
+        if start {
+            pts.insert(0, pt);
+        } else {
+            pts.push(pt);
+        }
+    }
+}
+
+/// Adds a point along a line string, unless it's very close to an existing point. Returns the
+/// point used.
+fn insert_pt(pts: &mut Vec<Pt2D>, pt: Pt2D) -> Pt2D {
+    if let Some(existing) = pts.iter().find(|x| x.approx_eq(pt, Distance::meters(0.1))) {
+        return *existing;
+    }
+    for idx in 0..pts.len() - 1 {
+        if let Ok(line) = Line::new(pts[idx], pts[idx + 1]) {
+            if line.contains_pt(pt) {
+                pts.insert(idx + 1, pt);
+                return pt;
+            }
+        }
+    }
+    // The point should be on the line string, but if precision gets in the way, just use the
+    // closest existing point
+    *pts.iter().min_by_key(|x| x.dist_to(pt)).unwrap()
+}
+
+/// Do the tags on a road fail to say anything definite about sidewalks on the road itself?
+fn has_implicit_sidewalks(tags: &Tags) -> bool {
+    !tags.contains_key(osm::SIDEWALK)
+        || tags.contains_key(osm::INFERRED_SIDEWALKS)
+        || tags.is(osm::SIDEWALK, "separate")
+}
+
+/// Parses values like "2", "1.5 m", or "3m". Other units aren't supported.
+fn parse_width(value: &str) -> Option<Distance> {
+    let meters = value
+        .trim()
+        .trim_end_matches('m')
+        .trim()
+        .parse::<f64>()
+        .ok()?;
+    if meters > 0.0 {
+        Some(Distance::meters(meters))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use abstio::MapName;
+    use geom::LonLat;
+    use raw_map::RawRoad;
+
+    use super::*;
+
+    fn tags(kv: Vec<(&str, &str)>) -> Tags {
+        let mut tags = Tags::empty();
+        for (k, v) in kv {
+            tags.insert(k, v);
+        }
+        tags
+    }
+
+    fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Vec<Pt2D> {
+        vec![Pt2D::new(x1, y1), Pt2D::new(x2, y2)]
+    }
+
+    fn gps_bounds() -> GPSBounds {
+        GPSBounds::from(vec![LonLat::new(-122.31, 47.6), LonLat::new(-122.3, 47.61)])
+    }
+
+    /// One residential road heading east, with no sidewalk tags
+    fn map_with_road() -> (RawMap, OriginalRoad) {
+        let mut map = RawMap::blank(MapName::new("zz", "oneshot", "sidewalks"));
+        map.gps_bounds = gps_bounds();
+        let id = OriginalRoad::new(1, (10, 11));
+        map.roads.insert(
+            id,
+            RawRoad::new(
+                line(100.0, 100.0, 300.0, 100.0),
+                tags(vec![(osm::HIGHWAY, "residential")]),
+                &map.config,
+            ),
+        );
+        (map, id)
+    }
+
+    #[test]
+    fn test_parse_width() {
+        assert_eq!(parse_width("2"), Some(Distance::meters(2.0)));
+        assert_eq!(parse_width("1.5 m"), Some(Distance::meters(1.5)));
+        assert_eq!(parse_width(" 3m"), Some(Distance::meters(3.0)));
+        assert_eq!(parse_width("0"), None);
+        assert_eq!(parse_width("-1"), None);
+        assert_eq!(parse_width("6'"), None);
+        assert_eq!(parse_width("wide"), None);
+    }
+
+    #[test]
+    fn test_match_one_side() {
+        let (mut map, id) = map_with_road();
+        let cfg = map.config.clone();
+        // Map-space grows down, so this is on the right side of the road
+        let sidewalks = vec![(
+            osm::WayID(2),
+            line(110.0, 108.0, 290.0, 108.0),
+            tags(vec![
+                (osm::HIGHWAY, "footway"),
+                ("footway", "sidewalk"),
+                ("width", "2.5 m"),
+            ]),
+        )];
+        snap_separate_sidewalks(&mut map, sidewalks, &cfg, &mut Timer::throwaway());
+
+        let tags = &map.roads[&id].osm_tags;
+        assert_eq!(tags.get(osm::SIDEWALK), Some(&"right".to_string()));
+        assert_eq!(tags.get("sidewalk:right:width"), Some(&"2.5".to_string()));
+        assert!(!tags.contains_key("sidewalk:left:width"));
+    }
+
+    #[test]
+    fn test_match_both_sides() {
+        let (mut map, id) = map_with_road();
+        let cfg = map.config.clone();
+        let sidewalk_tags = tags(vec![(osm::HIGHWAY, "footway"), ("footway", "sidewalk")]);
+        let sidewalks = vec![
+            (
+                osm::WayID(2),
+                line(110.0, 108.0, 290.0, 108.0),
+                sidewalk_tags.clone(),
+            ),
+            // Drawn in the opposite direction
+            (osm::WayID(3), line(290.0, 92.0, 110.0, 92.0), sidewalk_tags),
+        ];
+        snap_separate_sidewalks(&mut map, sidewalks, &cfg, &mut Timer::throwaway());
+
+        assert_eq!(
+            map.roads[&id].osm_tags.get(osm::SIDEWALK),
+            Some(&"both".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ignore_short_or_perpendicular_sidewalks() {
+        let (mut map, id) = map_with_road();
+        let cfg = map.config.clone();
+        let sidewalk_tags = tags(vec![(osm::HIGHWAY, "footway"), ("footway", "sidewalk")]);
+        let sidewalks = vec![
+            // Too little of the road
+            (
+                osm::WayID(2),
+                line(110.0, 108.0, 130.0, 108.0),
+                sidewalk_tags.clone(),
+            ),
+            // Runs away from the road
+            (osm::WayID(3), line(200.0, 92.0, 200.0, 20.0), sidewalk_tags),
+        ];
+        snap_separate_sidewalks(&mut map, sidewalks, &cfg, &mut Timer::throwaway());
+
+        assert!(!map.roads[&id].osm_tags.contains_key(osm::SIDEWALK));
+    }
+
+    #[test]
+    fn test_connect_footways() {
+        let cfg = RawMap::blank(MapName::new("zz", "oneshot", "sidewalks")).config;
+        let sidewalk = line(100.0, 108.0, 300.0, 108.0);
+        let mut extract = OsmExtract {
+            roads: vec![
+                (
+                    osm::WayID(1),
+                    RawRoad::new(
+                        line(100.0, 100.0, 300.0, 100.0),
+                        tags(vec![(osm::HIGHWAY, "residential")]),
+                        &cfg,
+                    ),
+                ),
+                // Ends on the middle of the separate sidewalk
+                (
+                    osm::WayID(3),
+                    RawRoad::new(
+                        vec![Pt2D::new(200.0, 150.0), Pt2D::new(200.0, 108.0)],
+                        tags(vec![(osm::HIGHWAY, "footway")]),
+                        &cfg,
+                    ),
+                ),
+            ],
+            traffic_signals: HashMap::new(),
+            osm_node_ids: HashMap::new(),
+            simple_turn_restrictions: Vec::new(),
+            complicated_turn_restrictions: Vec::new(),
+            amenities: Vec::new(),
+            crosswalks: HashSet::new(),
+            separate_sidewalks: Vec::new(),
+        };
+        let mut sidewalk_pts = sidewalk.clone();
+        sidewalk_pts.insert(1, Pt2D::new(200.0, 108.0));
+        let sidewalks = vec![(
+            osm::WayID(2),
+            sidewalk_pts,
+            tags(vec![(osm::HIGHWAY, "footway"), ("footway", "sidewalk")]),
+        )];
+        connect_footways(&mut extract, &sidewalks, &gps_bounds());
+
+        let meet = Pt2D::new(200.0, 100.0);
+        assert_eq!(extract.roads[1].1.osm_center_points.last().unwrap(), &meet);
+        assert!(extract.roads[0].1.osm_center_points.contains(&meet));
+        assert_eq!(extract.roads[0].1.osm_center_points.len(), 3);
+        assert!(extract.osm_node_ids[&meet.to_hashable()].0 < 0);
+    }
+}
@@ -16,7 +16,16 @@ pub struct Output {
     pub pt_to_road: HashMap<HashablePt2D, OriginalRoad>,
 }
 
-pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer) -> Output {
+/// Crossing nodes closer than this to an intersection or another crossing are just treated like a
+/// crosswalk at the nearest intersection, rather than splitting the road.
+const MIN_DIST_TO_CROSSING: Distance = Distance::const_meters(15.0);
+
+pub fn split_up_roads(
+    map: &mut RawMap,
+    mut input: OsmExtract,
+    mid_block_crossings: bool,
+    timer: &mut Timer,
+) -> Output {
     timer.start("splitting up roads");
 
     let mut roundabout_centers: HashMap<osm::NodeID, Pt2D> = HashMap::new();
@@ -58,6 +67,18 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
         }
     }
 
+    // Split ways at crossing nodes that aren't already intersections, so pedestrians can cross
+    // there
+    let mut crossing_pts: HashSet<HashablePt2D> = HashSet::new();
+    if mid_block_crossings {
+        for (_, r) in &input.roads {
+            for pt in find_mid_block_crossings(r, &input.crosswalks, &pt_to_intersection) {
+                pt_to_intersection.insert(pt, input.osm_node_ids[&pt]);
+                crossing_pts.insert(pt);
+            }
+        }
+    }
+
     for (pt, id) in &pt_to_intersection {
         map.intersections.insert(
             *id,
@@ -89,6 +110,7 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
         let endpt1 = pt_to_intersection[&orig_road.osm_center_points[0].to_hashable()];
         let endpt2 = pt_to_intersection[&orig_road.osm_center_points.last().unwrap().to_hashable()];
         let mut i1 = endpt1;
+        let mut starts_at_crossing = false;
 
         for pt in &orig_road.osm_center_points {
             pts.push(*pt);
@@ -104,6 +126,15 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
                     r.osm_tags
                         .insert(osm::ENDPT_FWD.to_string(), "true".to_string());
                 }
+                if starts_at_crossing {
+                    r.osm_tags
+                        .insert(osm::CROSSING_BACK.to_string(), "true".to_string());
+                }
+                starts_at_crossing = crossing_pts.contains(&pt.to_hashable());
+                if starts_at_crossing {
+                    r.osm_tags
+                        .insert(osm::CROSSING_FWD.to_string(), "true".to_string());
+                }
                 let id = OriginalRoad {
                     osm_way_id: *osm_way_id,
                     i1,
@@ -122,6 +153,8 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
                 map.roads.insert(id, r.clone());
                 r.osm_tags.remove(osm::ENDPT_FWD);
                 r.osm_tags.remove(osm::ENDPT_BACK);
+                r.osm_tags.remove(osm::CROSSING_FWD);
+                r.osm_tags.remove(osm::CROSSING_BACK);
                 i1 = *i2;
                 pts.push(*pt);
             }
@@ -223,6 +256,45 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
     }
 }
 
+/// Find crossing nodes in the middle of a way, far enough from any intersection or other crossing
+/// to be worth splitting the way.
+fn find_mid_block_crossings(
+    r: &RawRoad,
+    crosswalks: &HashSet<HashablePt2D>,
+    pt_to_intersection: &HashMap<HashablePt2D, osm::NodeID>,
+) -> Vec<HashablePt2D> {
+    let pts = &r.osm_center_points;
+    let mut dist_along = vec![Distance::ZERO];
+    for pair in pts.windows(2) {
+        dist_along.push(*dist_along.last().unwrap() + pair[0].dist_to(pair[1]));
+    }
+
+    let mut result = Vec::new();
+    let mut last_split = Distance::ZERO;
+    for idx in 1..pts.len() - 1 {
+        let pt = pts[idx].to_hashable();
+        if pt_to_intersection.contains_key(&pt) {
+            last_split = dist_along[idx];
+            continue;
+        }
+        if !crosswalks.contains(&pt) {
+            continue;
+        }
+        // The last point of every way is an intersection
+        let next_split = (idx + 1..pts.len())
+            .find(|i| pt_to_intersection.contains_key(&pts[*i].to_hashable()))
+            .map(|i| dist_along[i])
+            .unwrap();
+        if dist_along[idx] - last_split >= MIN_DIST_TO_CROSSING
+            && next_split - dist_along[idx] >= MIN_DIST_TO_CROSSING
+        {
+            result.push(pt);
+            last_split = dist_along[idx];
+        }
+    }
+    result
+}
+
 // TODO Consider doing this in PolyLine::new always. Also in extend() -- it attempts to dedupe
 // angles.
 fn simplify_linestring(pts: Vec<Pt2D>) -> Vec<Pt2D> {
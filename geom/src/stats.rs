@@ -198,3 +198,81 @@ impl<T: HgramValue<T>> Histogram<T> {
         self.describe() == other.describe()
     }
 }
+
+/// Summarizes repeated measurements of the same thing, like a metric from simulations that only
+/// differ by their random seed. There are usually only a handful of measurements, so intervals use
+/// Student's t-distribution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampleSummary {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl SampleSummary {
+    /// Needs at least two samples to say anything about the spread.
+    pub fn new(samples: &[f64]) -> Option<SampleSummary> {
+        if samples.len() < 2 {
+            return None;
+        }
+        let count = samples.len();
+        let mean = samples.iter().sum::<f64>() / (count as f64);
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ((count - 1) as f64);
+        Some(SampleSummary {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+
+    /// Half the width of the 95% confidence interval for the mean.
+    pub fn confidence_margin(&self) -> f64 {
+        t_critical_95(self.count - 1) * self.std_dev / (self.count as f64).sqrt()
+    }
+
+    /// Is a single new measurement outside the 95% prediction interval? If not, its difference
+    /// from the mean is within the noise between runs.
+    pub fn is_significant(&self, x: f64) -> bool {
+        let margin =
+            t_critical_95(self.count - 1) * self.std_dev * (1.0 + 1.0 / (self.count as f64)).sqrt();
+        (x - self.mean).abs() > margin
+    }
+}
+
+/// Two-tailed critical values of Student's t-distribution at 95% confidence
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match degrees_of_freedom {
+        0 => f64::INFINITY,
+        1..=30 => TABLE[degrees_of_freedom - 1],
+        31..=60 => 2.0,
+        61..=120 => 1.98,
+        _ => 1.96,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_summary() {
+        assert_eq!(None, SampleSummary::new(&[5.0]));
+
+        let summary = SampleSummary::new(&[10.0, 12.0, 14.0]).unwrap();
+        assert_eq!(3, summary.count);
+        assert_eq!(12.0, summary.mean);
+        assert_eq!(2.0, summary.std_dev);
+        // t = 4.303 for 2 degrees of freedom
+        assert!((summary.confidence_margin() - 4.969).abs() < 0.001);
+        // The prediction interval is 12 +/- 9.937
+        assert!(!summary.is_significant(20.0));
+        assert!(summary.is_significant(22.0));
+        assert!(summary.is_significant(1.0));
+    }
+}
@@ -28,13 +28,17 @@ use abstio::MapName;
 use abstutil::{serialize_btreemap, Timer};
 use geom::{Distance, Duration, FindClosest, LonLat, Time};
 use map_model::{
-    CompressedMovementID, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map,
+    CompressedMovementID, ControlTrafficSignal, EditIntersectionControl, IntersectionID, Map,
     MovementID, PermanentMapEdits, RoadID, TurnID,
 };
 use sim::{
-    AgentID, AgentType, DelayCause, PersonID, Sim, SimFlags, SimOptions, TripID, VehicleType,
+    AgentID, AgentType, DelayCause, PersonID, Sim, SimFlags, SimOptions, TrajectoryFormat,
+    TrajectorySampling, TripID, VehicleType,
+};
+use synthpop::{
+    Conditions, ConditionsChange, ExternalPerson, Scenario, ScenarioModifier, ScheduledIncident,
+    TripMode,
 };
-use synthpop::{ExternalPerson, Scenario, ScenarioModifier, TripMode};
 
 lazy_static::lazy_static! {
     static ref MAP: RwLock<Map> = RwLock::new(Map::blank());
@@ -174,7 +178,7 @@ fn handle_command(
                 bail!("{} is in the past. call /sim/reset first?", t)
             } else {
                 let dt = t - sim.time();
-                sim.timed_step(map, dt, &mut None, &mut Timer::new("goto-time"));
+                sim.timed_step_with_incidents(map, dt, &mut None, &mut Timer::new("goto-time"));
                 Ok(format!("it's now {}", t))
             }
         }
@@ -199,6 +203,36 @@ fn handle_command(
                 sim.get_all_people().last().unwrap().id
             ))
         }
+        "/sim/add-incidents" => {
+            let input: Vec<ScheduledIncident> = abstutil::from_json(body)?;
+            // Check everything before scheduling anything
+            for incident in &input {
+                incident.check(map)?;
+                if incident.end <= sim.time() {
+                    bail!(
+                        "It's {} now, so you can't add an incident ending at {}",
+                        sim.time(),
+                        incident.end
+                    );
+                }
+            }
+            let num = input.len();
+            for incident in input {
+                sim.schedule_incident(incident);
+            }
+            Ok(format!("{} incidents scheduled", num))
+        }
+        "/sim/change-conditions" => {
+            let input: Vec<ConditionsChange> = abstutil::from_json(body)?;
+            if load.opts.conditions.is_some() {
+                bail!("The conditions were fixed by --conditions, so they can't change");
+            }
+            let num = input.len();
+            for change in input {
+                sim.schedule_conditions_change(change);
+            }
+            Ok(format!("{} changes in conditions scheduled", num))
+        }
         // Traffic signals
         "/traffic-signals/get" => {
             let i = IntersectionID(get("id")?.parse::<usize>()?);
@@ -215,11 +249,9 @@ fn handle_command(
             // incremental_edit_traffic_signal is the cheap option, but since we may need to call
             // get-edits later, go through the proper flow.
             let mut edits = map.get_edits().clone();
-            edits.commands.push(EditCmd::ChangeIntersection {
-                i: id,
-                old: map.get_i_edit(id),
-                new: EditIntersection::TrafficSignal(ts.export(map)),
-            });
+            edits.commands.push(map.edit_intersection_cmd(id, |new| {
+                new.control = EditIntersectionControl::TrafficSignal(ts.export(map));
+            }));
             map.must_apply_edits(edits, &mut Timer::throwaway());
             map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
 
@@ -229,8 +261,8 @@ fn handle_command(
             let i = map.get_i(IntersectionID(get("id")?.parse::<usize>()?));
             let t1 = Time::parse(get("t1")?)?;
             let t2 = Time::parse(get("t2")?)?;
-            if !i.is_traffic_signal() {
-                bail!("{} isn't a traffic signal", i.id);
+            if !map.is_signal_or_yield(i.id) {
+                bail!("{} isn't a traffic signal or yield-controlled", i.id);
             }
             let movements: Vec<&MovementID> = i.movements.keys().collect();
 
@@ -255,8 +287,8 @@ fn handle_command(
         }
         "/traffic-signals/get-cumulative-thruput" => {
             let i = map.get_i(IntersectionID(get("id")?.parse::<usize>()?));
-            if !i.is_traffic_signal() {
-                bail!("{} isn't a traffic signal", i.id);
+            if !map.is_signal_or_yield(i.id) {
+                bail!("{} isn't a traffic signal or yield-controlled", i.id);
             }
 
             let mut thruput = Throughput {
@@ -333,6 +365,17 @@ fn handle_command(
                 })
                 .collect(),
         })),
+        "/data/get-incidents" => Ok(abstutil::to_json(&Incidents {
+            incidents: sim
+                .get_incidents()
+                .into_iter()
+                .map(|(incident, active)| (incident.clone(), active))
+                .collect(),
+        })),
+        "/data/get-conditions" => Ok(abstutil::to_json(&CurrentConditions {
+            current: sim.get_conditions(),
+            timeline: sim.get_conditions_timeline().clone(),
+        })),
         "/data/get-road-thruput" => Ok(abstutil::to_json(&RoadThroughput {
             counts: sim
                 .get_analytics()
@@ -354,6 +397,24 @@ fn handle_command(
                 })
                 .collect(),
         })),
+        "/data/record-trajectories" => {
+            if sim.get_trajectories().is_some() {
+                bail!("Already recording trajectories");
+            }
+            let sampling = match params.get("every") {
+                Some(secs) => TrajectorySampling::Every(Duration::seconds(secs.parse::<f64>()?)),
+                None => TrajectorySampling::OnEnteringTraversable,
+            };
+            sim.record_trajectories(sampling);
+            Ok("recording trajectories".to_string())
+        }
+        "/data/get-trajectories" => {
+            let format = TrajectoryFormat::parse(get("format")?)?;
+            match sim.get_trajectories() {
+                Some(trajectories) => Ok(trajectories.export(format)),
+                None => bail!("Call /data/record-trajectories first"),
+            }
+        }
         "/data/trip-time-lower-bound" => {
             let id = TripID(get("id")?.parse::<usize>()?);
             let duration = sim.get_trip_time_lower_bound(map, id)?;
@@ -380,12 +441,12 @@ fn handle_command(
             let mut edits = map.get_edits().clone();
             edits.commands.clear();
             edits.compress(map);
-            Ok(abstutil::to_json(&edits.to_permanent(map)))
+            Ok(abstutil::to_json(&edits.to_permanent(map)?))
         }
         "/map/get-edit-road-command" => {
             let r = RoadID(get("id")?.parse::<usize>()?);
             Ok(abstutil::to_json(
-                &map.edit_road_cmd(r, |_| {}).to_perma(map),
+                &map.edit_road_cmd(r, |_| {}).to_perma(map)?,
             ))
         }
         "/map/get-intersection-geometry" => {
@@ -463,6 +524,19 @@ struct AgentPosition {
     distance_crossed: Distance,
 }
 
+#[derive(Serialize)]
+struct Incidents {
+    // (incident, is it happening right now)
+    incidents: Vec<(ScheduledIncident, bool)>,
+}
+
+#[derive(Serialize)]
+struct CurrentConditions {
+    current: Conditions,
+    /// Every change to the conditions scheduled so far, including ones that already happened
+    timeline: Vec<ConditionsChange>,
+}
+
 #[derive(Serialize)]
 struct RoadThroughput {
     // (road, agent type, hour since midnight, throughput for that one hour period)
@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use geojson::{GeoJson, Value};
+use serde::Deserialize;
+
+use abstutil::{prettyprint_usize, Counter, Timer};
+use geom::{Distance, FindClosest, LonLat, PolyLine, Pt2D, Time};
+use map_model::{CurbActivity, CurbRegulation, Map, RoadID, SideOfRoad};
+
+/// How far from a road's center line can a curb feature be and still belong to it?
+const MAX_DIST_FROM_CENTER: Distance = Distance::const_meters(20.0);
+const STEP_SIZE: Distance = Distance::const_meters(2.0);
+const MAX_ANGLE_DIFF_DEGREES: f64 = 30.0;
+
+/// Reads curb regulations from a GeoJSON file in either the CurbLR
+/// (<https://github.com/sharedstreets/curblr>) or Curb Data Specification
+/// (<https://github.com/openmobilityfoundation/curb-data-specification>) format, matches every
+/// feature to the nearest side of a road, and stores the regulations on the map's roads. Existing
+/// regulations are replaced.
+///
+/// The simulation doesn't model days of the week, so regulations only applying on weekends are
+/// skipped.
+pub fn import_curb_regulations(map: &mut Map, path: String, timer: &mut Timer) -> Result<()> {
+    let geojson: GeoJson = abstio::maybe_read_json(path.clone(), timer)?;
+    let features = match geojson {
+        GeoJson::FeatureCollection(fc) => fc.features,
+        _ => bail!("{} isn't a FeatureCollection", path),
+    };
+
+    let mut closest: FindClosest<RoadID> = FindClosest::new(map.get_bounds());
+    for r in map.all_roads() {
+        if r.is_driveable() {
+            closest.add(r.id, r.center_pts.points());
+        }
+    }
+
+    let mut regulations: BTreeMap<RoadID, Vec<(usize, CurbRegulation)>> = BTreeMap::new();
+    let mut unmatched = 0;
+    let mut skipped = 0;
+    timer.start_iter("match curb regulations to roads", features.len());
+    for feature in features {
+        timer.next();
+        let pts = match feature.geometry.map(|g| g.value) {
+            Some(Value::LineString(pts)) => pts,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let props = feature.properties.unwrap_or_default();
+        let rules: Vec<Rule> = if let Some(regs) = props.get("regulations") {
+            match serde_json::from_value::<Vec<CurbLRRegulation>>(regs.clone()) {
+                Ok(regs) => regs
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(idx, reg)| reg.into_rule(idx))
+                    .collect(),
+                Err(err) => {
+                    warn!("Skipping a feature with broken CurbLR regulations: {}", err);
+                    skipped += 1;
+                    continue;
+                }
+            }
+        } else if let Some(policies) = props.get("curb_policies") {
+            match serde_json::from_value::<Vec<CdsPolicy>>(policies.clone()) {
+                Ok(policies) => policies
+                    .into_iter()
+                    .flat_map(|policy| policy.into_rules())
+                    .collect(),
+                Err(err) => {
+                    warn!("Skipping a feature with broken curb policies: {}", err);
+                    skipped += 1;
+                    continue;
+                }
+            }
+        } else {
+            skipped += 1;
+            continue;
+        };
+        if rules.is_empty() {
+            skipped += 1;
+            continue;
+        }
+
+        let pts: Vec<Pt2D> = pts
+            .into_iter()
+            .map(|pt| LonLat::new(pt[0], pt[1]).to_pt(map.get_gps_bounds()))
+            .collect();
+        let (r, side, start, end) = match PolyLine::deduping_new(pts)
+            .ok()
+            .and_then(|pl| match_to_road(map, &closest, &pl))
+        {
+            Some(x) => x,
+            None => {
+                unmatched += 1;
+                continue;
+            }
+        };
+        for rule in rules {
+            regulations
+                .entry(r)
+                .or_insert_with(Vec::new)
+                .push((rule.priority, rule.into_regulation(side, start, end)));
+        }
+    }
+
+    info!(
+        "Matched curb regulations to {} roads. {} features couldn't be matched, and {} had no \
+         usable regulations.",
+        prettyprint_usize(regulations.len()),
+        prettyprint_usize(unmatched),
+        prettyprint_usize(skipped)
+    );
+    for r in map.all_roads().iter().map(|r| r.id).collect::<Vec<_>>() {
+        let mut list = regulations.remove(&r).unwrap_or_default();
+        // Stable, so the order in the file breaks ties
+        list.sort_by_key(|(priority, _)| *priority);
+        map.mut_road(r).curb_regulations = list.into_iter().map(|(_, reg)| reg).collect();
+    }
+    Ok(())
+}
+
+/// Find the side of a road that a curb feature runs alongside, and the range it covers along the
+/// road's center line.
+fn match_to_road(
+    map: &Map,
+    closest: &FindClosest<RoadID>,
+    pl: &PolyLine,
+) -> Option<(RoadID, SideOfRoad, Distance, Distance)> {
+    // Let every sample point vote
+    let mut votes: Counter<(RoadID, SideOfRoad)> = Counter::new();
+    let mut samples: Vec<(RoadID, SideOfRoad, Distance)> = Vec::new();
+    for (pt, angle) in pl.step_along(STEP_SIZE, Distance::ZERO) {
+        let mut best: Option<(RoadID, Pt2D, Distance)> = None;
+        for (r, road_pt, dist) in closest.all_close_pts(pt, MAX_DIST_FROM_CENTER) {
+            if best.map(|(_, _, d)| dist >= d).unwrap_or(false) {
+                continue;
+            }
+            let center = &map.get_r(r).center_pts;
+            match center.dist_along_of_point(road_pt) {
+                Some((_, road_angle))
+                    if road_angle.approx_parallel(angle, MAX_ANGLE_DIFF_DEGREES) =>
+                {
+                    best = Some((r, road_pt, dist));
+                }
+                _ => {}
+            }
+        }
+        if let Some((r, road_pt, _)) = best {
+            let center = &map.get_r(r).center_pts;
+            let (dist_along, road_angle) = center.dist_along_of_point(road_pt).unwrap();
+            // Which side of the road is the point on? Note map-space has Y growing downwards.
+            let (dx, dy) = (pt.x() - road_pt.x(), pt.y() - road_pt.y());
+            let rads = road_angle.normalized_radians();
+            let side = if rads.cos() * dy - rads.sin() * dx > 0.0 {
+                SideOfRoad::Right
+            } else {
+                SideOfRoad::Left
+            };
+            votes.inc((r, side));
+            samples.push((r, side, dist_along));
+        }
+    }
+
+    if votes.is_empty() {
+        return None;
+    }
+    let (r, side) = votes.max_key();
+    let dists: Vec<Distance> = samples
+        .into_iter()
+        .filter(|(r2, side2, _)| *r2 == r && *side2 == side)
+        .map(|(_, _, dist)| dist)
+        .collect();
+    let start = dists.iter().min().cloned()?;
+    let end = dists.iter().max().cloned()?;
+    Some((r, side, start, end))
+}
+
+/// A regulation from either format, before being matched to a road.
+struct Rule {
+    /// Lower is more important
+    priority: usize,
+    activity: CurbActivity,
+    time_windows: Vec<(Time, Time)>,
+}
+
+impl Rule {
+    fn into_regulation(self, side: SideOfRoad, start: Distance, end: Distance) -> CurbRegulation {
+        CurbRegulation {
+            side,
+            start,
+            end,
+            activity: self.activity,
+            time_windows: self.time_windows,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurbLRRegulation {
+    rule: CurbLRRule,
+    #[serde(default)]
+    user_classes: Vec<CurbLRUserClass>,
+    #[serde(default)]
+    time_spans: Vec<CurbLRTimeSpan>,
+}
+
+#[derive(Deserialize)]
+struct CurbLRRule {
+    activity: String,
+}
+
+#[derive(Deserialize)]
+struct CurbLRUserClass {
+    #[serde(default)]
+    classes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurbLRTimeSpan {
+    days_of_week: Option<CurbLRDays>,
+    #[serde(default)]
+    times_of_day: Vec<CurbLRTimeOfDay>,
+}
+
+#[derive(Deserialize)]
+struct CurbLRDays {
+    #[serde(default)]
+    days: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CurbLRTimeOfDay {
+    from: String,
+    to: String,
+}
+
+impl CurbLRRegulation {
+    /// CurbLR lists regulations in priority order.
+    fn into_rule(self, priority: usize) -> Option<Rule> {
+        let classes: Vec<String> = self
+            .user_classes
+            .into_iter()
+            .flat_map(|x| x.classes)
+            .collect();
+        let activity = classify(&self.rule.activity, &classes)?;
+        let mut spans = Vec::new();
+        for span in self.time_spans {
+            let days = span.days_of_week.map(|x| x.days).unwrap_or_default();
+            let times = span
+                .times_of_day
+                .into_iter()
+                .map(|x| (x.from, x.to))
+                .collect();
+            spans.push((days, times));
+        }
+        let time_windows = time_windows(spans)?;
+        Some(Rule {
+            priority,
+            activity,
+            time_windows,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CdsPolicy {
+    /// Lower numbers take precedence
+    #[serde(default)]
+    priority: usize,
+    rules: Vec<CdsRule>,
+    #[serde(default)]
+    time_spans: Vec<CdsTimeSpan>,
+}
+
+#[derive(Deserialize)]
+struct CdsRule {
+    activity: String,
+    #[serde(default)]
+    user_classes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CdsTimeSpan {
+    #[serde(default)]
+    days_of_week: Vec<String>,
+    time_of_day_start: Option<String>,
+    time_of_day_end: Option<String>,
+}
+
+impl CdsPolicy {
+    fn into_rules(self) -> Vec<Rule> {
+        let spans = self
+            .time_spans
+            .into_iter()
+            .map(|span| {
+                let times = match (span.time_of_day_start, span.time_of_day_end) {
+                    (Some(start), Some(end)) => vec![(start, end)],
+                    _ => Vec::new(),
+                };
+                (span.days_of_week, times)
+            })
+            .collect();
+        let time_windows = match time_windows(spans) {
+            Some(x) => x,
+            None => {
+                return Vec::new();
+            }
+        };
+        let priority = self.priority;
+        self.rules
+            .into_iter()
+            .filter_map(|rule| {
+                Some(Rule {
+                    priority,
+                    activity: classify(&rule.activity, &rule.user_classes)?,
+                    time_windows: time_windows.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Interprets an activity from either format. Returns `None` for activities that don't affect
+/// how the curb can be used for stopping or parking.
+fn classify(activity: &str, user_classes: &[String]) -> Option<CurbActivity> {
+    let classes: Vec<String> = user_classes.iter().map(|x| x.to_lowercase()).collect();
+    let for_transit = classes.iter().any(|x| x == "bus" || x == "transit");
+    let for_loading = classes.iter().any(|x| {
+        x.contains("commercial")
+            || x.contains("delivery")
+            || x.contains("freight")
+            || x.contains("truck")
+    });
+    match activity.to_lowercase().as_str() {
+        "parking" => Some(if for_transit {
+            CurbActivity::TransitStop
+        } else if for_loading {
+            CurbActivity::Loading
+        } else if classes.is_empty() {
+            CurbActivity::Parking
+        } else {
+            CurbActivity::RestrictedParking
+        }),
+        "loading" | "standing" | "stopping" => Some(if for_transit {
+            CurbActivity::TransitStop
+        } else {
+            CurbActivity::Loading
+        }),
+        "no parking" | "no standing" | "no stopping" | "travel" => Some(CurbActivity::NoStopping),
+        "no loading" | "no travel" => None,
+        x => {
+            warn!("Unknown curb activity {}", x);
+            None
+        }
+    }
+}
+
+/// Turns a list of (days of week, (from, to) times of day) spans into time windows. An empty list
+/// of windows means the regulation always applies. Returns `None` if the regulation never applies
+/// on weekdays.
+fn time_windows(spans: Vec<(Vec<String>, Vec<(String, String)>)>) -> Option<Vec<(Time, Time)>> {
+    if spans.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut windows = Vec::new();
+    let mut all_day = false;
+    for (days, times) in spans {
+        let on_weekdays = days.is_empty()
+            || days.iter().any(|day| {
+                let day = day.to_lowercase();
+                !day.starts_with("sa") && !day.starts_with("su")
+            });
+        if !on_weekdays {
+            continue;
+        }
+        if times.is_empty() {
+            all_day = true;
+        }
+        for (from, to) in times {
+            match (Time::parse(&from), Time::parse(&to)) {
+                (Ok(from), Ok(to)) => {
+                    windows.push((from, to));
+                }
+                _ => {
+                    warn!("Can't parse curb regulation times {} to {}", from, to);
+                }
+            }
+        }
+    }
+    if all_day {
+        Some(Vec::new())
+    } else if windows.is_empty() {
+        None
+    } else {
+        Some(windows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::Duration;
+
+    use super::*;
+
+    fn classes(list: &[&str]) -> Vec<String> {
+        list.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn hours(h: usize) -> Time {
+        Time::START_OF_DAY + Duration::hours(h)
+    }
+
+    fn span(days: &[&str], times: &[(&str, &str)]) -> (Vec<String>, Vec<(String, String)>) {
+        (
+            classes(days),
+            times
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("parking", &[]), Some(CurbActivity::Parking));
+        assert_eq!(classify("Parking", &[]), Some(CurbActivity::Parking));
+        assert_eq!(
+            classify("parking", &classes(&["permit"])),
+            Some(CurbActivity::RestrictedParking)
+        );
+        assert_eq!(
+            classify("parking", &classes(&["Commercial"])),
+            Some(CurbActivity::Loading)
+        );
+        assert_eq!(
+            classify("parking", &classes(&["bus"])),
+            Some(CurbActivity::TransitStop)
+        );
+        assert_eq!(classify("loading", &[]), Some(CurbActivity::Loading));
+        assert_eq!(
+            classify("stopping", &classes(&["transit"])),
+            Some(CurbActivity::TransitStop)
+        );
+        assert_eq!(classify("no standing", &[]), Some(CurbActivity::NoStopping));
+        assert_eq!(classify("travel", &[]), Some(CurbActivity::NoStopping));
+        assert_eq!(classify("no loading", &[]), None);
+        assert_eq!(classify("juggling", &[]), None);
+    }
+
+    #[test]
+    fn test_time_windows() {
+        // No spans at all means always
+        assert_eq!(time_windows(Vec::new()), Some(Vec::new()));
+        // Neither do spans without times
+        assert_eq!(
+            time_windows(vec![span(&["mo", "tu"], &[])]),
+            Some(Vec::new())
+        );
+
+        assert_eq!(
+            time_windows(vec![span(
+                &["mo", "fr", "sa"],
+                &[("08:00", "12:00"), ("14:00", "18:00")]
+            )]),
+            Some(vec![(hours(8), hours(12)), (hours(14), hours(18))])
+        );
+        assert_eq!(
+            time_windows(vec![span(&[], &[("22:00", "06:00")])]),
+            Some(vec![(hours(22), hours(6))])
+        );
+
+        // Weekends aren't modelled
+        assert_eq!(
+            time_windows(vec![span(&["Saturday", "sun"], &[("08:00", "18:00")])]),
+            None
+        );
+        assert_eq!(
+            time_windows(vec![
+                span(&["sa"], &[("08:00", "18:00")]),
+                span(&["mo"], &[("09:00", "17:00")])
+            ]),
+            Some(vec![(hours(9), hours(17))])
+        );
+
+        // Nothing usable left
+        assert_eq!(
+            time_windows(vec![span(&["mo"], &[("morning", "evening")])]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_curblr() {
+        let reg: CurbLRRegulation = serde_json::from_str(
+            r#"{
+                "rule": { "activity": "parking" },
+                "userClasses": [{ "classes": ["permit"] }],
+                "timeSpans": [{
+                    "daysOfWeek": { "days": ["mo", "tu", "we", "th", "fr"] },
+                    "timesOfDay": [{ "from": "07:00", "to": "09:00" }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let rule = reg.into_rule(3).unwrap();
+        assert_eq!(rule.priority, 3);
+        assert_eq!(rule.activity, CurbActivity::RestrictedParking);
+        assert_eq!(rule.time_windows, vec![(hours(7), hours(9))]);
+    }
+
+    #[test]
+    fn test_parse_cds() {
+        let policy: CdsPolicy = serde_json::from_str(
+            r#"{
+                "priority": 2,
+                "rules": [
+                    { "activity": "loading", "user_classes": ["truck"] },
+                    { "activity": "no loading" }
+                ],
+                "time_spans": [{
+                    "days_of_week": ["mon"],
+                    "time_of_day_start": "06:00",
+                    "time_of_day_end": "10:00"
+                }]
+            }"#,
+        )
+        .unwrap();
+        let rules = policy.into_rules();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].priority, 2);
+        assert_eq!(rules[0].activity, CurbActivity::Loading);
+        assert_eq!(rules[0].time_windows, vec![(hours(6), hours(10))]);
+    }
+}
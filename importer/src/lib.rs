@@ -9,6 +9,7 @@ extern crate anyhow;
 #[macro_use]
 extern crate log;
 
+use anyhow::Result;
 use structopt::StructOpt;
 
 use abstio::{CityName, MapName};
@@ -17,20 +18,24 @@ use geom::Distance;
 use map_model::RawToMapOptions;
 
 use self::configuration::{load_configuration, ImporterConfiguration};
+pub use self::curbs::import_curb_regulations;
 pub use self::pick_geofabrik::pick_geofabrik;
+pub use self::stitch::stitch_maps;
 
 mod basemap;
 mod berlin;
 mod configuration;
+mod curbs;
 mod map_config;
 mod pick_geofabrik;
 mod seattle;
 mod soundcast;
+mod stitch;
 mod uk;
 mod utils;
 
 /// Regenerate all maps and scenarios from scratch.
-pub async fn regenerate_everything(shard_num: usize, num_shards: usize) {
+pub async fn regenerate_everything(shard_num: usize, num_shards: usize) -> Result<()> {
     // Discover all cities by looking at config. But always operate on Seattle first. Special
     // treatment ;)
     let mut all_cities = CityName::list_all_cities_from_importer_config();
@@ -41,9 +46,10 @@ pub async fn regenerate_everything(shard_num: usize, num_shards: usize) {
     for (cnt, city) in all_cities.into_iter().enumerate() {
         if cnt % num_shards == shard_num {
             let job = Job::full_for_city(city);
-            job.run(&mut timer).await;
+            job.run(&mut timer).await?;
         }
     }
+    Ok(())
 }
 
 /// Transforms a .osm file to a map in one step.
@@ -82,6 +88,8 @@ pub async fn oneshot(
             extra_buildings: None,
             skip_local_roads: false,
             filter_crosswalks,
+            separate_sidewalks: false,
+            mid_block_crossings: false,
             gtfs_url: None,
             elevation: false,
         },
@@ -187,7 +195,7 @@ impl Job {
         flags
     }
 
-    pub async fn run(self, timer: &mut Timer<'_>) {
+    pub async fn run(self, timer: &mut Timer<'_>) -> Result<()> {
         if !self.osm_to_raw && !self.raw_to_map && !self.scenario && !self.city_overview {
             println!(
                 "Nothing to do! Pass some combination of --raw, --map, --scenario, or --city_overview"
@@ -196,6 +204,8 @@ impl Job {
         }
 
         let config: ImporterConfiguration = load_configuration();
+        // Fail fast if the city's config file is invalid
+        map_config::CityConfig::load(&self.city)?;
 
         timer.start(format!("import {}", self.city.describe()));
         let names = if let Some(n) = self.only_map {
@@ -220,7 +230,7 @@ impl Job {
                 &mut built_raw_huge_seattle,
                 &mut built_map_huge_seattle,
             )
-            .await;
+            .await?;
             // Just assume --raw has been called...
             let shapes: kml::ExtraShapes =
                 abstio::read_binary(CityName::seattle().input_path("zoning_parcels.bin"), timer);
@@ -235,7 +245,7 @@ impl Job {
             if self.osm_to_raw
                 && (!built_raw_huge_seattle || name != MapName::seattle("huge_seattle"))
             {
-                let raw = utils::osm_to_raw(name.clone(), timer, &config).await;
+                let raw = utils::osm_to_raw(name.clone(), timer, &config).await?;
 
                 // The collision data will only cover one part of London, since we don't have a
                 // region-wide map there yet
@@ -253,7 +263,8 @@ impl Job {
                 {
                     map_model::Map::load_synchronously(name.path(), timer)
                 } else {
-                    utils::raw_to_map(&name, self.opts.clone(), timer)
+                    let opts = map_config::raw_to_map_options(&name, self.opts.clone())?;
+                    utils::raw_to_map(&name, opts, timer)
                 };
 
                 // Another strange step in the pipeline.
@@ -271,13 +282,20 @@ impl Job {
                     map.save();
                 }
 
+                if let Some(path) = map_config::curb_regulations_path(&name)? {
+                    timer.start(format!("import curb regulations for {}", name.describe()));
+                    curbs::import_curb_regulations(&mut map, path, timer)?;
+                    timer.stop(format!("import curb regulations for {}", name.describe()));
+
+                    map.save();
+                }
+
                 if name == MapName::new("br", "sao_paulo", "sao_miguel_paulista") {
                     basemap::override_sidewalk_widths(
                         &mut map,
                         abstio::path("system/proposals/smp_sidewalk_basemap.json"),
                         timer,
-                    )
-                    .unwrap();
+                    )?;
 
                     map.save();
                 }
@@ -341,9 +359,7 @@ impl Job {
                         map.minify_buildings(timer);
                         map.save();
                     } else {
-                        uk::generate_scenario(maybe_map.as_ref().unwrap(), &config, timer)
-                            .await
-                            .unwrap();
+                        uk::generate_scenario(maybe_map.as_ref().unwrap(), &config, timer).await?;
                     }
                 }
             }
@@ -369,5 +385,6 @@ impl Job {
         }
 
         timer.stop(format!("import {}", self.city.describe()));
+        Ok(())
     }
 }
@@ -1,14 +1,292 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Deserialize;
+
 use abstio::{CityName, MapName};
 use geom::Distance;
-use map_model::DrivingSide;
+use map_model::{DrivingSide, RawToMapOptions};
+
+/// Given the name of a map, configure its import. The defaults hardcoded in
+/// `default_config_for_map` are overridden by anything in the city's config file; see
+/// `CityConfig`.
+pub fn config_for_map(name: &MapName) -> Result<convert_osm::Options> {
+    let mut opts = default_config_for_map(name);
+    CityConfig::load(&name.city)?
+        .overrides_for(name)
+        .apply_to_convert_osm(&name.city, &mut opts);
+    Ok(opts)
+}
+
+/// Options for converting a RawMap to a Map. Anything set in the city's config file is used, but
+/// flags passed on the command line always take effect.
+pub fn raw_to_map_options(name: &MapName, cli: RawToMapOptions) -> Result<RawToMapOptions> {
+    let mut opts = RawToMapOptions::default();
+    CityConfig::load(&name.city)?
+        .overrides_for(name)
+        .apply_to_raw_to_map(&mut opts);
+    opts.skip_ch |= cli.skip_ch;
+    opts.consolidate_all_intersections |= cli.consolidate_all_intersections;
+    opts.keep_bldg_tags |= cli.keep_bldg_tags;
+    Ok(opts)
+}
+
+/// The path to a file with curb regulations for the map, if the city's config file has one.
+pub fn curb_regulations_path(name: &MapName) -> Result<Option<String>> {
+    Ok(CityConfig::load(&name.city)?
+        .overrides_for(name)
+        .curb_regulations
+        .map(|path| name.city.input_path(path)))
+}
+
+/// Optionally read from `importer/config/$country/$city/config.json`, next to the clipping
+/// polygons for every map in the city. This describes how to import the city, without having to
+/// recompile. Every field is optional, falling back to the hardcoded defaults.
+///
+/// The file lives with the rest of the importer config, not in `data/input/$country/$city/`,
+/// because the updater deletes anything there that isn't in the data manifest.
+///
+/// An example:
+///
+/// ```json
+/// {
+///   "city": {
+///     "driving_side": "Left",
+///     "street_parking_spot_length_meters": 6.5,
+///     "onstreet_parking": { "SomeAdditionalWhereNoData": { "pct": 50 } }
+///   },
+///   "maps": {
+///     "center": { "gtfs_url": "https://example.com/gtfs.zip", "skip_ch": true }
+///   }
+/// }
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CityConfig {
+    /// Applies to every map in the city
+    pub city: ImportOverrides,
+    /// Keyed by map name. Takes precedence over `city`.
+    pub maps: BTreeMap<String, ImportOverrides>,
+}
+
+/// Every field corresponds to something in `MapConfig`, `convert_osm::Options`, or
+/// `RawToMapOptions`, except for `curb_regulations`, a CurbLR or Curb Data Specification GeoJSON
+/// file applied to the finished map. Paths are relative to the city's input directory,
+/// `data/input/$country/$city/`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImportOverrides {
+    pub driving_side: Option<DrivingSide>,
+    pub bikes_can_use_bus_lanes: Option<bool>,
+    pub inferred_sidewalks: Option<bool>,
+    pub street_parking_spot_length_meters: Option<f64>,
+    pub turn_on_red: Option<bool>,
+
+    pub onstreet_parking: Option<convert_osm::OnstreetParking>,
+    pub public_offstreet_parking: Option<convert_osm::PublicOffstreetParking>,
+    pub private_offstreet_parking: Option<convert_osm::PrivateOffstreetParking>,
+    pub include_railroads: Option<bool>,
+    pub extra_buildings: Option<String>,
+    pub skip_local_roads: Option<bool>,
+    pub filter_crosswalks: Option<bool>,
+    pub separate_sidewalks: Option<bool>,
+    pub mid_block_crossings: Option<bool>,
+    pub gtfs_url: Option<String>,
+    pub elevation: Option<bool>,
+
+    pub skip_ch: Option<bool>,
+    pub consolidate_all_intersections: Option<bool>,
+    pub keep_bldg_tags: Option<bool>,
+
+    pub curb_regulations: Option<String>,
+}
+
+impl CityConfig {
+    pub fn path(city: &CityName) -> String {
+        format!("importer/config/{}/{}/config.json", city.country, city.city)
+    }
+
+    /// Reads and validates the config file for a city. If there's no file, nothing is overridden.
+    pub fn load(city: &CityName) -> Result<CityConfig> {
+        let path = CityConfig::path(city);
+        if !abstio::file_exists(&path) {
+            return Ok(CityConfig::default());
+        }
+        let config: CityConfig = serde_json::from_slice(&abstio::slurp_file(&path)?)
+            .map_err(|err| anyhow!("{} is invalid: {}", path, err))?;
+        config
+            .validate(city)
+            .map_err(|err| anyhow!("{} is invalid: {}", path, err))?;
+        Ok(config)
+    }
+
+    fn validate(&self, city: &CityName) -> Result<()> {
+        let all_maps: Vec<String> = city
+            .list_all_maps_in_city_from_importer_config()
+            .into_iter()
+            .map(|name| name.map)
+            .collect();
+        for map in self.maps.keys() {
+            if !all_maps.contains(map) {
+                bail!("maps.{} doesn't have a clipping polygon", map);
+            }
+        }
+        for overrides in std::iter::once(&self.city).chain(self.maps.values()) {
+            overrides.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Merges the city-wide and map-specific overrides.
+    pub fn overrides_for(&self, name: &MapName) -> ImportOverrides {
+        let mut result = self.city.clone();
+        if let Some(overrides) = self.maps.get(&name.map) {
+            result = result.merge(overrides.clone());
+        }
+        result
+    }
+}
+
+impl ImportOverrides {
+    fn validate(&self) -> Result<()> {
+        if let Some(x) = self.street_parking_spot_length_meters {
+            if !x.is_finite() || x <= 0.0 {
+                bail!(
+                    "street_parking_spot_length_meters must be positive, not {}",
+                    x
+                );
+            }
+        }
+        if let Some(convert_osm::OnstreetParking::SomeAdditionalWhereNoData { pct }) =
+            self.onstreet_parking
+        {
+            if pct > 100 {
+                bail!("onstreet_parking pct must be in [0, 100], not {}", pct);
+            }
+        }
+        Ok(())
+    }
+
+    /// Anything set in `other` wins.
+    fn merge(self, other: ImportOverrides) -> ImportOverrides {
+        ImportOverrides {
+            driving_side: other.driving_side.or(self.driving_side),
+            bikes_can_use_bus_lanes: other
+                .bikes_can_use_bus_lanes
+                .or(self.bikes_can_use_bus_lanes),
+            inferred_sidewalks: other.inferred_sidewalks.or(self.inferred_sidewalks),
+            street_parking_spot_length_meters: other
+                .street_parking_spot_length_meters
+                .or(self.street_parking_spot_length_meters),
+            turn_on_red: other.turn_on_red.or(self.turn_on_red),
+            onstreet_parking: other.onstreet_parking.or(self.onstreet_parking),
+            public_offstreet_parking: other
+                .public_offstreet_parking
+                .or(self.public_offstreet_parking),
+            private_offstreet_parking: other
+                .private_offstreet_parking
+                .or(self.private_offstreet_parking),
+            include_railroads: other.include_railroads.or(self.include_railroads),
+            extra_buildings: other.extra_buildings.or(self.extra_buildings),
+            skip_local_roads: other.skip_local_roads.or(self.skip_local_roads),
+            filter_crosswalks: other.filter_crosswalks.or(self.filter_crosswalks),
+            separate_sidewalks: other.separate_sidewalks.or(self.separate_sidewalks),
+            mid_block_crossings: other.mid_block_crossings.or(self.mid_block_crossings),
+            gtfs_url: other.gtfs_url.or(self.gtfs_url),
+            elevation: other.elevation.or(self.elevation),
+            skip_ch: other.skip_ch.or(self.skip_ch),
+            consolidate_all_intersections: other
+                .consolidate_all_intersections
+                .or(self.consolidate_all_intersections),
+            keep_bldg_tags: other.keep_bldg_tags.or(self.keep_bldg_tags),
+            curb_regulations: other.curb_regulations.or(self.curb_regulations),
+        }
+    }
+
+    fn apply_to_convert_osm(self, city: &CityName, opts: &mut convert_osm::Options) {
+        let cfg = &mut opts.map_config;
+        if let Some(x) = self.driving_side {
+            cfg.driving_side = x;
+        }
+        if let Some(x) = self.bikes_can_use_bus_lanes {
+            cfg.bikes_can_use_bus_lanes = x;
+        }
+        if let Some(x) = self.inferred_sidewalks {
+            cfg.inferred_sidewalks = x;
+        }
+        if let Some(x) = self.street_parking_spot_length_meters {
+            cfg.street_parking_spot_length = Distance::meters(x);
+        }
+        if let Some(x) = self.turn_on_red {
+            cfg.turn_on_red = x;
+        }
+
+        if let Some(x) = self.onstreet_parking {
+            opts.onstreet_parking = match x {
+                convert_osm::OnstreetParking::Blockface(path) => {
+                    convert_osm::OnstreetParking::Blockface(city.input_path(path))
+                }
+                x => x,
+            };
+        }
+        if let Some(x) = self.public_offstreet_parking {
+            opts.public_offstreet_parking = match x {
+                convert_osm::PublicOffstreetParking::Gis(path) => {
+                    convert_osm::PublicOffstreetParking::Gis(city.input_path(path))
+                }
+                x => x,
+            };
+        }
+        if let Some(x) = self.private_offstreet_parking {
+            opts.private_offstreet_parking = x;
+        }
+        if let Some(x) = self.include_railroads {
+            opts.include_railroads = x;
+        }
+        if let Some(path) = self.extra_buildings {
+            opts.extra_buildings = Some(city.input_path(path));
+        }
+        if let Some(x) = self.skip_local_roads {
+            opts.skip_local_roads = x;
+        }
+        if let Some(x) = self.filter_crosswalks {
+            opts.filter_crosswalks = x;
+        }
+        if let Some(x) = self.separate_sidewalks {
+            opts.separate_sidewalks = x;
+        }
+        if let Some(x) = self.mid_block_crossings {
+            opts.mid_block_crossings = x;
+        }
+        if let Some(x) = self.gtfs_url {
+            opts.gtfs_url = Some(x);
+        }
+        if let Some(x) = self.elevation {
+            opts.elevation = x;
+        }
+    }
+
+    fn apply_to_raw_to_map(self, opts: &mut RawToMapOptions) {
+        if let Some(x) = self.skip_ch {
+            opts.skip_ch = x;
+        }
+        if let Some(x) = self.consolidate_all_intersections {
+            opts.consolidate_all_intersections = x;
+        }
+        if let Some(x) = self.keep_bldg_tags {
+            opts.keep_bldg_tags = x;
+        }
+    }
+}
 
-/// Given the name of a map, configure its import.
+/// The hardcoded defaults for a map, used if the city's config file doesn't say otherwise.
 ///
 /// Note this was once expressed as config files for every city. That was less maintainable; most
-/// places used default values that were copied around.
+/// places used default values that were copied around. Config files now only need to express what
+/// differs from these defaults.
 // Slightly more verbose logic feels easier to read
 #[allow(clippy::match_like_matches_macro)]
-pub fn config_for_map(name: &MapName) -> convert_osm::Options {
+fn default_config_for_map(name: &MapName) -> convert_osm::Options {
     // Some maps have extra procedurally generated houses. Just see if a file in a canonical
     // location exists.
     let procgen_houses = name.city.input_path("procgen_houses.json");
@@ -72,6 +350,8 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
         extra_buildings,
         skip_local_roads: name == &MapName::new("us", "phoenix", "loop101"),
         filter_crosswalks: false,
+        separate_sidewalks: false,
+        mid_block_crossings: false,
         // https://www.transit.land is a great place to find the static GTFS URLs
         gtfs_url: if name == &MapName::new("us", "seattle", "arboretum") {
             Some("http://metro.kingcounty.gov/GTFS/google_transit.zip".to_string())
@@ -88,3 +368,52 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
         elevation: name != &MapName::new("br", "sao_paulo", "sao_miguel_paulista"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<CityConfig> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        assert!(parse(r#"{ "city": { "driving_side": "Left" } }"#).is_ok());
+        assert!(parse(r#"{ "city": { "driving_sid": "Left" } }"#).is_err());
+        assert!(parse(r#"{ "maps": { "center": { "skip_chh": true } } }"#).is_err());
+        assert!(parse(r#"{ "cities": {} }"#).is_err());
+    }
+
+    #[test]
+    fn test_validate_overrides() {
+        let config = parse(r#"{ "city": { "street_parking_spot_length_meters": -1 } }"#).unwrap();
+        assert!(config.city.validate().is_err());
+        let config = parse(
+            r#"{ "city": { "onstreet_parking": { "SomeAdditionalWhereNoData": { "pct": 150 } } } }"#,
+        )
+        .unwrap();
+        assert!(config.city.validate().is_err());
+        let config = parse(r#"{ "city": { "street_parking_spot_length_meters": 6.5 } }"#).unwrap();
+        assert!(config.city.validate().is_ok());
+    }
+
+    #[test]
+    fn test_map_overrides_win() {
+        let config = parse(
+            r#"{
+                "city": { "driving_side": "Left", "skip_ch": true, "gtfs_url": "city" },
+                "maps": { "center": { "gtfs_url": "center" } }
+            }"#,
+        )
+        .unwrap();
+
+        let center = config.overrides_for(&MapName::new("zz", "test", "center"));
+        assert_eq!(center.driving_side, Some(DrivingSide::Left));
+        assert_eq!(center.skip_ch, Some(true));
+        assert_eq!(center.gtfs_url, Some("center".to_string()));
+
+        let other = config.overrides_for(&MapName::new("zz", "test", "other"));
+        assert_eq!(other.gtfs_url, Some("city".to_string()));
+    }
+}
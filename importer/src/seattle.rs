@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
 use aabb_quadtree::QuadTree;
+use anyhow::Result;
 
 use abstio::{CityName, MapName};
 use abstutil::Timer;
@@ -103,29 +104,33 @@ pub async fn ensure_popdat_exists(
     config: &ImporterConfiguration,
     built_raw_huge_seattle: &mut bool,
     built_map_huge_seattle: &mut bool,
-) -> (crate::soundcast::PopDat, map_model::Map) {
+) -> Result<(crate::soundcast::PopDat, map_model::Map)> {
     let huge_name = MapName::seattle("huge_seattle");
 
     if abstio::file_exists(abstio::path_popdat()) {
         println!("- {} exists, not regenerating it", abstio::path_popdat());
-        return (
+        return Ok((
             abstio::read_binary(abstio::path_popdat(), timer),
             map_model::Map::load_synchronously(huge_name.path(), timer),
-        );
+        ));
     }
 
     if !abstio::file_exists(abstio::path_raw_map(&huge_name)) {
-        crate::utils::osm_to_raw(MapName::seattle("huge_seattle"), timer, config).await;
+        crate::utils::osm_to_raw(MapName::seattle("huge_seattle"), timer, config).await?;
         *built_raw_huge_seattle = true;
     }
     let huge_map = if abstio::file_exists(huge_name.path()) {
         map_model::Map::load_synchronously(huge_name.path(), timer)
     } else {
         *built_map_huge_seattle = true;
-        crate::utils::raw_to_map(&huge_name, map_model::RawToMapOptions::default(), timer)
+        let opts = crate::map_config::raw_to_map_options(
+            &huge_name,
+            map_model::RawToMapOptions::default(),
+        )?;
+        crate::utils::raw_to_map(&huge_name, opts, timer)
     };
 
-    (crate::soundcast::import_data(&huge_map, timer), huge_map)
+    Ok((crate::soundcast::import_data(&huge_map, timer), huge_map))
 }
 
 pub fn adjust_private_parking(map: &mut Map, scenario: &Scenario) {
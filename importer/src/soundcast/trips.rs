@@ -280,6 +280,7 @@ pub fn make_scenario(
         people.push(PersonSpec {
             orig_id: Some(orig_id),
             trips,
+            behavior: None,
         });
     }
     for maybe_t in individ_trips {
@@ -293,6 +294,8 @@ pub fn make_scenario(
         map_name: map.get_name().clone(),
         people,
         only_seed_buses: None,
+        incidents: Vec::new(),
+        conditions: Vec::new(),
     }
     .remove_weird_schedules(true)
 }
@@ -0,0 +1,855 @@
+//! Stitch several adjacent maps from the same city into one regional map, so that something like
+//! a corridor crossing two maps can be simulated end-to-end.
+//!
+//! Each map is clipped independently from its own boundary, so a road crossing from one map into
+//! another gets cut at the boundary twice, with a border intersection on each side. Stitching
+//! splices those pieces back together and removes the duplicate borders. Scenarios from the
+//! source maps are then re-projected onto the stitched map.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::Result;
+
+use abstio::MapName;
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Distance, GPSBounds, LonLat, PolyLine, Polygon, Pt2D};
+use map_model::raw::{OriginalRoad, RawMap, RawRoad, RawTransitRoute, RawTransitStop};
+use map_model::{
+    osm, BuildingID, IntersectionID, IntersectionType, Map, Position, RawToMapOptions, RoadID,
+};
+use synthpop::{BehaviorProfile, IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint};
+
+/// Two halves of a road clipped by neighboring maps must line up within this distance.
+const SPLICE_THRESHOLD: Distance = Distance::const_meters(2.0);
+/// A trip leaving one source map and a trip entering another are treated as the same journey if
+/// they use borders this close together.
+const MAX_BORDER_HANDOFF_DIST: Distance = Distance::const_meters(1000.0);
+
+/// Stitches the RawMaps for several maps in the same city, produces a new Map, and re-projects
+/// every scenario from the source maps onto it. Everything is saved.
+pub fn stitch_maps(
+    inputs: Vec<MapName>,
+    output: MapName,
+    opts: RawToMapOptions,
+    timer: &mut Timer,
+) -> Result<()> {
+    let mut raw_maps = Vec::new();
+    for name in &inputs {
+        raw_maps.push(abstio::maybe_read_binary::<RawMap>(
+            abstio::path_raw_map(name),
+            timer,
+        )?);
+    }
+    timer.start("stitch RawMaps");
+    let (raw, renumberings) = stitch_raw_maps(raw_maps, output.clone())?;
+    timer.stop("stitch RawMaps");
+    raw.save();
+
+    let map = Map::create_from_raw(raw, opts, timer);
+    timer.start("save map");
+    map.save();
+    timer.stop("save map");
+
+    // Group scenarios with the same name across all of the source maps
+    let mut scenarios_per_name: BTreeMap<String, Vec<(MapName, Scenario)>> = BTreeMap::new();
+    for name in &inputs {
+        for scenario_name in abstio::list_all_objects(abstio::path_all_scenarios(name)) {
+            let scenario: Scenario =
+                abstio::read_binary(abstio::path_scenario(name, &scenario_name), timer);
+            scenarios_per_name
+                .entry(scenario_name)
+                .or_insert_with(Vec::new)
+                .push((name.clone(), scenario));
+        }
+    }
+    if scenarios_per_name.is_empty() {
+        return Ok(());
+    }
+
+    let mut source_maps = HashMap::new();
+    for (name, renumbering) in inputs.iter().zip(renumberings) {
+        source_maps.insert(
+            name.clone(),
+            (Map::load_synchronously(name.path(), timer), renumbering),
+        );
+    }
+    for (scenario_name, scenarios) in scenarios_per_name {
+        timer.start(format!("re-project scenario {}", scenario_name));
+        let scenario = reproject_scenarios(&scenario_name, scenarios, &source_maps, &map);
+        scenario.save();
+        timer.stop(format!("re-project scenario {}", scenario_name));
+    }
+    Ok(())
+}
+
+/// Combines several RawMaps from the same city into one. Roads clipped by the boundaries between
+/// the maps are spliced back together, and duplicate border intersections are removed. Also
+/// returns how the synthetic IDs of each input were renumbered, in the same order.
+pub fn stitch_raw_maps(inputs: Vec<RawMap>, name: MapName) -> Result<(RawMap, Vec<Renumbering>)> {
+    if inputs.len() < 2 {
+        bail!("Need at least two maps to stitch together");
+    }
+    for raw in &inputs {
+        if raw.name.city != name.city {
+            bail!(
+                "Can't stitch {} into {}; they're in different cities",
+                raw.name.describe(),
+                name.describe()
+            );
+        }
+        if raw.config.driving_side != inputs[0].config.driving_side {
+            bail!(
+                "{} and {} have different driving sides",
+                raw.name.describe(),
+                inputs[0].name.describe()
+            );
+        }
+    }
+
+    let mut gps_bounds = GPSBounds::new();
+    for raw in &inputs {
+        gps_bounds.update(LonLat::new(raw.gps_bounds.min_lon, raw.gps_bounds.min_lat));
+        gps_bounds.update(LonLat::new(raw.gps_bounds.max_lon, raw.gps_bounds.max_lat));
+    }
+
+    let mut result = RawMap::blank(name);
+    result.gps_bounds = gps_bounds;
+    result.config = inputs[0].config.clone();
+
+    // For every road in the result, was each endpoint a border in the map it came from?
+    let mut road_borders: BTreeMap<OriginalRoad, (bool, bool)> = BTreeMap::new();
+    let mut boundaries = Vec::new();
+    let mut transit_routes: BTreeMap<String, Vec<RawTransitRoute>> = BTreeMap::new();
+    let mut renumberings = Vec::new();
+
+    for (idx, mut raw) in inputs.into_iter().enumerate() {
+        reproject(&mut raw, &result.gps_bounds);
+        renumberings.push(if idx > 0 {
+            renumber_synthetic_ids(&mut raw, &result)
+        } else {
+            Renumbering::default()
+        });
+        boundaries.push(raw.boundary_polygon.clone());
+
+        for (id, road) in std::mem::take(&mut raw.roads) {
+            let borders = (
+                raw.intersections[&id.i1].intersection_type == IntersectionType::Border,
+                raw.intersections[&id.i2].intersection_type == IntersectionType::Border,
+            );
+            if let Some(existing) = result.roads.remove(&id) {
+                let (road, borders) =
+                    merge_same_road((existing, road_borders[&id]), (road, borders));
+                result.roads.insert(id, road);
+                road_borders.insert(id, borders);
+            } else {
+                result.roads.insert(id, road);
+                road_borders.insert(id, borders);
+            }
+        }
+
+        // Prefer the version of an intersection that isn't a border
+        for (id, i) in raw.intersections {
+            match result.intersections.get(&id) {
+                Some(existing) if existing.intersection_type != IntersectionType::Border => {}
+                _ => {
+                    result.intersections.insert(id, i);
+                }
+            }
+        }
+
+        for (id, b) in raw.buildings {
+            result.buildings.entry(id).or_insert(b);
+        }
+        // Areas are clipped to each map's boundary, so the pieces don't overlap much
+        result.areas.extend(raw.areas);
+        for lot in raw.parking_lots {
+            if !result.parking_lots.iter().any(|x| x.osm_id == lot.osm_id) {
+                result.parking_lots.push(lot);
+            }
+        }
+        for (id, pts) in raw.parking_aisles {
+            if !result.parking_aisles.iter().any(|(x, _)| *x == id) {
+                result.parking_aisles.push((id, pts));
+            }
+        }
+        for (id, stop) in raw.transit_stops {
+            result.transit_stops.entry(id).or_insert(stop);
+        }
+        for route in raw.transit_routes {
+            transit_routes
+                .entry(route.gtfs_id.clone())
+                .or_insert_with(Vec::new)
+                .push(route);
+        }
+    }
+
+    splice_clipped_roads(&mut result, &mut road_borders);
+
+    // Borders that became interior will usually have been removed by splicing. If the other
+    // half of a road wasn't found, at least connect it to the real intersection.
+    let mut snapped = 0;
+    for (id, (border1, border2)) in &road_borders {
+        for (border, i) in [(*border1, id.i1), (*border2, id.i2)] {
+            if border && result.intersections[&i].intersection_type != IntersectionType::Border {
+                let pt = result.intersections[&i].point;
+                let road = result.roads.get_mut(id).unwrap();
+                if i == id.i1 {
+                    road.osm_center_points[0] = pt;
+                } else {
+                    *road.osm_center_points.last_mut().unwrap() = pt;
+                }
+                snapped += 1;
+            }
+        }
+    }
+    if snapped > 0 {
+        warn!(
+            "{} clipped road ends couldn't be spliced, so they were snapped to intersections",
+            snapped
+        );
+    }
+
+    // Remove intersections that nothing uses anymore
+    let mut used_intersections = BTreeSet::new();
+    for id in result.roads.keys() {
+        used_intersections.insert(id.i1);
+        used_intersections.insert(id.i2);
+    }
+    let orig_intersections = result.intersections.len();
+    result
+        .intersections
+        .retain(|id, _| used_intersections.contains(id));
+    info!(
+        "Removed {} duplicate border intersections",
+        orig_intersections - result.intersections.len()
+    );
+
+    result.transit_routes = unify_transit_routes(transit_routes, &result.transit_stops);
+
+    // Use the largest piece of the union. If the maps don't touch, some of them will lie outside
+    // the boundary.
+    result.boundary_polygon = Polygon::union_all_into_multipolygon(boundaries)
+        .0
+        .into_iter()
+        .map(Polygon::from)
+        .max_by_key(|p| p.area() as usize)
+        .unwrap();
+
+    Ok((result, renumberings))
+}
+
+/// Transforms all of the geometry from the RawMap's own GPS bounds into new ones. Converting
+/// between two `GPSBounds` is just scaling and translating along each axis.
+fn reproject(raw: &mut RawMap, to: &GPSBounds) {
+    let from = raw.gps_bounds.clone();
+    let origin = Pt2D::new(0.0, 0.0).to_gps(&from).to_pt(to);
+    let corner = Pt2D::new(1.0, 1.0).to_gps(&from).to_pt(to);
+    let (scale_x, scale_y) = (corner.x() - origin.x(), corner.y() - origin.y());
+    let pt = |pt: Pt2D| Pt2D::new(pt.x() * scale_x + origin.x(), pt.y() * scale_y + origin.y());
+    let polygon = |p: &Polygon| {
+        p.scale_xy(scale_x, scale_y)
+            .translate(origin.x(), origin.y())
+    };
+
+    for road in raw.roads.values_mut() {
+        for x in &mut road.osm_center_points {
+            *x = pt(*x);
+        }
+    }
+    for i in raw.intersections.values_mut() {
+        i.point = pt(i.point);
+        for x in i.trim_roads_for_merging.values_mut() {
+            *x = pt(*x);
+        }
+    }
+    for b in raw.buildings.values_mut() {
+        b.polygon = polygon(&b.polygon);
+    }
+    for a in &mut raw.areas {
+        a.polygon = polygon(&a.polygon);
+    }
+    for lot in &mut raw.parking_lots {
+        lot.polygon = polygon(&lot.polygon);
+    }
+    for (_, pts) in &mut raw.parking_aisles {
+        for x in pts {
+            *x = pt(*x);
+        }
+    }
+    for route in &mut raw.transit_routes {
+        route.shape =
+            PolyLine::unchecked_new(route.shape.points().iter().map(|x| pt(*x)).collect());
+    }
+    for stop in raw.transit_stops.values_mut() {
+        stop.position = pt(stop.position);
+    }
+    raw.boundary_polygon = polygon(&raw.boundary_polygon);
+    raw.gps_bounds = to.clone();
+}
+
+/// How the synthetic OSM IDs from one source map were changed in the stitched map. IDs that
+/// weren't renumbered stay the same.
+#[derive(Default)]
+pub struct Renumbering {
+    nodes: BTreeMap<osm::NodeID, osm::NodeID>,
+    ways: BTreeMap<osm::WayID, osm::WayID>,
+}
+
+impl Renumbering {
+    fn node(&self, id: osm::NodeID) -> osm::NodeID {
+        self.nodes.get(&id).cloned().unwrap_or(id)
+    }
+
+    fn way(&self, id: osm::WayID) -> osm::WayID {
+        self.ways.get(&id).cloned().unwrap_or(id)
+    }
+
+    fn road(&self, id: OriginalRoad) -> OriginalRoad {
+        OriginalRoad {
+            osm_way_id: self.way(id.osm_way_id),
+            i1: self.node(id.i1),
+            i2: self.node(id.i2),
+        }
+    }
+
+    fn osm_id(&self, id: osm::OsmID) -> osm::OsmID {
+        match id {
+            osm::OsmID::Way(w) => osm::OsmID::Way(self.way(w)),
+            _ => id,
+        }
+    }
+}
+
+/// Negative node and way IDs are invented during import (when clipping creates a border, or for
+/// procedurally generated buildings), so they'll collide between maps. Renumber them in `raw` to
+/// avoid anything already in `result`.
+fn renumber_synthetic_ids(raw: &mut RawMap, result: &RawMap) -> Renumbering {
+    let mut next_node = -1;
+    for id in result.intersections.keys().chain(raw.intersections.keys()) {
+        next_node = next_node.min(id.0 - 1);
+    }
+    let mut next_way = -1;
+    for id in result.roads.keys().chain(raw.roads.keys()) {
+        next_way = next_way.min(id.osm_way_id.0 - 1);
+    }
+    for id in result.buildings.keys().chain(raw.buildings.keys()) {
+        if let osm::OsmID::Way(w) = id {
+            next_way = next_way.min(w.0 - 1);
+        }
+    }
+
+    let mut nodes: BTreeMap<osm::NodeID, osm::NodeID> = BTreeMap::new();
+    for id in raw.intersections.keys() {
+        if id.0 < 0 {
+            nodes.insert(*id, osm::NodeID(next_node));
+            next_node -= 1;
+        }
+    }
+    let mut ways: BTreeMap<osm::WayID, osm::WayID> = BTreeMap::new();
+    for id in raw.roads.keys() {
+        if id.osm_way_id.0 < 0 && !ways.contains_key(&id.osm_way_id) {
+            ways.insert(id.osm_way_id, osm::WayID(next_way));
+            next_way -= 1;
+        }
+    }
+    for id in raw.buildings.keys() {
+        if let osm::OsmID::Way(w) = id {
+            if w.0 < 0 && !ways.contains_key(w) {
+                ways.insert(*w, osm::WayID(next_way));
+                next_way -= 1;
+            }
+        }
+    }
+    let renumbering = Renumbering { nodes, ways };
+    if renumbering.nodes.is_empty() && renumbering.ways.is_empty() {
+        return renumbering;
+    }
+
+    let node = |id: osm::NodeID| renumbering.node(id);
+    let way = |id: osm::WayID| renumbering.way(id);
+    let road = |id: OriginalRoad| renumbering.road(id);
+
+    raw.intersections = std::mem::take(&mut raw.intersections)
+        .into_iter()
+        .map(|(id, mut i)| {
+            i.trim_roads_for_merging = std::mem::take(&mut i.trim_roads_for_merging)
+                .into_iter()
+                .map(|((w, fwd), pt)| ((way(w), fwd), pt))
+                .collect();
+            (node(id), i)
+        })
+        .collect();
+    raw.roads = std::mem::take(&mut raw.roads)
+        .into_iter()
+        .map(|(id, mut r)| {
+            for (_, to) in &mut r.turn_restrictions {
+                *to = road(*to);
+            }
+            for (via, to) in &mut r.complicated_turn_restrictions {
+                *via = road(*via);
+                *to = road(*to);
+            }
+            (road(id), r)
+        })
+        .collect();
+    raw.buildings = std::mem::take(&mut raw.buildings)
+        .into_iter()
+        .map(|(id, b)| (renumbering.osm_id(id), b))
+        .collect();
+    renumbering
+}
+
+/// The same road appears in two maps. Take each end from the version where it isn't a border.
+fn merge_same_road(
+    (road1, borders1): (RawRoad, (bool, bool)),
+    (road2, borders2): (RawRoad, (bool, bool)),
+) -> (RawRoad, (bool, bool)) {
+    match (borders1, borders2) {
+        // One version is complete, or they're clipped the same way
+        (_, (false, false)) if borders1 != (false, false) => (road2, borders2),
+        ((false, true), (true, false)) => (splice(road1, road2), (false, false)),
+        ((true, false), (false, true)) => (splice(road2, road1), (false, false)),
+        _ => {
+            if borders1 == borders2 && road2.length() > road1.length() {
+                (road2, borders2)
+            } else {
+                (road1, borders1)
+            }
+        }
+    }
+}
+
+/// `first` is clipped at its end and `second` at its start, but both follow the same OSM way in
+/// the same direction. The two pieces may overlap, if the source maps did.
+fn splice(mut first: RawRoad, second: RawRoad) -> RawRoad {
+    first.osm_center_points.pop();
+    for pt in second.osm_center_points.into_iter().skip(1) {
+        if !first
+            .osm_center_points
+            .iter()
+            .any(|x| x.approx_eq(pt, Distance::meters(0.1)))
+        {
+            first.osm_center_points.push(pt);
+        }
+    }
+    // Each crosswalk is near one end, so use the version where that end wasn't clipped
+    first.crosswalk_forward = second.crosswalk_forward;
+    first
+}
+
+/// When an intersection right outside a map's boundary had other roads connected, clipping
+/// invented a new border intersection, so the two halves of a road crossing between maps don't
+/// share an ID. Find these halves geometrically and splice them together.
+fn splice_clipped_roads(map: &mut RawMap, road_borders: &mut BTreeMap<OriginalRoad, (bool, bool)>) {
+    let mut ends_clipped: BTreeMap<osm::WayID, Vec<OriginalRoad>> = BTreeMap::new();
+    let mut starts_clipped: BTreeMap<osm::WayID, Vec<OriginalRoad>> = BTreeMap::new();
+    for (id, borders) in road_borders.iter() {
+        match borders {
+            (false, true) => ends_clipped
+                .entry(id.osm_way_id)
+                .or_insert_with(Vec::new)
+                .push(*id),
+            (true, false) => starts_clipped
+                .entry(id.osm_way_id)
+                .or_insert_with(Vec::new)
+                .push(*id),
+            _ => {}
+        }
+    }
+
+    let mut num_spliced = 0;
+    for (way, firsts) in ends_clipped {
+        let seconds = if let Some(x) = starts_clipped.get_mut(&way) {
+            x
+        } else {
+            continue;
+        };
+        for first in firsts {
+            let first_pl = match PolyLine::new(map.roads[&first].osm_center_points.clone()) {
+                Ok(pl) => pl,
+                Err(_) => continue,
+            };
+            let matching_idx = seconds.iter().position(|second| {
+                if second.i2 == first.i1 {
+                    return false;
+                }
+                let second_pts = &map.roads[second].osm_center_points;
+                if let Ok(second_pl) = PolyLine::new(second_pts.clone()) {
+                    near_polyline(&second_pl, first_pl.last_pt())
+                        && near_polyline(&first_pl, second_pl.first_pt())
+                } else {
+                    false
+                }
+            });
+            if let Some(idx) = matching_idx {
+                let second = seconds.remove(idx);
+                let road = splice(
+                    map.roads.remove(&first).unwrap(),
+                    map.roads.remove(&second).unwrap(),
+                );
+                road_borders.remove(&first);
+                road_borders.remove(&second);
+
+                let id = OriginalRoad {
+                    osm_way_id: way,
+                    i1: first.i1,
+                    i2: second.i2,
+                };
+                rename_road(map, first, id);
+                rename_road(map, second, id);
+                map.roads.insert(id, road);
+                road_borders.insert(id, (false, false));
+                num_spliced += 1;
+            }
+        }
+    }
+    info!("Spliced {} roads clipped by neighboring maps", num_spliced);
+}
+
+fn near_polyline(pl: &PolyLine, pt: Pt2D) -> bool {
+    pl.project_pt(pt).approx_eq(pt, SPLICE_THRESHOLD)
+}
+
+/// Fix turn restrictions referring to a road that's been spliced into another.
+fn rename_road(map: &mut RawMap, from: OriginalRoad, to: OriginalRoad) {
+    for road in map.roads.values_mut() {
+        for (_, x) in &mut road.turn_restrictions {
+            if *x == from {
+                *x = to;
+            }
+        }
+        for (via, x) in &mut road.complicated_turn_restrictions {
+            if *via == from {
+                *via = to;
+            }
+            if *x == from {
+                *x = to;
+            }
+        }
+    }
+}
+
+/// Each source map only keeps the stops of a route within its own boundary. Combine the stops,
+/// ordering them by their position along the route's shape.
+fn unify_transit_routes(
+    routes: BTreeMap<String, Vec<RawTransitRoute>>,
+    stops: &BTreeMap<String, RawTransitStop>,
+) -> Vec<RawTransitRoute> {
+    let mut result = Vec::new();
+    for (_, mut versions) in routes {
+        let mut route = versions.remove(0);
+        let mut all_stops: Vec<String> = route.stops.drain(..).collect();
+        for other in versions {
+            for stop in other.stops {
+                if !all_stops.contains(&stop) {
+                    all_stops.push(stop);
+                }
+            }
+        }
+        all_stops.sort_by_key(|id| {
+            let pt = route.shape.project_pt(stops[id].position);
+            route
+                .shape
+                .dist_along_of_point(pt)
+                .map(|(dist, _)| dist)
+                .unwrap_or(Distance::ZERO)
+        });
+        route.stops = all_stops;
+        result.push(route);
+    }
+    result
+}
+
+/// Where does an endpoint from a source map wind up in the stitched map?
+#[derive(Clone, Copy, PartialEq)]
+enum Reprojected {
+    Endpoint(TripEndpoint),
+    /// A border in the source map that's now inside the stitched map. The trip continues into a
+    /// neighboring map.
+    FormerBorder(IntersectionID),
+    Missing,
+}
+
+type ReprojectedTrip = (IndividTrip, Reprojected, Reprojected);
+
+struct Lookups {
+    buildings: HashMap<osm::OsmID, BuildingID>,
+    intersections: HashMap<osm::NodeID, IntersectionID>,
+    roads: HashMap<OriginalRoad, RoadID>,
+}
+
+impl Lookups {
+    fn new(map: &Map) -> Lookups {
+        Lookups {
+            buildings: map
+                .all_buildings()
+                .iter()
+                .map(|b| (b.orig_id, b.id))
+                .collect(),
+            intersections: map
+                .all_intersections()
+                .iter()
+                .map(|i| (i.orig_id, i.id))
+                .collect(),
+            roads: map.all_roads().iter().map(|r| (r.orig_id, r.id)).collect(),
+        }
+    }
+
+    /// `renumbering` describes how `src`'s synthetic IDs changed in `dst`.
+    fn reproject(
+        &self,
+        endpt: TripEndpoint,
+        src: &Map,
+        renumbering: &Renumbering,
+        dst: &Map,
+    ) -> Reprojected {
+        match endpt {
+            TripEndpoint::Building(b) => match self
+                .buildings
+                .get(&renumbering.osm_id(src.get_b(b).orig_id))
+            {
+                Some(b) => Reprojected::Endpoint(TripEndpoint::Building(*b)),
+                None => Reprojected::Missing,
+            },
+            TripEndpoint::Border(i) => match self
+                .intersections
+                .get(&renumbering.node(src.get_i(i).orig_id))
+            {
+                Some(i) if dst.get_i(*i).is_border() => {
+                    Reprojected::Endpoint(TripEndpoint::Border(*i))
+                }
+                Some(i) => Reprojected::FormerBorder(*i),
+                // The border may have been renumbered and spliced away. Find the closest
+                // intersection to where it was.
+                None => {
+                    let pt = src.get_i(i).polygon.center().to_gps(src.get_gps_bounds());
+                    let pt = pt.to_pt(dst.get_gps_bounds());
+                    match dst
+                        .all_intersections()
+                        .iter()
+                        .min_by_key(|i| i.polygon.center().dist_to(pt))
+                    {
+                        Some(i) if i.is_border() => {
+                            Reprojected::Endpoint(TripEndpoint::Border(i.id))
+                        }
+                        Some(i) => Reprojected::FormerBorder(i.id),
+                        None => Reprojected::Missing,
+                    }
+                }
+            },
+            TripEndpoint::SuddenlyAppear(pos) => {
+                let lane = pos.lane();
+                match self
+                    .roads
+                    .get(&renumbering.road(src.get_r(lane.road).orig_id))
+                {
+                    Some(r) if dst.get_r(*r).lanes.len() == src.get_r(lane.road).lanes.len() => {
+                        let new_lane = dst.get_r(*r).lanes[lane.offset].id;
+                        let dist = pos.dist_along().min(dst.get_l(new_lane).length());
+                        Reprojected::Endpoint(TripEndpoint::SuddenlyAppear(Position::new(
+                            new_lane, dist,
+                        )))
+                    }
+                    _ => Reprojected::Missing,
+                }
+            }
+        }
+    }
+}
+
+/// Combines scenarios with the same name from every source map. People are matched up between
+/// scenarios by their original ID when possible, so that a trip leaving one map and a trip
+/// entering the neighboring map become one trip in the stitched map.
+fn reproject_scenarios(
+    scenario_name: &str,
+    scenarios: Vec<(MapName, Scenario)>,
+    source_maps: &HashMap<MapName, (Map, Renumbering)>,
+    map: &Map,
+) -> Scenario {
+    let lookups = Lookups::new(map);
+
+    // Everyone's trips, with each endpoint re-projected
+    let mut people: Vec<(
+        Option<OrigPersonID>,
+        Option<BehaviorProfile>,
+        Vec<ReprojectedTrip>,
+    )> = Vec::new();
+    let mut people_by_orig_id: HashMap<OrigPersonID, usize> = HashMap::new();
+    let mut only_seed_buses: Option<BTreeSet<String>> = Some(BTreeSet::new());
+    for (name, scenario) in scenarios {
+        let (src, renumbering) = &source_maps[&name];
+        match (&mut only_seed_buses, scenario.only_seed_buses) {
+            (Some(all), Some(routes)) => {
+                all.extend(routes);
+            }
+            _ => {
+                only_seed_buses = None;
+            }
+        }
+
+        for person in scenario.people {
+            let trips = person
+                .trips
+                .into_iter()
+                .map(|trip| {
+                    let origin = lookups.reproject(trip.origin, src, renumbering, map);
+                    let destination = lookups.reproject(trip.destination, src, renumbering, map);
+                    (trip, origin, destination)
+                })
+                .collect::<Vec<_>>();
+            if let Some(orig_id) = person.orig_id {
+                if let Some(idx) = people_by_orig_id.get(&orig_id) {
+                    people[*idx].2.extend(trips);
+                    continue;
+                }
+                people_by_orig_id.insert(orig_id, people.len());
+            }
+            people.push((person.orig_id, person.behavior, trips));
+        }
+    }
+
+    let mut result = Scenario::empty(map, scenario_name);
+    result.only_seed_buses = only_seed_buses;
+    let mut dropped_trips = 0;
+    for (orig_id, behavior, mut trips) in people {
+        trips.sort_by_key(|(trip, _, _)| trip.depart);
+        let mut fused: Vec<ReprojectedTrip> = Vec::new();
+        for (trip, origin, destination) in trips {
+            if let Some((prev, prev_origin, prev_destination)) = fused.last_mut() {
+                // The same trip, seen by two overlapping maps
+                if *prev_origin == origin
+                    && *prev_destination == destination
+                    && prev.mode == trip.mode
+                    && prev.depart == trip.depart
+                {
+                    continue;
+                }
+                // One journey, split by the boundary between two maps
+                if let (Reprojected::FormerBorder(i1), Reprojected::FormerBorder(i2)) =
+                    (*prev_destination, origin)
+                {
+                    if prev.mode == trip.mode
+                        && map
+                            .get_i(i1)
+                            .polygon
+                            .center()
+                            .dist_to(map.get_i(i2).polygon.center())
+                            <= MAX_BORDER_HANDOFF_DIST
+                    {
+                        *prev_destination = destination;
+                        continue;
+                    }
+                }
+            }
+            fused.push((trip, origin, destination));
+        }
+
+        let mut person = PersonSpec {
+            orig_id,
+            trips: Vec::new(),
+            behavior,
+        };
+        for (mut trip, origin, destination) in fused {
+            if let (Reprojected::Endpoint(origin), Reprojected::Endpoint(destination)) =
+                (origin, destination)
+            {
+                trip.origin = origin;
+                trip.destination = destination;
+                person.trips.push(trip);
+            } else {
+                dropped_trips += 1;
+            }
+        }
+        if !person.trips.is_empty() {
+            result.people.push(person);
+        }
+    }
+    if dropped_trips > 0 {
+        warn!(
+            "{} trips couldn't be re-projected onto {}",
+            prettyprint_usize(dropped_trips),
+            map.get_name().describe()
+        );
+    }
+    result.remove_weird_schedules(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Tags;
+    use geom::Ring;
+    use map_model::raw::RawIntersection;
+
+    use super::*;
+
+    /// A map with one road between two borders. Every piece uses the same synthetic IDs.
+    fn piece(name: &str, min_lon: f64) -> RawMap {
+        let mut raw = RawMap::blank(MapName::new("zz", "oneshot", name));
+        let corners = vec![
+            LonLat::new(min_lon, 0.0),
+            LonLat::new(min_lon + 0.001, 0.0),
+            LonLat::new(min_lon + 0.001, 0.001),
+            LonLat::new(min_lon, 0.001),
+            LonLat::new(min_lon, 0.0),
+        ];
+        raw.gps_bounds = GPSBounds::from(corners.clone());
+        raw.boundary_polygon = Ring::must_new(raw.gps_bounds.convert(&corners)).into_polygon();
+
+        let pts = raw.gps_bounds.convert(&[
+            LonLat::new(min_lon + 0.0002, 0.0005),
+            LonLat::new(min_lon + 0.0008, 0.0005),
+        ]);
+        let id = OriginalRoad::new(-1, (-1, -2));
+        for (node, pt) in [(id.i1, pts[0]), (id.i2, pts[1])] {
+            raw.intersections
+                .insert(node, RawIntersection::new(pt, IntersectionType::Border));
+        }
+        let mut tags = Tags::empty();
+        tags.insert("highway", "residential");
+        tags.insert(osm::OSM_WAY_ID, id.osm_way_id.0.to_string());
+        raw.roads.insert(id, RawRoad::new(pts, tags, &raw.config));
+        raw
+    }
+
+    #[test]
+    fn test_reproject_renumbered_borders() {
+        let mut timer = Timer::throwaway();
+        let sources: Vec<Map> = [("west", 0.0), ("east", 0.001)]
+            .into_iter()
+            .map(|(name, lon)| {
+                Map::create_from_raw(piece(name, lon), RawToMapOptions::default(), &mut timer)
+            })
+            .collect();
+        let (raw, renumberings) = stitch_raw_maps(
+            vec![piece("west", 0.0), piece("east", 0.001)],
+            MapName::new("zz", "oneshot", "stitched"),
+        )
+        .unwrap();
+        assert!(renumberings[0].nodes.is_empty());
+        assert_eq!(renumberings[1].nodes.len(), 2);
+        let map = Map::create_from_raw(raw, RawToMapOptions::default(), &mut timer);
+        let lookups = Lookups::new(&map);
+
+        for (src, renumbering) in sources.iter().zip(renumberings.iter()) {
+            for i in src.all_intersections() {
+                let pt = i
+                    .polygon
+                    .center()
+                    .to_gps(src.get_gps_bounds())
+                    .to_pt(map.get_gps_bounds());
+                match lookups.reproject(TripEndpoint::Border(i.id), src, renumbering, &map) {
+                    Reprojected::Endpoint(TripEndpoint::Border(new_i)) => {
+                        assert!(
+                            map.get_i(new_i).polygon.center().dist_to(pt) < Distance::meters(5.0)
+                        );
+                    }
+                    _ => panic!(
+                        "{} in {} didn't wind up at a border",
+                        i.id,
+                        src.get_name().describe()
+                    ),
+                }
+            }
+        }
+    }
+}
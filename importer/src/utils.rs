@@ -1,6 +1,8 @@
 use std::path::Path;
 use std::process::Command;
 
+use anyhow::Result;
+
 use abstio::{CityName, MapName};
 use abstutil::{must_run_cmd, Timer};
 use map_model::raw::RawMap;
@@ -117,11 +119,11 @@ pub async fn osm_to_raw(
     name: MapName,
     timer: &mut abstutil::Timer<'_>,
     config: &ImporterConfiguration,
-) -> RawMap {
+) -> Result<RawMap> {
     if name.city == CityName::seattle() {
         crate::seattle::input(config, timer).await;
     }
-    let opts = crate::map_config::config_for_map(&name);
+    let opts = crate::map_config::config_for_map(&name)?;
     if let Some(ref url) = opts.gtfs_url {
         download(config, name.city.input_path("gtfs/"), url).await;
     }
@@ -130,9 +132,7 @@ pub async fn osm_to_raw(
         "importer/config/{}/{}/{}.poly",
         name.city.country, name.city.city, name.map
     );
-    let osm_url = crate::pick_geofabrik(boundary_polygon.clone())
-        .await
-        .unwrap();
+    let osm_url = crate::pick_geofabrik(boundary_polygon.clone()).await?;
 
     let local_osm_file = name.city.input_path(format!(
         "osm/{}",
@@ -160,7 +160,7 @@ pub async fn osm_to_raw(
         timer,
     );
     map.save();
-    map
+    Ok(map)
 }
 
 /// Converts a RawMap to a Map.
@@ -75,7 +75,9 @@ impl DrawIntersection {
                 );
             }
             IntersectionType::StopSign => {
-                for ss in map.get_stop_sign(i.id).roads.values() {
+                let sign = map.get_stop_sign(i.id);
+                let label = if sign.yield_on_entry { "YIELD" } else { "STOP" };
+                for ss in sign.roads.values() {
                     if ss.must_stop {
                         if let Some((octagon, pole, angle)) =
                             DrawIntersection::stop_sign_geom(ss, map)
@@ -88,7 +90,7 @@ impl DrawIntersection {
                             // a fixed SVG asset and just rotate it, but we'd still need to
                             // calculate the octagon hitbox for the stop sign editor.
                             default_geom.append(
-                                Text::from(widgetry::Line("STOP").small_heading().fg(Color::WHITE))
+                                Text::from(widgetry::Line(label).small_heading().fg(Color::WHITE))
                                     .render_autocropped(prerender.as_ref())
                                     .scale(0.02)
                                     .centered_on(center)
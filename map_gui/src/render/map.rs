@@ -476,28 +476,77 @@ impl DrawMap {
         batch
     }
 
+    /// The intersection may have been created by map edits.
     pub fn recreate_intersection(&mut self, i: IntersectionID, map: &Map) {
-        let item_id = self.quadtree_ids.remove(&ID::Intersection(i)).unwrap();
-        self.quadtree.remove(item_id).unwrap();
+        if let Some(item_id) = self.quadtree_ids.remove(&ID::Intersection(i)) {
+            self.quadtree.remove(item_id).unwrap();
+        }
 
         let draw = DrawIntersection::new(map.get_i(i), map);
         let item_id = self
             .quadtree
             .insert_with_box(draw.get_id(), draw.get_outline(map).get_bounds().as_bbox());
         self.quadtree_ids.insert(draw.get_id(), item_id);
-        self.intersections[i.0] = draw;
+        if i.0 == self.intersections.len() {
+            self.intersections.push(draw);
+        } else {
+            self.intersections[i.0] = draw;
+        }
     }
 
+    /// The road may have been created by map edits.
     pub fn recreate_road(&mut self, road: &Road, map: &Map) {
-        let item_id = self.quadtree_ids.remove(&ID::Road(road.id)).unwrap();
-        self.quadtree.remove(item_id).unwrap();
+        if let Some(item_id) = self.quadtree_ids.remove(&ID::Road(road.id)) {
+            self.quadtree.remove(item_id).unwrap();
+        }
 
         let draw = DrawRoad::new(road);
         let item_id = self
             .quadtree
             .insert_with_box(draw.get_id(), draw.get_outline(map).get_bounds().as_bbox());
         self.quadtree_ids.insert(draw.get_id(), item_id);
-        self.roads[road.id.0] = draw;
+        if road.id.0 == self.roads.len() {
+            self.roads.push(draw);
+        } else {
+            self.roads[road.id.0] = draw;
+        }
+        self.zorder_range = (
+            self.zorder_range.0.min(road.zorder),
+            self.zorder_range.1.max(road.zorder),
+        );
+    }
+
+    /// Remove an intersection created by map edits. Only the most recently created one can be
+    /// removed.
+    pub fn delete_intersection(&mut self, i: IntersectionID) {
+        assert_eq!(i.0 + 1, self.intersections.len());
+        let item_id = self.quadtree_ids.remove(&ID::Intersection(i)).unwrap();
+        self.quadtree.remove(item_id).unwrap();
+        self.intersections.pop();
+    }
+
+    /// Remove a road created by map edits. Only the most recently created one can be removed.
+    pub fn delete_road(&mut self, r: RoadID) {
+        assert_eq!(r.0 + 1, self.roads.len());
+        let item_id = self.quadtree_ids.remove(&ID::Road(r)).unwrap();
+        self.quadtree.remove(item_id).unwrap();
+        self.roads.pop();
+    }
+
+    /// The stop may have been created, moved, or deleted.
+    pub fn recreate_transit_stop(
+        &mut self,
+        ctx: &EventCtx,
+        id: TransitStopID,
+        map: &Map,
+        cs: &ColorScheme,
+    ) {
+        if let Some(stop) = map.maybe_get_ts(id) {
+            self.bus_stops
+                .insert(id, DrawTransitStop::new(ctx, stop, map, cs));
+        } else {
+            self.bus_stops.remove(&id);
+        }
     }
 
     pub fn free_memory(&mut self) {
@@ -111,6 +111,13 @@ pub fn upgrade(mut value: Value, map: &Map) -> Result<PermanentMapEdits> {
             .unwrap()
             .insert("version".to_string(), Value::Number(11.into()));
     }
+    if value["version"] == Value::Number(11.into()) {
+        add_banned_movements(&mut value);
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(12.into()));
+    }
 
     abstutil::from_json(&value.to_string().into_bytes())
 }
@@ -255,8 +262,9 @@ fn fix_old_lane_cmds(value: &mut Value, map: &Map) -> Result<()> {
 
     for (r, new) in modified {
         let old = map.get_r_edit(r);
-        commands
-            .push(serde_json::to_value(EditCmd::ChangeRoad { r, old, new }.to_perma(map)).unwrap());
+        commands.push(serde_json::to_value(
+            EditCmd::ChangeRoad { r, old, new }.to_perma(map)?,
+        )?);
     }
     value.as_object_mut().unwrap()["commands"] = Value::Array(commands);
     Ok(())
@@ -442,6 +450,29 @@ fn remove_vehicle_caps(value: &mut Value) {
     });
 }
 
+// Intersection edits became a control plus a list of banned movements
+fn add_banned_movements(value: &mut Value) {
+    walk(value, &|map| {
+        if map.len() == 1 && map.contains_key("ChangeIntersection") {
+            let cmd = map
+                .get_mut("ChangeIntersection")
+                .unwrap()
+                .as_object_mut()
+                .unwrap();
+            for key in ["new", "old"] {
+                let control = cmd.remove(key).unwrap();
+                let mut edit = serde_json::Map::new();
+                edit.insert("control".to_string(), control);
+                edit.insert("banned_movements".to_string(), Value::Array(Vec::new()));
+                cmd.insert(key.to_string(), Value::Object(edit));
+            }
+            true
+        } else {
+            false
+        }
+    });
+}
+
 // These're old structs used in fix_old_lane_cmds.
 #[derive(Debug, Deserialize)]
 struct OriginalLane {
@@ -499,3 +530,50 @@ impl OriginalLane {
         Ok((r.id, l.offset))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_add_banned_movements() {
+        let closed = json!({
+            "control": "Closed",
+            "banned_movements": [],
+        });
+        let v11 = json!({
+            "map_name": MapName::new("zz", "oneshot", "test"),
+            "edits_name": "test",
+            "version": 11,
+            "commands": [
+                {
+                    "ChangeIntersection": {
+                        "i": 123,
+                        "new": "Closed",
+                        "old": "Closed",
+                    }
+                },
+            ],
+            "merge_zones": true,
+            "proposal_description": [],
+            "proposal_link": null,
+        });
+
+        let upgraded = serde_json::to_value(upgrade(v11, &Map::blank()).unwrap()).unwrap();
+        assert_eq!(upgraded["version"], json!(12));
+        assert_eq!(
+            upgraded["commands"],
+            json!([
+                {
+                    "ChangeIntersection": {
+                        "i": 123,
+                        "new": closed,
+                        "old": closed,
+                    }
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Compare and combine proposals that were made independently against the same map. Each set of
+//! edits is boiled down to the net change to every road, intersection, transit stop, and route it
+//! touches, no matter how many commands it took to get there.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::edits::{EditCmd, MapEdits};
+use crate::{IntersectionID, Map, RoadID, TransitRouteID, TransitStopID};
+
+/// Something on the map that a set of edits can change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EditedObject {
+    Road(RoadID),
+    Intersection(IntersectionID),
+    TransitStop(TransitStopID),
+    TransitRoute(TransitRouteID),
+}
+
+impl fmt::Display for EditedObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditedObject::Road(r) => write!(f, "{}", r),
+            EditedObject::Intersection(i) => write!(f, "{}", i),
+            EditedObject::TransitStop(ts) => write!(f, "{}", ts),
+            EditedObject::TransitRoute(tr) => write!(f, "{}", tr),
+        }
+    }
+}
+
+/// How two sets of edits differ, relative to the basemap.
+#[derive(Debug, Default)]
+pub struct EditsDiff {
+    /// Only changed by the first set of edits
+    pub only_first: BTreeSet<EditedObject>,
+    /// Only changed by the second set of edits
+    pub only_second: BTreeSet<EditedObject>,
+    /// Changed by both, with different results
+    pub different: BTreeSet<EditedObject>,
+    /// Changed by both, with the same result
+    pub same: BTreeSet<EditedObject>,
+}
+
+/// The result of a three-way merge.
+pub struct MergedEdits {
+    /// Not applied to any map yet
+    pub edits: MapEdits,
+    /// Objects that both sides changed in different ways. The merged edits keep the first side's
+    /// version of these, unless it couldn't keep everything that side created. Also includes new
+    /// roads and intersections that had to be dropped.
+    pub conflicts: BTreeSet<EditedObject>,
+}
+
+impl MapEdits {
+    /// The net change to every object touched by these edits, expressed as commands from the
+    /// basemap's state. A command creating a road may touch several objects.
+    pub fn net_changes(&self, map: &Map) -> BTreeMap<EditedObject, Vec<EditCmd>> {
+        let mut net: Vec<EditCmd> = Vec::new();
+        for cmd in &self.commands {
+            if let Some(idx) = net.iter().position(|x| x.same_subject(cmd)) {
+                net[idx] = net[idx].chain(cmd);
+            } else {
+                net.push(cmd.clone());
+            }
+        }
+        net.retain(|cmd| !cmd.is_noop());
+        net.sort_by_key(|cmd| cmd.merge_order());
+
+        let mut per_object: BTreeMap<EditedObject, Vec<EditCmd>> = BTreeMap::new();
+        for cmd in net {
+            for obj in cmd.edited_objects(map) {
+                per_object.entry(obj).or_default().push(cmd.clone());
+            }
+        }
+        per_object
+    }
+
+    /// Compare two sets of edits made against the same map.
+    pub fn diff(&self, other: &MapEdits, map: &Map) -> EditsDiff {
+        let first = self.net_changes(map);
+        let second = other.net_changes(map);
+        let mut diff = EditsDiff::default();
+        for (obj, cmds) in &first {
+            match second.get(obj) {
+                Some(other_cmds) if other_cmds == cmds => {
+                    diff.same.insert(*obj);
+                }
+                Some(_) => {
+                    diff.different.insert(*obj);
+                }
+                None => {
+                    diff.only_first.insert(*obj);
+                }
+            }
+        }
+        for obj in second.keys() {
+            if !first.contains_key(obj) {
+                diff.only_second.insert(*obj);
+            }
+        }
+        diff
+    }
+
+    /// Combine two proposals that both started from `base`. Objects changed by only one side take
+    /// that side's changes. When both sides changed the same object differently, that's a
+    /// conflict, and the merged edits keep `ours`. All three must have been made against the same
+    /// basemap as `map`.
+    ///
+    /// New roads and intersections from one side are kept or dropped all together. Since both
+    /// sides number new objects starting from the same basemap, if both created different roads,
+    /// only the ones from `ours` survive.
+    pub fn merge(base: &MapEdits, ours: &MapEdits, theirs: &MapEdits, map: &Map) -> MergedEdits {
+        let base_net = base.net_changes(map);
+        let ours_net = ours.net_changes(map);
+        let theirs_net = theirs.net_changes(map);
+
+        let no_changes = Vec::new();
+        let mut conflicts = BTreeSet::new();
+        let mut resolved: BTreeMap<EditedObject, &Vec<EditCmd>> = BTreeMap::new();
+        for obj in base_net
+            .keys()
+            .chain(ours_net.keys())
+            .chain(theirs_net.keys())
+        {
+            let in_base = base_net.get(obj).unwrap_or(&no_changes);
+            let in_ours = ours_net.get(obj).unwrap_or(&no_changes);
+            let in_theirs = theirs_net.get(obj).unwrap_or(&no_changes);
+            let pick = if in_ours == in_theirs || in_theirs == in_base {
+                in_ours
+            } else if in_ours == in_base {
+                in_theirs
+            } else {
+                conflicts.insert(*obj);
+                in_ours
+            };
+            resolved.insert(*obj, pick);
+        }
+
+        // A command only survives if every object it touches resolved to its side. Otherwise
+        // something like a new road could be kept while the other side's changes to the
+        // intersection it connects to win.
+        let mut commands: Vec<EditCmd> = Vec::new();
+        for cmd in ours_net.values().chain(theirs_net.values()).flatten() {
+            if commands.contains(cmd) {
+                continue;
+            }
+            if cmd
+                .edited_objects(map)
+                .into_iter()
+                .all(|obj| resolved[&obj].contains(cmd))
+            {
+                commands.push(cmd.clone());
+            }
+        }
+
+        // New roads and intersections are numbered in the order they're created, and both sides
+        // started counting from the same basemap. If some object one side created can't be kept,
+        // later IDs would be out of order, so drop everything that side created.
+        let mut dropped: Vec<EditCmd> = Vec::new();
+        for (side, other) in [(&ours_net, &theirs_net), (&theirs_net, &ours_net)] {
+            let created: BTreeSet<EditedObject> = side
+                .iter()
+                .filter(|(obj, cmds)| {
+                    !base_net.contains_key(*obj)
+                        && other.get(*obj) != Some(*cmds)
+                        && cmds.iter().any(|cmd| cmd.created_objects().contains(*obj))
+                })
+                .map(|(obj, _)| *obj)
+                .collect();
+            let all_kept = created.iter().all(|obj| {
+                side[obj]
+                    .iter()
+                    .filter(|cmd| cmd.created_objects().contains(obj))
+                    .all(|cmd| commands.contains(cmd))
+            });
+            if all_kept {
+                continue;
+            }
+            for cmd in side.values().flatten() {
+                if cmd
+                    .edited_objects(map)
+                    .into_iter()
+                    .any(|obj| created.contains(&obj))
+                {
+                    dropped.push(cmd.clone());
+                }
+            }
+            conflicts.extend(created);
+        }
+        commands.retain(|cmd| !dropped.contains(cmd));
+        commands.sort_by_key(|cmd| cmd.merge_order());
+
+        let mut edits = MapEdits::new();
+        edits.edits_name = format!("{} + {}", ours.edits_name, theirs.edits_name);
+        edits.merge_zones = ours.merge_zones;
+        edits.commands = commands;
+        MergedEdits { edits, conflicts }
+    }
+}
+
+impl EditCmd {
+    /// Everything this command changes. Creating or splitting a road also changes the
+    /// intersections it connects to.
+    pub fn edited_objects(&self, map: &Map) -> Vec<EditedObject> {
+        match self {
+            EditCmd::ChangeRoad { r, .. } => vec![EditedObject::Road(*r)],
+            EditCmd::ChangeIntersection { i, .. } | EditCmd::ChangeCrosswalks { i, .. } => {
+                vec![EditedObject::Intersection(*i)]
+            }
+            EditCmd::ChangeRouteSchedule { id, .. } | EditCmd::ChangeTransitRoute { id, .. } => {
+                vec![EditedObject::TransitRoute(*id)]
+            }
+            EditCmd::ChangeTransitStop { id, .. } => vec![EditedObject::TransitStop(*id)],
+            EditCmd::AddRoad { r, old, new } => {
+                let mut objects = vec![EditedObject::Road(*r)];
+                if let Some(nr) = old.as_ref().or(new.as_ref()) {
+                    objects.push(EditedObject::Intersection(nr.src_i));
+                    objects.push(EditedObject::Intersection(nr.dst_i));
+                }
+                objects
+            }
+            EditCmd::SplitRoad { r, old, new } => {
+                let mut objects = vec![EditedObject::Road(*r)];
+                if let Some(split) = old.as_ref().or(new.as_ref()) {
+                    objects.push(EditedObject::Road(split.new_r));
+                    objects.push(EditedObject::Intersection(split.new_i));
+                    if let Ok(i) = map.find_i_by_osm_id(split.orig_id.i2) {
+                        objects.push(EditedObject::Intersection(i));
+                    }
+                }
+                objects
+            }
+        }
+    }
+
+    /// The new roads and intersections this command creates.
+    fn created_objects(&self) -> Vec<EditedObject> {
+        match self {
+            EditCmd::AddRoad {
+                r,
+                old: None,
+                new: Some(_),
+            } => vec![EditedObject::Road(*r)],
+            EditCmd::SplitRoad {
+                old: None,
+                new: Some(split),
+                ..
+            } => vec![
+                EditedObject::Road(split.new_r),
+                EditedObject::Intersection(split.new_i),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Do both commands change the same thing in the same way, such that the later one can be
+    /// folded into the earlier?
+    fn same_subject(&self, other: &EditCmd) -> bool {
+        match (self, other) {
+            (EditCmd::ChangeRoad { r: r1, .. }, EditCmd::ChangeRoad { r: r2, .. })
+            | (EditCmd::AddRoad { r: r1, .. }, EditCmd::AddRoad { r: r2, .. }) => r1 == r2,
+            (
+                EditCmd::ChangeIntersection { i: i1, .. },
+                EditCmd::ChangeIntersection { i: i2, .. },
+            )
+            | (EditCmd::ChangeCrosswalks { i: i1, .. }, EditCmd::ChangeCrosswalks { i: i2, .. }) => {
+                i1 == i2
+            }
+            (
+                EditCmd::ChangeRouteSchedule { id: id1, .. },
+                EditCmd::ChangeRouteSchedule { id: id2, .. },
+            )
+            | (
+                EditCmd::ChangeTransitRoute { id: id1, .. },
+                EditCmd::ChangeTransitRoute { id: id2, .. },
+            ) => id1 == id2,
+            (
+                EditCmd::ChangeTransitStop { id: id1, .. },
+                EditCmd::ChangeTransitStop { id: id2, .. },
+            ) => id1 == id2,
+            // The same road may be split several times, so match the piece that gets created
+            (
+                EditCmd::SplitRoad {
+                    old: old1,
+                    new: new1,
+                    ..
+                },
+                EditCmd::SplitRoad {
+                    old: old2,
+                    new: new2,
+                    ..
+                },
+            ) => {
+                old1.as_ref().or(new1.as_ref()).map(|s| s.new_r)
+                    == old2.as_ref().or(new2.as_ref()).map(|s| s.new_r)
+            }
+            _ => false,
+        }
+    }
+
+    /// Assuming `later` has the same subject and happens after this command, produce one command
+    /// with the combined effect.
+    fn chain(&self, later: &EditCmd) -> EditCmd {
+        let mut cmd = later.clone();
+        match (&mut cmd, self) {
+            (EditCmd::ChangeRoad { old, .. }, EditCmd::ChangeRoad { old: orig, .. }) => {
+                *old = orig.clone();
+            }
+            (
+                EditCmd::ChangeIntersection { old, .. },
+                EditCmd::ChangeIntersection { old: orig, .. },
+            ) => {
+                *old = orig.clone();
+            }
+            (
+                EditCmd::ChangeCrosswalks { old, .. },
+                EditCmd::ChangeCrosswalks { old: orig, .. },
+            ) => {
+                *old = orig.clone();
+            }
+            (
+                EditCmd::ChangeRouteSchedule { old, .. },
+                EditCmd::ChangeRouteSchedule { old: orig, .. },
+            ) => {
+                *old = orig.clone();
+            }
+            (
+                EditCmd::ChangeTransitStop { old, .. },
+                EditCmd::ChangeTransitStop { old: orig, .. },
+            ) => {
+                *old = orig.clone();
+            }
+            (
+                EditCmd::ChangeTransitRoute { old, .. },
+                EditCmd::ChangeTransitRoute { old: orig, .. },
+            ) => {
+                *old = orig.clone();
+            }
+            (EditCmd::AddRoad { old, .. }, EditCmd::AddRoad { old: orig, .. }) => {
+                *old = orig.clone();
+            }
+            (EditCmd::SplitRoad { old, .. }, EditCmd::SplitRoad { old: orig, .. }) => {
+                *old = orig.clone();
+            }
+            _ => unreachable!("chaining commands with different subjects"),
+        }
+        cmd
+    }
+
+    fn is_noop(&self) -> bool {
+        match self {
+            EditCmd::ChangeRoad { old, new, .. } => old == new,
+            EditCmd::ChangeIntersection { old, new, .. } => old == new,
+            EditCmd::ChangeRouteSchedule { old, new, .. } => old == new,
+            EditCmd::ChangeCrosswalks { old, new, .. } => old == new,
+            EditCmd::ChangeTransitStop { old, new, .. } => old == new,
+            EditCmd::ChangeTransitRoute { old, new, .. } => old == new,
+            EditCmd::AddRoad { old, new, .. } => old == new,
+            EditCmd::SplitRoad { old, new, .. } => old == new,
+        }
+    }
+
+    /// The same order `MapEdits::compress` uses: new roads first, in the order of their IDs, and
+    /// transit stops exist before routes use them.
+    fn merge_order(&self) -> (usize, usize) {
+        match self {
+            EditCmd::AddRoad { r, .. } => (0, r.0),
+            EditCmd::SplitRoad { old, new, .. } => (
+                0,
+                old.as_ref()
+                    .or(new.as_ref())
+                    .map(|split| split.new_r.0)
+                    .unwrap_or(0),
+            ),
+            EditCmd::ChangeRoad { .. } => (1, 0),
+            EditCmd::ChangeIntersection { .. } => (2, 0),
+            EditCmd::ChangeCrosswalks { .. } => (3, 0),
+            EditCmd::ChangeTransitStop { new: Some(_), .. } => (4, 0),
+            EditCmd::ChangeTransitRoute { .. } => (5, 0),
+            EditCmd::ChangeRouteSchedule { .. } => (6, 0),
+            EditCmd::ChangeTransitStop { new: None, .. } => (7, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::{Tags, Timer};
+    use geom::Speed;
+
+    use super::*;
+    use crate::testing::import_map;
+
+    fn edits(map: &Map, commands: Vec<EditCmd>) -> MapEdits {
+        let mut edits = map.new_edits();
+        edits.commands = commands;
+        edits
+    }
+
+    fn speed_limit_cmd(map: &Map, r: RoadID, mph: f64) -> EditCmd {
+        map.edit_road_cmd(r, |new| {
+            new.speed_limit = Speed::miles_per_hour(mph);
+        })
+    }
+
+    /// Connect two of the intersections that aren't borders.
+    fn new_road_cmd(map: &Map, i1: usize, i2: usize, highway: &str) -> EditCmd {
+        let intersections: Vec<IntersectionID> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| !i.is_border())
+            .map(|i| i.id)
+            .collect();
+        let mut tags = Tags::empty();
+        tags.insert("highway", highway);
+        map.new_road_cmd(intersections[i1], intersections[i2], Vec::new(), tags)
+            .unwrap()
+    }
+
+    /// Pretend `cmd` was made after another new road already existed.
+    fn renumber_new_road(mut cmd: EditCmd) -> EditCmd {
+        if let EditCmd::AddRoad {
+            ref mut r,
+            new: Some(ref mut new),
+            ..
+        } = cmd
+        {
+            r.0 += 1;
+            new.orig_id.osm_way_id.0 -= 1;
+        }
+        cmd
+    }
+
+    #[test]
+    fn test_clean_merge() {
+        let map = import_map();
+        let ours = edits(&map, vec![speed_limit_cmd(&map, RoadID(0), 15.0)]);
+        let theirs = edits(&map, vec![speed_limit_cmd(&map, RoadID(1), 40.0)]);
+
+        let merged = MapEdits::merge(&map.new_edits(), &ours, &theirs, &map);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.edits.commands,
+            vec![
+                speed_limit_cmd(&map, RoadID(0), 15.0),
+                speed_limit_cmd(&map, RoadID(1), 40.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflict_keeps_ours() {
+        let map = import_map();
+        let ours = edits(&map, vec![speed_limit_cmd(&map, RoadID(0), 15.0)]);
+        let theirs = edits(
+            &map,
+            vec![
+                speed_limit_cmd(&map, RoadID(0), 40.0),
+                speed_limit_cmd(&map, RoadID(1), 40.0),
+            ],
+        );
+
+        let merged = MapEdits::merge(&map.new_edits(), &ours, &theirs, &map);
+        assert_eq!(
+            merged.conflicts,
+            vec![EditedObject::Road(RoadID(0))].into_iter().collect()
+        );
+        assert_eq!(
+            merged.edits.commands,
+            vec![
+                speed_limit_cmd(&map, RoadID(0), 15.0),
+                speed_limit_cmd(&map, RoadID(1), 40.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_both_add_roads() {
+        let mut map = import_map();
+        let new_r = RoadID(map.all_roads().len());
+        let ours_road = new_road_cmd(&map, 0, 1, "residential");
+        let theirs_road = new_road_cmd(&map, 0, 1, "footway");
+        let ours = edits(&map, vec![ours_road.clone()]);
+        // Their second road would survive on its own, but it'd be created out of order.
+        let theirs = edits(
+            &map,
+            vec![
+                theirs_road,
+                renumber_new_road(new_road_cmd(&map, 2, 3, "cycleway")),
+                speed_limit_cmd(&map, RoadID(0), 40.0),
+            ],
+        );
+
+        let merged = MapEdits::merge(&map.new_edits(), &ours, &theirs, &map);
+        assert!(merged.conflicts.contains(&EditedObject::Road(new_r)));
+        assert!(merged
+            .conflicts
+            .contains(&EditedObject::Road(RoadID(new_r.0 + 1))));
+        assert_eq!(
+            merged.edits.commands,
+            vec![ours_road, speed_limit_cmd(&map, RoadID(0), 40.0)]
+        );
+
+        map.must_apply_edits(merged.edits, &mut Timer::throwaway());
+        assert_eq!(map.all_roads().len(), new_r.0 + 1);
+    }
+}
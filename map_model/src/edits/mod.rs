@@ -11,17 +11,22 @@ use abstutil::Timer;
 use geom::{Distance, HashablePt2D, Line, Speed, Time};
 use raw_map::{get_lane_specs_ltr, InputRoad};
 
+pub use self::merge::{EditedObject, EditsDiff, MergedEdits};
 pub use self::perma::PermanentMapEdits;
+pub use self::roads::{NewRoad, RoadSplit};
 use crate::make::{match_points_to_lanes, snap_driveway, trim_path};
 use crate::{
-    connectivity, AccessRestrictions, BuildingID, ControlStopSign, ControlTrafficSignal, Direction,
-    IntersectionID, IntersectionType, LaneID, LaneSpec, LaneType, Map, MapConfig, Movement,
-    ParkingLotID, PathConstraints, Pathfinder, Road, RoadID, TransitRouteID, TurnID, TurnType,
-    Zone,
+    connectivity, osm, AccessRestrictions, BuildingID, ControlStopSign, ControlTrafficSignal,
+    Direction, IntersectionID, IntersectionType, LaneID, LaneSpec, LaneType, Map, MapConfig,
+    Movement, MovementID, ParkingLotID, PathConstraints, Pathfinder, Road, RoadID, TransitRoute,
+    TransitRouteID, TransitStop, TransitStopID, TurnID, TurnType, Zone,
 };
 
 mod compat;
+mod merge;
 mod perma;
+mod roads;
+mod transit;
 
 /// Represents changes to a map. Note this isn't serializable -- that's what `PermanentMapEdits`
 /// does.
@@ -41,6 +46,11 @@ pub struct MapEdits {
     pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
     pub original_crosswalks: BTreeMap<IntersectionID, EditCrosswalks>,
     pub changed_routes: BTreeSet<TransitRouteID>,
+    /// `None` means the stop or route didn't exist in the basemap
+    pub original_transit_stops: BTreeMap<TransitStopID, Option<TransitStop>>,
+    pub original_transit_routes: BTreeMap<TransitRouteID, Option<TransitRoute>>,
+    /// The commands creating roads that don't exist in the basemap, keyed by the new road.
+    pub created_roads: BTreeMap<RoadID, EditCmd>,
 
     /// Some edits are included in the game by default, in data/system/proposals, as "community
     /// proposals." They require a description and may have a link to a write-up.
@@ -49,7 +59,15 @@ pub struct MapEdits {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum EditIntersection {
+pub struct EditIntersection {
+    pub control: EditIntersectionControl,
+    /// Vehicle movements that aren't allowed here, like "no left turn." Crosswalks can't be
+    /// banned; edit the sidewalks instead.
+    pub banned_movements: BTreeSet<MovementID>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditIntersectionControl {
     StopSign(ControlStopSign),
     // Don't keep ControlTrafficSignal here, because it contains movements that should be
     // generated after all lane edits are applied.
@@ -201,6 +219,32 @@ pub enum EditCmd {
         old: EditCrosswalks,
         new: EditCrosswalks,
     },
+    /// Creates, changes, or deletes a stop. `None` means the stop doesn't exist.
+    ChangeTransitStop {
+        id: TransitStopID,
+        old: Option<TransitStop>,
+        new: Option<TransitStop>,
+    },
+    /// Creates, reroutes, or deletes a route. `None` means the route doesn't exist.
+    ChangeTransitRoute {
+        id: TransitRouteID,
+        old: Option<TransitRoute>,
+        new: Option<TransitRoute>,
+    },
+    /// Creates a road, or removes one created by an earlier edit. `None` means the road doesn't
+    /// exist.
+    AddRoad {
+        r: RoadID,
+        old: Option<NewRoad>,
+        new: Option<NewRoad>,
+    },
+    /// Splits a road in two, or merges a road split by an earlier edit back together. `None`
+    /// means the road isn't split.
+    SplitRoad {
+        r: RoadID,
+        old: Option<RoadSplit>,
+        new: Option<RoadSplit>,
+    },
 }
 
 pub struct EditEffects {
@@ -211,9 +255,32 @@ pub struct EditEffects {
     pub added_turns: BTreeSet<TurnID>,
     pub deleted_turns: BTreeSet<TurnID>,
     pub changed_parking_lots: BTreeSet<ParkingLotID>,
+    pub changed_transit_stops: BTreeSet<TransitStopID>,
+    pub changed_transit_routes: BTreeSet<TransitRouteID>,
+    /// Roads and intersections that were created by edits and have been removed again
+    pub deleted_roads: BTreeSet<RoadID>,
+    pub deleted_intersections: BTreeSet<IntersectionID>,
     modified_lanes: BTreeSet<LaneID>,
 }
 
+impl EditEffects {
+    fn new() -> EditEffects {
+        EditEffects {
+            changed_roads: BTreeSet::new(),
+            deleted_lanes: BTreeSet::new(),
+            changed_intersections: BTreeSet::new(),
+            added_turns: BTreeSet::new(),
+            deleted_turns: BTreeSet::new(),
+            changed_parking_lots: BTreeSet::new(),
+            changed_transit_stops: BTreeSet::new(),
+            changed_transit_routes: BTreeSet::new(),
+            deleted_roads: BTreeSet::new(),
+            deleted_intersections: BTreeSet::new(),
+            modified_lanes: BTreeSet::new(),
+        }
+    }
+}
+
 impl MapEdits {
     pub(crate) fn new() -> MapEdits {
         MapEdits {
@@ -227,6 +294,9 @@ impl MapEdits {
             original_intersections: BTreeMap::new(),
             original_crosswalks: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_transit_stops: BTreeMap::new(),
+            original_transit_routes: BTreeMap::new(),
+            created_roads: BTreeMap::new(),
         }
     }
 
@@ -276,10 +346,14 @@ impl MapEdits {
             return;
         }
 
-        abstio::write_json(
-            abstio::path_edits(map.get_name(), &self.edits_name),
-            &self.to_permanent(map),
-        );
+        match self.to_permanent(map) {
+            Ok(perma) => {
+                abstio::write_json(abstio::path_edits(map.get_name(), &self.edits_name), &perma);
+            }
+            Err(err) => {
+                error!("Couldn't save {}: {}", self.edits_name, err);
+            }
+        }
     }
 
     fn update_derived(&mut self, map: &Map) {
@@ -287,6 +361,9 @@ impl MapEdits {
         self.original_intersections.clear();
         self.original_crosswalks.clear();
         self.changed_routes.clear();
+        self.original_transit_stops.clear();
+        self.original_transit_routes.clear();
+        self.created_roads.clear();
 
         for cmd in &self.commands {
             match cmd {
@@ -306,24 +383,60 @@ impl MapEdits {
                 EditCmd::ChangeRouteSchedule { id, .. } => {
                     self.changed_routes.insert(*id);
                 }
+                EditCmd::ChangeTransitStop { id, ref old, .. } => {
+                    if !self.original_transit_stops.contains_key(id) {
+                        self.original_transit_stops.insert(*id, old.clone());
+                    }
+                }
+                EditCmd::ChangeTransitRoute { id, ref old, .. } => {
+                    if !self.original_transit_routes.contains_key(id) {
+                        self.original_transit_routes.insert(*id, old.clone());
+                    }
+                }
+                EditCmd::AddRoad { r, .. } => {
+                    self.created_roads.insert(*r, cmd.clone());
+                }
+                EditCmd::SplitRoad {
+                    ref old, ref new, ..
+                } => {
+                    if let Some(split) = old.as_ref().or(new.as_ref()) {
+                        self.created_roads.insert(split.new_r, cmd.clone());
+                    }
+                }
             }
         }
 
         self.changed_roads.retain(|r| {
-            map.get_r_edit(*r) != EditRoad::get_orig_from_osm(map.get_r(*r), &map.config)
+            // The road may have been created by an edit that was undone
+            map.maybe_get_r(*r)
+                .map(|road| map.get_r_edit(*r) != EditRoad::get_orig_from_osm(road, &map.config))
+                .unwrap_or(false)
         });
         self.original_intersections
             .retain(|i, orig| map.get_i_edit(*i) != orig.clone());
         self.original_crosswalks
             .retain(|i, orig| map.get_i_crosswalks_edit(*i) != orig.clone());
         self.changed_routes.retain(|br| {
-            let r = map.get_tr(*br);
-            r.spawn_times != r.orig_spawn_times
+            // The route may have been deleted
+            map.maybe_get_tr(*br)
+                .map(|r| r.spawn_times != r.orig_spawn_times)
+                .unwrap_or(false)
         });
+        self.original_transit_stops
+            .retain(|id, orig| map.maybe_get_ts(*id) != orig.as_ref());
+        self.original_transit_routes
+            .retain(|id, orig| map.maybe_get_tr(*id) != orig.as_ref());
+        self.created_roads
+            .retain(|r, _| map.maybe_get_r(*r).is_some());
     }
 
     /// Assumes update_derived has been called.
     pub fn compress(&mut self, map: &Map) {
+        // Everything else might refer to new roads and intersections, and they have to be created
+        // in the same order to get the same IDs.
+        for cmd in self.created_roads.values() {
+            self.commands.push(cmd.clone());
+        }
         for r in &self.changed_roads {
             self.commands.push(EditCmd::ChangeRoad {
                 r: *r,
@@ -345,6 +458,24 @@ impl MapEdits {
                 new: map.get_i_crosswalks_edit(*i),
             });
         }
+        // Stops have to exist before routes use them, and routes have to stop using them before
+        // they're deleted.
+        for (id, old) in &self.original_transit_stops {
+            if let Some(new) = map.maybe_get_ts(*id) {
+                self.commands.push(EditCmd::ChangeTransitStop {
+                    id: *id,
+                    old: old.clone(),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+        for (id, old) in &self.original_transit_routes {
+            self.commands.push(EditCmd::ChangeTransitRoute {
+                id: *id,
+                old: old.clone(),
+                new: map.maybe_get_tr(*id).cloned(),
+            });
+        }
         for r in &self.changed_routes {
             let r = map.get_tr(*r);
             self.commands.push(EditCmd::ChangeRouteSchedule {
@@ -353,6 +484,15 @@ impl MapEdits {
                 old: r.orig_spawn_times.clone(),
             });
         }
+        for (id, old) in &self.original_transit_stops {
+            if map.maybe_get_ts(*id).is_none() {
+                self.commands.push(EditCmd::ChangeTransitStop {
+                    id: *id,
+                    old: old.clone(),
+                    new: None,
+                });
+            }
+        }
     }
 
     /// Pick apart changed_roads and figure out if an entire road was edited, or just a few lanes.
@@ -379,15 +519,22 @@ impl MapEdits {
                 }
             }
         }
+        // New roads and both pieces of split roads are entirely changed
+        for (r, cmd) in &self.created_roads {
+            roads.insert(*r);
+            if let EditCmd::SplitRoad { r, .. } = cmd {
+                roads.insert(*r);
+            }
+        }
         (lanes, roads)
     }
 
     /// Produces an md5sum of the contents of the edits.
-    pub fn get_checksum(&self, map: &Map) -> String {
-        let bytes = abstutil::to_json(&self.to_permanent(map));
+    pub fn get_checksum(&self, map: &Map) -> Result<String> {
+        let bytes = abstutil::to_json(&self.to_permanent(map)?);
         let mut context = md5::Context::new();
         context.consume(&bytes);
-        format!("{:x}", context.compute())
+        Ok(format!("{:x}", context.compute()))
     }
 
     /// Get the human-friendly of these edits. If they have a descrption, the first line is the
@@ -417,15 +564,70 @@ impl EditCmd {
                 format!("road #{}", r.0)
             }
             // TODO Describe changes
-            EditCmd::ChangeIntersection { i, new, .. } => match new {
-                EditIntersection::StopSign(_) => format!("stop sign #{}", i.0),
-                EditIntersection::TrafficSignal(_) => format!("traffic signal #{}", i.0),
-                EditIntersection::Closed => format!("close {}", i),
-            },
+            EditCmd::ChangeIntersection { i, new, old } => {
+                for m in new.banned_movements.difference(&old.banned_movements) {
+                    details.push(format!("ban {}", describe_movement(*m, map)));
+                }
+                for m in old.banned_movements.difference(&new.banned_movements) {
+                    details.push(format!("allow {}", describe_movement(*m, map)));
+                }
+                match new.control {
+                    EditIntersectionControl::StopSign(ref ss) => {
+                        if ss.yield_on_entry {
+                            format!("yield #{}", i.0)
+                        } else {
+                            format!("stop sign #{}", i.0)
+                        }
+                    }
+                    EditIntersectionControl::TrafficSignal(_) => {
+                        format!("traffic signal #{}", i.0)
+                    }
+                    EditIntersectionControl::Closed => format!("close {}", i),
+                }
+            }
             EditCmd::ChangeCrosswalks { i, .. } => format!("crosswalks at {}", i),
             EditCmd::ChangeRouteSchedule { id, .. } => {
                 format!("reschedule route {}", map.get_tr(*id).short_name)
             }
+            EditCmd::ChangeTransitStop { id, old, new } => match (old, new) {
+                (None, Some(ts)) => format!("add stop {}", ts.name),
+                (Some(ts), None) => format!("remove stop {}", ts.name),
+                _ => format!("change stop {}", id),
+            },
+            EditCmd::ChangeTransitRoute { id, old, new } => match (old, new) {
+                (None, Some(tr)) => {
+                    details.push(format!("{} stops", tr.stops.len()));
+                    format!("add route {}", tr.short_name)
+                }
+                (Some(tr), None) => format!("remove route {}", tr.short_name),
+                (Some(old), Some(new)) => {
+                    details.push(format!(
+                        "{} stops, previously {}",
+                        new.stops.len(),
+                        old.stops.len()
+                    ));
+                    format!("reroute {}", new.short_name)
+                }
+                (None, None) => format!("change {}", id),
+            },
+            EditCmd::AddRoad { r, old, new } => match (old, new) {
+                (None, Some(nr)) => format!(
+                    "add {} #{}",
+                    nr.osm_tags
+                        .get(osm::HIGHWAY)
+                        .map(|x| x.as_str())
+                        .unwrap_or("road"),
+                    r.0
+                ),
+                _ => format!("remove road #{}", r.0),
+            },
+            EditCmd::SplitRoad { r, old, new } => match (old, new) {
+                (None, Some(split)) => {
+                    details.push(format!("new intersection #{}", split.new_i.0));
+                    format!("split road #{}", r.0)
+                }
+                _ => format!("merge road #{}", r.0),
+            },
         };
         (summary, details)
     }
@@ -446,21 +648,8 @@ impl EditCmd {
                 effects.changed_roads.insert(road.id);
                 for i in [road.src_i, road.dst_i] {
                     effects.changed_intersections.insert(i);
-                    let i = &mut map.intersections[i.0];
-                    i.outgoing_lanes.clear();
-                    i.incoming_lanes.clear();
-                    for r in &i.roads {
-                        for lane in &map.roads[r.0].lanes {
-                            if lane.src_i == i.id {
-                                i.outgoing_lanes.push(lane.id);
-                            } else {
-                                assert_eq!(lane.dst_i, i.id);
-                                i.incoming_lanes.push(lane.id);
-                            }
-                        }
-                    }
-
-                    recalculate_turns(i.id, map, effects);
+                    recalculate_lane_connections(i, map);
+                    recalculate_turns(i, map, effects);
                 }
             }
             EditCmd::ChangeIntersection {
@@ -475,28 +664,40 @@ impl EditCmd {
                 map.stop_signs.remove(i);
                 map.traffic_signals.remove(i);
                 effects.changed_intersections.insert(*i);
-                match new {
-                    EditIntersection::StopSign(ref ss) => {
-                        map.intersections[i.0].intersection_type = IntersectionType::StopSign;
+                map.intersections[i.0].intersection_type = match new.control {
+                    EditIntersectionControl::StopSign(_) => IntersectionType::StopSign,
+                    EditIntersectionControl::TrafficSignal(_) => IntersectionType::TrafficSignal,
+                    EditIntersectionControl::Closed => IntersectionType::Construction,
+                };
+                // Opening or closing the intersection or changing the banned movements changes
+                // the turns. This also regenerates the default stop sign or traffic signal, which
+                // is overwritten below.
+                if old.control == EditIntersectionControl::Closed
+                    || new.control == EditIntersectionControl::Closed
+                    || map.get_i(*i).banned_movements != new.banned_movements
+                {
+                    map.intersections[i.0].banned_movements = new.banned_movements.clone();
+                    recalculate_turns(*i, map, effects);
+                }
+
+                match new.control {
+                    EditIntersectionControl::StopSign(ref ss) => {
                         map.stop_signs.insert(*i, ss.clone());
                     }
-                    EditIntersection::TrafficSignal(ref raw_ts) => {
-                        map.intersections[i.0].intersection_type = IntersectionType::TrafficSignal;
-                        if old == &EditIntersection::Closed {
-                            recalculate_turns(*i, map, effects);
+                    EditIntersectionControl::TrafficSignal(ref raw_ts) => {
+                        // The signal might've been configured before some movements were banned
+                        let raw_ts = without_banned_movements(raw_ts.clone(), *i, map);
+                        match ControlTrafficSignal::import(raw_ts, *i, map) {
+                            Ok(ts) => {
+                                map.traffic_signals.insert(*i, ts);
+                            }
+                            Err(err) => {
+                                // Keep the signal recalculate_turns generated
+                                warn!("Traffic signal edit at {} no longer valid: {}", i, err);
+                            }
                         }
-                        map.traffic_signals.insert(
-                            *i,
-                            ControlTrafficSignal::import(raw_ts.clone(), *i, map).unwrap(),
-                        );
-                    }
-                    EditIntersection::Closed => {
-                        map.intersections[i.0].intersection_type = IntersectionType::Construction;
                     }
-                }
-
-                if old == &EditIntersection::Closed || new == &EditIntersection::Closed {
-                    recalculate_turns(*i, map, effects);
+                    EditIntersectionControl::Closed => {}
                 }
             }
             EditCmd::ChangeCrosswalks { i, ref new, .. } => {
@@ -509,8 +710,63 @@ impl EditCmd {
                 }
             }
             EditCmd::ChangeRouteSchedule { id, new, .. } => {
-                map.transit_routes[id.0].spawn_times = new.clone();
+                map.transit_routes.get_mut(id).unwrap().spawn_times = new.clone();
             }
+            EditCmd::ChangeTransitStop { id, ref new, .. } => {
+                if map.maybe_get_ts(*id) == new.as_ref() {
+                    return;
+                }
+
+                effects.changed_transit_stops.insert(*id);
+                let road = &mut map.roads[id.road.0];
+                if let Some(ts) = new {
+                    road.transit_stops.insert(*id);
+                    map.transit_stops.insert(*id, ts.clone());
+                } else {
+                    road.transit_stops.remove(id);
+                    map.transit_stops.remove(id);
+                }
+            }
+            EditCmd::ChangeTransitRoute { id, ref new, .. } => {
+                if map.maybe_get_tr(*id) == new.as_ref() {
+                    return;
+                }
+
+                effects.changed_transit_routes.insert(*id);
+                if let Some(tr) = new {
+                    map.transit_routes.insert(*id, tr.clone());
+                } else {
+                    map.transit_routes.remove(id);
+                }
+            }
+            EditCmd::AddRoad { r, ref new, .. } => {
+                if map.maybe_get_r(*r).is_some() == new.is_some() {
+                    return;
+                }
+
+                if let Some(nr) = new {
+                    roads::add_road(map, *r, nr, effects);
+                } else {
+                    roads::remove_road(map, *r, effects);
+                }
+            }
+            EditCmd::SplitRoad {
+                r,
+                ref old,
+                ref new,
+            } => match (old, new) {
+                (None, Some(split)) => {
+                    if map.maybe_get_r(split.new_r).is_none() {
+                        roads::split_road(map, *r, split, effects);
+                    }
+                }
+                (Some(split), None) => {
+                    if map.maybe_get_r(split.new_r).is_some() {
+                        roads::merge_road(map, *r, split, effects);
+                    }
+                }
+                _ => {}
+            },
         }
     }
 
@@ -536,6 +792,26 @@ impl EditCmd {
                 old: new,
                 new: old,
             },
+            EditCmd::ChangeTransitStop { id, old, new } => EditCmd::ChangeTransitStop {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeTransitRoute { id, old, new } => EditCmd::ChangeTransitRoute {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::AddRoad { r, old, new } => EditCmd::AddRoad {
+                r,
+                old: new,
+                new: old,
+            },
+            EditCmd::SplitRoad { r, old, new } => EditCmd::SplitRoad {
+                r,
+                old: new,
+                new: old,
+            },
         }
     }
 }
@@ -589,6 +865,50 @@ fn recalculate_turns(id: IntersectionID, map: &mut Map, effects: &mut EditEffect
     }
 }
 
+fn recalculate_lane_connections(id: IntersectionID, map: &mut Map) {
+    let i = &mut map.intersections[id.0];
+    i.outgoing_lanes.clear();
+    i.incoming_lanes.clear();
+    for r in &i.roads {
+        for lane in &map.roads[r.0].lanes {
+            if lane.src_i == i.id {
+                i.outgoing_lanes.push(lane.id);
+            } else {
+                assert_eq!(lane.dst_i, i.id);
+                i.incoming_lanes.push(lane.id);
+            }
+        }
+    }
+}
+
+fn describe_movement(m: MovementID, map: &Map) -> String {
+    format!(
+        "{} to {}",
+        map.get_r(m.from.road).get_name(None),
+        map.get_r(m.to.road).get_name(None)
+    )
+}
+
+fn without_banned_movements(
+    mut raw: traffic_signal_data::TrafficSignal,
+    i: IntersectionID,
+    map: &Map,
+) -> traffic_signal_data::TrafficSignal {
+    let banned = &map.get_i(i).banned_movements;
+    let allowed = |t: &traffic_signal_data::Turn| {
+        MovementID::from_permanent(t.clone(), map)
+            .map(|m| !banned.contains(&m))
+            .unwrap_or(true)
+    };
+    for plan in &mut raw.plans {
+        for stage in &mut plan.stages {
+            stage.protected_turns.retain(allowed);
+            stage.permitted_turns.retain(allowed);
+        }
+    }
+    raw
+}
+
 fn modify_lanes(map: &mut Map, r: RoadID, lanes_ltr: Vec<LaneSpec>, effects: &mut EditEffects) {
     // First update intersection geometry and re-trim the road centers.
     let mut road_geom_changed = Vec::new();
@@ -828,16 +1148,76 @@ impl Map {
 
     /// Panics on borders
     pub fn get_i_edit(&self, i: IntersectionID) -> EditIntersection {
-        match self.get_i(i).intersection_type {
-            IntersectionType::StopSign => EditIntersection::StopSign(self.get_stop_sign(i).clone()),
+        let control = match self.get_i(i).intersection_type {
+            IntersectionType::StopSign => {
+                EditIntersectionControl::StopSign(self.get_stop_sign(i).clone())
+            }
             IntersectionType::TrafficSignal => {
-                EditIntersection::TrafficSignal(self.get_traffic_signal(i).export(self))
+                EditIntersectionControl::TrafficSignal(self.get_traffic_signal(i).export(self))
             }
-            IntersectionType::Construction => EditIntersection::Closed,
+            IntersectionType::Construction => EditIntersectionControl::Closed,
             IntersectionType::Border => unreachable!(),
+        };
+        EditIntersection {
+            control,
+            banned_movements: self.get_i(i).banned_movements.clone(),
         }
     }
 
+    pub fn edit_intersection_cmd<F: Fn(&mut EditIntersection)>(
+        &self,
+        i: IntersectionID,
+        f: F,
+    ) -> EditCmd {
+        let old = self.get_i_edit(i);
+        let mut new = old.clone();
+        f(&mut new);
+        EditCmd::ChangeIntersection { i, old, new }
+    }
+
+    /// Ban or allow a vehicle movement at an intersection, like "no left turn from Main St onto
+    /// 1st Ave". Fails if the movement is a crosswalk, doesn't exist, or banning it would leave
+    /// some lane with no way out.
+    pub fn ban_movement_cmd(&self, mvmnt: MovementID, banned: bool) -> Result<EditCmd> {
+        let i = self.get_i(mvmnt.parent);
+        if mvmnt.crosswalk {
+            bail!("Crosswalks can't be banned");
+        }
+        if banned {
+            if !i.movements.contains_key(&mvmnt) {
+                bail!("{:?} doesn't exist", mvmnt);
+            }
+            // Every incoming lane must still lead somewhere
+            for l in &i.incoming_lanes {
+                let lane = self.get_l(*l);
+                if !lane.is_walkable()
+                    && i.turns.iter().any(|t| t.id.src == *l)
+                    && i.turns
+                        .iter()
+                        .all(|t| t.id.src != *l || t.id.to_movement(self) == mvmnt)
+                {
+                    bail!("Banning this would leave {} with no way out", l);
+                }
+            }
+        } else if !i.banned_movements.contains(&mvmnt) {
+            bail!("{:?} isn't banned", mvmnt);
+        }
+        let turn = mvmnt.to_permanent(self);
+        Ok(self.edit_intersection_cmd(i.id, |new| {
+            if banned {
+                new.banned_movements.insert(mvmnt);
+                if let EditIntersectionControl::TrafficSignal(ref mut raw) = new.control {
+                    for stage in raw.plans.iter_mut().flat_map(|p| p.stages.iter_mut()) {
+                        stage.protected_turns.remove(&turn);
+                        stage.permitted_turns.remove(&turn);
+                    }
+                }
+            } else {
+                new.banned_movements.remove(&mvmnt);
+            }
+        }))
+    }
+
     pub fn get_i_crosswalks_edit(&self, i: IntersectionID) -> EditCrosswalks {
         let mut turns = BTreeMap::new();
         for turn in &self.get_i(i).turns {
@@ -872,25 +1252,37 @@ impl Map {
         self.edits = self.new_edits();
     }
 
+    /// Apply commands without making them part of the map's edits, for temporary changes like a
+    /// simulation closing roads for a while. They're never saved, and the caller has to revert
+    /// them with more commands later. Doesn't update pathfinding yet.
+    pub fn apply_temporary_edits(&mut self, cmds: &[EditCmd], timer: &mut Timer) -> EditEffects {
+        self.edits_generation += 1;
+
+        let mut effects = EditEffects::new();
+        if cmds.is_empty() {
+            return effects;
+        }
+
+        timer.start_iter("apply temporary edits", cmds.len());
+        for cmd in cmds {
+            timer.next();
+            cmd.apply(&mut effects, self);
+        }
+
+        self.finish_applying_edits(effects, true, None)
+    }
+
     // new_edits don't necessarily have to be valid; this could be used for speculatively testing
     // edits. Doesn't update pathfinding yet.
     fn apply_edits(
         &mut self,
-        mut new_edits: MapEdits,
+        new_edits: MapEdits,
         enforce_valid: bool,
         timer: &mut Timer,
     ) -> EditEffects {
         self.edits_generation += 1;
 
-        let mut effects = EditEffects {
-            changed_roads: BTreeSet::new(),
-            deleted_lanes: BTreeSet::new(),
-            changed_intersections: BTreeSet::new(),
-            added_turns: BTreeSet::new(),
-            deleted_turns: BTreeSet::new(),
-            changed_parking_lots: BTreeSet::new(),
-            modified_lanes: BTreeSet::new(),
-        };
+        let mut effects = EditEffects::new();
 
         // Short-circuit to avoid marking pathfinder_dirty
         if self.edits == new_edits {
@@ -927,6 +1319,25 @@ impl Map {
             cmd.apply(&mut effects, self);
         }
 
+        self.finish_applying_edits(effects, enforce_valid, Some(new_edits))
+    }
+
+    // Fix up everything derived from the commands just applied. If the edits themselves changed,
+    // record them too.
+    fn finish_applying_edits(
+        &mut self,
+        mut effects: EditEffects,
+        enforce_valid: bool,
+        new_edits: Option<MapEdits>,
+    ) -> EditEffects {
+        // Roads created by edits may have been removed again, or the other way around
+        effects
+            .deleted_roads
+            .retain(|r| self.maybe_get_r(*r).is_none());
+        effects
+            .changed_roads
+            .retain(|r| self.maybe_get_r(*r).is_some());
+
         timer.start("re-snap buildings");
         let mut recalc_buildings = Vec::new();
         for b in self.all_buildings() {
@@ -969,10 +1380,12 @@ impl Map {
             }
         }
 
-        let merge_zones_changed = self.edits.merge_zones != new_edits.merge_zones;
-
-        new_edits.update_derived(self);
-        self.edits = new_edits;
+        let mut merge_zones_changed = false;
+        if let Some(mut new_edits) = new_edits {
+            merge_zones_changed = self.edits.merge_zones != new_edits.merge_zones;
+            new_edits.update_derived(self);
+            self.edits = new_edits;
+        }
         self.pathfinder_dirty = true;
 
         // Update zones after setting the new edits, since it'll pull merge_zones from there
@@ -996,6 +1409,12 @@ impl Map {
         effects
             .changed_intersections
             .extend(more_changed_intersections);
+        effects
+            .deleted_intersections
+            .retain(|i| self.maybe_get_i(*i).is_none());
+        effects
+            .changed_intersections
+            .retain(|i| self.maybe_get_i(*i).is_some());
 
         self.recalculate_road_to_buildings();
 
@@ -1047,3 +1466,58 @@ impl Map {
         self.edits_generation
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::import_map;
+
+    fn apply(map: &mut Map, cmd: EditCmd) {
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(cmd);
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+    }
+
+    fn has_turns(map: &Map, mvmnt: MovementID) -> bool {
+        map.get_i(mvmnt.parent)
+            .turns
+            .iter()
+            .any(|t| t.id.to_movement(map) == mvmnt)
+    }
+
+    #[test]
+    fn test_ban_movement() {
+        let mut map = import_map();
+        // Find the first movement that can be banned without stranding a lane
+        let (mvmnt, cmd) = map
+            .all_intersections()
+            .iter()
+            .flat_map(|i| i.movements.keys())
+            .filter(|m| !m.crosswalk)
+            .find_map(|m| map.ban_movement_cmd(*m, true).ok().map(|cmd| (*m, cmd)))
+            .unwrap();
+        assert!(has_turns(&map, mvmnt));
+        assert!(map.ban_movement_cmd(mvmnt, false).is_err());
+
+        apply(&mut map, cmd);
+        assert!(map.get_i(mvmnt.parent).banned_movements.contains(&mvmnt));
+        assert!(!has_turns(&map, mvmnt));
+
+        let cmd = map.ban_movement_cmd(mvmnt, false).unwrap();
+        apply(&mut map, cmd);
+        assert!(map.get_i(mvmnt.parent).banned_movements.is_empty());
+        assert!(has_turns(&map, mvmnt));
+    }
+
+    #[test]
+    fn test_crosswalks_cant_be_banned() {
+        let map = import_map();
+        let crosswalk = map
+            .all_intersections()
+            .iter()
+            .flat_map(|i| i.movements.keys())
+            .find(|m| m.crosswalk)
+            .unwrap();
+        assert!(map.ban_movement_cmd(*crosswalk, true).is_err());
+    }
+}
@@ -4,12 +4,18 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
-use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::Time;
+use abstutil::{deserialize_btreemap, serialize_btreemap, Tags};
+use geom::{Distance, LonLat, PolyLine, Time};
 
-use crate::edits::{EditCmd, EditCrosswalks, EditIntersection, EditRoad, MapEdits};
+use crate::edits::{
+    EditCmd, EditCrosswalks, EditEffects, EditIntersection, EditIntersectionControl, EditRoad,
+    MapEdits, NewRoad, RoadSplit,
+};
 use crate::raw::OriginalRoad;
-use crate::{osm, ControlStopSign, IntersectionID, Map, MovementID, TurnType};
+use crate::{
+    osm, ControlStopSign, IntersectionID, LaneID, Map, MovementID, PathConstraints, Pathfinder,
+    Position, RoadID, TransitRoute, TransitRouteID, TransitStop, TransitStopID, TurnType,
+};
 
 /// MapEdits are converted to this before serializing. Referencing things like LaneID in a Map won't
 /// work if the basemap is rebuilt from new OSM data, so instead we use stabler OSM IDs that're less
@@ -32,13 +38,21 @@ pub struct PermanentMapEdits {
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-pub enum PermanentEditIntersection {
+pub struct PermanentEditIntersection {
+    control: PermanentEditIntersectionControl,
+    banned_movements: Vec<traffic_signal_data::Turn>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum PermanentEditIntersectionControl {
     StopSign {
         #[serde(
             serialize_with = "serialize_btreemap",
             deserialize_with = "deserialize_btreemap"
         )]
         must_stop: BTreeMap<OriginalRoad, bool>,
+        #[serde(default)]
+        yield_on_entry: bool,
     },
     TrafficSignal(traffic_signal_data::TrafficSignal),
     Closed,
@@ -76,11 +90,84 @@ pub enum PermanentEditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeTransitStop {
+        gtfs_id: String,
+        old: Option<PermanentTransitStop>,
+        new: Option<PermanentTransitStop>,
+    },
+    ChangeTransitRoute {
+        gtfs_id: String,
+        old: Option<PermanentTransitRoute>,
+        new: Option<PermanentTransitRoute>,
+    },
+    AddRoad {
+        r: OriginalRoad,
+        old: Option<PermanentNewRoad>,
+        new: Option<PermanentNewRoad>,
+    },
+    /// `r` is the road's ID before it's split
+    SplitRoad {
+        r: OriginalRoad,
+        old: Option<PermanentRoadSplit>,
+        new: Option<PermanentRoadSplit>,
+    },
+}
+
+/// Refers to a lane by its road's OSM ID and its position in the road, left-to-right.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentLane {
+    r: OriginalRoad,
+    idx: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentTransitStop {
+    name: String,
+    sidewalk: PermanentLane,
+    sidewalk_dist: Distance,
+    driving: PermanentLane,
+    driving_dist: Distance,
+    is_train_stop: bool,
+}
+
+/// Stops are referred to by their GTFS ID. Stops created by edits have a made-up one.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentTransitRoute {
+    long_name: String,
+    short_name: String,
+    stops: Vec<String>,
+    start: PermanentLane,
+    end_border: Option<PermanentLane>,
+    route_type: PathConstraints,
+    spawn_times: Vec<Time>,
+    orig_spawn_times: Vec<Time>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentNewRoad {
+    src_i: osm::NodeID,
+    dst_i: osm::NodeID,
+    untrimmed_center_pts: Vec<LonLat>,
+    osm_tags: Tags,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentRoadSplit {
+    dist: Distance,
+    new_i: osm::NodeID,
+}
+
+/// Stops and routes created by edits don't exist in the basemap, so they get new IDs as the
+/// commands are converted.
+#[derive(Default)]
+struct NewTransitIDs {
+    stops: BTreeMap<String, TransitStopID>,
+    routes: BTreeMap<String, TransitRouteID>,
 }
 
 impl EditCmd {
-    pub fn to_perma(&self, map: &Map) -> PermanentEditCmd {
-        match self {
+    pub fn to_perma(&self, map: &Map) -> Result<PermanentEditCmd> {
+        Ok(match self {
             EditCmd::ChangeRoad { r, new, old } => PermanentEditCmd::ChangeRoad {
                 r: map.get_r(*r).orig_id,
                 new: new.clone(),
@@ -103,12 +190,36 @@ impl EditCmd {
                     new: new.clone(),
                 }
             }
-        }
+            EditCmd::ChangeTransitStop { old, new, .. } => PermanentEditCmd::ChangeTransitStop {
+                gtfs_id: old.as_ref().or(new.as_ref()).unwrap().gtfs_id.clone(),
+                old: old.as_ref().map(|ts| ts.to_permanent(map)),
+                new: new.as_ref().map(|ts| ts.to_permanent(map)),
+            },
+            EditCmd::ChangeTransitRoute { old, new, .. } => PermanentEditCmd::ChangeTransitRoute {
+                gtfs_id: old.as_ref().or(new.as_ref()).unwrap().gtfs_id.clone(),
+                old: old.as_ref().map(|tr| tr.to_permanent(map)).transpose()?,
+                new: new.as_ref().map(|tr| tr.to_permanent(map)).transpose()?,
+            },
+            EditCmd::AddRoad { old, new, .. } => PermanentEditCmd::AddRoad {
+                r: old.as_ref().or(new.as_ref()).unwrap().orig_id,
+                old: old.as_ref().map(|nr| nr.to_permanent(map)),
+                new: new.as_ref().map(|nr| nr.to_permanent(map)),
+            },
+            EditCmd::SplitRoad { old, new, .. } => PermanentEditCmd::SplitRoad {
+                r: old.as_ref().or(new.as_ref()).unwrap().orig_id,
+                old: old.as_ref().map(|split| split.to_permanent()),
+                new: new.as_ref().map(|split| split.to_permanent()),
+            },
+        })
     }
 }
 
 impl PermanentEditCmd {
     pub fn into_cmd(self, map: &Map) -> Result<EditCmd> {
+        self.into_cmd_with_ids(map, &mut NewTransitIDs::default())
+    }
+
+    fn into_cmd_with_ids(self, map: &Map, new_ids: &mut NewTransitIDs) -> Result<EditCmd> {
         match self {
             PermanentEditCmd::ChangeRoad { r, new, old } => {
                 let id = map.find_r_by_osm_id(r)?;
@@ -155,23 +266,211 @@ impl PermanentEditCmd {
                     .ok_or_else(|| anyhow!("can't find {}", gtfs_id))?;
                 Ok(EditCmd::ChangeRouteSchedule { id, old, new })
             }
+            PermanentEditCmd::ChangeTransitStop { gtfs_id, old, new } => {
+                let id = if let Some(id) = map.find_ts_by_gtfs(&gtfs_id) {
+                    id
+                } else if let Some(id) = new_ids.stops.get(&gtfs_id) {
+                    *id
+                } else {
+                    // A stop that doesn't exist in the basemap has to be created by this command
+                    let road = match new {
+                        Some(ref ts) => ts.sidewalk.resolve(map)?.road,
+                        None => bail!("stop {} doesn't exist", gtfs_id),
+                    };
+                    let idx = map
+                        .get_r(road)
+                        .transit_stops
+                        .iter()
+                        .chain(new_ids.stops.values())
+                        .filter(|ts| ts.road == road)
+                        .map(|ts| ts.idx + 1)
+                        .max()
+                        .unwrap_or(0);
+                    let id = TransitStopID { road, idx };
+                    new_ids.stops.insert(gtfs_id.clone(), id);
+                    id
+                };
+                Ok(EditCmd::ChangeTransitStop {
+                    id,
+                    old: old
+                        .map(|ts| ts.with_permanent(id, gtfs_id.clone(), map))
+                        .transpose()?,
+                    new: new
+                        .map(|ts| ts.with_permanent(id, gtfs_id.clone(), map))
+                        .transpose()?,
+                })
+            }
+            PermanentEditCmd::ChangeTransitRoute { gtfs_id, old, new } => {
+                let id = if let Some(id) = map.find_tr_by_gtfs(&gtfs_id) {
+                    id
+                } else if let Some(id) = new_ids.routes.get(&gtfs_id) {
+                    *id
+                } else {
+                    let id = TransitRouteID(
+                        map.all_transit_routes()
+                            .map(|tr| tr.id)
+                            .chain(new_ids.routes.values().cloned())
+                            .map(|id| id.0 + 1)
+                            .max()
+                            .unwrap_or(0),
+                    );
+                    new_ids.routes.insert(gtfs_id.clone(), id);
+                    id
+                };
+                Ok(EditCmd::ChangeTransitRoute {
+                    id,
+                    old: old
+                        .map(|tr| tr.with_permanent(id, gtfs_id.clone(), map, new_ids))
+                        .transpose()?,
+                    new: new
+                        .map(|tr| tr.with_permanent(id, gtfs_id.clone(), map, new_ids))
+                        .transpose()?,
+                })
+            }
+            PermanentEditCmd::AddRoad { r, old, new } => {
+                let id = if new.is_some() {
+                    if map.find_r_by_osm_id(r).is_ok() {
+                        bail!("{} already exists", r);
+                    }
+                    RoadID(map.all_roads().len())
+                } else {
+                    map.find_r_by_osm_id(r)?
+                };
+                Ok(EditCmd::AddRoad {
+                    r: id,
+                    old: old.map(|nr| nr.with_permanent(r, map)).transpose()?,
+                    new: new.map(|nr| nr.with_permanent(r, map)).transpose()?,
+                })
+            }
+            PermanentEditCmd::SplitRoad { r, old, new } => {
+                let new_i_osm = match old.as_ref().or(new.as_ref()) {
+                    Some(split) => split.new_i,
+                    None => bail!("SplitRoad of {} doesn't do anything", r),
+                };
+                let (id, new_i, new_r) = if new.is_some() {
+                    (
+                        map.find_r_by_osm_id(r)?,
+                        IntersectionID(map.all_intersections().len()),
+                        RoadID(map.all_roads().len()),
+                    )
+                } else {
+                    // Merging a road split by an earlier command
+                    (
+                        map.find_r_by_osm_id(OriginalRoad {
+                            osm_way_id: r.osm_way_id,
+                            i1: r.i1,
+                            i2: new_i_osm,
+                        })?,
+                        map.find_i_by_osm_id(new_i_osm)?,
+                        map.find_r_by_osm_id(OriginalRoad {
+                            osm_way_id: r.osm_way_id,
+                            i1: new_i_osm,
+                            i2: r.i2,
+                        })?,
+                    )
+                };
+                let convert = |split: PermanentRoadSplit| RoadSplit {
+                    orig_id: r,
+                    dist: split.dist,
+                    new_i,
+                    new_i_osm: split.new_i,
+                    new_r,
+                };
+                Ok(EditCmd::SplitRoad {
+                    r: id,
+                    old: old.map(convert),
+                    new: new.map(convert),
+                })
+            }
         }
     }
+
+    fn changes_geometry(&self) -> bool {
+        matches!(
+            self,
+            PermanentEditCmd::AddRoad { .. } | PermanentEditCmd::SplitRoad { .. }
+        )
+    }
+}
+
+/// Commands may refer to roads and intersections that earlier commands create or split. In that
+/// case, convert against a copy of the basemap with each command applied in turn. If `permissive`,
+/// skip broken commands instead of failing.
+fn into_cmds(commands: Vec<PermanentEditCmd>, map: &Map, permissive: bool) -> Result<Vec<EditCmd>> {
+    let mut new_ids = NewTransitIDs::default();
+    let mut scratch = if commands.iter().any(|cmd| cmd.changes_geometry()) {
+        Some(unedited_copy(map))
+    } else {
+        None
+    };
+    let mut effects = EditEffects::new();
+
+    let mut result = Vec::new();
+    for cmd in commands {
+        match cmd.into_cmd_with_ids(scratch.as_ref().unwrap_or(map), &mut new_ids) {
+            Ok(cmd) => {
+                if let Some(ref mut scratch) = scratch {
+                    cmd.apply(&mut effects, scratch);
+                }
+                result.push(cmd);
+            }
+            Err(err) => {
+                if permissive {
+                    warn!("Skipping broken command: {}", err);
+                } else {
+                    return Err(err);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Copy the map and undo its current edits. Pathfinding isn't needed to apply edits, so it's
+/// dropped from the copy.
+fn unedited_copy(map: &Map) -> Map {
+    let mut copy = map.clone();
+    copy.pathfinder = Pathfinder::empty();
+    copy.pathfinder_dirty = false;
+    copy.edits = MapEdits::new();
+    copy.edits_generation = 0;
+
+    let mut effects = EditEffects::new();
+    for cmd in map.edits.commands.iter().rev() {
+        cmd.clone().undo().apply(&mut effects, &mut copy);
+    }
+    copy
 }
 
 impl MapEdits {
     /// Encode the edits in a permanent format, referring to more-stable OSM IDs.
-    pub fn to_permanent(&self, map: &Map) -> PermanentMapEdits {
-        PermanentMapEdits {
+    pub fn to_permanent(&self, map: &Map) -> Result<PermanentMapEdits> {
+        // Splitting a road changes its OSM ID, so commands before the split can't refer to the
+        // road by its current ID. Compressing puts all new roads first.
+        let compressed;
+        let commands = if self.created_roads.is_empty() {
+            &self.commands
+        } else {
+            let mut edits = self.clone();
+            edits.commands.clear();
+            edits.compress(map);
+            compressed = edits.commands;
+            &compressed
+        };
+
+        Ok(PermanentMapEdits {
             map_name: map.get_name().clone(),
             edits_name: self.edits_name.clone(),
             // Increase this every time there's a schema change
-            version: 11,
+            version: 12,
             proposal_description: self.proposal_description.clone(),
             proposal_link: self.proposal_link.clone(),
-            commands: self.commands.iter().map(|cmd| cmd.to_perma(map)).collect(),
+            commands: commands
+                .iter()
+                .map(|cmd| cmd.to_perma(map))
+                .collect::<Result<_>>()?,
             merge_zones: self.merge_zones,
-        }
+        })
     }
 }
 
@@ -183,17 +482,16 @@ impl PermanentMapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
             proposal_link: self.proposal_link,
-            commands: self
-                .commands
-                .into_iter()
-                .map(|cmd| cmd.into_cmd(map))
-                .collect::<Result<Vec<EditCmd>>>()?,
+            commands: into_cmds(self.commands, map, false)?,
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             original_crosswalks: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_transit_stops: BTreeMap::new(),
+            original_transit_routes: BTreeMap::new(),
+            created_roads: BTreeMap::new(),
         };
         edits.update_derived(map);
         Ok(edits)
@@ -206,23 +504,17 @@ impl PermanentMapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
             proposal_link: self.proposal_link,
-            commands: self
-                .commands
-                .into_iter()
-                .filter_map(|cmd| match cmd.into_cmd(map) {
-                    Ok(cmd) => Some(cmd),
-                    Err(err) => {
-                        warn!("Skipping broken command: {}", err);
-                        None
-                    }
-                })
-                .collect(),
+            // Can't fail when permissive
+            commands: into_cmds(self.commands, map, true).unwrap(),
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             original_crosswalks: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_transit_stops: BTreeMap::new(),
+            original_transit_routes: BTreeMap::new(),
+            created_roads: BTreeMap::new(),
         };
         edits.update_derived(map);
         edits
@@ -241,26 +533,39 @@ impl PermanentMapEdits {
 
 impl EditIntersection {
     fn to_permanent(&self, map: &Map) -> PermanentEditIntersection {
-        match self {
-            EditIntersection::StopSign(ref ss) => PermanentEditIntersection::StopSign {
-                must_stop: ss
-                    .roads
-                    .iter()
-                    .map(|(r, val)| (map.get_r(*r).orig_id, val.must_stop))
-                    .collect(),
+        PermanentEditIntersection {
+            control: match self.control {
+                EditIntersectionControl::StopSign(ref ss) => {
+                    PermanentEditIntersectionControl::StopSign {
+                        must_stop: ss
+                            .roads
+                            .iter()
+                            .map(|(r, val)| (map.get_r(*r).orig_id, val.must_stop))
+                            .collect(),
+                        yield_on_entry: ss.yield_on_entry,
+                    }
+                }
+                EditIntersectionControl::TrafficSignal(ref raw_ts) => {
+                    PermanentEditIntersectionControl::TrafficSignal(raw_ts.clone())
+                }
+                EditIntersectionControl::Closed => PermanentEditIntersectionControl::Closed,
             },
-            EditIntersection::TrafficSignal(ref raw_ts) => {
-                PermanentEditIntersection::TrafficSignal(raw_ts.clone())
-            }
-            EditIntersection::Closed => PermanentEditIntersection::Closed,
+            banned_movements: self
+                .banned_movements
+                .iter()
+                .map(|m| m.to_permanent(map))
+                .collect(),
         }
     }
 }
 
 impl PermanentEditIntersection {
     fn with_permanent(self, i: IntersectionID, map: &Map) -> Result<EditIntersection> {
-        match self {
-            PermanentEditIntersection::StopSign { must_stop } => {
+        let control = match self.control {
+            PermanentEditIntersectionControl::StopSign {
+                must_stop,
+                yield_on_entry,
+            } => {
                 let mut translated_must_stop = BTreeMap::new();
                 for (r, stop) in must_stop {
                     translated_must_stop.insert(map.find_r_by_osm_id(r)?, stop);
@@ -282,12 +587,29 @@ impl PermanentEditIntersection {
                         bail!("{} doesn't connect to {}", i, r);
                     }
                 }
+                ss.yield_on_entry = yield_on_entry;
 
-                Ok(EditIntersection::StopSign(ss))
+                EditIntersectionControl::StopSign(ss)
+            }
+            PermanentEditIntersectionControl::TrafficSignal(ts) => {
+                EditIntersectionControl::TrafficSignal(ts)
             }
-            PermanentEditIntersection::TrafficSignal(ts) => Ok(EditIntersection::TrafficSignal(ts)),
-            PermanentEditIntersection::Closed => Ok(EditIntersection::Closed),
+            PermanentEditIntersectionControl::Closed => EditIntersectionControl::Closed,
+        };
+
+        let mut banned_movements = BTreeSet::new();
+        for t in self.banned_movements {
+            let m = MovementID::from_permanent(t, map)?;
+            if m.parent != i {
+                bail!("Banned movement {:?} isn't at {}", m, i);
+            }
+            banned_movements.insert(m);
         }
+
+        Ok(EditIntersection {
+            control,
+            banned_movements,
+        })
     }
 }
 
@@ -327,3 +649,168 @@ impl PermanentEditCrosswalks {
         Ok(EditCrosswalks(turns))
     }
 }
+
+impl PermanentLane {
+    fn new(l: LaneID, map: &Map) -> PermanentLane {
+        PermanentLane {
+            r: map.get_r(l.road).orig_id,
+            idx: l.offset,
+        }
+    }
+
+    /// The lane might not exist in the basemap; earlier edits could create it.
+    fn resolve(&self, map: &Map) -> Result<LaneID> {
+        Ok(LaneID {
+            road: map.find_r_by_osm_id(self.r)?,
+            offset: self.idx,
+        })
+    }
+}
+
+impl TransitStop {
+    fn to_permanent(&self, map: &Map) -> PermanentTransitStop {
+        PermanentTransitStop {
+            name: self.name.clone(),
+            sidewalk: PermanentLane::new(self.sidewalk_pos.lane(), map),
+            sidewalk_dist: self.sidewalk_pos.dist_along(),
+            driving: PermanentLane::new(self.driving_pos.lane(), map),
+            driving_dist: self.driving_pos.dist_along(),
+            is_train_stop: self.is_train_stop,
+        }
+    }
+}
+
+impl PermanentTransitStop {
+    fn with_permanent(self, id: TransitStopID, gtfs_id: String, map: &Map) -> Result<TransitStop> {
+        let sidewalk = self
+            .sidewalk
+            .resolve(map)
+            .with_context(|| format!("sidewalk of stop {} invalid", gtfs_id))?;
+        let driving = self
+            .driving
+            .resolve(map)
+            .with_context(|| format!("driving lane of stop {} invalid", gtfs_id))?;
+        Ok(TransitStop {
+            id,
+            name: self.name,
+            gtfs_id,
+            driving_pos: Position::new(driving, self.driving_dist),
+            sidewalk_pos: Position::new(sidewalk, self.sidewalk_dist),
+            is_train_stop: self.is_train_stop,
+        })
+    }
+}
+
+impl TransitRoute {
+    fn to_permanent(&self, map: &Map) -> Result<PermanentTransitRoute> {
+        Ok(PermanentTransitRoute {
+            long_name: self.long_name.clone(),
+            short_name: self.short_name.clone(),
+            stops: self
+                .stops
+                .iter()
+                .map(|ts| stop_gtfs_id(*ts, map))
+                .collect::<Result<_>>()?,
+            start: PermanentLane::new(self.start, map),
+            end_border: self.end_border.map(|l| PermanentLane::new(l, map)),
+            route_type: self.route_type,
+            spawn_times: self.spawn_times.clone(),
+            orig_spawn_times: self.orig_spawn_times.clone(),
+        })
+    }
+}
+
+impl PermanentTransitRoute {
+    fn with_permanent(
+        self,
+        id: TransitRouteID,
+        gtfs_id: String,
+        map: &Map,
+        new_ids: &NewTransitIDs,
+    ) -> Result<TransitRoute> {
+        let mut stops = Vec::new();
+        for stop in self.stops {
+            if let Some(ts) = map
+                .find_ts_by_gtfs(&stop)
+                .or_else(|| new_ids.stops.get(&stop).cloned())
+            {
+                stops.push(ts);
+            } else {
+                bail!("route {} uses unknown stop {}", gtfs_id, stop);
+            }
+        }
+        Ok(TransitRoute {
+            id,
+            long_name: self.long_name,
+            short_name: self.short_name,
+            gtfs_id,
+            stops,
+            start: self.start.resolve(map)?,
+            end_border: self.end_border.map(|l| l.resolve(map)).transpose()?,
+            route_type: self.route_type,
+            spawn_times: self.spawn_times,
+            orig_spawn_times: self.orig_spawn_times,
+        })
+    }
+}
+
+impl NewRoad {
+    fn to_permanent(&self, map: &Map) -> PermanentNewRoad {
+        PermanentNewRoad {
+            src_i: map.get_i(self.src_i).orig_id,
+            dst_i: map.get_i(self.dst_i).orig_id,
+            untrimmed_center_pts: self
+                .untrimmed_center_pts
+                .points()
+                .iter()
+                .map(|pt| pt.to_gps(map.get_gps_bounds()))
+                .collect(),
+            osm_tags: self.osm_tags.clone(),
+        }
+    }
+}
+
+impl PermanentNewRoad {
+    fn with_permanent(self, orig_id: OriginalRoad, map: &Map) -> Result<NewRoad> {
+        let untrimmed_center_pts = PolyLine::deduping_new(
+            self.untrimmed_center_pts
+                .into_iter()
+                .map(|pt| pt.to_pt(map.get_gps_bounds()))
+                .collect(),
+        )
+        .with_context(|| format!("geometry of new road {} invalid", orig_id))?;
+        Ok(NewRoad {
+            orig_id,
+            src_i: map.find_i_by_osm_id(self.src_i)?,
+            dst_i: map.find_i_by_osm_id(self.dst_i)?,
+            untrimmed_center_pts,
+            osm_tags: self.osm_tags,
+        })
+    }
+}
+
+impl RoadSplit {
+    fn to_permanent(&self) -> PermanentRoadSplit {
+        PermanentRoadSplit {
+            dist: self.dist,
+            new_i: self.new_i_osm,
+        }
+    }
+}
+
+/// A route might refer to a stop that's since been deleted, so also look through the edits.
+fn stop_gtfs_id(id: TransitStopID, map: &Map) -> Result<String> {
+    if let Some(ts) = map.maybe_get_ts(id) {
+        return Ok(ts.gtfs_id.clone());
+    }
+    for cmd in &map.get_edits().commands {
+        if let EditCmd::ChangeTransitStop { id: id2, old, new } = cmd {
+            if *id2 == id {
+                if let Some(ts) = old.as_ref().or(new.as_ref()) {
+                    return Ok(ts.gtfs_id.clone());
+                }
+            }
+        }
+    }
+    bail!("A route uses {}, but it doesn't exist", id);
+}
@@ -0,0 +1,531 @@
+//! Helpers to add new roads and split existing roads as map edits. Unlike other edits, these
+//! change the structure of the map. New roads and intersections are appended after everything in
+//! the basemap, so they have to be removed in the opposite order they were created. Undoing edits
+//! in order guarantees this.
+//!
+//! Blocks aren't stored in the map, so they aren't regenerated here. Anything that traced them
+//! with `Perimeter`, like the LTN tool's partitioning, has to trace them again after these edits.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+
+use abstutil::Tags;
+use geom::{Circle, Distance, PolyLine, Pt2D, Speed};
+use raw_map::get_lane_specs_ltr;
+
+use crate::edits::{
+    recalculate_intersection_polygon, recalculate_lane_connections, recalculate_turns, EditCmd,
+    EditEffects,
+};
+use crate::raw::OriginalRoad;
+use crate::{
+    osm, AccessRestrictions, Intersection, IntersectionCluster, IntersectionID, IntersectionType,
+    Map, Road, RoadID,
+};
+
+/// Don't create roads shorter than this, or split a road this close to its ends. The
+/// intersection geometry would swallow the whole road.
+const MIN_LENGTH: Distance = Distance::const_meters(10.0);
+
+/// A road that doesn't exist in the basemap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewRoad {
+    /// The OSM way ID is made up
+    pub orig_id: OriginalRoad,
+    pub src_i: IntersectionID,
+    pub dst_i: IntersectionID,
+    /// Starts and ends at the middle of the intersections
+    pub untrimmed_center_pts: PolyLine,
+    /// Determines the lanes, speed limit, and access restrictions, just like for roads from OSM.
+    /// Use `EditCmd::ChangeRoad` to change these afterwards.
+    pub osm_tags: Tags,
+}
+
+/// Where a road is cut in two by a new intersection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoadSplit {
+    /// The road's ID before it was split. Afterwards, it ends at the new intersection.
+    pub orig_id: OriginalRoad,
+    /// Measured along the road's untrimmed center
+    pub dist: Distance,
+    pub new_i: IntersectionID,
+    /// A made-up OSM node ID
+    pub new_i_osm: osm::NodeID,
+    /// Continues from the new intersection to where the road used to end
+    pub new_r: RoadID,
+}
+
+impl Map {
+    /// Create a new road between two existing intersections, bending through any `pts` in
+    /// between. The lanes, speed limit, and access restrictions come from the OSM `tags`, like
+    /// `highway=footway` for a new pedestrian bridge.
+    ///
+    /// Neither intersection can be a border or closed, and to keep undo simple, they can't have
+    /// any other edits yet.
+    pub fn new_road_cmd(
+        &self,
+        src_i: IntersectionID,
+        dst_i: IntersectionID,
+        pts: Vec<Pt2D>,
+        osm_tags: Tags,
+    ) -> Result<EditCmd> {
+        if src_i == dst_i {
+            bail!("A road can't start and end at {}", src_i);
+        }
+        for i in [src_i, dst_i] {
+            if self.get_i(i).is_border() {
+                bail!("{} is a border", i);
+            }
+            self.check_no_intersection_edits(i)?;
+        }
+
+        let mut all_pts = vec![self.intersection_center(src_i)];
+        all_pts.extend(pts);
+        all_pts.push(self.intersection_center(dst_i));
+        let untrimmed_center_pts = PolyLine::deduping_new(all_pts)?;
+        if untrimmed_center_pts.length() < MIN_LENGTH {
+            bail!("A new road must be at least {} long", MIN_LENGTH);
+        }
+
+        let r = RoadID(self.roads.len());
+        Ok(EditCmd::AddRoad {
+            r,
+            old: None,
+            new: Some(NewRoad {
+                orig_id: OriginalRoad {
+                    osm_way_id: self.new_osm_way_id(),
+                    i1: self.get_i(src_i).orig_id,
+                    i2: self.get_i(dst_i).orig_id,
+                },
+                src_i,
+                dst_i,
+                untrimmed_center_pts,
+                osm_tags,
+            }),
+        })
+    }
+
+    /// Split a road in two at some distance along it, creating a new intersection there. The
+    /// road keeps its ID and ends at the new intersection; a new road with the same lanes
+    /// continues to where it used to end.
+    ///
+    /// Roads with transit stops, turn restrictions, or curb regulations can't be split yet. The
+    /// intersection at the end of the road can't have any other edits.
+    pub fn split_road_cmd(&self, r: RoadID, dist: Distance) -> Result<EditCmd> {
+        let road = self.get_r(r);
+        if dist < MIN_LENGTH || road.length() - dist < MIN_LENGTH {
+            bail!("Can't split {} within {} of its ends", r, MIN_LENGTH);
+        }
+        if !road.transit_stops.is_empty() {
+            bail!("{} has transit stops", r);
+        }
+        if !road.curb_regulations.is_empty() {
+            bail!("{} has curb regulations", r);
+        }
+        if !road.turn_restrictions.is_empty()
+            || !road.complicated_turn_restrictions.is_empty()
+            || self.all_roads().iter().any(|other| {
+                other.turn_restrictions.iter().any(|(_, to)| *to == r)
+                    || other
+                        .complicated_turn_restrictions
+                        .iter()
+                        .any(|(via, to)| *via == r || *to == r)
+            })
+        {
+            bail!("{} has turn restrictions", r);
+        }
+        for tr in self.all_transit_routes() {
+            if tr.start.road == r || tr.end_border.map(|l| l.road == r).unwrap_or(false) {
+                bail!("Route {} starts or ends on {}", tr.short_name, r);
+            }
+        }
+        if !self.get_i(road.dst_i).is_border() {
+            self.check_no_intersection_edits(road.dst_i)?;
+        }
+        // Uber-turns for pathfinding are only calculated once, and they'd refer to movements that
+        // won't exist anymore
+        if IntersectionCluster::autodetect(road.dst_i, self).is_some() {
+            bail!("{} is part of a cluster of traffic signals", road.dst_i);
+        }
+
+        let pt = road.center_pts.dist_along(dist)?.0;
+        let untrimmed_dist = match road.untrimmed_center_pts.dist_along_of_point(pt) {
+            Some((d, _)) => d,
+            None => bail!("{} isn't on the untrimmed center of {}", pt, r),
+        };
+
+        Ok(EditCmd::SplitRoad {
+            r,
+            old: None,
+            new: Some(RoadSplit {
+                orig_id: road.orig_id,
+                dist: untrimmed_dist,
+                new_i: IntersectionID(self.intersections.len()),
+                new_i_osm: self.new_osm_node_id(),
+                new_r: RoadID(self.roads.len()),
+            }),
+        })
+    }
+
+    /// Add a mid-block crossing to a road, by splitting it at some distance along. The new
+    /// degenerate intersection gets a crosswalk between the sidewalks on either side. Whether it's
+    /// marked or not is inherited from the road's existing crossings; use
+    /// `EditCmd::ChangeCrosswalks` afterwards to change this.
+    pub fn mid_block_crossing_cmd(&self, r: RoadID, dist: Distance) -> Result<EditCmd> {
+        let road = self.get_r(r);
+        if road.is_footway() {
+            bail!("{} is already for pedestrians", r);
+        }
+        let (left, right) = (&road.lanes[0], road.lanes.last().unwrap());
+        if !left.is_walkable() || !right.is_walkable() {
+            bail!("{} doesn't have sidewalks or shoulders on both sides", r);
+        }
+        self.split_road_cmd(r, dist)
+    }
+
+    fn check_no_intersection_edits(&self, i: IntersectionID) -> Result<()> {
+        if self.get_i(i).is_closed() {
+            bail!("{} is closed", i);
+        }
+        if self.edits.original_intersections.contains_key(&i)
+            || self.edits.original_crosswalks.contains_key(&i)
+        {
+            bail!("{} has already been edited; undo that first", i);
+        }
+        Ok(())
+    }
+
+    /// Where roads meet in the middle of an intersection, before trimming.
+    fn intersection_center(&self, i: IntersectionID) -> Pt2D {
+        let i = self.get_i(i);
+        match i.roads.iter().next() {
+            Some(r) => {
+                let road = self.get_r(*r);
+                if road.src_i == i.id {
+                    road.untrimmed_center_pts.first_pt()
+                } else {
+                    road.untrimmed_center_pts.last_pt()
+                }
+            }
+            None => i.polygon.center(),
+        }
+    }
+
+    /// Never reuses an ID in the map or mentioned by the current edits, so that undoing and
+    /// redoing stays unambiguous.
+    fn new_osm_way_id(&self) -> osm::WayID {
+        let mut ids: Vec<i64> = self.roads.iter().map(|r| r.orig_id.osm_way_id.0).collect();
+        for cmd in &self.edits.commands {
+            if let EditCmd::AddRoad { old, new, .. } = cmd {
+                ids.extend(
+                    old.iter()
+                        .chain(new.iter())
+                        .map(|nr| nr.orig_id.osm_way_id.0),
+                );
+            }
+        }
+        osm::WayID(ids.into_iter().min().unwrap_or(0).min(0) - 1)
+    }
+
+    /// Same rules as `new_osm_way_id`
+    fn new_osm_node_id(&self) -> osm::NodeID {
+        let mut ids: Vec<i64> = self.intersections.iter().map(|i| i.orig_id.0).collect();
+        for cmd in &self.edits.commands {
+            if let EditCmd::SplitRoad { old, new, .. } = cmd {
+                ids.extend(old.iter().chain(new.iter()).map(|s| s.new_i_osm.0));
+            }
+        }
+        osm::NodeID(ids.into_iter().min().unwrap_or(0).min(0) - 1)
+    }
+}
+
+pub(crate) fn add_road(map: &mut Map, r: RoadID, new: &NewRoad, effects: &mut EditEffects) {
+    assert_eq!(
+        r.0,
+        map.roads.len(),
+        "New roads have to be created in order"
+    );
+    let length = new.untrimmed_center_pts.length();
+    let mut road = Road {
+        id: r,
+        osm_tags: new.osm_tags.clone(),
+        turn_restrictions: Vec::new(),
+        complicated_turn_restrictions: Vec::new(),
+        orig_id: new.orig_id,
+        speed_limit: Speed::ZERO,
+        access_restrictions: AccessRestrictions::new(),
+        zorder: new
+            .osm_tags
+            .get("layer")
+            .and_then(|layer| layer.parse::<f64>().ok())
+            .map(|layer| layer as isize)
+            .unwrap_or(0),
+        percent_incline: (map.get_i(new.dst_i).elevation - map.get_i(new.src_i).elevation) / length,
+        lanes: Vec::new(),
+        // Trimmed below
+        center_pts: new.untrimmed_center_pts.clone(),
+        untrimmed_center_pts: new.untrimmed_center_pts.clone(),
+        src_i: new.src_i,
+        dst_i: new.dst_i,
+        crosswalk_forward: true,
+        crosswalk_backward: true,
+        transit_stops: BTreeSet::new(),
+        curb_regulations: Vec::new(),
+    };
+    road.speed_limit = road.speed_limit_from_osm();
+    road.access_restrictions = road.access_restrictions_from_osm();
+    road.recreate_lanes(get_lane_specs_ltr(&road.osm_tags, &map.config));
+    map.roads.push(road);
+
+    for i in [new.src_i, new.dst_i] {
+        map.intersections[i.0].roads.insert(r);
+    }
+    reconnect(map, &[new.src_i, new.dst_i], effects);
+}
+
+pub(crate) fn remove_road(map: &mut Map, r: RoadID, effects: &mut EditEffects) {
+    assert_eq!(
+        r.0 + 1,
+        map.roads.len(),
+        "Only the most recently created road can be removed"
+    );
+    let road = map.roads.pop().unwrap();
+    for lane in &road.lanes {
+        effects.deleted_lanes.insert(lane.id);
+        effects.modified_lanes.insert(lane.id);
+    }
+    effects.deleted_roads.insert(r);
+
+    for i in [road.src_i, road.dst_i] {
+        map.intersections[i.0].roads.remove(&r);
+    }
+    reconnect(map, &[road.src_i, road.dst_i], effects);
+}
+
+pub(crate) fn split_road(map: &mut Map, r: RoadID, split: &RoadSplit, effects: &mut EditEffects) {
+    assert_eq!(
+        split.new_r.0,
+        map.roads.len(),
+        "New roads have to be created in order"
+    );
+    assert_eq!(split.new_i.0, map.intersections.len());
+
+    let road = map.get_r(r);
+    let (src_i, dst_i) = (road.src_i, road.dst_i);
+    let untrimmed_length = road.untrimmed_center_pts.length();
+    let first = road
+        .untrimmed_center_pts
+        .exact_slice(Distance::ZERO, split.dist);
+    let second = road
+        .untrimmed_center_pts
+        .exact_slice(split.dist, untrimmed_length);
+    let pt = second.first_pt();
+    // Keep the existing trimming at the far ends; the new intersection trims the middle below.
+    let first_trimmed = first
+        .safe_get_slice_starting_at(road.center_pts.first_pt())
+        .unwrap_or_else(|| first.clone());
+    let second_trimmed = second
+        .safe_get_slice_ending_at(road.center_pts.last_pt())
+        .unwrap_or_else(|| second.clone());
+
+    let elevation = map.get_i(src_i).elevation
+        + (map.get_i(dst_i).elevation - map.get_i(src_i).elevation)
+            * (split.dist / untrimmed_length);
+
+    let mut new_road = road.clone();
+    new_road.id = split.new_r;
+    new_road.orig_id = OriginalRoad {
+        osm_way_id: split.orig_id.osm_way_id,
+        i1: split.new_i_osm,
+        i2: split.orig_id.i2,
+    };
+    new_road.src_i = split.new_i;
+    new_road.untrimmed_center_pts = second;
+    new_road.center_pts = second_trimmed;
+
+    let road = &mut map.roads[r.0];
+    road.orig_id.i2 = split.new_i_osm;
+    road.dst_i = split.new_i;
+    road.untrimmed_center_pts = first;
+    road.center_pts = first_trimmed;
+
+    map.roads.push(new_road);
+    map.intersections.push(Intersection {
+        id: split.new_i,
+        // Calculated below
+        polygon: Circle::new(pt, Distance::meters(1.0)).to_polygon(),
+        turns: Vec::new(),
+        elevation,
+        intersection_type: IntersectionType::StopSign,
+        orig_id: split.new_i_osm,
+        incoming_lanes: Vec::new(),
+        outgoing_lanes: Vec::new(),
+        roads: BTreeSet::from([r, split.new_r]),
+        merged: false,
+        banned_movements: BTreeSet::new(),
+        movements: BTreeMap::new(),
+    });
+    let i = &mut map.intersections[dst_i.0];
+    i.roads.remove(&r);
+    i.roads.insert(split.new_r);
+
+    reconnect(map, &[split.new_i, dst_i], effects);
+}
+
+pub(crate) fn merge_road(map: &mut Map, r: RoadID, split: &RoadSplit, effects: &mut EditEffects) {
+    assert_eq!(
+        split.new_r.0 + 1,
+        map.roads.len(),
+        "Only the most recently split road can be merged"
+    );
+    assert_eq!(split.new_i.0 + 1, map.intersections.len());
+
+    let new_road = map.roads.pop().unwrap();
+    for lane in &new_road.lanes {
+        effects.deleted_lanes.insert(lane.id);
+        effects.modified_lanes.insert(lane.id);
+    }
+    effects.deleted_roads.insert(new_road.id);
+    let new_i = map.intersections.pop().unwrap();
+    for t in &new_i.turns {
+        effects.deleted_turns.insert(t.id);
+    }
+    effects.deleted_intersections.insert(new_i.id);
+
+    let dst_i = new_road.dst_i;
+    let road = &mut map.roads[r.0];
+    let untrimmed = {
+        let mut pts = road.untrimmed_center_pts.clone().into_points();
+        pts.pop();
+        pts.extend(new_road.untrimmed_center_pts.into_points());
+        PolyLine::unchecked_new(pts)
+    };
+    road.center_pts = untrimmed
+        .safe_get_slice_starting_at(road.center_pts.first_pt())
+        .unwrap_or_else(|| untrimmed.clone());
+    road.untrimmed_center_pts = untrimmed;
+    road.orig_id = split.orig_id;
+    road.dst_i = dst_i;
+
+    let i = &mut map.intersections[dst_i.0];
+    i.roads.remove(&split.new_r);
+    i.roads.insert(r);
+
+    reconnect(map, &[dst_i], effects);
+}
+
+/// After the roads connected to some intersections change, recalculate the intersection
+/// geometry, re-trim and recreate the lanes of every road touching them, and regenerate turns.
+fn reconnect(map: &mut Map, intersections: &[IntersectionID], effects: &mut EditEffects) {
+    let mut roads = BTreeSet::new();
+    for i in intersections {
+        // No road is changing width
+        let changed_road = *map.get_i(*i).roads.iter().next().unwrap();
+        let width = map.get_r(changed_road).get_width();
+        roads.insert(changed_road);
+        roads.extend(recalculate_intersection_polygon(
+            map,
+            changed_road,
+            width,
+            *i,
+        ));
+    }
+
+    for r in roads {
+        effects.changed_roads.insert(r);
+        let lane_specs = map.get_r(r).lane_specs();
+        let road = &mut map.roads[r.0];
+        road.recreate_lanes(lane_specs);
+        for lane in &road.lanes {
+            effects.modified_lanes.insert(lane.id);
+        }
+    }
+
+    for i in intersections {
+        effects.changed_intersections.insert(*i);
+        recalculate_lane_connections(*i, map);
+        recalculate_turns(*i, map, effects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Timer;
+
+    use super::*;
+    use crate::edits::PermanentMapEdits;
+    use crate::testing::import_map;
+
+    fn apply(map: &mut Map, cmd: EditCmd) {
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(cmd);
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+    }
+
+    /// A new footway between two intersections that're far enough apart to split it later
+    fn new_footway_cmd(map: &Map) -> EditCmd {
+        let intersections: Vec<&Intersection> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| !i.is_border())
+            .collect();
+        let mut tags = Tags::empty();
+        tags.insert("highway", "footway");
+        for i1 in &intersections {
+            for i2 in &intersections {
+                if i1.polygon.center().dist_to(i2.polygon.center()) < Distance::meters(50.0) {
+                    continue;
+                }
+                if let Ok(cmd) = map.new_road_cmd(i1.id, i2.id, Vec::new(), tags.clone()) {
+                    return cmd;
+                }
+            }
+        }
+        panic!("No intersections are far enough apart");
+    }
+
+    #[test]
+    fn test_add_and_split_road_round_trip() {
+        let mut map = import_map();
+        let num_roads = map.all_roads().len();
+        let num_intersections = map.all_intersections().len();
+
+        apply(&mut map, new_footway_cmd(&map));
+        let r = RoadID(num_roads);
+        let split = map.split_road_cmd(r, map.get_r(r).length() / 2.0).unwrap();
+        apply(&mut map, split);
+        assert_eq!(map.all_roads().len(), num_roads + 2);
+        assert_eq!(map.all_intersections().len(), num_intersections + 1);
+
+        // Saving puts the new roads first, so compare against that order
+        let mut expected = map.get_edits().clone();
+        expected.commands.clear();
+        expected.compress(&map);
+
+        let json = abstutil::to_json(&map.get_edits().to_permanent(&map).unwrap());
+        let perma: PermanentMapEdits = abstutil::from_json(json.as_bytes()).unwrap();
+        let loaded = perma.into_edits(&map).unwrap();
+        assert_eq!(loaded.commands, expected.commands);
+
+        // The same proposal applies to a fresh copy of the basemap
+        let mut fresh = import_map();
+        let perma: PermanentMapEdits = abstutil::from_json(json.as_bytes()).unwrap();
+        let loaded = perma.into_edits(&fresh).unwrap();
+        assert_eq!(loaded.commands, expected.commands);
+        fresh.must_apply_edits(loaded, &mut Timer::throwaway());
+        assert_eq!(fresh.all_roads().len(), num_roads + 2);
+        assert_eq!(fresh.all_intersections().len(), num_intersections + 1);
+        for r in [RoadID(num_roads), RoadID(num_roads + 1)] {
+            assert_eq!(fresh.get_r(r).orig_id, map.get_r(r).orig_id);
+            assert_eq!(fresh.get_r(r).center_pts, map.get_r(r).center_pts);
+        }
+
+        // Undoing everything restores the basemap
+        let edits = map.new_edits();
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        assert_eq!(map.all_roads().len(), num_roads);
+        assert_eq!(map.all_intersections().len(), num_intersections);
+        assert!(map.get_edits().created_roads.is_empty());
+    }
+}
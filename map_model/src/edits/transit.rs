@@ -0,0 +1,371 @@
+//! Helpers to create, reroute, and delete transit stops and routes as map edits. These produce an
+//! `EditCmd`, which the caller pushes onto `MapEdits` like any other edit.
+
+use anyhow::Result;
+
+use crate::edits::EditCmd;
+use crate::{
+    LaneID, Map, PathConstraints, Position, TransitRoute, TransitRouteID, TransitStop,
+    TransitStopID,
+};
+
+impl Map {
+    /// Create a new stop along a sidewalk or shoulder. Vehicles will stop on the closest lane that
+    /// buses (or trains, if `is_train_stop`) can use.
+    pub fn new_transit_stop_cmd(
+        &self,
+        name: String,
+        sidewalk_pos: Position,
+        is_train_stop: bool,
+    ) -> Result<EditCmd> {
+        let sidewalk = self.get_l(sidewalk_pos.lane());
+        if !sidewalk.is_walkable() {
+            bail!("{} isn't a sidewalk or shoulder", sidewalk.id);
+        }
+        let vehicle = if is_train_stop {
+            PathConstraints::Train
+        } else {
+            PathConstraints::Bus
+        };
+        let driving_pos = match self
+            .get_parent(sidewalk.id)
+            .find_closest_lane(sidewalk.id, |l| vehicle.can_use(l, self))
+        {
+            Some(l) => sidewalk_pos.equiv_pos(l, self),
+            None => bail!(
+                "Couldn't find a lane for {:?} next to sidewalk {}",
+                vehicle,
+                sidewalk.id
+            ),
+        };
+
+        let id = self.new_transit_stop_id(sidewalk.id);
+        Ok(EditCmd::ChangeTransitStop {
+            id,
+            old: None,
+            new: Some(TransitStop {
+                id,
+                name,
+                // Stops created from edits don't come from GTFS, but they need some stable name
+                // to be referred to in PermanentMapEdits.
+                gtfs_id: format!("edits/{}/{}", id.road.0, id.idx),
+                driving_pos,
+                sidewalk_pos,
+                is_train_stop,
+            }),
+        })
+    }
+
+    /// Fails if any route still serves the stop.
+    pub fn delete_transit_stop_cmd(&self, id: TransitStopID) -> Result<EditCmd> {
+        let routes = self.get_routes_serving_stop(id);
+        if !routes.is_empty() {
+            bail!(
+                "{} is still served by {}",
+                id,
+                routes
+                    .into_iter()
+                    .map(|r| r.short_name.clone())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(EditCmd::ChangeTransitStop {
+            id,
+            old: Some(self.get_ts(id).clone()),
+            new: None,
+        })
+    }
+
+    /// Create a new route visiting some existing stops in order. Vehicles spawn at the beginning
+    /// of `start`, and either vanish at the last stop or exit through `end_border`. The route
+    /// initially runs on the default schedule; use `EditCmd::ChangeRouteSchedule` to change that.
+    ///
+    /// The route is validated against the current pathfinder, so if roads have been edited, call
+    /// `recalculate_pathfinding_after_edits` first.
+    pub fn new_transit_route_cmd(
+        &self,
+        long_name: String,
+        short_name: String,
+        route_type: PathConstraints,
+        stops: Vec<TransitStopID>,
+        start: LaneID,
+        end_border: Option<LaneID>,
+    ) -> Result<EditCmd> {
+        let id = self.new_transit_route_id();
+        let spawn_times = TransitRoute::default_spawn_times();
+        let route = TransitRoute {
+            id,
+            long_name,
+            short_name,
+            gtfs_id: format!("edits/{}", id.0),
+            stops,
+            start,
+            end_border,
+            route_type,
+            spawn_times: spawn_times.clone(),
+            orig_spawn_times: spawn_times,
+        };
+        self.validate_transit_route(&route)?;
+        Ok(EditCmd::ChangeTransitRoute {
+            id,
+            old: None,
+            new: Some(route),
+        })
+    }
+
+    /// Change the stops an existing route visits, and where it starts and ends. The schedule is
+    /// unchanged. Like `new_transit_route_cmd`, this is validated against the current pathfinder.
+    pub fn reroute_transit_route_cmd(
+        &self,
+        id: TransitRouteID,
+        stops: Vec<TransitStopID>,
+        start: LaneID,
+        end_border: Option<LaneID>,
+    ) -> Result<EditCmd> {
+        let old = self.get_tr(id).clone();
+        let mut new = old.clone();
+        new.stops = stops;
+        new.start = start;
+        new.end_border = end_border;
+        self.validate_transit_route(&new)?;
+        Ok(EditCmd::ChangeTransitRoute {
+            id,
+            old: Some(old),
+            new: Some(new),
+        })
+    }
+
+    pub fn delete_transit_route_cmd(&self, id: TransitRouteID) -> EditCmd {
+        EditCmd::ChangeTransitRoute {
+            id,
+            old: Some(self.get_tr(id).clone()),
+            new: None,
+        }
+    }
+
+    fn validate_transit_route(&self, route: &TransitRoute) -> Result<()> {
+        if route.route_type != PathConstraints::Bus && route.route_type != PathConstraints::Train {
+            bail!("Transit routes can't be for {:?}", route.route_type);
+        }
+        if route.stops.is_empty() {
+            bail!("A route needs at least one stop");
+        }
+        for ts in &route.stops {
+            if self.maybe_get_ts(*ts).is_none() {
+                bail!("{} doesn't exist", ts);
+            }
+        }
+        if !route.route_type.can_use(self.get_l(route.start), self) {
+            bail!("{} can't start at {}", route.plural_noun(), route.start);
+        }
+        if let Some(l) = route.end_border {
+            if !self.get_i(self.get_l(l).dst_i).is_border() {
+                bail!("{} doesn't lead to a border", l);
+            }
+        }
+        route.all_paths(self)?;
+        Ok(())
+    }
+
+    /// Never reuses the ID of a stop that's in the map or mentioned by the current edits, so that
+    /// undoing and redoing stays unambiguous. Apply each new stop before making the next one.
+    fn new_transit_stop_id(&self, sidewalk: LaneID) -> TransitStopID {
+        let road = sidewalk.road;
+        let mut ids: Vec<TransitStopID> = self.get_r(road).transit_stops.iter().cloned().collect();
+        for cmd in &self.edits.commands {
+            if let EditCmd::ChangeTransitStop { id, .. } = cmd {
+                if id.road == road {
+                    ids.push(*id);
+                }
+            }
+        }
+        let idx = ids.into_iter().map(|ts| ts.idx + 1).max().unwrap_or(0);
+        TransitStopID { road, idx }
+    }
+
+    /// Same rules as `new_transit_stop_id`
+    fn new_transit_route_id(&self) -> TransitRouteID {
+        let mut ids: Vec<TransitRouteID> = self.transit_routes.keys().cloned().collect();
+        for cmd in &self.edits.commands {
+            if let EditCmd::ChangeTransitRoute { id, .. } = cmd {
+                ids.push(*id);
+            }
+        }
+        TransitRouteID(ids.into_iter().map(|id| id.0 + 1).max().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Timer;
+
+    use super::*;
+    use crate::edits::PermanentMapEdits;
+    use crate::testing::import_map;
+    use crate::RoadID;
+
+    fn apply(map: &mut Map, cmd: EditCmd) {
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(cmd);
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+    }
+
+    fn new_stop_cmd(map: &Map) -> EditCmd {
+        for l in map.all_lanes() {
+            if l.is_sidewalk() {
+                let pos = Position::new(l.id, l.length() / 2.0);
+                if let Ok(cmd) = map.new_transit_stop_cmd("test stop".to_string(), pos, false) {
+                    return cmd;
+                }
+            }
+        }
+        panic!("No sidewalk is next to a lane buses can use");
+    }
+
+    fn round_trip(map: &Map) -> String {
+        abstutil::to_json(&map.get_edits().to_permanent(map).unwrap())
+    }
+
+    #[test]
+    fn test_new_stop_and_route_round_trip() {
+        let mut map = import_map();
+        let num_stops = map.all_transit_stops().len();
+        let num_routes = map.all_transit_routes().count();
+
+        let cmd = new_stop_cmd(&map);
+        let (ts, driving_lane) = match cmd {
+            EditCmd::ChangeTransitStop {
+                id,
+                new: Some(ref new),
+                ..
+            } => (id, new.driving_pos.lane()),
+            _ => unreachable!(),
+        };
+        apply(&mut map, cmd);
+        let stop_gtfs = map.get_ts(ts).gtfs_id.clone();
+        assert!(stop_gtfs.starts_with("edits/"));
+
+        let cmd = map
+            .new_transit_route_cmd(
+                "Test route".to_string(),
+                "T".to_string(),
+                PathConstraints::Bus,
+                vec![ts],
+                driving_lane,
+                None,
+            )
+            .unwrap();
+        let tr = match cmd {
+            EditCmd::ChangeTransitRoute { id, .. } => id,
+            _ => unreachable!(),
+        };
+        apply(&mut map, cmd);
+        assert!(map.get_tr(tr).gtfs_id.starts_with("edits/"));
+        assert_eq!(map.get_tr(tr).stops, vec![ts]);
+
+        // The stop and route get the same IDs when loaded into a fresh copy of the basemap
+        let json = round_trip(&map);
+        let mut fresh = import_map();
+        let perma: PermanentMapEdits = abstutil::from_json(json.as_bytes()).unwrap();
+        let loaded = perma.into_edits(&fresh).unwrap();
+        fresh.must_apply_edits(loaded, &mut Timer::throwaway());
+        assert_eq!(fresh.find_ts_by_gtfs(&stop_gtfs), Some(ts));
+        assert_eq!(fresh.get_tr(tr).stops, vec![ts]);
+        assert_eq!(round_trip(&fresh), json);
+
+        // Deleting both means the route's old stop has to be found through the edits
+        let cmd = map.delete_transit_route_cmd(tr);
+        apply(&mut map, cmd);
+        let cmd = map.delete_transit_stop_cmd(ts).unwrap();
+        apply(&mut map, cmd);
+        assert!(map.maybe_get_ts(ts).is_none());
+        let json = round_trip(&map);
+        let mut fresh = import_map();
+        let perma: PermanentMapEdits = abstutil::from_json(json.as_bytes()).unwrap();
+        let loaded = perma.into_edits(&fresh).unwrap();
+        fresh.must_apply_edits(loaded, &mut Timer::throwaway());
+        assert!(fresh.maybe_get_ts(ts).is_none());
+        assert!(fresh.maybe_get_tr(tr).is_none());
+        assert_eq!(round_trip(&fresh), json);
+
+        // Undoing everything restores the basemap
+        let edits = map.new_edits();
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        assert_eq!(map.all_transit_stops().len(), num_stops);
+        assert_eq!(map.all_transit_routes().count(), num_routes);
+    }
+
+    #[test]
+    fn test_undo_new_stop_and_route() {
+        let mut map = import_map();
+        let num_stops = map.all_transit_stops().len();
+        let num_routes = map.all_transit_routes().count();
+
+        let cmd = new_stop_cmd(&map);
+        let (ts, driving_lane) = match cmd {
+            EditCmd::ChangeTransitStop {
+                id,
+                new: Some(ref new),
+                ..
+            } => (id, new.driving_pos.lane()),
+            _ => unreachable!(),
+        };
+        apply(&mut map, cmd);
+        let cmd = map
+            .new_transit_route_cmd(
+                "Test route".to_string(),
+                "T".to_string(),
+                PathConstraints::Bus,
+                vec![ts],
+                driving_lane,
+                None,
+            )
+            .unwrap();
+        apply(&mut map, cmd);
+        assert_eq!(map.all_transit_stops().len(), num_stops + 1);
+        assert_eq!(map.all_transit_routes().count(), num_routes + 1);
+
+        // Pop the route, then the stop
+        let mut edits = map.get_edits().clone();
+        edits.commands.pop();
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        assert_eq!(map.all_transit_routes().count(), num_routes);
+        assert!(map.get_routes_serving_stop(ts).is_empty());
+
+        let mut edits = map.get_edits().clone();
+        edits.commands.pop();
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        assert_eq!(map.all_transit_stops().len(), num_stops);
+        assert!(map.maybe_get_ts(ts).is_none());
+    }
+
+    #[test]
+    fn test_route_with_unknown_stop_isnt_saved() {
+        let map = import_map();
+        let start = map.all_lanes().find(|l| l.is_driving()).unwrap().id;
+        let bogus = TransitStopID {
+            road: RoadID(0),
+            idx: 1000,
+        };
+        let spawn_times = TransitRoute::default_spawn_times();
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(EditCmd::ChangeTransitRoute {
+            id: TransitRouteID(1000),
+            old: None,
+            new: Some(TransitRoute {
+                id: TransitRouteID(1000),
+                long_name: "Broken route".to_string(),
+                short_name: "B".to_string(),
+                gtfs_id: "edits/1000".to_string(),
+                stops: vec![bogus],
+                start,
+                end_border: None,
+                route_type: PathConstraints::Bus,
+                spawn_times: spawn_times.clone(),
+                orig_spawn_times: spawn_times,
+            }),
+        });
+        assert!(edits.to_permanent(&map).is_err());
+    }
+}
@@ -43,12 +43,14 @@ pub use raw_map::{
 
 pub use crate::city::City;
 pub use crate::edits::{
-    EditCmd, EditEffects, EditIntersection, EditRoad, MapEdits, PermanentMapEdits,
+    EditCmd, EditEffects, EditIntersection, EditIntersectionControl, EditRoad, EditedObject,
+    EditsDiff, MapEdits, MergedEdits, NewRoad, PermanentMapEdits, RoadSplit,
 };
 pub use crate::make::RawToMapOptions;
 pub use crate::objects::area::{Area, AreaID};
 pub use crate::objects::block::{Block, Perimeter};
 pub use crate::objects::building::{Building, BuildingID, BuildingType, OffstreetParking};
+pub use crate::objects::curb::{CurbActivity, CurbRegulation};
 pub use crate::objects::intersection::{Intersection, IntersectionID};
 pub use crate::objects::lane::{CommonEndpoint, Lane, LaneID, PARKING_LOT_SPOT_LENGTH};
 pub use crate::objects::movement::{CompressedMovementID, Movement, MovementID};
@@ -73,6 +75,8 @@ mod make;
 mod map;
 mod objects;
 mod pathfind;
+#[cfg(test)]
+mod testing;
 mod traversable;
 
 // The map used by the simulation and UI. This struct is declared here so that the rest of the
@@ -87,7 +91,11 @@ pub struct Map {
         deserialize_with = "deserialize_btreemap"
     )]
     transit_stops: BTreeMap<TransitStopID, TransitStop>,
-    transit_routes: Vec<TransitRoute>,
+    #[serde(
+        serialize_with = "crate::objects::transit::serialize_transit_routes",
+        deserialize_with = "crate::objects::transit::deserialize_transit_routes"
+    )]
+    transit_routes: BTreeMap<TransitRouteID, TransitRoute>,
     areas: Vec<Area>,
     parking_lots: Vec<ParkingLot>,
     boundary_polygon: Polygon,
@@ -56,7 +56,7 @@ impl Map {
             intersections: Vec::new(),
             buildings: Vec::new(),
             transit_stops: BTreeMap::new(),
-            transit_routes: Vec::new(),
+            transit_routes: BTreeMap::new(),
             areas: Vec::new(),
             parking_lots: Vec::new(),
             zones: Vec::new(),
@@ -90,6 +90,7 @@ impl Map {
                 polygon: i.polygon.clone(),
                 turns: Vec::new(),
                 movements: BTreeMap::new(),
+                banned_movements: BTreeSet::new(),
                 elevation: i.elevation,
                 // Might change later
                 intersection_type: i.intersection_type,
@@ -152,6 +153,7 @@ impl Map {
                 crosswalk_forward: raw_road.crosswalk_forward,
                 crosswalk_backward: raw_road.crosswalk_backward,
                 transit_stops: BTreeSet::new(),
+                curb_regulations: Vec::new(),
             };
             road.speed_limit = road.speed_limit_from_osm();
             road.access_restrictions = road.access_restrictions_from_osm();
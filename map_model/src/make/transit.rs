@@ -3,7 +3,7 @@ use std::collections::{HashMap, HashSet};
 use anyhow::Result;
 
 use abstutil::Timer;
-use geom::{Distance, Duration, FindClosest, HashablePt2D, Time};
+use geom::{Distance, FindClosest, HashablePt2D};
 
 use crate::make::match_points_to_lanes;
 use crate::raw::{RawMap, RawTransitRoute, RawTransitStop, RawTransitType};
@@ -221,10 +221,8 @@ fn create_route(
         }
     };
 
-    // TODO This'll come from the RawTransitRoute eventually. For now, every 30 minutes.
-    let spawn_times: Vec<Time> = (0..48)
-        .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
-        .collect();
+    // TODO This'll come from the RawTransitRoute eventually.
+    let spawn_times = TransitRoute::default_spawn_times();
 
     let result = TransitRoute {
         id: TransitRouteID(map.transit_routes.len()),
@@ -245,6 +243,6 @@ fn create_route(
     // Check that the paths are valid
     result.all_paths(map)?;
 
-    map.transit_routes.push(result);
+    map.transit_routes.insert(result.id, result);
     Ok(())
 }
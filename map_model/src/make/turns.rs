@@ -19,10 +19,12 @@ pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
     ));
     let unique_turns = ensure_unique(raw_turns);
     // Never allow turns that go against road-level turn restrictions; that upstream OSM data is
-    // usually not extremely broken.
+    // usually not extremely broken. Movements banned by map edits are likewise never allowed.
     let all_turns: Vec<Turn> = unique_turns
         .into_iter()
-        .filter(|t| t.permitted_by_road(i, map))
+        .filter(|t| {
+            t.permitted_by_road(i, map) && !i.banned_movements.contains(&t.id.to_movement(map))
+        })
         .collect();
 
     // Try to use turn lane tags...
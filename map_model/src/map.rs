@@ -131,7 +131,7 @@ impl Map {
             intersections: Vec::new(),
             buildings: Vec::new(),
             transit_stops: BTreeMap::new(),
-            transit_routes: Vec::new(),
+            transit_routes: BTreeMap::new(),
             areas: Vec::new(),
             parking_lots: Vec::new(),
             zones: Vec::new(),
@@ -210,7 +210,7 @@ impl Map {
     pub fn maybe_get_t(&self, id: TurnID) -> Option<&Turn> {
         // Looking up the intersection is fast. Linearly scanning through all of the turns to find
         // this one actually turns out to be fast too; thanks cache locality.
-        for turn in &self.intersections[id.parent.0].turns {
+        for turn in &self.maybe_get_i(id.parent)?.turns {
             if turn.id == id {
                 return Some(turn);
             }
@@ -243,7 +243,7 @@ impl Map {
     }
 
     pub fn maybe_get_tr(&self, route: TransitRouteID) -> Option<&TransitRoute> {
-        self.transit_routes.get(route.0)
+        self.transit_routes.get(&route)
     }
 
     pub fn get_r(&self, id: RoadID) -> &Road {
@@ -441,20 +441,20 @@ impl Map {
     }
 
     pub fn get_tr(&self, route: TransitRouteID) -> &TransitRoute {
-        &self.transit_routes[route.0]
+        &self.transit_routes[&route]
     }
 
-    pub fn all_transit_routes(&self) -> &Vec<TransitRoute> {
-        &self.transit_routes
+    pub fn all_transit_routes(&self) -> impl Iterator<Item = &TransitRoute> {
+        self.transit_routes.values()
     }
 
     pub fn get_transit_route(&self, name: &str) -> Option<&TransitRoute> {
-        self.transit_routes.iter().find(|r| r.long_name == name)
+        self.transit_routes.values().find(|r| r.long_name == name)
     }
 
     pub fn get_routes_serving_stop(&self, stop: TransitStopID) -> Vec<&TransitRoute> {
         let mut routes = Vec::new();
-        for r in &self.transit_routes {
+        for r in self.transit_routes.values() {
             if r.stops.contains(&stop) {
                 routes.push(r);
             }
@@ -605,16 +605,29 @@ impl Map {
         self.pathfinder.all_costs_from(req, self)
     }
 
-    /// None for SharedSidewalkCorners and turns not belonging to traffic signals
+    /// None for SharedSidewalkCorners and turns not belonging to traffic signals or
+    /// yield-controlled intersections
     pub fn get_movement_for_traffic_signal(
         &self,
         t: TurnID,
     ) -> Option<(MovementID, CompressedMovementID)> {
-        let i = self.get_i(t.parent);
-        if !i.is_traffic_signal() || self.get_t(t).turn_type == TurnType::SharedSidewalkCorner {
+        if !self.is_signal_or_yield(t.parent)
+            || self.get_t(t).turn_type == TurnType::SharedSidewalkCorner
+        {
             return None;
         }
-        Some(i.turn_to_movement(t))
+        Some(self.get_i(t.parent).turn_to_movement(t))
+    }
+
+    /// Traffic signals and yield-controlled intersections, like roundabouts and all-way yields.
+    /// Delay and throughput are tracked per movement at these.
+    pub fn is_signal_or_yield(&self, i: IntersectionID) -> bool {
+        self.get_i(i).is_traffic_signal()
+            || self
+                .stop_signs
+                .get(&i)
+                .map(|ss| ss.yield_on_entry)
+                .unwrap_or(false)
     }
 
     pub fn find_r_by_osm_id(&self, id: OriginalRoad) -> Result<RoadID> {
@@ -644,6 +657,15 @@ impl Map {
         None
     }
 
+    pub fn find_ts_by_gtfs(&self, gtfs_id: &str) -> Option<TransitStopID> {
+        for ts in self.transit_stops.values() {
+            if ts.gtfs_id == gtfs_id {
+                return Some(ts.id);
+            }
+        }
+        None
+    }
+
     pub fn find_tr_by_gtfs(&self, gtfs_id: &str) -> Option<TransitRouteID> {
         for tr in self.all_transit_routes() {
             if tr.gtfs_id == gtfs_id {
@@ -673,8 +695,9 @@ impl Map {
     }
 
     pub fn hack_override_orig_spawn_times(&mut self, br: TransitRouteID, times: Vec<Time>) {
-        self.transit_routes[br.0].orig_spawn_times = times.clone();
-        self.transit_routes[br.0].spawn_times = times;
+        let route = self.transit_routes.get_mut(&br).unwrap();
+        route.orig_spawn_times = times.clone();
+        route.spawn_times = times;
     }
 
     pub fn hack_add_area(&mut self, area_type: AreaType, polygon: Polygon, osm_tags: Tags) {
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Time};
+
+use crate::{Road, SideOfRoad};
+
+/// A rule for how part of the curb along one side of a road may be used, possibly only during
+/// certain times of day. Usually imported from CurbLR or the Curb Data Specification.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurbRegulation {
+    pub side: SideOfRoad,
+    /// Measured along the road's center line from its `src_i`
+    pub start: Distance,
+    pub end: Distance,
+    pub activity: CurbActivity,
+    /// Times of day (between midnight and 24 hours) when this regulation applies. If a window ends
+    /// before it starts, it wraps around midnight. If there are no windows, the regulation always
+    /// applies.
+    pub time_windows: Vec<(Time, Time)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CurbActivity {
+    /// Anybody may park
+    Parking,
+    /// Only some people may park, like permit holders, taxis, or people with disabilities
+    RestrictedParking,
+    /// Reserved for commercial vehicles loading and unloading
+    Loading,
+    /// Reserved for buses picking up and dropping off passengers
+    TransitStop,
+    /// Nobody may park or stop
+    NoStopping,
+}
+
+impl CurbRegulation {
+    /// Does this regulation apply at this time? Simulations can run past midnight; regulations
+    /// repeat every day.
+    pub fn applies_at(&self, now: Time) -> bool {
+        if self.time_windows.is_empty() {
+            return true;
+        }
+        let day = Duration::hours(24).inner_seconds();
+        let time_of_day = Time::START_OF_DAY + Duration::seconds(now.inner_seconds() % day);
+        self.time_windows.iter().any(|(start, end)| {
+            if start <= end {
+                *start <= time_of_day && time_of_day < *end
+            } else {
+                *start <= time_of_day || time_of_day < *end
+            }
+        })
+    }
+
+    pub fn contains(&self, side: SideOfRoad, dist: Distance) -> bool {
+        self.side == side && self.start <= dist && dist <= self.end
+    }
+}
+
+impl Road {
+    /// How can the curb be used at some point along one side of this road, at a certain time?
+    /// Regulations are listed in priority order, so the first one applying wins. `None` means no
+    /// regulation applies, so whatever the lanes say holds.
+    pub fn get_curb_activity(
+        &self,
+        side: SideOfRoad,
+        dist: Distance,
+        now: Time,
+    ) -> Option<CurbActivity> {
+        self.curb_regulations
+            .iter()
+            .find(|reg| reg.contains(side, dist) && reg.applies_at(now))
+            .map(|reg| reg.activity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regulation(time_windows: Vec<(usize, usize)>) -> CurbRegulation {
+        CurbRegulation {
+            side: SideOfRoad::Right,
+            start: Distance::ZERO,
+            end: Distance::meters(10.0),
+            activity: CurbActivity::NoStopping,
+            time_windows: time_windows
+                .into_iter()
+                .map(|(start, end)| {
+                    (
+                        Time::START_OF_DAY + Duration::hours(start),
+                        Time::START_OF_DAY + Duration::hours(end),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn at(hours: f64) -> Time {
+        Time::START_OF_DAY + Duration::seconds(hours * 3600.0)
+    }
+
+    #[test]
+    fn test_applies_at() {
+        let always = regulation(Vec::new());
+        assert!(always.applies_at(at(3.0)));
+        assert!(always.applies_at(at(30.0)));
+
+        let daytime = regulation(vec![(8, 18)]);
+        assert!(!daytime.applies_at(at(7.9)));
+        assert!(daytime.applies_at(at(8.0)));
+        assert!(daytime.applies_at(at(17.9)));
+        assert!(!daytime.applies_at(at(18.0)));
+        // The next day
+        assert!(daytime.applies_at(at(32.0)));
+        assert!(!daytime.applies_at(at(43.0)));
+
+        let overnight = regulation(vec![(22, 6)]);
+        assert!(overnight.applies_at(at(23.0)));
+        assert!(overnight.applies_at(at(3.0)));
+        assert!(!overnight.applies_at(at(12.0)));
+    }
+}
@@ -54,6 +54,10 @@ pub struct Intersection {
 
     /// Was a short road adjacent to this intersection merged?
     pub merged: bool,
+    /// Vehicle movements removed by map edits. No turns are generated for these. Only edits set
+    /// this, and they're applied after the map is loaded.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub banned_movements: BTreeSet<MovementID>,
     // These increase the map file size, so instead, just use `recalculate_all_movements` after
     // deserializing.
     #[serde(skip_serializing, skip_deserializing)]
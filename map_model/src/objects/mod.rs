@@ -1,6 +1,7 @@
 pub mod area;
 pub mod block;
 pub mod building;
+pub mod curb;
 pub mod intersection;
 pub mod lane;
 pub mod movement;
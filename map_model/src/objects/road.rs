@@ -11,8 +11,8 @@ use raw_map::Direction;
 
 use crate::raw::{OriginalRoad, RestrictionType};
 use crate::{
-    osm, AccessRestrictions, CommonEndpoint, DrivingSide, IntersectionID, Lane, LaneID, LaneSpec,
-    LaneType, Map, PathConstraints, TransitStopID, Zone,
+    osm, AccessRestrictions, CommonEndpoint, CurbRegulation, DrivingSide, IntersectionID, Lane,
+    LaneID, LaneSpec, LaneType, Map, PathConstraints, TransitStopID, Zone,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -176,6 +176,8 @@ pub struct Road {
 
     /// Meaningless order
     pub transit_stops: BTreeSet<TransitStopID>,
+    /// In priority order. Usually empty, unless imported from a separate source.
+    pub curb_regulations: Vec<CurbRegulation>,
 }
 
 impl Road {
@@ -42,6 +42,10 @@ pub struct ControlStopSign {
         deserialize_with = "deserialize_btreemap"
     )]
     pub roads: BTreeMap<RoadID, RoadWithStopSign>,
+    /// Roundabouts and all-way yields. Vehicles on roads with `must_stop` don't come to a full
+    /// stop; they just yield, entering once there's a big enough gap in the traffic on the other
+    /// roads.
+    pub yield_on_entry: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -55,6 +59,7 @@ impl ControlStopSign {
         let mut ss = ControlStopSign {
             id,
             roads: BTreeMap::new(),
+            yield_on_entry: false,
         };
         // One-way outbound roads don't need a stop sign, so skip them entirely.
         for r in map.get_i(id).get_sorted_incoming_roads(map) {
@@ -93,15 +98,14 @@ impl ControlStopSign {
             }
         }
 
-        // Degenerate roads and deadends don't need any stop signs. But be careful with
-        // roundabouts; we want it to be lower priority to enter a roundabout than continue through
-        // it.
-        if ss.roads.len() <= 2
-            && ss
-                .roads
-                .keys()
-                .all(|r| !map.get_r(*r).osm_tags.is("junction", "roundabout"))
-        {
+        // Entering a roundabout yields to traffic already circulating.
+        if ss.is_roundabout(map) {
+            ss.convert_to_yield(map);
+            return ss;
+        }
+
+        // Degenerate roads and deadends don't need any stop signs.
+        if ss.roads.len() <= 2 {
             return ss;
         }
         if map.get_i(id).is_cycleway(map) {
@@ -111,7 +115,6 @@ impl ControlStopSign {
 
         // Rank each road based on OSM highway type, and additionally:
         // - Treat cycleways as lower priority than local roads (sad but typical reality)
-        // - Prioritize roundabouts, so they clear out faster than people enter them
         // - Treat on/off ramps with less priority than the main part of the highway
         // - Lower the priority of service roads
         let mut rank: HashMap<RoadID, (osm::RoadRank, usize)> = HashMap::new();
@@ -120,8 +123,6 @@ impl ControlStopSign {
             // Lower number is lower priority
             let priority = if r.is_cycleway() || r.osm_tags.is(osm::HIGHWAY, "service") {
                 0
-            } else if r.osm_tags.is("junction", "roundabout") {
-                3
             } else if r
                 .osm_tags
                 .get("highway")
@@ -170,8 +171,69 @@ impl ControlStopSign {
         }
     }
 
+    /// Turn this into a yield-controlled intersection. Traffic circulating around a roundabout has
+    /// priority; elsewhere, every road yields.
+    pub fn convert_to_yield(&mut self, map: &Map) {
+        self.yield_on_entry = true;
+        let roundabout = self.is_roundabout(map);
+        for (r, cfg) in self.roads.iter_mut() {
+            cfg.must_stop = !roundabout || !map.get_r(*r).osm_tags.is("junction", "roundabout");
+        }
+    }
+
+    /// Does every road yield, so nobody has priority? The first vehicle to arrive goes first, once
+    /// there's a big enough gap in the traffic coming from every other road.
+    pub fn is_all_way_yield(&self) -> bool {
+        self.yield_on_entry && self.roads.values().all(|cfg| cfg.must_stop)
+    }
+
+    /// Is some part of a roundabout coming into this intersection?
+    pub fn is_roundabout(&self, map: &Map) -> bool {
+        self.roads
+            .keys()
+            .any(|r| map.get_r(*r).osm_tags.is("junction", "roundabout"))
+    }
+
     pub fn flip_sign(&mut self, r: RoadID) {
         let ss = self.roads.get_mut(&r).unwrap();
         ss.must_stop = !ss.must_stop;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::import_map;
+
+    #[test]
+    fn test_convert_to_all_way_yield() {
+        let map = import_map();
+        let mut ss = map
+            .all_intersections()
+            .iter()
+            .filter_map(|i| map.maybe_get_stop_sign(i.id))
+            .find(|ss| ss.roads.len() >= 2 && !ss.is_roundabout(&map))
+            .unwrap()
+            .clone();
+
+        ss.convert_to_yield(&map);
+        assert!(ss.yield_on_entry);
+        assert!(ss.is_all_way_yield());
+        for turn in &map.get_i(ss.id).turns {
+            if !turn.between_sidewalks() {
+                assert_eq!(ss.get_priority(turn.id, &map), TurnPriority::Yield);
+            }
+        }
+
+        // Giving one road priority means the others yield to it
+        let r = *ss.roads.keys().next().unwrap();
+        ss.flip_sign(r);
+        assert!(ss.yield_on_entry);
+        assert!(!ss.is_all_way_yield());
+
+        // An all-way stop isn't a yield
+        ss.flip_sign(r);
+        ss.yield_on_entry = false;
+        assert!(!ss.is_all_way_yield());
+    }
+}
@@ -1,12 +1,13 @@
 //! Public transit stops and routes.
 
+use std::collections::BTreeMap;
 use std::fmt;
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::Time;
+use geom::{Duration, Time};
 
 use crate::{LaneID, Map, Path, PathConstraints, PathRequest, Position, RoadID};
 
@@ -51,7 +52,7 @@ pub struct TransitStop {
     pub is_train_stop: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct TransitRoute {
     pub id: TransitRouteID,
     pub long_name: String,
@@ -138,6 +139,13 @@ impl TransitRoute {
         Ok(paths)
     }
 
+    /// Until schedules are imported, routes run every 30 minutes all day.
+    pub fn default_spawn_times() -> Vec<Time> {
+        (0..48)
+            .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
+            .collect()
+    }
+
     pub fn plural_noun(&self) -> &'static str {
         if self.route_type == PathConstraints::Bus {
             "buses"
@@ -146,3 +154,19 @@ impl TransitRoute {
         }
     }
 }
+
+/// Routes are stored as a plain list in the map file, the same way they were before they could be
+/// deleted, so existing maps still load. The ID of each route is stored inside of it.
+pub(crate) fn serialize_transit_routes<S: Serializer>(
+    routes: &BTreeMap<TransitRouteID, TransitRoute>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.collect_seq(routes.values())
+}
+
+pub(crate) fn deserialize_transit_routes<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<BTreeMap<TransitRouteID, TransitRoute>, D::Error> {
+    let routes = <Vec<TransitRoute>>::deserialize(d)?;
+    Ok(routes.into_iter().map(|tr| (tr.id, tr)).collect())
+}
@@ -2,7 +2,7 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Angle, Line, PolyLine};
+use geom::{Angle, Line, PolyLine, Speed};
 
 use crate::raw::RestrictionType;
 use crate::{
@@ -10,6 +10,10 @@ use crate::{
     PathConstraints,
 };
 
+/// Pedestrians only consider jaywalking across local roads with a speed limit of about 25mph or
+/// less.
+const JAYWALKING_MAX_SPEED_LIMIT: Speed = Speed::const_meters_per_second(11.2);
+
 /// Turns are uniquely identified by their (src, dst) lanes and their parent intersection.
 /// Intersection is needed to distinguish crosswalks that exist at two ends of a sidewalk.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -220,6 +224,24 @@ impl Turn {
         })
     }
 
+    /// Is this a pedestrian crossing in the middle of a block, at an intersection that only joins
+    /// two road segments?
+    pub fn is_mid_block_crossing(&self, map: &Map) -> bool {
+        self.turn_type.pedestrian_crossing() && map.get_i(self.id.parent).is_degenerate()
+    }
+
+    /// Is this a pedestrian crossing over a slow, local road, where somebody might cross without
+    /// the right-of-way if there's a gap in traffic?
+    pub fn allows_jaywalking(&self, map: &Map) -> bool {
+        use crate::osm::RoadRank;
+        self.crosswalk_over_road(map)
+            .map(|dr| {
+                let road = map.get_r(dr.road);
+                road.get_rank() == RoadRank::Local && road.speed_limit <= JAYWALKING_MAX_SPEED_LIMIT
+            })
+            .unwrap_or(false)
+    }
+
     /// Is this turn legal, according to turn lane tagging?
     pub(crate) fn permitted_by_lane(&self, map: &Map) -> bool {
         if let Some(types) = map
@@ -196,6 +196,10 @@ pub struct RoutingParams {
     /// Don't allow movements between these roads at all. Only affects vehicle routing, not
     /// pedestrian.
     pub avoid_movements_between: BTreeSet<(RoadID, RoadID)>,
+
+    /// For pedestrian routing. Don't penalize unmarked crossings over slow, local roads, because
+    /// people are willing to cross them wherever there's a gap in traffic.
+    pub jaywalking: bool,
 }
 
 impl Default for RoutingParams {
@@ -216,6 +220,8 @@ impl Default for RoutingParams {
 
             avoid_roads: BTreeSet::new(),
             avoid_movements_between: BTreeSet::new(),
+
+            jaywalking: false,
         }
     }
 }
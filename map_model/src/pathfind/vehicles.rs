@@ -142,8 +142,18 @@ impl VehiclePathfinder {
             return;
         }
 
-        // The NodeMap is just all roads and uber-turns -- it won't change. So we can also reuse
-        // the node ordering.
+        // The NodeMap is just all roads and uber-turns. Edits might create new roads. Roads
+        // removed again keep their nodes, just without any edges.
+        let mut new_nodes = false;
+        for r in map.all_roads() {
+            for dr in r.id.both_directions() {
+                if !self.nodes.contains(Node::Road(dr)) {
+                    self.nodes.get_or_insert(Node::Road(dr));
+                    new_nodes = true;
+                }
+            }
+        }
+
         // TODO Make sure the result of this is deterministic and equivalent to computing from
         // scratch.
         let input_graph = make_input_graph(
@@ -153,7 +163,14 @@ impl VehiclePathfinder {
             &self.params,
             map,
         );
-        let engine = self.engine.reuse_ordering().create(input_graph);
+        // If the nodes haven't changed, we can reuse the node ordering
+        let engine = if !new_nodes {
+            self.engine.reuse_ordering().create(input_graph)
+        } else if self.engine.is_dijkstra() {
+            CreateEngine::Dijkstra.create(input_graph)
+        } else {
+            CreateEngine::CH.create(input_graph)
+        };
         self.engine = engine;
     }
 
@@ -14,7 +14,7 @@ use crate::pathfind::zone_cost;
 use crate::pathfind::{round, unround};
 use crate::{
     DirectedRoadID, IntersectionID, Map, PathConstraints, PathRequest, PathStep, PathStepV2,
-    PathV2, Position, TransitRoute, TransitRouteID, TransitStopID, TurnType,
+    PathV2, Position, RoutingParams, TransitRoute, TransitRouteID, TransitStopID, TurnType,
 };
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -22,6 +22,7 @@ pub struct SidewalkPathfinder {
     #[serde(deserialize_with = "deserialize_nodemap")]
     nodes: NodeMap<WalkingNode>,
     use_transit: bool,
+    params: RoutingParams,
     engine: PathfindEngine,
 }
 
@@ -59,6 +60,7 @@ impl SidewalkPathfinder {
         SidewalkPathfinder {
             nodes: NodeMap::new(),
             use_transit: false,
+            params: RoutingParams::default(),
             engine: PathfindEngine::Empty,
         }
     }
@@ -66,6 +68,7 @@ impl SidewalkPathfinder {
     pub fn new(
         map: &Map,
         use_transit: Option<(&VehiclePathfinder, &VehiclePathfinder)>,
+        params: &RoutingParams,
         engine: &CreateEngine,
     ) -> SidewalkPathfinder {
         let mut nodes = NodeMap::new();
@@ -89,12 +92,13 @@ impl SidewalkPathfinder {
             }
         }
 
-        let input_graph = make_input_graph(&nodes, use_transit, map);
+        let input_graph = make_input_graph(&nodes, use_transit, params, map);
         let engine = engine.create(input_graph);
 
         SidewalkPathfinder {
             nodes,
             use_transit: use_transit.is_some(),
+            params: params.clone(),
             engine,
         }
     }
@@ -108,8 +112,41 @@ impl SidewalkPathfinder {
             return;
         }
 
-        let input_graph = make_input_graph(&self.nodes, use_transit, map);
-        let engine = self.engine.reuse_ordering().create(input_graph);
+        // Edits might create new roads and transit stops. Deleted ones keep their node, just
+        // without any edges.
+        let mut new_nodes = false;
+        for r in map.all_roads() {
+            for dr in r.id.both_directions() {
+                for endpt in [true, false] {
+                    if !self
+                        .nodes
+                        .contains(WalkingNode::SidewalkEndpoint(dr, endpt))
+                    {
+                        self.nodes
+                            .get_or_insert(WalkingNode::SidewalkEndpoint(dr, endpt));
+                        new_nodes = true;
+                    }
+                }
+            }
+        }
+        if use_transit.is_some() {
+            for ts in map.all_transit_stops().keys() {
+                if !self.nodes.contains(WalkingNode::RideTransit(*ts)) {
+                    self.nodes.get_or_insert(WalkingNode::RideTransit(*ts));
+                    new_nodes = true;
+                }
+            }
+        }
+
+        let input_graph = make_input_graph(&self.nodes, use_transit, &self.params, map);
+        // The previous node ordering doesn't cover new nodes, so start over
+        let engine = if !new_nodes {
+            self.engine.reuse_ordering().create(input_graph)
+        } else if self.engine.is_dijkstra() {
+            CreateEngine::Dijkstra.create(input_graph)
+        } else {
+            CreateEngine::CH.create(input_graph)
+        };
         self.engine = engine;
     }
 
@@ -264,6 +301,7 @@ impl SidewalkPathfinder {
 fn make_input_graph(
     nodes: &NodeMap<WalkingNode>,
     use_transit: Option<(&VehiclePathfinder, &VehiclePathfinder)>,
+    params: &RoutingParams,
     map: &Map,
 ) -> InputGraph {
     let max_speed = Some(crate::MAX_WALKING_SPEED);
@@ -311,7 +349,9 @@ fn make_input_graph(
                 / PathStep::Turn(t.id).max_speed_along(max_speed, PathConstraints::Pedestrian, map)
                 + zone_cost(t.id.to_movement(map), PathConstraints::Pedestrian, map);
 
-            if t.turn_type == TurnType::UnmarkedCrossing {
+            if t.turn_type == TurnType::UnmarkedCrossing
+                && !(params.jaywalking && t.allows_jaywalking(map))
+            {
                 // TODO Add to RoutingParams
                 cost = 3.0 * cost;
             }
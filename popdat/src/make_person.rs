@@ -184,6 +184,7 @@ impl PersonFactory {
         let mut output = PersonSpec {
             orig_id: None,
             trips: Vec::new(),
+            behavior: Some(sim::rand_behavior(rng, Some(person.age))),
         };
 
         let mut current_location = TripEndpoint::Building(person.home);
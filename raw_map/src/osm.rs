@@ -26,6 +26,10 @@ pub const OSM_REL_ID: &str = "abst:osm_rel_id";
 // for interpreting turn restrictions.
 pub const ENDPT_FWD: &str = "abst:endpt_fwd";
 pub const ENDPT_BACK: &str = "abst:endpt_back";
+// Ways with a mid-block `highway=crossing` node are split there. The roads on either side are
+// marked at the end touching the crossing, so the degenerate intersection isn't collapsed.
+pub const CROSSING_FWD: &str = "abst:crossing_fwd";
+pub const CROSSING_BACK: &str = "abst:crossing_back";
 
 // Any roads might have these.
 pub const INFERRED_PARKING: &str = "abst:parking_inferred";
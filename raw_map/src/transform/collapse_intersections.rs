@@ -5,7 +5,7 @@ use anyhow::Result;
 use geom::{Distance, Pt2D};
 
 use crate::osm::NodeID;
-use crate::{osm, IntersectionType, OriginalRoad, RawMap};
+use crate::{osm, IntersectionType, OriginalRoad, RawMap, RawRoad};
 
 /// Collapse degenerate intersections:
 /// - between two cycleways
@@ -61,6 +61,15 @@ fn should_collapse(r1: OriginalRoad, r2: OriginalRoad, raw: &RawMap) -> Result<(
         bail!("zorders don't match");
     }
 
+    let i = if r1.i1 == r2.i1 || r1.i1 == r2.i2 {
+        r1.i1
+    } else {
+        r1.i2
+    };
+    if ends_at_crossing(r1, road1, i) || ends_at_crossing(r2, road2, i) {
+        bail!("there's a mid-block crossing here");
+    }
+
     if road1.is_cycleway() && road2.is_cycleway() {
         return Ok(());
     }
@@ -132,6 +141,12 @@ fn should_collapse(r1: OriginalRoad, r2: OriginalRoad, raw: &RawMap) -> Result<(
     Ok(())
 }
 
+/// Does the road have a mid-block crossing where it meets intersection `i`?
+fn ends_at_crossing(id: OriginalRoad, road: &RawRoad, i: NodeID) -> bool {
+    (id.i2 == i && road.osm_tags.contains_key(osm::CROSSING_FWD))
+        || (id.i1 == i && road.osm_tags.contains_key(osm::CROSSING_BACK))
+}
+
 pub fn collapse_intersection(raw: &mut RawMap, i: NodeID) {
     let roads = raw.roads_per_intersection(i);
     assert_eq!(roads.len(), 2);
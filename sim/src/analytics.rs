@@ -6,14 +6,19 @@ use fs_err::File;
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
-use geom::{Duration, Pt2D, Time};
+use geom::{Distance, Duration, Pt2D, Speed, Time};
 use map_model::{
     CompressedMovementID, IntersectionID, LaneID, Map, MovementID, ParkingLotID, Path, PathRequest,
     RoadID, TransitRouteID, TransitStopID, Traversable, TurnID,
 };
-use synthpop::TripMode;
+use synthpop::{Conditions, DailyFlow, TripMode};
 
-use crate::{AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripPhaseType};
+use crate::emissions::estimate_segment;
+use crate::safety::ConflictDetector;
+use crate::{
+    AgentID, AgentType, AlertLocation, CarID, Emissions, Event, ParkingSpot, Powertrain,
+    SafetyConflict, TripID, TripPhaseType, VehicleType,
+};
 
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
 /// organizing and storing some information from them. The UI queries Analytics to draw time-series
@@ -48,6 +53,11 @@ pub struct Analytics {
 
     /// Record different problems that each trip encounters.
     pub problems_per_trip: BTreeMap<TripID, Vec<(Time, Problem)>>,
+    /// Every time a vehicle over-took a cyclist: where, how much room they left sideways, and if
+    /// they pulled into oncoming traffic to do it
+    pub bike_overtakes: Vec<(Time, LaneID, Distance, bool)>,
+    /// Every time the weather or daylight changed. Before the first change, it's dry daylight.
+    pub conditions: Vec<(Time, Conditions)>,
 
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
@@ -62,6 +72,25 @@ pub struct Analytics {
 
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
 
+    /// Emissions from vehicles while on each road. Time spent in intersections is only counted
+    /// in the per-trip and total emissions.
+    pub emissions_per_road: BTreeMap<RoadID, Emissions>,
+    /// Only the trip driving the vehicle is charged; transit riders aren't.
+    pub emissions_per_trip: BTreeMap<TripID, Emissions>,
+    pub total_emissions: Emissions,
+    /// From 0 to 1
+    electric_car_share: f64,
+    /// Where each vehicle currently is, since when, and its average speed on the previous lane or
+    /// turn.
+    #[serde(skip_serializing, skip_deserializing)]
+    vehicle_progress: BTreeMap<CarID, VehicleProgress>,
+    #[serde(skip_serializing, skip_deserializing)]
+    conflict_detector: ConflictDetector,
+    /// Near misses depend on precisely when agents pass through intersections, which the
+    /// mesoscopic engine doesn't model.
+    #[serde(skip_serializing, skip_deserializing)]
+    skip_conflict_detection: bool,
+
     /// For benchmarking, we may want to disable collecting data.
     record_anything: bool,
 }
@@ -76,8 +105,17 @@ pub enum Problem {
     ArterialIntersectionCrossing(TurnID),
     /// Another vehicle wanted to over-take this cyclist somewhere on this lane or turn.
     OvertakeDesired(Traversable),
+    /// Another vehicle over-took this cyclist on this lane, leaving less room sideways than the
+    /// minimum passing distance.
+    ClosePass(Traversable, Distance),
     /// Too many people are crossing the same sidewalk or crosswalk at the same time.
     PedestrianOvercrowding(Traversable),
+    /// Another agent passed through the same spot in an intersection very close in time.
+    NearMiss(SafetyConflict),
+    /// A pedestrian crossed a road in the middle of a block, away from a junction.
+    MidBlockCrossing(TurnID),
+    /// A pedestrian crossed against a red light, waiting for a gap in traffic instead.
+    Jaywalking(TurnID),
 }
 
 impl Problem {
@@ -88,16 +126,19 @@ impl Problem {
             Problem::IntersectionDelay(i, _) | Problem::ComplexIntersectionCrossing(i) => {
                 map.get_i(*i).polygon.center()
             }
-            Problem::OvertakeDesired(on) | Problem::PedestrianOvercrowding(on) => {
-                on.get_polyline(map).middle()
-            }
-            Problem::ArterialIntersectionCrossing(t) => map.get_t(*t).geom.middle(),
+            Problem::OvertakeDesired(on)
+            | Problem::ClosePass(on, _)
+            | Problem::PedestrianOvercrowding(on) => on.get_polyline(map).middle(),
+            Problem::ArterialIntersectionCrossing(t)
+            | Problem::MidBlockCrossing(t)
+            | Problem::Jaywalking(t) => map.get_t(*t).geom.middle(),
+            Problem::NearMiss(conflict) => conflict.pt,
         }
     }
 }
 
 impl Analytics {
-    pub fn new(record_anything: bool) -> Analytics {
+    pub fn new(record_anything: bool, electric_car_share: f64) -> Analytics {
         Analytics {
             road_thruput: TimeSeriesCount::new(),
             intersection_thruput: TimeSeriesCount::new(),
@@ -109,15 +150,28 @@ impl Analytics {
             started_trips: BTreeMap::new(),
             finished_trips: Vec::new(),
             problems_per_trip: BTreeMap::new(),
+            bike_overtakes: Vec::new(),
+            conditions: Vec::new(),
             trip_log: Vec::new(),
             intersection_delays: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
             alerts: Vec::new(),
+            emissions_per_road: BTreeMap::new(),
+            emissions_per_trip: BTreeMap::new(),
+            total_emissions: Emissions::default(),
+            electric_car_share,
+            vehicle_progress: BTreeMap::new(),
+            conflict_detector: ConflictDetector::default(),
+            skip_conflict_detection: false,
             record_anything,
         }
     }
 
+    pub(crate) fn disable_conflict_detection(&mut self) {
+        self.skip_conflict_detection = true;
+    }
+
     pub fn event(&mut self, ev: Event, time: Time, map: &Map) {
         if !self.record_anything {
             return;
@@ -172,6 +226,63 @@ impl Analytics {
             _ => {}
         }
 
+        // Emissions. A segment is only counted once the vehicle enters the next one, so the end of
+        // each vehicle's last segment before parking or leaving the map is lost.
+        match ev {
+            Event::AgentEntersTraversable(AgentID::Car(car), trip, to, _) => {
+                if let Some(powertrain) = Powertrain::for_vehicle(car, self.electric_car_share) {
+                    let mut speed = Speed::ZERO;
+                    if let Some(prev) = self.vehicle_progress.remove(&car) {
+                        let (emissions, new_speed) = estimate_segment(
+                            car,
+                            powertrain,
+                            prev.on,
+                            time - prev.entered,
+                            prev.speed,
+                            map,
+                        );
+                        speed = new_speed;
+                        if let Traversable::Lane(l) = prev.on {
+                            *self.emissions_per_road.entry(l.road).or_default() += emissions;
+                        }
+                        if let Some(trip) = prev.trip {
+                            *self.emissions_per_trip.entry(trip).or_default() += emissions;
+                        }
+                        self.total_emissions += emissions;
+                    }
+                    self.vehicle_progress.insert(
+                        car,
+                        VehicleProgress {
+                            on: to,
+                            entered: time,
+                            speed,
+                            trip,
+                        },
+                    );
+                }
+            }
+            Event::CarReachedParkingSpot(car, _)
+            | Event::PersonLeavesMap(_, Some(AgentID::Car(car)), _) => {
+                self.vehicle_progress.remove(&car);
+            }
+            _ => {}
+        }
+
+        // Overtaking cyclists
+        if let Event::VehicleOvertook {
+            overtaken,
+            lane,
+            clearance,
+            into_oncoming_traffic,
+            ..
+        } = ev
+        {
+            if overtaken.vehicle_type == VehicleType::Bike {
+                self.bike_overtakes
+                    .push((time, lane, clearance, into_oncoming_traffic));
+            }
+        }
+
         // Bus arrivals
         if let Event::BusArrivedAtStop(bus, route, stop) = ev {
             self.bus_arrivals.push((time, bus, route, stop));
@@ -285,6 +396,27 @@ impl Analytics {
                     .or_insert_with(Vec::new)
                     .push((time, Problem::ArterialIntersectionCrossing(turn.id)));
             }
+            if a.to_type() == AgentType::Pedestrian && turn.is_mid_block_crossing(map) {
+                self.problems_per_trip
+                    .entry(trip)
+                    .or_insert_with(Vec::new)
+                    .push((time, Problem::MidBlockCrossing(turn.id)));
+            }
+        }
+
+        if let Event::AgentEntersTraversable(a, trip, to, _) = ev {
+            if !self.skip_conflict_detection {
+                for (trip1, trip2, conflict) in
+                    self.conflict_detector.agent_enters(a, trip, to, time, map)
+                {
+                    for trip in [trip1, trip2].into_iter().flatten() {
+                        self.problems_per_trip
+                            .entry(trip)
+                            .or_insert_with(Vec::new)
+                            .push((time, Problem::NearMiss(conflict.clone())));
+                    }
+                }
+            }
         }
 
         // TODO Kinda hacky, but these all consume the event, so kinda bundle em.
@@ -326,6 +458,26 @@ impl Analytics {
         }
     }
 
+    pub(crate) fn handle_live_edits(&mut self, map: &Map) {
+        self.conflict_detector.forget_deleted_turns(map);
+    }
+
+    pub fn record_conditions(&mut self, time: Time, conditions: Conditions) {
+        if self.record_anything {
+            self.conditions.push((time, conditions));
+        }
+    }
+
+    /// The weather and daylight at some time, for comparing results across conditions.
+    pub fn conditions_at(&self, time: Time) -> Conditions {
+        self.conditions
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= time)
+            .map(|(_, c)| *c)
+            .unwrap_or_default()
+    }
+
     // TODO If these ever need to be speeded up, just cache the histogram and index in the events
     // list.
 
@@ -490,6 +642,35 @@ impl Analytics {
         }
     }
 
+    /// Vehicle volumes along each road, for estimating traffic noise. Only hours up to `now` are
+    /// used.
+    pub fn road_flows(&self, now: Time) -> BTreeMap<RoadID, DailyFlow> {
+        let num_hours = (now.get_hours() + 1).min(24);
+        let mut per_road: BTreeMap<RoadID, Vec<(usize, usize)>> = BTreeMap::new();
+        for ((r, agent_type, hour), cnt) in &self.road_thruput.counts {
+            if *hour >= num_hours {
+                continue;
+            }
+            let counts = per_road
+                .entry(*r)
+                .or_insert_with(|| vec![(0, 0); num_hours]);
+            match agent_type {
+                AgentType::Car => {
+                    counts[*hour].0 += cnt;
+                }
+                AgentType::Bus | AgentType::Train => {
+                    counts[*hour].1 += cnt;
+                }
+                // Cyclists, pedestrians, and transit riders don't add noticeable noise
+                AgentType::Bike | AgentType::Pedestrian | AgentType::TransitRider => {}
+            }
+        }
+        per_road
+            .into_iter()
+            .map(|(r, counts)| (r, DailyFlow::from_hourly_counts(&counts)))
+            .collect()
+    }
+
     fn parking_spot_availability(
         now: Time,
         changes: &[(Time, bool)],
@@ -527,10 +708,18 @@ impl Analytics {
 
 impl Default for Analytics {
     fn default() -> Analytics {
-        Analytics::new(false)
+        Analytics::new(false, 0.0)
     }
 }
 
+#[derive(Clone)]
+struct VehicleProgress {
+    on: Traversable,
+    entered: Time,
+    speed: Speed,
+    trip: Option<TripID>,
+}
+
 #[derive(Debug)]
 pub struct TripPhase {
     pub start_time: Time,
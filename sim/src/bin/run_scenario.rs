@@ -12,6 +12,23 @@ struct Args {
     /// How many hours to simulate.
     #[structopt(long)]
     hours: usize,
+    /// Simulate twice, with and without --prefetch-paths, then compare how long each took and
+    /// check the results match.
+    #[structopt(long)]
+    benchmark_prefetch: bool,
+    /// Use the much faster, coarser mesoscopic model instead of simulating every vehicle exactly.
+    /// The results are saved next to the scenario's prebaked results, with a `_mesoscopic`
+    /// suffix, so they don't replace the normal simulation's.
+    #[structopt(long)]
+    mesoscopic: bool,
+    /// Record where every agent goes, then write the trajectories to this file. The extension
+    /// determines the format: .csv, .geojson for GeoJSON-LD, or .xml for SUMO's FCD.
+    #[structopt(long)]
+    trajectories: Option<String>,
+    /// How often to record every agent's position. If this isn't set, agents are recorded whenever
+    /// they enter a lane or turn.
+    #[structopt(long)]
+    trajectory_interval_seconds: Option<f64>,
     #[structopt(flatten)]
     flags: sim::SimFlags,
 }
@@ -21,9 +38,25 @@ fn main() {
     let mut args = Args::from_args();
     args.flags.initialize();
     let hours = geom::Duration::hours(args.hours);
+    if args.benchmark_prefetch {
+        benchmark_prefetch(args.flags, hours);
+        return;
+    }
+    if args.mesoscopic {
+        run_mesoscopic(args.flags, hours);
+        return;
+    }
     let (mut map, mut sim, _) = args
         .flags
         .load_synchronously(&mut abstutil::Timer::new("setup"));
+    let trajectories = args.trajectories.map(|path| {
+        let format = sim::TrajectoryFormat::from_path(&path).unwrap();
+        sim.record_trajectories(match args.trajectory_interval_seconds {
+            Some(secs) => sim::TrajectorySampling::Every(geom::Duration::seconds(secs)),
+            None => sim::TrajectorySampling::OnEnteringTraversable,
+        });
+        (path, format)
+    });
 
     if args.interruptible {
         // Pressing ^C will savestate. This needs a more complex loop to check for the interrupt.
@@ -53,21 +86,119 @@ fn main() {
                 geom::Duration::seconds(1.0),
                 &mut None,
             );
+            sim.apply_incidents(&mut map, &mut abstutil::Timer::throwaway());
             if sim.time() == goal_time {
+                save_trajectories(&mut sim, trajectories);
                 return;
             }
         }
         println!("\n\nInterrupting at {}", sim.time());
+        save_trajectories(&mut sim, trajectories);
         sim.save();
         for x in sim.describe_internal_stats() {
             println!("{}", x);
         }
     } else {
-        sim.timed_step(
+        sim.timed_step_with_incidents(
+            &mut map,
+            hours,
+            &mut None,
+            &mut abstutil::Timer::new("run simulation"),
+        );
+        save_trajectories(&mut sim, trajectories);
+    }
+}
+
+fn save_trajectories(sim: &mut sim::Sim, output: Option<(String, sim::TrajectoryFormat)>) {
+    if let Some((path, format)) = output {
+        let trajectories = sim.take_trajectories().unwrap();
+        abstio::write_raw(path.clone(), trajectories.export(format).as_bytes()).unwrap();
+        println!(
+            "Wrote {} trajectory points to {}",
+            abstutil::prettyprint_usize(trajectories.points.len()),
+            path
+        );
+    }
+}
+
+fn benchmark_prefetch(flags: sim::SimFlags, hours: geom::Duration) {
+    let mut results = Vec::new();
+    for prefetch in [false, true] {
+        let mut flags = flags.clone();
+        flags.opts.prefetch_paths = prefetch;
+        let (mut map, mut sim, _) = flags.load_synchronously(&mut abstutil::Timer::new("setup"));
+
+        let start = instant::Instant::now();
+        sim.timed_step_with_incidents(
             &mut map,
             hours,
             &mut None,
             &mut abstutil::Timer::new("run simulation"),
         );
+        let elapsed = geom::Duration::realtime_elapsed(start);
+        println!(
+            "With prefetch_paths = {}, simulating took {}",
+            prefetch, elapsed
+        );
+        results.push((elapsed, sim.get_analytics().finished_trips.clone()));
+    }
+
+    println!("Speedup: {:.2}x", results[0].0 / results[1].0);
+    if results[0].1 != results[1].1 {
+        println!("The results differ, so prefetching paths isn't deterministic!");
+        std::process::exit(1);
+    }
+    println!(
+        "Both runs finished or cancelled the same {} trips at the same times",
+        abstutil::prettyprint_usize(results[0].1.len())
+    );
+}
+
+fn run_mesoscopic(flags: sim::SimFlags, hours: geom::Duration) {
+    if !flags.load.contains("/scenarios/") {
+        panic!("--mesoscopic needs a scenario, not {}", flags.load);
+    }
+    let mut timer = abstutil::Timer::new("setup");
+    let mut scenario: synthpop::Scenario = abstio::must_read_object(flags.load.clone(), &mut timer);
+    let map = map_model::Map::load_synchronously(scenario.map_name.path(), &mut timer);
+    for m in &flags.scenario_modifiers {
+        scenario = m.apply(&map, scenario);
+    }
+    let mut sim = sim::MesoSim::new(&scenario, &flags.opts);
+
+    let start = instant::Instant::now();
+    sim.run_until(
+        &map,
+        geom::Time::START_OF_DAY + hours,
+        &mut abstutil::Timer::new("run mesoscopic simulation"),
+    );
+    let (done, total) = sim.num_trips_done();
+    println!(
+        "Simulating took {}. {} / {} trips finished or were cancelled",
+        geom::Duration::realtime_elapsed(start),
+        abstutil::prettyprint_usize(done),
+        abstutil::prettyprint_usize(total)
+    );
+
+    let mut busiest: Vec<_> = sim.link_stats().iter().collect();
+    busiest.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.volume));
+    for (dr, stats) in busiest.into_iter().take(10) {
+        println!(
+            "{} ({}): {} vehicles, {} on average",
+            dr,
+            map.get_r(dr.road).get_name(None),
+            abstutil::prettyprint_usize(stats.volume),
+            stats
+                .mean_travel_time()
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| "no travel time yet".to_string())
+        );
     }
+
+    let path = abstio::path_prebaked_results(
+        &scenario.map_name,
+        &format!("{}_mesoscopic", scenario.scenario_name),
+    );
+    abstio::write_binary(path.clone(), sim.get_analytics());
+    println!("Wrote {}", path);
 }
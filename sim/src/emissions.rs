@@ -0,0 +1,323 @@
+//! A rough estimate of the energy that vehicles use and what they emit. Vehicles aren't tracked
+//! continuously; instead, each time one finishes a lane or turn, its average speed there, the
+//! change in speed from the previous segment, and the grade of the road give the work done over
+//! that segment.
+//!
+//! The constants are ballpark figures for a typical modern fleet. They're useful for comparing
+//! scenarios or map edits against each other, not as absolute measurements.
+
+use std::ops::{Add, AddAssign};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Speed};
+use map_model::{Direction, Map, Traversable};
+
+use crate::{CarID, VehicleType};
+
+const GRAVITY: f64 = 9.81;
+const AIR_DENSITY: f64 = 1.2;
+const JOULES_PER_KWH: f64 = 3_600_000.0;
+
+/// What a vehicle emitted and the energy it used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Emissions {
+    pub co2_grams: f64,
+    pub nox_grams: f64,
+    /// Both from the exhaust and from tire, brake, and road wear
+    pub pm_grams: f64,
+    pub fuel_liters: f64,
+    /// Only used by electric vehicles. Emissions from generating the electricity aren't counted.
+    pub electricity_kwh: f64,
+}
+
+impl Add for Emissions {
+    type Output = Emissions;
+
+    fn add(self, other: Emissions) -> Emissions {
+        Emissions {
+            co2_grams: self.co2_grams + other.co2_grams,
+            nox_grams: self.nox_grams + other.nox_grams,
+            pm_grams: self.pm_grams + other.pm_grams,
+            fuel_liters: self.fuel_liters + other.fuel_liters,
+            electricity_kwh: self.electricity_kwh + other.electricity_kwh,
+        }
+    }
+}
+
+impl AddAssign for Emissions {
+    fn add_assign(&mut self, other: Emissions) {
+        *self = *self + other;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Powertrain {
+    Gasoline,
+    Diesel,
+    Electric,
+}
+
+impl Powertrain {
+    /// Buses run on diesel and trains on electricity. Of the cars, `electric_car_share` of them
+    /// (from 0 to 1) are electric, picked deterministically from the ID. Bikes don't have a
+    /// powertrain.
+    pub fn for_vehicle(car: CarID, electric_car_share: f64) -> Option<Powertrain> {
+        match car.vehicle_type {
+            VehicleType::Car => {
+                if car.pseudorandom_fraction() < electric_car_share {
+                    Some(Powertrain::Electric)
+                } else {
+                    Some(Powertrain::Gasoline)
+                }
+            }
+            VehicleType::Bus => Some(Powertrain::Diesel),
+            VehicleType::Train => Some(Powertrain::Electric),
+            VehicleType::Bike => None,
+        }
+    }
+}
+
+struct VehicleParams {
+    mass_kg: f64,
+    /// Drag coefficient times frontal area, in square meters
+    drag_area: f64,
+    rolling_resistance: f64,
+    /// Fuel burned by a combustion engine just to keep running, even when idling
+    idle_kw: f64,
+    /// Power drawn by an electric vehicle for lights, climate control, etc
+    auxiliary_kw: f64,
+    non_exhaust_pm_grams_per_km: f64,
+}
+
+impl VehicleParams {
+    fn new(vehicle_type: VehicleType) -> VehicleParams {
+        match vehicle_type {
+            // Bikes don't have a powertrain, so this is never used for them
+            VehicleType::Car | VehicleType::Bike => VehicleParams {
+                mass_kg: 1500.0,
+                drag_area: 0.7,
+                rolling_resistance: 0.01,
+                idle_kw: 7.0,
+                auxiliary_kw: 1.0,
+                non_exhaust_pm_grams_per_km: 0.03,
+            },
+            VehicleType::Bus => VehicleParams {
+                mass_kg: 15_000.0,
+                drag_area: 5.0,
+                rolling_resistance: 0.008,
+                idle_kw: 25.0,
+                auxiliary_kw: 8.0,
+                non_exhaust_pm_grams_per_km: 0.2,
+            },
+            VehicleType::Train => VehicleParams {
+                mass_kg: 50_000.0,
+                drag_area: 8.0,
+                rolling_resistance: 0.002,
+                idle_kw: 60.0,
+                auxiliary_kw: 20.0,
+                non_exhaust_pm_grams_per_km: 0.1,
+            },
+        }
+    }
+}
+
+struct FuelParams {
+    /// How much of the fuel's energy turns into work at the wheels
+    efficiency: f64,
+    kwh_per_liter: f64,
+    co2_grams_per_liter: f64,
+    nox_grams_per_liter: f64,
+    pm_grams_per_liter: f64,
+}
+
+const GASOLINE: FuelParams = FuelParams {
+    efficiency: 0.25,
+    kwh_per_liter: 8.9,
+    co2_grams_per_liter: 2310.0,
+    nox_grams_per_liter: 0.9,
+    pm_grams_per_liter: 0.07,
+};
+const DIESEL: FuelParams = FuelParams {
+    efficiency: 0.3,
+    kwh_per_liter: 10.0,
+    co2_grams_per_liter: 2680.0,
+    nox_grams_per_liter: 5.0,
+    pm_grams_per_liter: 0.05,
+};
+const ELECTRIC_EFFICIENCY: f64 = 0.85;
+/// How much energy lost to braking or going downhill an electric vehicle recovers
+const REGENERATIVE_BRAKING: f64 = 0.6;
+
+/// Estimate what a vehicle emits while crossing one lane or turn, given the time it spent there
+/// and its average speed on the previous segment. Also returns the average speed on this segment.
+pub(crate) fn estimate_segment(
+    car: CarID,
+    powertrain: Powertrain,
+    on: Traversable,
+    dt: Duration,
+    prev_speed: Speed,
+    map: &Map,
+) -> (Emissions, Speed) {
+    let grade = match on {
+        Traversable::Lane(l) => {
+            let incline = map.get_parent(l).percent_incline;
+            if map.get_l(l).dir == Direction::Fwd {
+                incline
+            } else {
+                -incline
+            }
+        }
+        // Turns are short; ignore elevation
+        Traversable::Turn(_) => 0.0,
+    };
+    estimate(
+        car.vehicle_type,
+        powertrain,
+        on.get_polyline(map).length(),
+        grade,
+        dt,
+        prev_speed,
+    )
+}
+
+/// `grade` is the rise over the run, positive for uphill.
+fn estimate(
+    vehicle_type: VehicleType,
+    powertrain: Powertrain,
+    dist: Distance,
+    grade: f64,
+    dt: Duration,
+    prev_speed: Speed,
+) -> (Emissions, Speed) {
+    if dt == Duration::ZERO {
+        return (Emissions::default(), prev_speed);
+    }
+    let speed = Speed::from_dist_time(dist, dt);
+    let params = VehicleParams::new(vehicle_type);
+
+    // All in joules
+    let v0 = prev_speed.inner_meters_per_second();
+    let v1 = speed.inner_meters_per_second();
+    let d = dist.inner_meters();
+    let kinetic = 0.5 * params.mass_kg * (v1 * v1 - v0 * v0);
+    let potential = params.mass_kg * GRAVITY * grade * d;
+    let resistance = params.rolling_resistance * params.mass_kg * GRAVITY * d
+        + 0.5 * AIR_DENSITY * params.drag_area * v1 * v1 * d;
+    let tractive = kinetic + potential + resistance;
+    let seconds = dt.inner_seconds();
+
+    let mut emissions = Emissions {
+        pm_grams: params.non_exhaust_pm_grams_per_km * d / 1000.0,
+        ..Default::default()
+    };
+    match powertrain {
+        Powertrain::Gasoline | Powertrain::Diesel => {
+            let fuel = if powertrain == Powertrain::Gasoline {
+                &GASOLINE
+            } else {
+                &DIESEL
+            };
+            // Combustion engines can't recover energy from braking
+            let joules = tractive.max(0.0) / fuel.efficiency + params.idle_kw * 1000.0 * seconds;
+            let liters = joules / JOULES_PER_KWH / fuel.kwh_per_liter;
+            emissions.fuel_liters = liters;
+            emissions.co2_grams = liters * fuel.co2_grams_per_liter;
+            emissions.nox_grams = liters * fuel.nox_grams_per_liter;
+            emissions.pm_grams += liters * fuel.pm_grams_per_liter;
+        }
+        Powertrain::Electric => {
+            let joules = if tractive >= 0.0 {
+                tractive / ELECTRIC_EFFICIENCY
+            } else {
+                tractive * REGENERATIVE_BRAKING
+            };
+            // Even when regenerating, the net can't be negative for a whole segment
+            emissions.electricity_kwh =
+                (joules + params.auxiliary_kw * 1000.0 * seconds).max(0.0) / JOULES_PER_KWH;
+        }
+    }
+    (emissions, speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cruise(powertrain: Powertrain, grade: f64) -> Emissions {
+        estimate(
+            VehicleType::Car,
+            powertrain,
+            Distance::meters(100.0),
+            grade,
+            Duration::seconds(10.0),
+            Speed::meters_per_second(10.0),
+        )
+        .0
+    }
+
+    /// Slow from 15m/s to an average of 5m/s over 50m
+    fn decelerate(powertrain: Powertrain) -> Emissions {
+        estimate(
+            VehicleType::Car,
+            powertrain,
+            Distance::meters(50.0),
+            0.0,
+            Duration::seconds(10.0),
+            Speed::meters_per_second(15.0),
+        )
+        .0
+    }
+
+    #[test]
+    fn test_uphill_uses_more_than_flat() {
+        for powertrain in [Powertrain::Gasoline, Powertrain::Diesel] {
+            let flat = cruise(powertrain, 0.0);
+            let uphill = cruise(powertrain, 0.05);
+            assert!(flat.fuel_liters > 0.0);
+            assert!(uphill.fuel_liters > flat.fuel_liters);
+            assert!(uphill.co2_grams > flat.co2_grams);
+            assert_eq!(flat.electricity_kwh, 0.0);
+        }
+
+        let flat = cruise(Powertrain::Electric, 0.0);
+        let uphill = cruise(Powertrain::Electric, 0.05);
+        assert!(flat.electricity_kwh > 0.0);
+        assert!(uphill.electricity_kwh > flat.electricity_kwh);
+        assert_eq!(flat.fuel_liters, 0.0);
+        assert_eq!(flat.co2_grams, 0.0);
+    }
+
+    #[test]
+    fn test_deceleration() {
+        // Combustion engines can't recover anything, so they just idle
+        let gas = decelerate(Powertrain::Gasoline);
+        let idle_liters = VehicleParams::new(VehicleType::Car).idle_kw * 1000.0 * 10.0
+            / JOULES_PER_KWH
+            / GASOLINE.kwh_per_liter;
+        assert!((gas.fuel_liters - idle_liters).abs() < 1e-9);
+
+        // Regenerative braking covers the auxiliary load, but the total never goes negative
+        let ev = decelerate(Powertrain::Electric);
+        assert_eq!(ev.electricity_kwh, 0.0);
+
+        // Tire and brake wear still happens either way
+        assert!(ev.pm_grams > 0.0);
+        assert!(gas.pm_grams > ev.pm_grams);
+    }
+
+    #[test]
+    fn test_no_time_spent() {
+        let prev_speed = Speed::meters_per_second(10.0);
+        let (emissions, speed) = estimate(
+            VehicleType::Car,
+            Powertrain::Gasoline,
+            Distance::meters(100.0),
+            0.0,
+            Duration::ZERO,
+            prev_speed,
+        );
+        assert_eq!(emissions, Emissions::default());
+        assert_eq!(speed, prev_speed);
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use geom::Duration;
+use geom::{Distance, Duration};
 use map_model::{
     BuildingID, IntersectionID, LaneID, Map, Path, PathRequest, TransitRouteID, TransitStopID,
     Traversable, TurnID,
@@ -37,6 +37,14 @@ pub enum Event {
     PedReachedParkingSpot(PedestrianID, ParkingSpot),
 
     BikeStoppedAtSidewalk(CarID, LaneID),
+    /// A vehicle passed a slower one along a lane, leaving some room sideways.
+    VehicleOvertook {
+        overtaker: CarID,
+        overtaken: CarID,
+        lane: LaneID,
+        clearance: Distance,
+        into_oncoming_traffic: bool,
+    },
 
     ProblemEncountered(TripID, Problem),
 
@@ -92,10 +100,10 @@ impl TripPhaseType {
             TripPhaseType::Biking => "Biking".to_string(),
             TripPhaseType::Parking => "Parking".to_string(),
             TripPhaseType::WaitingForBus(r, _) => {
-                format!("Waiting for transit route {}", map.get_tr(r).long_name)
+                format!("Waiting for transit route {}", route_name(r, map))
             }
             TripPhaseType::RidingBus(r, _, _) => {
-                format!("Riding route {}", map.get_tr(r).long_name)
+                format!("Riding route {}", route_name(r, map))
             }
             TripPhaseType::Cancelled => "Trip was cancelled due to some bug".to_string(),
             TripPhaseType::Finished => "Trip finished".to_string(),
@@ -103,3 +111,10 @@ impl TripPhaseType {
         }
     }
 }
+
+/// The route might've been deleted by live map edits since the trip phase happened.
+fn route_name(r: TransitRouteID, map: &Map) -> String {
+    map.maybe_get_tr(r)
+        .map(|tr| tr.long_name.clone())
+        .unwrap_or_else(|| r.to_string())
+}
@@ -0,0 +1,230 @@
+//! Incidents scheduled in a scenario, like crashes and road closures, are temporary map edits. The
+//! simulation only borrows the map, so it can't edit it directly. Instead, it halts whenever an
+//! incident starts or ends, and whoever owns the map applies the edits. They're applied separately
+//! from the map's own edits, so they're never saved or shared as part of a proposal.
+//!
+//! Several incidents might overlap on the same lanes or intersections, so this remembers what
+//! everything looked like before the first of them, and only restores that once the last one
+//! ends.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::Time;
+use map_model::{
+    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersectionControl, IntersectionID,
+    LaneID, LaneType, Map, RoadID,
+};
+use synthpop::{Incident, ScheduledIncident};
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct IncidentState {
+    scheduled: Vec<ScheduledIncident>,
+    active: BTreeSet<usize>,
+    /// Incidents that started (true) or ended (false), but haven't been applied to the map yet
+    pending: Vec<(usize, bool)>,
+    /// The original type of every lane closed by an active incident, and which incidents are
+    /// closing it
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    closed_lanes: BTreeMap<LaneID, (LaneType, BTreeSet<usize>)>,
+    /// The original signal at every intersection where it's failed, and which incidents are
+    /// responsible
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    failed_signals: BTreeMap<IntersectionID, (ControlTrafficSignal, BTreeSet<usize>)>,
+}
+
+impl IncidentState {
+    /// Returns an index to refer to the incident later.
+    pub fn add(&mut self, incident: ScheduledIncident) -> usize {
+        self.scheduled.push(incident);
+        self.scheduled.len() - 1
+    }
+
+    pub fn mark_pending(&mut self, idx: usize, start: bool) {
+        self.pending.push((idx, start));
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn all(&self) -> Vec<(&ScheduledIncident, bool)> {
+        self.scheduled
+            .iter()
+            .enumerate()
+            .map(|(idx, incident)| (incident, self.active.contains(&idx)))
+            .collect()
+    }
+
+    /// Calculates the edits for every pending incident, relative to the current map.
+    pub fn make_edits(&mut self, now: Time, map: &Map) -> Vec<EditCmd> {
+        let mut restore_lanes: BTreeMap<LaneID, LaneType> = BTreeMap::new();
+        let mut restore_signals: BTreeMap<IntersectionID, ControlTrafficSignal> = BTreeMap::new();
+        let mut changed_roads: BTreeSet<RoadID> = BTreeSet::new();
+        let mut changed_intersections: BTreeSet<IntersectionID> = BTreeSet::new();
+
+        for (idx, start) in std::mem::take(&mut self.pending) {
+            if start {
+                if let Err(err) = self.scheduled[idx].check(map) {
+                    warn!("Skipping incident: {}", err);
+                    continue;
+                }
+                self.active.insert(idx);
+                info!(
+                    "At {}, {} starts",
+                    now,
+                    self.scheduled[idx].incident.describe()
+                );
+
+                match self.scheduled[idx].incident {
+                    Incident::SignalFailure(i) => {
+                        self.failed_signals
+                            .entry(i)
+                            .or_insert_with(|| (map.get_traffic_signal(i).clone(), BTreeSet::new()))
+                            .1
+                            .insert(idx);
+                        restore_signals.remove(&i);
+                        changed_intersections.insert(i);
+                    }
+                    _ => {
+                        for l in lanes_to_close(&self.scheduled[idx].incident, map) {
+                            self.closed_lanes
+                                .entry(l)
+                                .or_insert_with(|| (map.get_l(l).lane_type, BTreeSet::new()))
+                                .1
+                                .insert(idx);
+                            restore_lanes.remove(&l);
+                            changed_roads.insert(l.road);
+                        }
+                    }
+                }
+            } else {
+                if !self.active.remove(&idx) {
+                    continue;
+                }
+                info!(
+                    "At {}, {} ends",
+                    now,
+                    self.scheduled[idx].incident.describe()
+                );
+
+                self.closed_lanes.retain(|l, (lt, incidents)| {
+                    if incidents.remove(&idx) && incidents.is_empty() {
+                        restore_lanes.insert(*l, *lt);
+                        changed_roads.insert(l.road);
+                        return false;
+                    }
+                    true
+                });
+                self.failed_signals.retain(|i, (signal, incidents)| {
+                    if incidents.remove(&idx) && incidents.is_empty() {
+                        restore_signals.insert(*i, signal.clone());
+                        changed_intersections.insert(*i);
+                        return false;
+                    }
+                    true
+                });
+            }
+        }
+
+        let mut commands = Vec::new();
+        for r in changed_roads {
+            // Other edits might've deleted the road in the meantime
+            if map.maybe_get_r(r).is_none() {
+                continue;
+            }
+            let cmd = map.edit_road_cmd(r, |new| {
+                for (offset, spec) in new.lanes_ltr.iter_mut().enumerate() {
+                    let l = LaneID { road: r, offset };
+                    if self.closed_lanes.contains_key(&l) {
+                        spec.lt = LaneType::Construction;
+                    } else if let Some(lt) = restore_lanes.get(&l) {
+                        spec.lt = *lt;
+                    }
+                }
+            });
+            if let EditCmd::ChangeRoad {
+                ref old, ref new, ..
+            } = cmd
+            {
+                if old != new {
+                    commands.push(cmd);
+                }
+            }
+        }
+        for i in changed_intersections {
+            if self.failed_signals.contains_key(&i) {
+                if map.maybe_get_traffic_signal(i).is_some() {
+                    commands.push(map.edit_intersection_cmd(i, |new| {
+                        new.control = EditIntersectionControl::StopSign(all_way_stop(map, i));
+                    }));
+                }
+            } else if let Some(signal) = restore_signals.remove(&i) {
+                commands.push(map.edit_intersection_cmd(i, |new| {
+                    new.control = EditIntersectionControl::TrafficSignal(signal.export(map));
+                }));
+            }
+        }
+
+        commands
+    }
+}
+
+fn lanes_to_close(incident: &Incident, map: &Map) -> Vec<LaneID> {
+    match incident {
+        Incident::LaneBlockage(lanes) => {
+            let mut result = lanes.clone();
+            // If there aren't any driving lanes left, also close the parking. This mimics the
+            // check that the UI does when editing.
+            let roads: BTreeSet<RoadID> = lanes.iter().map(|l| l.road).collect();
+            for r in roads {
+                let road = map.get_r(r);
+                if road
+                    .lanes
+                    .iter()
+                    .all(|l| l.lane_type != LaneType::Driving || lanes.contains(&l.id))
+                {
+                    result.extend(
+                        road.lanes
+                            .iter()
+                            .filter(|l| l.lane_type == LaneType::Parking)
+                            .map(|l| l.id),
+                    );
+                }
+            }
+            result
+        }
+        Incident::RoadClosure(r) => close_road(*r, map),
+        Incident::SpecialEvent { roads, .. } => {
+            roads.iter().flat_map(|r| close_road(*r, map)).collect()
+        }
+        Incident::SignalFailure(_) => Vec::new(),
+    }
+}
+
+// Everything except for sidewalks and shoulders
+fn close_road(r: RoadID, map: &Map) -> Vec<LaneID> {
+    map.get_r(r)
+        .lanes
+        .iter()
+        .filter(|l| !l.is_walkable())
+        .map(|l| l.id)
+        .collect()
+}
+
+// When a signal fails, drivers are supposed to treat it like an all-way stop
+fn all_way_stop(map: &Map, i: IntersectionID) -> ControlStopSign {
+    let mut ss = ControlStopSign::new(map, i);
+    for r in ss.roads.values_mut() {
+        r.must_stop = true;
+    }
+    ss
+}
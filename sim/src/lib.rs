@@ -22,7 +22,7 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize};
-use geom::{Distance, Speed, Time};
+use geom::{Distance, Duration, Speed, Time};
 use map_model::{
     BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints, Position,
     TransitRouteID, TransitStopID,
@@ -35,37 +35,51 @@ pub use crate::render::{
 };
 
 pub use self::analytics::{Analytics, Problem, SlidingWindow, TripPhase};
+pub use self::emissions::{Emissions, Powertrain};
 pub(crate) use self::events::Event;
 pub use self::events::{AlertLocation, TripPhaseType};
-pub use self::make::{fork_rng, BorderSpawnOverTime, ScenarioGenerator, SimFlags, SpawnOverTime};
+pub(crate) use self::incidents::IncidentState;
+pub use self::make::{
+    fork_rng, rand_behavior, BorderSpawnOverTime, ScenarioGenerator, SimFlags, SpawnOverTime,
+};
 pub(crate) use self::make::{StartTripArgs, TripSpec};
 pub(crate) use self::mechanics::{
     DrivingSimState, IntersectionSimState, ParkingSim, ParkingSimState, WalkingSimState,
 };
+pub use self::mesoscopic::{LinkStats, MesoSim};
 pub(crate) use self::pandemic::PandemicModel;
-pub use self::prebake::PrebakeSummary;
+pub use self::prebake::{MultiSeedResults, PrebakeSummary};
 pub(crate) use self::recorder::TrafficRecorder;
 pub(crate) use self::router::{ActionAtEnd, Router};
+pub use self::safety::{ConflictSeverity, SafetyConflict};
 pub(crate) use self::scheduler::{Command, Scheduler};
 pub use self::sim::{
     count_parked_cars_per_bldg, rand_dist, AgentProperties, AlertHandler, DelayCause, Sim,
     SimCallback, SimOptions,
 };
+pub use self::trajectories::{Trajectories, TrajectoryFormat, TrajectoryPoint, TrajectorySampling};
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
 pub(crate) use self::trips::{TripLeg, TripManager};
 
 mod analytics;
+mod emissions;
 mod events;
+mod incidents;
 mod make;
 mod mechanics;
+mod mesoscopic;
 mod pandemic;
 pub mod prebake;
 mod recorder;
 mod render;
 mod router;
+mod safety;
 mod scheduler;
 mod sim;
+#[cfg(test)]
+mod testing;
+mod trajectories;
 mod transit;
 mod trips;
 
@@ -98,6 +112,15 @@ pub struct CarID {
     pub vehicle_type: VehicleType,
 }
 
+impl CarID {
+    /// A number in [0, 1) that's fixed per vehicle, but scrambled, so that picking vehicles below
+    /// some threshold doesn't just pick the ones created first.
+    pub(crate) fn pseudorandom_fraction(self) -> f64 {
+        let hash = (self.id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 11;
+        (hash as f64) / ((1_u64 << 53) as f64)
+    }
+}
+
 impl fmt::Display for CarID {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.vehicle_type {
@@ -309,6 +332,37 @@ impl VehicleType {
             VehicleType::Bike => false,
         }
     }
+
+    /// Ballpark figures for how quickly this type of vehicle speeds up and slows down in normal
+    /// driving.
+    pub fn acceleration_profile(self) -> AccelerationProfile {
+        match self {
+            VehicleType::Car => AccelerationProfile {
+                max_acceleration: 2.0,
+                comfortable_deceleration: 3.0,
+            },
+            VehicleType::Bus => AccelerationProfile {
+                max_acceleration: 1.0,
+                comfortable_deceleration: 1.5,
+            },
+            VehicleType::Train => AccelerationProfile {
+                max_acceleration: 1.0,
+                comfortable_deceleration: 1.2,
+            },
+            VehicleType::Bike => AccelerationProfile {
+                max_acceleration: 1.0,
+                comfortable_deceleration: 2.0,
+            },
+        }
+    }
+}
+
+/// How quickly a vehicle speeds up and slows down, in meters per second squared.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AccelerationProfile {
+    pub max_acceleration: f64,
+    /// Vehicles brake harder than this if they have to, but plan to stop with this.
+    pub comfortable_deceleration: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -318,6 +372,23 @@ pub struct Vehicle {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    /// How fast the driver goes, relative to the speed limit. Never exceeds max_speed.
+    pub speed_factor: f64,
+    /// The smallest gap in traffic with priority the driver accepts for an unprotected turn. If
+    /// None, uses a default.
+    pub critical_gap: Option<Duration>,
+}
+
+impl Vehicle {
+    /// Adjusts the fastest speed along something by the driver's preferences.
+    pub fn desired_speed(&self, speed: Speed) -> Speed {
+        let speed = speed * self.speed_factor;
+        if let Some(max) = self.max_speed {
+            speed.min(max)
+        } else {
+            speed
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -325,6 +396,8 @@ pub struct VehicleSpec {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    pub speed_factor: f64,
+    pub critical_gap: Option<Duration>,
 }
 
 impl VehicleSpec {
@@ -336,6 +409,8 @@ impl VehicleSpec {
             vehicle_type: self.vehicle_type,
             length: self.length,
             max_speed: self.max_speed,
+            speed_factor: self.speed_factor,
+            critical_gap: self.critical_gap,
         }
     }
 }
@@ -13,7 +13,7 @@ use geom::{Distance, Duration, Time};
 use map_model::{BuildingID, BuildingType, Map, PathConstraints, PathRequest};
 use synthpop::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
 
-use crate::make::fork_rng;
+use crate::make::{fork_rng, rand_behavior};
 use crate::ScenarioGenerator;
 
 impl ScenarioGenerator {
@@ -273,6 +273,7 @@ fn create_prole(
             IndividTrip::new(depart_am, TripPurpose::Work, home, work, mode),
             IndividTrip::new(depart_pm, TripPurpose::Home, work, home, mode),
         ],
+        behavior: Some(rand_behavior(rng, None)),
     })
 }
 
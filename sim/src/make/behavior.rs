@@ -0,0 +1,39 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use rand_xorshift::XorShiftRng;
+
+use geom::{Duration, Speed};
+use synthpop::{BehaviorProfile, CyclistComfort};
+
+/// Randomly pick how somebody behaves. If their age is known, it affects walking speed.
+pub fn rand_behavior(rng: &mut XorShiftRng, age: Option<usize>) -> BehaviorProfile {
+    // Most drivers stay within about 10% of the limit
+    let desired_speed_factor = Normal::new(1.0, 0.05)
+        .unwrap()
+        .sample(rng)
+        .clamp(0.85, 1.15);
+    // Gaps accepted for unprotected turns mostly range from 3 to 7 seconds
+    let critical_gap = Duration::seconds(rng.gen_range(3.0..7.0));
+    let cyclist_comfort = match rng.gen_range(0..10) {
+        0 => CyclistComfort::StrongAndFearless,
+        1..=3 => CyclistComfort::EnthusedAndConfident,
+        _ => CyclistComfort::InterestedButConcerned,
+    };
+    // Older people and young children walk slower
+    let (low, high) = match age {
+        Some(age) if age < 13 => (0.9, 1.2),
+        Some(age) if age >= 65 => (0.7, 1.1),
+        _ => (
+            Speed::miles_per_hour(2.0).inner_meters_per_second(),
+            map_model::MAX_WALKING_SPEED.inner_meters_per_second(),
+        ),
+    };
+    let walking_speed = Speed::meters_per_second(rng.gen_range(low..high));
+
+    BehaviorProfile {
+        desired_speed_factor,
+        critical_gap,
+        cyclist_comfort,
+        walking_speed,
+    }
+}
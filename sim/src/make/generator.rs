@@ -13,6 +13,8 @@ use geom::{Duration, Time};
 use map_model::{IntersectionID, Map};
 use synthpop::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
 
+use crate::make::rand_behavior;
+
 // TODO This can be simplified dramatically.
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -171,6 +173,7 @@ impl SpawnOverTime {
                 }),
                 mode,
             )],
+            behavior: Some(rand_behavior(rng, None)),
         });
     }
 }
@@ -189,6 +192,7 @@ impl BorderSpawnOverTime {
                 }),
                 mode,
             )],
+            behavior: Some(rand_behavior(rng, None)),
         });
     }
 }
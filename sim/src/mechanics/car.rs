@@ -2,12 +2,13 @@ use std::collections::{BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, PolyLine, Time, EPSILON_DIST};
+use geom::{Distance, Duration, PolyLine, Speed, Time, EPSILON_DIST};
 use map_model::{Direction, LaneID, Map, Traversable};
 
+use crate::mechanics::kinematics::SpeedProfile;
 use crate::{
-    CarID, CarStatus, DistanceInterval, DrawCarInput, Intent, ParkingSpot, PersonID, Router,
-    TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
+    AccelerationProfile, CarID, CarStatus, DistanceInterval, DrawCarInput, Intent, ParkingSpot,
+    PersonID, Router, TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
 };
 
 /// Represents a single vehicle. Note "car" is a misnomer; it could also be a bus or bike.
@@ -26,14 +27,35 @@ pub(crate) struct Car {
     /// vehicle.length.
     pub last_steps: VecDeque<Traversable>,
 
-    /// Since lane over-taking isn't implemented yet, a vehicle tends to be stuck behind a slow
-    /// leader for a while. Avoid duplicate events.
+    /// A vehicle may be stuck behind a slow leader for a while before there's a chance to
+    /// overtake. Avoid duplicate events.
     pub wants_to_overtake: BTreeSet<CarID>,
+
+    /// Only set when the simulation models acceleration; otherwise vehicles instantly change
+    /// speed.
+    pub acceleration: Option<AccelerationProfile>,
+    /// How fast the vehicle was going when it finished its last Crossing state
+    pub last_speed: Speed,
+    /// Scales the driver's desired speed by the current weather and daylight
+    pub conditions_speed_factor: f64,
 }
 
 impl Car {
-    /// Assumes the current head of the path is the thing to cross.
-    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
+    /// Adjusts the fastest speed along something by the driver's preferences and the current
+    /// conditions.
+    pub fn desired_speed(&self, speed: Speed) -> Speed {
+        self.vehicle.desired_speed(speed) * self.conditions_speed_factor
+    }
+
+    /// Assumes the current head of the path is the thing to cross. `stop_at_end` only matters when
+    /// acceleration is modelled; then the vehicle brakes to a stop by the end.
+    pub fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        stop_at_end: bool,
+        map: &Map,
+    ) -> CarState {
         let end_dist = if self.router.last_step() {
             self.router.get_end_dist()
         } else {
@@ -47,13 +69,14 @@ impl Car {
         }
 
         let dist_int = DistanceInterval::new_driving(start_dist, end_dist);
-        self.crossing_state_with_end_dist(dist_int, start_time, map)
+        self.crossing_state_with_end_dist(dist_int, start_time, stop_at_end, map)
     }
 
     pub fn crossing_state_with_end_dist(
         &self,
         dist_int: DistanceInterval,
         start_time: Time,
+        stop_at_end: bool,
         map: &Map,
     ) -> CarState {
         let (speed, percent_incline) = self
@@ -65,11 +88,80 @@ impl Car {
                 self.vehicle.vehicle_type.to_constraints(),
                 map,
             );
+        let speed = self.desired_speed(speed);
+        let steep_uphill = percent_incline >= 0.08;
+
+        if let Some(accel) = self.acceleration {
+            // Stop at the end of the path or when asked, and otherwise slow down enough for the
+            // next step
+            let end_speed = match self.router.get_path().maybe_next_step() {
+                Some(step) if !stop_at_end && !self.router.last_step() => {
+                    self.desired_speed(step.max_speed_along(
+                        self.vehicle.max_speed,
+                        self.vehicle.vehicle_type.to_constraints(),
+                        map,
+                    ))
+                }
+                _ => Speed::ZERO,
+            };
+            let profile = SpeedProfile::new(
+                dist_int.end - dist_int.start,
+                self.current_speed(start_time),
+                speed,
+                end_speed,
+                accel,
+            );
+            return CarState::Crossing {
+                time_int: TimeInterval::new(start_time, start_time + profile.duration()),
+                dist_int,
+                steep_uphill,
+                profile: Some(profile),
+            };
+        }
+
         let dt = (dist_int.end - dist_int.start) / speed;
         CarState::Crossing {
             time_int: TimeInterval::new(start_time, start_time + dt),
             dist_int,
-            steep_uphill: percent_incline >= 0.08,
+            steep_uphill,
+            profile: None,
+        }
+    }
+
+    /// How fast the vehicle is going at some time, used to start the next Crossing state. Without
+    /// a speed profile, this is only an average.
+    pub fn current_speed(&self, now: Time) -> Speed {
+        match self.state {
+            CarState::Crossing {
+                ref time_int,
+                profile: Some(ref profile),
+                ..
+            } => profile.speed_after(now - time_int.start),
+            CarState::Crossing {
+                ref time_int,
+                ref dist_int,
+                profile: None,
+                ..
+            }
+            | CarState::ChangingLanes {
+                new_time: ref time_int,
+                new_dist: ref dist_int,
+                ..
+            } => {
+                if time_int.start == time_int.end {
+                    Speed::ZERO
+                } else {
+                    Speed::from_dist_time(dist_int.length(), time_int.end - time_int.start)
+                }
+            }
+            // Still rolling if they only just reached the end of the previous step
+            CarState::Queued { blocked_since, .. }
+            | CarState::WaitingToAdvance { blocked_since }
+                if blocked_since == now =>
+            {
+                self.last_speed
+            }
+            _ => Speed::ZERO,
         }
     }
 
@@ -296,11 +388,9 @@ impl Car {
             label: if self.vehicle.vehicle_type == VehicleType::Bus
                 || self.vehicle.vehicle_type == VehicleType::Train
             {
-                Some(
-                    map.get_tr(transit.bus_route(self.vehicle.id))
-                        .short_name
-                        .clone(),
-                )
+                // The route might've been deleted by live map edits
+                map.maybe_get_tr(transit.bus_route(self.vehicle.id))
+                    .map(|tr| tr.short_name.clone())
             } else {
                 None
             },
@@ -325,6 +415,9 @@ pub(crate) enum CarState {
         time_int: TimeInterval,
         dist_int: DistanceInterval,
         steep_uphill: bool,
+        /// Only when acceleration is modelled. Then the vehicle's position follows this, instead
+        /// of moving at a constant speed over the intervals.
+        profile: Option<SpeedProfile>,
     },
     ChangingLanes {
         from: LaneID,
@@ -338,6 +431,9 @@ pub(crate) enum CarState {
     Queued {
         blocked_since: Time,
         want_to_change_lanes: Option<LaneID>,
+        /// Waiting for a chance to pass this slow vehicle directly in front, without changing
+        /// lanes
+        want_to_pass: Option<CarID>,
     },
     WaitingToAdvance {
         blocked_since: Time,
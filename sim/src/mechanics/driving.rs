@@ -3,8 +3,11 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
-use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
+use map_model::{
+    Direction, DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable,
+};
+use synthpop::{Conditions, TripMode};
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::queue::{Queue, QueueEntry, Queued};
@@ -18,6 +21,8 @@ use crate::{
 
 const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
 const TIME_TO_CHANGE_LANES: Duration = Duration::const_seconds(1.0);
+/// How often a vehicle stuck behind a slow leader checks for a chance to pass
+const BLIND_RETRY_TO_OVERTAKE: Duration = Duration::const_seconds(2.0);
 
 // TODO Do something else.
 pub const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
@@ -45,6 +50,11 @@ pub(crate) struct DrivingSimState {
 
     recalc_lanechanging: bool,
     handle_uber_turns: bool,
+    overtake_into_oncoming_traffic: bool,
+    min_passing_distance: Distance,
+    impatient_driver_share: f64,
+    enable_acceleration: bool,
+    conditions: Conditions,
 
     time_to_unpark_onstreet: Duration,
     time_to_park_onstreet: Duration,
@@ -61,6 +71,11 @@ impl DrivingSimState {
             events: Vec::new(),
             recalc_lanechanging: !opts.dont_recalc_lanechanging,
             handle_uber_turns: !opts.dont_handle_uber_turns,
+            overtake_into_oncoming_traffic: opts.overtake_into_oncoming_traffic,
+            min_passing_distance: Distance::meters(opts.min_passing_distance_meters),
+            impatient_driver_share: opts.impatient_driver_share,
+            enable_acceleration: opts.enable_acceleration,
+            conditions: Conditions::DEFAULT,
             waiting_to_spawn: BTreeMap::new(),
 
             time_to_unpark_onstreet: Duration::seconds(10.0),
@@ -89,6 +104,16 @@ impl DrivingSimState {
         sim
     }
 
+    /// Every vehicle changes speed, starting from the next thing they cross.
+    pub fn set_conditions(&mut self, conditions: Conditions) {
+        self.conditions = conditions;
+        for car in self.cars.values_mut() {
+            car.conditions_speed_factor = conditions.speed_factor(TripMode::from_constraints(
+                car.vehicle.vehicle_type.to_constraints(),
+            ));
+        }
+    }
+
     /// None if it worked, otherwise returns the CreateCar unmodified for possible retry.
     pub fn start_car_on_lane(
         &mut self,
@@ -185,12 +210,22 @@ impl DrivingSimState {
                 state: CarState::Queued {
                     blocked_since: now,
                     want_to_change_lanes: None,
+                    want_to_pass: None,
                 },
                 last_steps: VecDeque::new(),
                 started_at: now,
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
                 wants_to_overtake: BTreeSet::new(),
+                acceleration: if self.enable_acceleration {
+                    Some(params.vehicle.vehicle_type.acceleration_profile())
+                } else {
+                    None
+                },
+                last_speed: Speed::ZERO,
+                conditions_speed_factor: self.conditions.speed_factor(TripMode::from_constraints(
+                    params.vehicle.vehicle_type.to_constraints(),
+                )),
             };
             let mut start_crossing = false;
             if let Some(p) = params.maybe_parked_car {
@@ -266,7 +301,8 @@ impl DrivingSimState {
                     }
                 }
 
-                car.state = car.crossing_state(start_dist, now, ctx.map);
+                car.state =
+                    car.crossing_state(start_dist, now, self.plan_to_stop(&car, ctx), ctx.map);
                 start_crossing = true;
             }
             ctx.scheduler
@@ -328,7 +364,9 @@ impl DrivingSimState {
         let mut need_distances = {
             let car = &self.cars[&id];
             match car.state {
-                CarState::Queued { .. } => car.router.last_step(),
+                CarState::Queued { want_to_pass, .. } => {
+                    car.router.last_step() || want_to_pass.is_some()
+                }
                 CarState::Parking(_, _, _) => true,
                 CarState::IdlingAtStop(_, _) => true,
                 _ => false,
@@ -382,9 +420,11 @@ impl DrivingSimState {
     ) -> bool {
         match car.state {
             CarState::Crossing { .. } => {
+                car.last_speed = car.current_speed(now);
                 car.state = CarState::Queued {
                     blocked_since: now,
                     want_to_change_lanes: None,
+                    want_to_pass: None,
                 };
                 if car.router.last_step() {
                     // Immediately run update_car_with_distances.
@@ -425,6 +465,17 @@ impl DrivingSimState {
                         car.state = CarState::Queued {
                             blocked_since: now,
                             want_to_change_lanes: Some(target_lane),
+                            want_to_pass: None,
+                        };
+                        return true;
+                    }
+                    if car.router.head().maybe_lane().is_some() {
+                        // Try to pass them without changing lanes. This needs the current
+                        // position of everybody nearby, so trigger update_car_with_distances.
+                        car.state = CarState::Queued {
+                            blocked_since: now,
+                            want_to_change_lanes: None,
+                            want_to_pass: Some(slow_leader),
                         };
                         return true;
                     }
@@ -478,7 +529,7 @@ impl DrivingSimState {
                         &mut self.events,
                     );
                 }
-                car.state = car.crossing_state(front, now, ctx.map);
+                car.state = car.crossing_state(front, now, self.plan_to_stop(car, ctx), ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.new_crossing_state(ctx, car);
@@ -493,11 +544,11 @@ impl DrivingSimState {
                     if !ctx.intersections.maybe_start_turn(
                         AgentID::Car(car.vehicle.id),
                         t,
-                        PathStep::Turn(t).max_speed_along(
+                        car.desired_speed(PathStep::Turn(t).max_speed_along(
                             car.vehicle.max_speed,
                             car.vehicle.vehicle_type.to_constraints(),
                             ctx.map,
-                        ),
+                        )),
                         now,
                         ctx.map,
                         ctx.scheduler,
@@ -532,7 +583,8 @@ impl DrivingSimState {
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
-                car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
+                car.state =
+                    car.crossing_state(Distance::ZERO, now, self.plan_to_stop(car, ctx), ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.events.push(Event::AgentEntersTraversable(
@@ -559,6 +611,7 @@ impl DrivingSimState {
                             car.vehicle.length + FOLLOWING_DISTANCE,
                         ),
                         now,
+                        false,
                         ctx.map,
                     )
                     .get_end_time(),
@@ -577,11 +630,13 @@ impl DrivingSimState {
                 ..
             } => {
                 // The car is already in the target queue. Just set them in the crossing state; we
-                // already calculated the intervals for it.
+                // already calculated the intervals for it. Even with acceleration, just move at a
+                // constant speed for the rest of the lane.
                 car.state = CarState::Crossing {
                     time_int: new_time,
                     dist_int: new_dist,
                     steep_uphill: false,
+                    profile: None,
                 };
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -630,13 +685,18 @@ impl DrivingSimState {
             CarState::Queued {
                 blocked_since,
                 want_to_change_lanes,
+                want_to_pass,
             } => {
-                // Two totally different reasons we'll wind up here: we want to lane-change, and
-                // we're on our last step.
+                // A few totally different reasons we'll wind up here: we want to lane-change, we
+                // want to pass somebody slow, and we're on our last step.
                 if let Some(target_lane) = want_to_change_lanes {
                     self.try_start_lc(car, our_dist, idx, target_lane, now, ctx);
                     return true;
                 }
+                if let Some(leader) = want_to_pass {
+                    self.try_to_pass(car, dists, idx, leader, now, ctx);
+                    return true;
+                }
 
                 match car.router.maybe_handle_end(
                     our_dist,
@@ -694,7 +754,8 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::GotoLaneEnd) => {
                         car.total_blocked_time += now - blocked_since;
-                        car.state = car.crossing_state(our_dist, now, ctx.map);
+                        car.state =
+                            car.crossing_state(our_dist, now, self.plan_to_stop(car, ctx), ctx.map);
                         ctx.scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         self.new_crossing_state(ctx, car);
@@ -776,7 +837,7 @@ impl DrivingSimState {
                 car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
                 self.events
                     .push(Event::PathAmended(car.router.get_path().clone()));
-                car.state = car.crossing_state(dist, now, ctx.map);
+                car.state = car.crossing_state(dist, now, self.plan_to_stop(car, ctx), ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.new_crossing_state(ctx, car);
@@ -889,6 +950,7 @@ impl DrivingSimState {
                 }
             }
 
+            let stop_at_end = self.plan_to_stop(&self.cars[&follower_id], ctx);
             let mut follower = self.cars.get_mut(&follower_id).unwrap();
             // TODO If the leader vanished at a border node, this still jumps a bit -- the lead
             // car's back is still sticking out. Need to still be bound by them, even though they
@@ -900,7 +962,8 @@ impl DrivingSimState {
 
                     // Prevent them from jumping forwards.
                     follower.total_blocked_time += now - blocked_since;
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state =
+                        follower.crossing_state(follower_dist, now, stop_at_end, ctx.map);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -912,7 +975,8 @@ impl DrivingSimState {
                     // If the follower was still Crossing, they might not've been blocked by the
                     // leader yet. But recalculating their Crossing state isn't necessarily a no-op
                     // -- this could prevent them from suddenly warping past a blockage.
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state =
+                        follower.crossing_state(follower_dist, now, stop_at_end, ctx.map);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -927,13 +991,15 @@ impl DrivingSimState {
                     // This is a fun case -- something stopped blocking somebody that was in the
                     // process of lane-changing! Similar to the Crossing case above, we just have
                     // to update the distance/time intervals, but otherwise leave them in the
-                    // middle of their lane-changing. It's guaranteed that lc_time will continue to
-                    // finish before the new time interval, because there's no possible way
-                    // recalculating this crossing state here will speed things up from the
-                    // original estimate.
-                    let (new_time, new_dist) = match follower.crossing_state_with_end_dist(
+                    // middle of their lane-changing. Without acceleration, it's guaranteed that
+                    // lc_time will continue to finish before the new time interval, because
+                    // there's no possible way recalculating this crossing state here will speed
+                    // things up from the original estimate. With acceleration, it might, so
+                    // stretch the new interval to finish with the lane-change.
+                    let (mut new_time, new_dist) = match follower.crossing_state_with_end_dist(
                         DistanceInterval::new_driving(follower_dist, ctx.map.get_l(to).length()),
                         now,
+                        stop_at_end,
                         ctx.map,
                     ) {
                         CarState::Crossing {
@@ -941,7 +1007,10 @@ impl DrivingSimState {
                         } => (time_int, dist_int),
                         _ => unreachable!(),
                     };
-                    assert!(new_time.end >= lc_time.end);
+                    if new_time.end < lc_time.end {
+                        assert!(follower.acceleration.is_some());
+                        new_time = TimeInterval::new(new_time.start, lc_time.end);
+                    }
                     follower.state = CarState::ChangingLanes {
                         from,
                         to,
@@ -1005,6 +1074,7 @@ impl DrivingSimState {
                         self.cars[&id].vehicle.length + FOLLOWING_DISTANCE,
                     ),
                     now,
+                    false,
                     ctx.map,
                 )
                 .get_end_time();
@@ -1081,8 +1151,10 @@ impl DrivingSimState {
                                             self.handle_uber_turns,
                                         );
                                     }
+                                    // They might've been waiting to pass us, with a
+                                    // retry already scheduled.
                                     ctx.scheduler
-                                        .push(now, Command::UpdateCar(follower.vehicle.id));
+                                        .update(now, Command::UpdateCar(follower.vehicle.id));
                                 }
                             }
                             CarState::WaitingToAdvance { .. } => unreachable!(),
@@ -1105,8 +1177,8 @@ impl DrivingSimState {
     }
 
     /// If the car wants to over-take somebody, what adjacent lane should they use?
-    /// - The lane must be in the same direction as the current; no support for crossing the road's
-    ///   yellow line yet.
+    /// - The lane must be in the same direction as the current. Crossing the road's yellow line is
+    ///   handled separately by `try_to_pass`, without changing lanes.
     /// - Prefer passing on the left (for DrivingSide::Right)
     /// For now, just pick one candidate lane, even if both might be usable.
     fn pick_overtaking_lane(&self, car: &Car, map: &Map) -> Option<LaneID> {
@@ -1156,6 +1228,233 @@ impl DrivingSimState {
         None
     }
 
+    /// Try to pass the slow vehicle directly in front without changing lanes. The maneuver itself
+    /// isn't simulated in detail; the two vehicles just swap places in the queue. But this only
+    /// happens when there's time to finish the pass before the end of the lane and, if the
+    /// vehicle has to pull into the oncoming lane, without any oncoming traffic in the way.
+    fn try_to_pass(
+        &mut self,
+        car: &mut Car,
+        dists: &[QueueEntry],
+        idx: usize,
+        leader: CarID,
+        now: Time,
+        ctx: &mut Ctx,
+    ) {
+        let blocked_since = match car.state {
+            CarState::Queued { blocked_since, .. } => blocked_since,
+            _ => unreachable!(),
+        };
+
+        // If the leader isn't directly in front anymore or is stuck themselves, give up. We'll be
+        // woken up as usual when whatever's in front of us moves.
+        if idx == 0
+            || dists[idx - 1].member != Queued::Vehicle(leader)
+            || matches!(
+                self.cars[&leader].state,
+                CarState::Queued { .. } | CarState::WaitingToAdvance { .. }
+            )
+        {
+            car.state = CarState::Queued {
+                blocked_since,
+                want_to_change_lanes: None,
+                want_to_pass: None,
+            };
+            return;
+        }
+
+        // If we are a laggy head somewhere else, wait to clear that first.
+        let maybe_pass = if car.last_steps.is_empty() {
+            self.plan_pass(car, dists, idx, now, ctx.map)
+        } else {
+            None
+        };
+        let (new_front, clearance, into_oncoming_traffic) = match maybe_pass {
+            Some(pass) => pass,
+            None => {
+                ctx.scheduler.update(
+                    now + BLIND_RETRY_TO_OVERTAKE,
+                    Command::UpdateCar(car.vehicle.id),
+                );
+                return;
+            }
+        };
+
+        let lane = car.router.head().as_lane();
+        self.queues
+            .get_mut(&car.router.head())
+            .unwrap()
+            .swap_with_leader(car.vehicle.id, idx);
+        car.total_blocked_time += now - blocked_since;
+        car.state = car.crossing_state(new_front, now, self.plan_to_stop(car, ctx), ctx.map);
+        ctx.scheduler
+            .update(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+        self.new_crossing_state(ctx, car);
+        // Whoever was behind us is now following the slow vehicle instead
+        self.update_follower(idx, dists, now, ctx);
+
+        self.events.push(Event::VehicleOvertook {
+            overtaker: car.vehicle.id,
+            overtaken: leader,
+            lane,
+            clearance,
+            into_oncoming_traffic,
+        });
+        if leader.vehicle_type == VehicleType::Bike && clearance < self.min_passing_distance {
+            if let Some((trip, _)) = self.cars[&leader].trip_and_person {
+                self.events.push(Event::ProblemEncountered(
+                    trip,
+                    Problem::ClosePass(Traversable::Lane(lane), clearance),
+                ));
+            }
+        }
+    }
+
+    /// Can the car pass the leader directly in front right now? If so, returns the car's new
+    /// front position, how much room it leaves sideways, and if it has to use the oncoming lane.
+    fn plan_pass(
+        &self,
+        car: &Car,
+        dists: &[QueueEntry],
+        idx: usize,
+        now: Time,
+        map: &Map,
+    ) -> Option<(Distance, Distance, bool)> {
+        let lane = map.get_l(car.router.head().maybe_lane()?);
+        let leader = match dists[idx - 1].member {
+            Queued::Vehicle(id) => &self.cars[&id],
+            _ => unreachable!(),
+        };
+
+        // Is there room in front of them to pull back in?
+        let new_front = dists[idx - 1].front + FOLLOWING_DISTANCE + car.vehicle.length;
+        if idx >= 2 {
+            if dists[idx - 2].back - FOLLOWING_DISTANCE < new_front {
+                return None;
+            }
+        } else if self.queues[&car.router.head()].laggy_head.is_some() {
+            return None;
+        }
+
+        // Is there time to finish before the next intersection?
+        let our_step = car.router.get_path().current_step();
+        let our_speed = car.desired_speed(our_step.max_speed_along(
+            car.vehicle.max_speed,
+            car.vehicle.vehicle_type.to_constraints(),
+            map,
+        ));
+        let their_step = leader.router.get_path().current_step();
+        let their_speed = leader.desired_speed(their_step.max_speed_along(
+            leader.vehicle.max_speed,
+            leader.vehicle.vehicle_type.to_constraints(),
+            map,
+        ));
+        if our_speed <= their_speed {
+            return None;
+        }
+        let our_front = dists[idx].front;
+        let pass_time = (new_front - our_front) / (our_speed - their_speed);
+        let pass_end = our_front + our_speed * pass_time;
+        if pass_end >= lane.length() {
+            return None;
+        }
+
+        // Cyclists ride near the edge of the lane, so a wide lane might leave enough room
+        let passing_bike = leader.vehicle.vehicle_type == VehicleType::Bike;
+        let room_in_lane = lane.width
+            - vehicle_width(car.vehicle.vehicle_type)
+            - vehicle_width(leader.vehicle.vehicle_type);
+        if passing_bike && room_in_lane >= self.min_passing_distance {
+            return Some((new_front, room_in_lane, false));
+        }
+
+        if self.overtake_into_oncoming_traffic {
+            if let Some(oncoming) = self.pick_oncoming_lane(car, map) {
+                if self.oncoming_lane_clear(
+                    oncoming,
+                    lane.id,
+                    our_front - car.vehicle.length,
+                    pass_end + map.get_r(lane.id.road).speed_limit * pass_time,
+                    now,
+                    map,
+                ) {
+                    // Measure between the middle of both lanes
+                    let clearance = (lane.width + map.get_l(oncoming).width) / 2.0
+                        - (vehicle_width(car.vehicle.vehicle_type)
+                            + vehicle_width(leader.vehicle.vehicle_type))
+                            / 2.0;
+                    return Some((new_front, clearance, true));
+                }
+            }
+        }
+
+        // Some drivers won't wait
+        if passing_bike
+            && room_in_lane > Distance::ZERO
+            && car.vehicle.id.pseudorandom_fraction() < self.impatient_driver_share
+        {
+            return Some((new_front, room_in_lane, false));
+        }
+        None
+    }
+
+    /// If the car wants to over-take somebody by pulling into oncoming traffic, what lane should
+    /// they use? Only the adjacent lane on the side closer to the center of the road is
+    /// considered.
+    fn pick_oncoming_lane(&self, car: &Car, map: &Map) -> Option<LaneID> {
+        let current_lane = map.get_l(car.router.head().maybe_lane()?);
+        let road = map.get_parent(current_lane.id);
+        let target_idx = lane_toward_center(
+            current_lane.id.offset,
+            current_lane.dir,
+            map.get_config().driving_side,
+        )?;
+        let target_lane = road.lanes.get(target_idx)?;
+        if target_lane.dir == current_lane.dir
+            || !car
+                .vehicle
+                .vehicle_type
+                .to_constraints()
+                .can_use(target_lane, map)
+        {
+            return None;
+        }
+        Some(target_lane.id)
+    }
+
+    /// Is anything in the oncoming lane between `start` and `end`, measured along our lane? Traffic
+    /// beyond the end of the road can't be seen, so that has to be part of the lane.
+    fn oncoming_lane_clear(
+        &self,
+        oncoming: LaneID,
+        our_lane: LaneID,
+        start: Distance,
+        end: Distance,
+        now: Time,
+        map: &Map,
+    ) -> bool {
+        if end >= map.get_l(our_lane).length() {
+            return false;
+        }
+        let queue = &self.queues[&Traversable::Lane(oncoming)];
+        if queue.laggy_head.is_some() {
+            return false;
+        }
+        for entry in queue.get_car_positions(now, &self.cars, &self.queues) {
+            // The lanes point in opposite directions, so the front is closer to our start
+            let front = Position::new(oncoming, entry.front)
+                .equiv_pos(our_lane, map)
+                .dist_along();
+            let back = Position::new(oncoming, entry.back)
+                .equiv_pos(our_lane, map)
+                .dist_along();
+            if front < end && back > start {
+                return false;
+            }
+        }
+        true
+    }
+
     fn try_start_lc(
         &mut self,
         car: &mut Car,
@@ -1185,6 +1484,7 @@ impl DrivingSimState {
         let (new_time, new_dist) = match car.crossing_state_with_end_dist(
             DistanceInterval::new_driving(front_target_queue, ctx.map.get_l(target_lane).length()),
             now,
+            self.plan_to_stop(car, ctx),
             ctx.map,
         ) {
             CarState::Crossing {
@@ -1291,6 +1591,29 @@ impl DrivingSimState {
         }
     }
 
+    /// With acceleration, a vehicle about to cross its current lane or turn plans to brake to a
+    /// stop at the end if it'll have to wait there: at a red light or stop sign, or behind a
+    /// vehicle that's already stopped.
+    fn plan_to_stop(&self, car: &Car, ctx: &Ctx) -> bool {
+        if car.acceleration.is_none() {
+            return false;
+        }
+        if let Some(Traversable::Turn(turn)) = car.router.maybe_next() {
+            if ctx.intersections.must_stop_at(turn, ctx.map) {
+                return true;
+            }
+        }
+        let leader = self
+            .queues
+            .get(&car.router.head())
+            .and_then(|queue| queue.get_leader(car.vehicle.id))
+            .and_then(|leader| self.cars.get(&leader));
+        match leader.map(|leader| &leader.state) {
+            Some(CarState::Crossing { .. }) | Some(CarState::ChangingLanes { .. }) | None => false,
+            Some(_) => true,
+        }
+    }
+
     fn new_crossing_state(&self, ctx: &mut Ctx, car: &Car) {
         if self.queues[&car.router.head()].is_car_at_front(car.vehicle.id) {
             if let Some(Traversable::Turn(turn)) = car.router.maybe_next() {
@@ -1380,6 +1703,12 @@ impl DrivingSimState {
         result
     }
 
+    /// How fast the car is going right now. Without acceleration enabled, this is the average
+    /// speed along its current step.
+    pub fn current_speed(&self, id: CarID, now: Time) -> Option<Speed> {
+        Some(self.cars.get(&id)?.current_speed(now))
+    }
+
     /// This is about as expensive as get_draw_cars_on.
     pub fn get_single_draw_car(
         &self,
@@ -1684,16 +2013,23 @@ impl DrivingSimState {
             _ => {}
         }
 
-        // TODO Check if there's room in front of them to pass. Handle passing two bikes?
-        // TODO Check remaining distance before next intersection
-        // TODO Check relative speed difference, and time to pass them
-        // TODO Eventually, check if there's room to do the maneuever (lane-changing to
-        // the left only, or popping into an oncoming lane!
+        // Room in front of them, the distance left before the next intersection, and oncoming
+        // traffic are checked when actually trying to pass.
+        // TODO Handle passing two bikes?
 
         Some(leader.vehicle.id)
     }
 }
 
+/// How much of a lane a vehicle takes up sideways, for deciding if there's room to pass
+fn vehicle_width(vehicle_type: VehicleType) -> Distance {
+    match vehicle_type {
+        VehicleType::Car => Distance::meters(1.8),
+        VehicleType::Bus | VehicleType::Train => Distance::meters(2.55),
+        VehicleType::Bike => Distance::meters(0.75),
+    }
+}
+
 // This implementation relies on the fact that car IDs are unique just by their number. Vehicle
 // type is also in there, but during lookup, it'll be ignored!
 impl IndexableKey for CarID {
@@ -1701,3 +2037,52 @@ impl IndexableKey for CarID {
         self.id
     }
 }
+
+/// Lanes are ordered left-to-right, looking along the road's forward direction. Which lane is next
+/// to this one, on the side closer to the center of the road?
+fn lane_toward_center(idx: usize, dir: Direction, driving_side: DrivingSide) -> Option<usize> {
+    // When driving on the right, forward lanes are on the right half of the road, so the center
+    // is to their left. Everything is mirrored for backward lanes and driving on the left.
+    if (driving_side == DrivingSide::Right) == (dir == Direction::Fwd) {
+        idx.checked_sub(1)
+    } else {
+        Some(idx + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lane_toward_center() {
+        // A sidewalk, two backward lanes, two forward lanes, and a sidewalk
+        for (driving_side, fwd_lanes, back_lanes) in [
+            (DrivingSide::Right, [3, 4], [1, 2]),
+            (DrivingSide::Left, [1, 2], [3, 4]),
+        ] {
+            let (inner_fwd, inner_back) = if driving_side == DrivingSide::Right {
+                (fwd_lanes[0], back_lanes[1])
+            } else {
+                (fwd_lanes[1], back_lanes[0])
+            };
+            assert_eq!(
+                lane_toward_center(inner_fwd, Direction::Fwd, driving_side),
+                Some(inner_back)
+            );
+            assert_eq!(
+                lane_toward_center(inner_back, Direction::Back, driving_side),
+                Some(inner_fwd)
+            );
+        }
+        // There's nothing further left
+        assert_eq!(
+            lane_toward_center(0, Direction::Fwd, DrivingSide::Right),
+            None
+        );
+        assert_eq!(
+            lane_toward_center(0, Direction::Back, DrivingSide::Left),
+            None
+        );
+    }
+}
@@ -8,6 +8,7 @@ use map_model::{
     ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, StageType,
     Traversable, TurnID, TurnPriority, TurnType, UberTurn,
 };
+use synthpop::Conditions;
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
@@ -17,6 +18,12 @@ use crate::{
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+/// At roundabouts and all-way yields, don't enter if a vehicle with priority will reach a
+/// conflicting turn within this long. Drivers may have their own gap instead.
+const CRITICAL_GAP: Duration = Duration::const_seconds(4.0);
+/// Pedestrians jaywalking against a red light want this much spare time before the next vehicle
+/// with the green arrives.
+const JAYWALKING_GAP_BUFFER: Duration = Duration::const_seconds(3.0);
 
 /// Manages conflicts at intersections. When an agent has reached the end of a lane, they call
 /// maybe_start_turn to make a Request. Based on the intersection type (stop sign, traffic signal,
@@ -34,6 +41,9 @@ pub(crate) struct IntersectionSimState {
     break_turn_conflict_cycles: bool,
     handle_uber_turns: bool,
     disable_turn_conflicts: bool,
+    jaywalking: bool,
+    /// How well signals notice waiting vehicles, from 0 to 1. Bad weather and darkness lower this.
+    detection_sensitivity: f64,
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
@@ -107,6 +117,8 @@ impl IntersectionSimState {
             break_turn_conflict_cycles: !opts.dont_break_turn_conflict_cycles,
             handle_uber_turns: !opts.dont_handle_uber_turns,
             disable_turn_conflicts: opts.disable_turn_conflicts,
+            jaywalking: opts.jaywalking,
+            detection_sensitivity: 1.0,
             blocked_by: BTreeSet::new(),
             events: Vec::new(),
 
@@ -140,6 +152,10 @@ impl IntersectionSimState {
         sim
     }
 
+    pub fn set_conditions(&mut self, conditions: Conditions) {
+        self.detection_sensitivity = conditions.detection_sensitivity();
+    }
+
     pub fn turn_finished(
         &mut self,
         now: Time,
@@ -243,10 +259,16 @@ impl IntersectionSimState {
                     TurnPriority::Yield => {
                         yielding.push(req);
                     }
-                    // No need to wake up unless it has reserved
+                    // No need to wake up unless it has reserved, or it's a pedestrian looking
+                    // for a gap to jaywalk
                     TurnPriority::Banned => {
                         if reserved.contains(&req) {
                             protected.push(req);
+                        } else if self.jaywalking
+                            && req.agent.is_pedestrian()
+                            && map.get_t(req.turn).allows_jaywalking(map)
+                        {
+                            yielding.push(req);
                         }
                     }
                 }
@@ -338,9 +360,10 @@ impl IntersectionSimState {
                 // Filter out pedestrians, as they've had their chance and the delay
                 // could be short enough to keep them on the curb.
                 let delay = std::cmp::max(Duration::const_seconds(1.0), delay);
-                // Only extend for the fixed additional time
+                // Only extend for the fixed additional time. When detectors miss some vehicles,
+                // the stage runs out of demand sooner.
                 if signal_state.extensions_count as f64 * delay.inner_seconds()
-                    >= additional.inner_seconds()
+                    >= additional.inner_seconds() * self.detection_sensitivity
                 {
                     self.events.push(Event::Alert(
                         AlertLocation::Intersection(id),
@@ -473,10 +496,17 @@ impl IntersectionSimState {
         } else if self.use_freeform_policy_everywhere {
             // If we made it this far, we don't conflict with an accepted turn
             true
+        } else if self.jaywalking && self.gap_to_jaywalk(&req, map, speed, now) {
+            // Cross against the light, since nobody's coming
+            true
         } else if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
             self.traffic_signal_policy(&req, map, signal, speed, now, Some(scheduler))
         } else if let Some(sign) = map.maybe_get_stop_sign(turn.parent) {
-            self.stop_sign_policy(&req, map, sign, speed, now, scheduler)
+            let critical_gap = maybe_cars_and_queues
+                .as_ref()
+                .and_then(|(car, _, _)| car.vehicle.critical_gap)
+                .unwrap_or(CRITICAL_GAP);
+            self.stop_sign_policy(&req, map, sign, speed, critical_gap, now, scheduler)
         } else {
             unreachable!()
         };
@@ -604,6 +634,20 @@ impl IntersectionSimState {
         map: &Map,
         scheduler: &mut Scheduler,
     ) {
+        // Edits might split roads, creating new intersections, or undo that
+        self.state.retain(|i, _| map.maybe_get_i(*i).is_some());
+        for i in map.all_intersections() {
+            self.state.entry(i.id).or_insert_with(|| State {
+                id: i.id,
+                accepted: BTreeSet::new(),
+                waiting: BTreeMap::new(),
+                reserved: BTreeSet::new(),
+                uber_turn_neighbors: Vec::new(),
+                signal: None,
+                leader_eta: BTreeMap::new(),
+            });
+        }
+
         for state in self.state.values_mut() {
             match (
                 map.maybe_get_traffic_signal(state.id),
@@ -684,6 +728,23 @@ impl IntersectionSimState {
 
 // Queries
 impl IntersectionSimState {
+    /// Is a pedestrian starting this crossing right now doing so against a red light?
+    pub fn is_jaywalking(&self, turn: TurnID, map: &Map) -> bool {
+        if !self.jaywalking || self.use_freeform_policy_everywhere {
+            return false;
+        }
+        if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
+            let stage = &signal.stages[self.state[&turn.parent]
+                .signal
+                .as_ref()
+                .unwrap()
+                .current_stage];
+            stage.get_priority_of_turn(turn, map.get_i(turn.parent)) == TurnPriority::Banned
+        } else {
+            false
+        }
+    }
+
     pub fn nobody_headed_towards(&self, lane: LaneID, i: IntersectionID) -> bool {
         let state = &self.state[&i];
         !state
@@ -755,6 +816,26 @@ impl IntersectionSimState {
         (state.current_stage, state.stage_ends_at - now)
     }
 
+    /// Would a vehicle arriving at this turn right now have to stop first? True at stop signs and
+    /// at signals not currently allowing the movement.
+    pub fn must_stop_at(&self, turn: TurnID, map: &Map) -> bool {
+        if let Some(sign) = map.maybe_get_stop_sign(turn.parent) {
+            return sign.get_priority(turn, map) == TurnPriority::Yield && !sign.yield_on_entry;
+        }
+        if let (Some(signal), Some(state)) = (
+            map.maybe_get_traffic_signal(turn.parent),
+            self.state
+                .get(&turn.parent)
+                .and_then(|state| state.signal.as_ref()),
+        ) {
+            if let Some(stage) = signal.stages.get(state.current_stage) {
+                return stage.get_priority_of_turn(turn, map.get_i(turn.parent))
+                    == TurnPriority::Banned;
+            }
+        }
+        false
+    }
+
     pub fn describe_stats(&self) -> Vec<String> {
         vec![
             "intersection stats".to_string(),
@@ -848,6 +929,7 @@ impl IntersectionSimState {
         map: &Map,
         sign: &ControlStopSign,
         speed: Speed,
+        critical_gap: Duration,
         now: Time,
         scheduler: &mut Scheduler,
     ) -> bool {
@@ -855,7 +937,20 @@ impl IntersectionSimState {
         assert!(our_priority != TurnPriority::Banned);
         let (our_time, _) = self.state[&req.turn.parent].waiting[req];
 
-        if our_priority == TurnPriority::Yield && now < our_time + WAIT_AT_STOP_SIGN {
+        if our_priority == TurnPriority::Yield && sign.yield_on_entry {
+            // When nobody has priority, whoever arrived first goes first. We'll get woken up when
+            // they finish their turn.
+            if sign.is_all_way_yield() && self.earlier_conflicting_arrival(req, map, our_time) {
+                return false;
+            }
+            // Don't stop, but wait for a big enough gap in the traffic with priority. If the
+            // vehicle we're yielding to winds up starting its turn, we'll get woken up when it
+            // finishes; otherwise, try again once it should've arrived.
+            if let Some(eta) = self.next_priority_arrival(req, map, sign, critical_gap, now) {
+                scheduler.update(eta + Duration::EPSILON, Command::update_agent(req.agent));
+                return false;
+            }
+        } else if our_priority == TurnPriority::Yield && now < our_time + WAIT_AT_STOP_SIGN {
             // Since we have "ownership" of scheduling for req.agent, don't need to use
             // scheduler.update.
             scheduler.push(
@@ -915,6 +1010,56 @@ impl IntersectionSimState {
         true
     }
 
+    /// Is a vehicle that arrived before this request waiting to do a conflicting turn? Ties go by
+    /// the order of the requests, so exactly one of two simultaneous arrivals goes first.
+    fn earlier_conflicting_arrival(&self, req: &Request, map: &Map, our_time: Time) -> bool {
+        let turn = map.get_t(req.turn);
+        self.state[&req.turn.parent]
+            .waiting
+            .iter()
+            .any(|(other, (other_time, _))| {
+                matches!(other.agent, AgentID::Car(_))
+                    && (*other_time, other) < (our_time, req)
+                    && map
+                        .maybe_get_t(other.turn)
+                        .map(|t| t.conflicts_with(turn))
+                        .unwrap_or(false)
+            })
+    }
+
+    /// Will a vehicle with priority reach a turn conflicting with this request within the critical
+    /// gap? At an all-way yield, every vehicle counts. If so, returns the earliest arrival.
+    fn next_priority_arrival(
+        &self,
+        req: &Request,
+        map: &Map,
+        sign: &ControlStopSign,
+        critical_gap: Duration,
+        now: Time,
+    ) -> Option<Time> {
+        let turn = map.get_t(req.turn);
+        self.state[&req.turn.parent]
+            .leader_eta
+            .values()
+            .filter(|(other, eta)| {
+                // If the leader is late, it's stuck somewhere upstream; don't wait for it
+                other.agent != req.agent
+                    && *eta >= now
+                    && *eta < now + critical_gap
+                    && map
+                        .maybe_get_t(other.turn)
+                        .map(|t| {
+                            t.conflicts_with(turn)
+                                && (sign.is_all_way_yield()
+                                    || sign.get_priority(other.turn, map)
+                                        == TurnPriority::Protected)
+                        })
+                        .unwrap_or(false)
+            })
+            .map(|(_, eta)| *eta)
+            .min()
+    }
+
     fn traffic_signal_policy(
         &mut self,
         req: &Request,
@@ -977,6 +1122,48 @@ impl IntersectionSimState {
         true
     }
 
+    /// Can a pedestrian cross a slow road against a red light, because there's a big enough gap in
+    /// the traffic that has the green?
+    fn gap_to_jaywalk(&self, req: &Request, map: &Map, speed: Speed, now: Time) -> bool {
+        if !req.agent.is_pedestrian() {
+            return false;
+        }
+        let signal = match map.maybe_get_traffic_signal(req.turn.parent) {
+            Some(signal) => signal,
+            None => return false,
+        };
+        let turn = map.get_t(req.turn);
+        if !turn.allows_jaywalking(map) {
+            return false;
+        }
+
+        let state = &self.state[&req.turn.parent];
+        let stage = &signal.stages[state.signal.as_ref().unwrap().current_stage];
+        let i = map.get_i(state.id);
+        // Only the crossings the signal doesn't allow right now count as jaywalking
+        if stage.get_priority_of_turn(req.turn, i) != TurnPriority::Banned {
+            return false;
+        }
+
+        let conflicts = |other: &Request| {
+            other.agent != req.agent
+                && map
+                    .maybe_get_t(other.turn)
+                    .map(|t| t.conflicts_with(turn))
+                    .unwrap_or(false)
+        };
+        // Don't step out in front of somebody waiting to go
+        if state.waiting.keys().any(|other| {
+            conflicts(other) && stage.get_priority_of_turn(other.turn, i) != TurnPriority::Banned
+        }) {
+            return false;
+        }
+        let time_to_cross = turn.geom.length() / speed;
+        !state.leader_eta.values().any(|(other, eta)| {
+            conflicts(other) && *eta >= now && *eta < now + time_to_cross + JAYWALKING_GAP_BUFFER
+        })
+    }
+
     // If true, the request can go.
     fn handle_accepted_conflicts(
         &mut self,
@@ -1173,3 +1360,104 @@ fn allow_block_the_box(i: &Intersection) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Timer;
+    use map_model::{EditIntersectionControl, Turn};
+
+    use super::*;
+    use crate::testing::import_map;
+    use crate::VehicleType;
+
+    /// Turns a stop sign into an all-way yield. Returns two conflicting vehicle turns there from
+    /// different roads.
+    fn all_way_yield() -> (Map, TurnID, TurnID) {
+        let mut map = import_map();
+        let (i, t1, t2) = find_conflicting_turns(&map);
+        let mut sign = map.get_stop_sign(i).clone();
+        sign.convert_to_yield(&map);
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(map.edit_intersection_cmd(i, |new| {
+            new.control = EditIntersectionControl::StopSign(sign.clone());
+        }));
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        assert!(map.get_stop_sign(i).is_all_way_yield());
+        (map, t1, t2)
+    }
+
+    fn find_conflicting_turns(map: &Map) -> (IntersectionID, TurnID, TurnID) {
+        for i in map.all_intersections() {
+            if !i.is_stop_sign() {
+                continue;
+            }
+            let turns: Vec<&Turn> = i.turns.iter().filter(|t| !t.between_sidewalks()).collect();
+            for t1 in &turns {
+                for t2 in &turns {
+                    if t1.id.src.road != t2.id.src.road && t1.conflicts_with(t2) {
+                        return (i.id, t1.id, t2.id);
+                    }
+                }
+            }
+        }
+        panic!("No stop sign has conflicting turns");
+    }
+
+    fn car(id: usize) -> AgentID {
+        AgentID::Car(CarID {
+            id,
+            vehicle_type: VehicleType::Car,
+        })
+    }
+
+    fn new_state(map: &Map, scheduler: &mut Scheduler) -> IntersectionSimState {
+        let mut opts = SimOptions::new("all_way_yield");
+        // There are no real cars to check for cycles
+        opts.dont_break_turn_conflict_cycles = true;
+        IntersectionSimState::new(map, scheduler, &opts)
+    }
+
+    #[test]
+    fn test_all_way_yield_gap_acceptance() {
+        let (map, t1, t2) = all_way_yield();
+        let speed = Speed::miles_per_hour(20.0);
+        let now = Time::START_OF_DAY + Duration::minutes(1);
+
+        // Nobody has priority, but somebody from another road is about to arrive
+        let mut scheduler = Scheduler::new();
+        let mut state = new_state(&map, &mut scheduler);
+        state.approaching_leader(car(2), t2, now + Duration::seconds(2.0));
+        assert!(!state.maybe_start_turn(car(1), t1, speed, now, &map, &mut scheduler, None));
+
+        // If they're far enough away, don't wait for them
+        let mut scheduler = Scheduler::new();
+        let mut state = new_state(&map, &mut scheduler);
+        state.approaching_leader(car(2), t2, now + CRITICAL_GAP + Duration::seconds(1.0));
+        assert!(state.maybe_start_turn(car(1), t1, speed, now, &map, &mut scheduler, None));
+    }
+
+    #[test]
+    fn test_all_way_yield_first_arrival() {
+        let (map, t1, t2) = all_way_yield();
+        let speed = Speed::miles_per_hour(20.0);
+        let now = Time::START_OF_DAY + Duration::minutes(1);
+        let mut scheduler = Scheduler::new();
+        let mut state = new_state(&map, &mut scheduler);
+
+        // The first car waits for the second to arrive
+        let arrival = now + Duration::seconds(2.0);
+        state.approaching_leader(car(2), t2, arrival);
+        assert!(!state.maybe_start_turn(car(1), t1, speed, now, &map, &mut scheduler, None));
+        // But once the second car is there, the first car was waiting longer
+        assert!(!state.maybe_start_turn(car(2), t2, speed, arrival, &map, &mut scheduler, None));
+        assert!(state.maybe_start_turn(
+            car(1),
+            t1,
+            speed,
+            arrival + Duration::EPSILON,
+            &map,
+            &mut scheduler,
+            None
+        ));
+    }
+}
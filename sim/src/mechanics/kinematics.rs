@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Speed};
+
+use crate::AccelerationProfile;
+
+/// How a vehicle's speed changes over one Crossing state when acceleration is modelled: speed up
+/// from the entry speed as quickly as possible, cruise at the speed limit, then brake just in
+/// time to leave at the exit speed. Short distances might never reach the cruising speed. All
+/// values are in meters and seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SpeedProfile {
+    start_speed: f64,
+    peak_speed: f64,
+    acceleration: f64,
+    deceleration: f64,
+    accel_time: f64,
+    cruise_time: f64,
+    decel_time: f64,
+    total_dist: f64,
+}
+
+impl SpeedProfile {
+    /// `end_speed` is the fastest the vehicle should be going at the end. If it can't speed up
+    /// that much over the distance, it leaves slower. If it can't slow down comfortably enough,
+    /// it brakes harder.
+    pub fn new(
+        dist: Distance,
+        start_speed: Speed,
+        max_speed: Speed,
+        end_speed: Speed,
+        accel: AccelerationProfile,
+    ) -> SpeedProfile {
+        let d = dist.inner_meters();
+        let vmax = max_speed.inner_meters_per_second();
+        let a = accel.max_acceleration;
+        let v0 = start_speed.inner_meters_per_second().clamp(0.0, vmax);
+        if d <= 0.0 {
+            return SpeedProfile {
+                start_speed: v0,
+                peak_speed: v0,
+                acceleration: a,
+                deceleration: accel.comfortable_deceleration,
+                accel_time: 0.0,
+                cruise_time: 0.0,
+                decel_time: 0.0,
+                total_dist: 0.0,
+            };
+        }
+        let ve = end_speed
+            .inner_meters_per_second()
+            .clamp(0.0, vmax)
+            .min((v0 * v0 + 2.0 * a * d).sqrt());
+        let b = accel
+            .comfortable_deceleration
+            .max((v0 * v0 - ve * ve) / (2.0 * d));
+
+        // The speed reached if the vehicle accelerates, then immediately brakes to cover the
+        // whole distance
+        let peak_speed = ((2.0 * a * b * d + b * v0 * v0 + a * ve * ve) / (a + b))
+            .sqrt()
+            .min(vmax)
+            .max(v0)
+            .max(ve);
+        let accel_time = (peak_speed - v0) / a;
+        let decel_time = (peak_speed - ve) / b;
+        let accel_dist = (v0 + peak_speed) / 2.0 * accel_time;
+        let decel_dist = (peak_speed + ve) / 2.0 * decel_time;
+        let cruise_time = if peak_speed > 0.0 {
+            (d - accel_dist - decel_dist).max(0.0) / peak_speed
+        } else {
+            0.0
+        };
+
+        SpeedProfile {
+            start_speed: v0,
+            peak_speed,
+            acceleration: a,
+            deceleration: b,
+            accel_time,
+            cruise_time,
+            decel_time,
+            total_dist: d,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.accel_time + self.cruise_time + self.decel_time)
+    }
+
+    /// How far the vehicle has gone this long after starting.
+    pub fn dist_after(&self, dt: Duration) -> Distance {
+        // Durations are rounded, so don't fall just short of the end
+        if dt >= self.duration() {
+            return Distance::meters(self.total_dist);
+        }
+        let t = dt.inner_seconds().max(0.0);
+        let dist = if t <= self.accel_time {
+            self.start_speed * t + 0.5 * self.acceleration * t * t
+        } else if t <= self.accel_time + self.cruise_time {
+            self.accel_dist() + self.peak_speed * (t - self.accel_time)
+        } else {
+            let t = (t - self.accel_time - self.cruise_time).min(self.decel_time);
+            self.accel_dist() + self.peak_speed * self.cruise_time + self.peak_speed * t
+                - 0.5 * self.deceleration * t * t
+        };
+        Distance::meters(dist.clamp(0.0, self.total_dist))
+    }
+
+    /// How fast the vehicle is going this long after starting.
+    pub fn speed_after(&self, dt: Duration) -> Speed {
+        let t = dt.inner_seconds().max(0.0);
+        let speed = if t <= self.accel_time {
+            self.start_speed + self.acceleration * t
+        } else if t <= self.accel_time + self.cruise_time {
+            self.peak_speed
+        } else {
+            let t = (t - self.accel_time - self.cruise_time).min(self.decel_time);
+            self.peak_speed - self.deceleration * t
+        };
+        Speed::meters_per_second(speed.max(0.0))
+    }
+
+    fn accel_dist(&self) -> f64 {
+        (self.start_speed + self.peak_speed) / 2.0 * self.accel_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car() -> AccelerationProfile {
+        AccelerationProfile {
+            max_acceleration: 2.0,
+            comfortable_deceleration: 3.0,
+        }
+    }
+
+    fn approx_eq(x: f64, y: f64) -> bool {
+        // Durations are rounded
+        (x - y).abs() < 0.01
+    }
+
+    #[test]
+    fn test_accelerate_from_rest() {
+        let profile = SpeedProfile::new(
+            Distance::meters(200.0),
+            Speed::ZERO,
+            Speed::meters_per_second(15.0),
+            Speed::meters_per_second(15.0),
+            car(),
+        );
+        assert_eq!(profile.speed_after(Duration::ZERO), Speed::ZERO);
+        // v = at, d = at^2 / 2
+        let t = Duration::seconds(2.0);
+        assert!(approx_eq(
+            profile.speed_after(t).inner_meters_per_second(),
+            4.0
+        ));
+        assert!(approx_eq(profile.dist_after(t).inner_meters(), 4.0));
+        // Slower than teleporting to the speed limit
+        assert!(profile.duration() > Duration::seconds(200.0 / 15.0));
+        assert_eq!(
+            profile.dist_after(profile.duration()),
+            Distance::meters(200.0)
+        );
+    }
+
+    #[test]
+    fn test_braking_distance() {
+        // With enough room, brake comfortably: v^2 / 2b = 37.5m
+        let profile = SpeedProfile::new(
+            Distance::meters(100.0),
+            Speed::meters_per_second(15.0),
+            Speed::meters_per_second(15.0),
+            Speed::ZERO,
+            car(),
+        );
+        assert!(approx_eq(profile.deceleration, 3.0));
+        let braking_starts = profile.duration() - Duration::seconds(profile.decel_time);
+        assert!(approx_eq(
+            profile.dist_after(braking_starts).inner_meters(),
+            100.0 - 37.5
+        ));
+        assert_eq!(profile.speed_after(profile.duration()), Speed::ZERO);
+
+        // Without enough room, brake harder, but still stop by the end
+        let profile = SpeedProfile::new(
+            Distance::meters(10.0),
+            Speed::meters_per_second(15.0),
+            Speed::meters_per_second(15.0),
+            Speed::ZERO,
+            car(),
+        );
+        assert!(profile.deceleration > 3.0);
+        assert_eq!(profile.speed_after(profile.duration()), Speed::ZERO);
+        assert_eq!(
+            profile.dist_after(profile.duration()),
+            Distance::meters(10.0)
+        );
+        for i in 0..10 {
+            let t = profile.duration() * (i as f64 / 10.0);
+            assert!(profile.dist_after(t) <= Distance::meters(10.0));
+        }
+    }
+
+    #[test]
+    fn test_reach_speed_limit() {
+        let limit = Speed::meters_per_second(15.0);
+        let profile = SpeedProfile::new(Distance::meters(1000.0), Speed::ZERO, limit, limit, car());
+        // 7.5 seconds to get up to speed, then cruise
+        assert!(approx_eq(profile.accel_time, 7.5));
+        assert!(profile.cruise_time > 0.0);
+        assert_eq!(profile.speed_after(Duration::seconds(30.0)), limit);
+        // Never faster than the limit
+        for i in 0..=20 {
+            let t = profile.duration() * (i as f64 / 20.0);
+            assert!(profile.speed_after(t) <= limit);
+        }
+
+        // Too short to reach the limit
+        let profile = SpeedProfile::new(Distance::meters(10.0), Speed::ZERO, limit, limit, car());
+        assert!(profile.peak_speed < 15.0);
+    }
+}
@@ -9,10 +9,10 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, serialize_btreemap, serialize_multimap, MultiMap,
     Timer,
 };
-use geom::{Distance, PolyLine, Pt2D};
+use geom::{Distance, PolyLine, Pt2D, Time};
 use map_model::{
-    BuildingID, Lane, LaneID, LaneType, Map, OffstreetParking, ParkingLotID, PathConstraints,
-    PathStep, Position, Traversable, TurnID,
+    BuildingID, CurbActivity, Direction, Lane, LaneID, LaneType, Map, OffstreetParking,
+    ParkingLotID, PathConstraints, PathStep, Position, SideOfRoad, Traversable, TurnID,
 };
 
 use crate::{CarID, CarStatus, DrawCarInput, Event, ParkedCar, ParkingSpot, PersonID, Vehicle};
@@ -26,7 +26,7 @@ pub trait ParkingSim {
     /// Returns any cars that got very abruptly evicted from existence, and also cars actively
     /// moving into a deleted spot.
     fn handle_live_edits(&mut self, map: &Map, timer: &mut Timer) -> (Vec<ParkedCar>, Vec<CarID>);
-    fn get_free_onstreet_spots(&self, l: LaneID) -> Vec<ParkingSpot>;
+    fn get_free_onstreet_spots(&self, l: LaneID, map: &Map) -> Vec<ParkingSpot>;
     fn get_free_offstreet_spots(&self, b: BuildingID) -> Vec<ParkingSpot>;
     fn get_free_lot_spots(&self, pl: ParkingLotID) -> Vec<ParkingSpot>;
     fn reserve_spot(&mut self, spot: ParkingSpot, car: CarID);
@@ -73,6 +73,9 @@ pub trait ParkingSim {
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)>;
     fn collect_events(&mut self) -> Vec<Event>;
+    /// Curb regulations can make on-street spots unavailable during some times of day. Cars
+    /// already parked there aren't evicted.
+    fn update_time(&mut self, now: Time);
     fn all_parked_car_positions(&self, map: &Map) -> Vec<(Position, PersonID)>;
     fn bldg_to_parked_cars(&self, b: BuildingID) -> Vec<CarID>;
 }
@@ -148,6 +151,8 @@ pub struct NormalParkingSimState {
     driving_to_lots: MultiMap<LaneID, ParkingLotID>,
 
     events: Vec<Event>,
+    // Only used to check curb regulations
+    now: Time,
 }
 
 impl NormalParkingSimState {
@@ -165,6 +170,7 @@ impl NormalParkingSimState {
             driving_to_lots: MultiMap::new(),
 
             events: Vec::new(),
+            now: Time::START_OF_DAY,
         };
         for l in map.all_lanes() {
             if let Some(lane) = ParkingLane::new(l, map) {
@@ -254,11 +260,11 @@ impl ParkingSim for NormalParkingSimState {
         (evicted, moving_into_deleted_spot)
     }
 
-    fn get_free_onstreet_spots(&self, l: LaneID) -> Vec<ParkingSpot> {
+    fn get_free_onstreet_spots(&self, l: LaneID, map: &Map) -> Vec<ParkingSpot> {
         let mut spots: Vec<ParkingSpot> = Vec::new();
         if let Some(lane) = self.onstreet_lanes.get(&l) {
             for spot in lane.spots() {
-                if self.is_free(spot) {
+                if self.is_free(spot) && lane.curb_allows_parking(spot, self.now, map) {
                     spots.push(spot);
                 }
             }
@@ -451,8 +457,10 @@ impl ParkingSim for NormalParkingSimState {
         let mut candidates = Vec::new();
 
         for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
-            for spot in self.onstreet_lanes[l].spots() {
+            let lane = &self.onstreet_lanes[l];
+            for spot in lane.spots() {
                 if self.is_free(spot)
+                    && lane.curb_allows_parking(spot, self.now, map)
                     && driving_pos.dist_along()
                         <= self.spot_to_driving_pos(spot, vehicle, map).dist_along()
                 {
@@ -632,6 +640,10 @@ impl ParkingSim for NormalParkingSimState {
         std::mem::take(&mut self.events)
     }
 
+    fn update_time(&mut self, now: Time) {
+        self.now = now;
+    }
+
     fn all_parked_car_positions(&self, map: &Map) -> Vec<(Position, PersonID)> {
         self.parked_cars
             .values()
@@ -663,6 +675,10 @@ struct ParkingLane {
     sidewalk: LaneID,
     // The front of the parking spot (farthest along the lane)
     spot_dist_along: Vec<Distance>,
+    // The side of the road this lane is on, and the middle of each spot, measured along the
+    // road's center line. The distances are empty if there are no regulations on this side.
+    side: SideOfRoad,
+    spot_dist_along_road: Vec<Distance>,
 }
 
 impl ParkingLane {
@@ -690,16 +706,61 @@ impl ParkingLane {
             return None;
         };
 
+        let spot_length = map.get_config().street_parking_spot_length;
+        let spot_dist_along: Vec<Distance> = (0..lane.number_parking_spots(map.get_config()))
+            .map(|idx| spot_length * (2.0 + idx as f64))
+            .collect();
+
+        let road = map.get_parent(lane.id);
+        let side = lane.get_nearest_side_of_road(map).side;
+        let spot_dist_along_road = if road.curb_regulations.iter().all(|reg| reg.side != side) {
+            Vec::new()
+        } else {
+            let ratio = road.center_pts.length() / lane.length();
+            spot_dist_along
+                .iter()
+                .map(|front| {
+                    let middle = *front - spot_length / 2.0;
+                    if lane.dir == Direction::Fwd {
+                        ratio * middle
+                    } else {
+                        ratio * (lane.length() - middle)
+                    }
+                })
+                .collect()
+        };
+
         Some(ParkingLane {
             parking_lane: lane.id,
             driving_lane,
             sidewalk,
-            spot_dist_along: (0..lane.number_parking_spots(map.get_config()))
-                .map(|idx| map.get_config().street_parking_spot_length * (2.0 + idx as f64))
-                .collect(),
+            spot_dist_along,
+            side,
+            spot_dist_along_road,
         })
     }
 
+    /// Only curbs where anybody may park count. Nothing simulated makes deliveries or holds a
+    /// permit, so loading zones and restricted parking are just off-limits. Maximum stays aren't
+    /// imported either, so time-limited parking is treated like any other parking.
+    fn curb_allows_parking(&self, spot: ParkingSpot, now: Time, map: &Map) -> bool {
+        if self.spot_dist_along_road.is_empty() {
+            return true;
+        }
+        let idx = match spot {
+            ParkingSpot::Onstreet(_, idx) => idx,
+            _ => unreachable!(),
+        };
+        let dist = self.spot_dist_along_road[idx];
+        match map
+            .get_parent(self.parking_lane)
+            .get_curb_activity(self.side, dist, now)
+        {
+            Some(activity) => activity == CurbActivity::Parking,
+            None => true,
+        }
+    }
+
     fn dist_along_for_car(&self, spot_idx: usize, vehicle: &Vehicle, map: &Map) -> Distance {
         // Find the offset to center this particular car in the parking spot
         self.spot_dist_along[spot_idx]
@@ -842,7 +903,7 @@ impl ParkingSim for InfiniteParkingSimState {
         (Vec::new(), Vec::new())
     }
 
-    fn get_free_onstreet_spots(&self, _: LaneID) -> Vec<ParkingSpot> {
+    fn get_free_onstreet_spots(&self, _: LaneID, _: &Map) -> Vec<ParkingSpot> {
         Vec::new()
     }
 
@@ -1045,6 +1106,8 @@ impl ParkingSim for InfiniteParkingSimState {
         std::mem::take(&mut self.events)
     }
 
+    fn update_time(&mut self, _: Time) {}
+
     fn all_parked_car_positions(&self, map: &Map) -> Vec<(Position, PersonID)> {
         self.parked_cars
             .values()
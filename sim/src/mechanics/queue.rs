@@ -231,11 +231,18 @@ impl Queue {
                         CarState::Crossing {
                             ref time_int,
                             ref dist_int,
+                            ref profile,
                             ..
                         } => {
-                            // TODO Why percent_clamp_end? We process car updates in any order, so we might
-                            // calculate this before moving this car from Crossing to another state.
-                            dist_int.lerp(time_int.percent_clamp_end(now)).min(bound)
+                            if let Some(profile) = profile {
+                                (dist_int.start + profile.dist_after(now - time_int.start))
+                                    .min(dist_int.end)
+                                    .min(bound)
+                            } else {
+                                // TODO Why percent_clamp_end? We process car updates in any order, so we might
+                                // calculate this before moving this car from Crossing to another state.
+                                dist_int.lerp(time_int.percent_clamp_end(now)).min(bound)
+                            }
                         }
                         CarState::ChangingLanes {
                             ref new_time,
@@ -549,6 +556,13 @@ impl Queue {
         assert_eq!(self.members.remove(idx), Some(Queued::Vehicle(car)));
     }
 
+    /// Record that a car has passed the vehicle directly in front of it.
+    pub fn swap_with_leader(&mut self, car: CarID, idx: usize) {
+        assert_eq!(self.members[idx], Queued::Vehicle(car));
+        assert!(matches!(self.members[idx - 1], Queued::Vehicle(_)));
+        self.members.swap(idx - 1, idx);
+    }
+
     /// If a car thinks it's reached the end of the queue, double check. Blockages or laggy heads
     /// might be in the way.
     pub fn is_car_at_front(&self, car: CarID) -> bool {
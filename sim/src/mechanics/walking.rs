@@ -6,8 +6,9 @@ use abstutil::{deserialize_multimap, serialize_multimap, FixedMap, IndexableKey,
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
     BuildingID, DrivingSide, Map, ParkingLotID, Path, PathConstraints, PathStep, TransitRouteID,
-    Traversable,
+    Traversable, TurnID,
 };
+use synthpop::{Conditions, TripMode};
 
 use crate::sim::Ctx;
 use crate::{
@@ -20,10 +21,23 @@ use crate::{
 
 const TIME_TO_START_BIKING: Duration = Duration::const_seconds(30.0);
 const TIME_TO_FINISH_BIKING: Duration = Duration::const_seconds(45.0);
+/// How often somebody waiting for room on a full crosswalk checks again
+const BLIND_RETRY_TO_ENTER_CROSSWALK: Duration = Duration::const_seconds(1.0);
+
+/// People per square meter when a crowd can't move at all
+const JAM_DENSITY: f64 = 5.4;
+/// People per square meter when walking becomes noticeably impeded
+const CROWDED_DENSITY: f64 = 1.5;
+/// Nobody else steps onto a crosswalk when it's this full, in people per square meter
+const CROSSWALK_CAPACITY_DENSITY: f64 = 2.5;
+/// Corners and some crosswalks are tiny. Treat them as at least this long when working out how
+/// crowded they are, since people spill out onto the sidewalks nearby.
+const MIN_LENGTH_FOR_DENSITY: Distance = Distance::const_meters(3.0);
 
 /// Simulates pedestrians. Unlike vehicles, pedestrians can move bidirectionally on sidewalks and
-/// just "ghost" through each other. There's no queueing or slowdown when many people are
-/// overlapping. They're simply grouped together into a DrawPedCrowdInput for rendering.
+/// pass through each other, but they slow down as a sidewalk or crosswalk gets more crowded, and
+/// wait at the corner when a crosswalk is full. Overlapping people are grouped together into a
+/// DrawPedCrowdInput for rendering.
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct WalkingSimState {
     peds: FixedMap<PedestrianID, Pedestrian>,
@@ -33,6 +47,7 @@ pub(crate) struct WalkingSimState {
     )]
     peds_per_traversable: MultiMap<Traversable, PedestrianID>,
     events: Vec<Event>,
+    conditions: Conditions,
 }
 
 impl WalkingSimState {
@@ -41,6 +56,16 @@ impl WalkingSimState {
             peds: FixedMap::new(),
             peds_per_traversable: MultiMap::new(),
             events: Vec::new(),
+            conditions: Conditions::DEFAULT,
+        }
+    }
+
+    /// Everybody walking changes speed, starting from the next thing they cross.
+    pub fn set_conditions(&mut self, conditions: Conditions) {
+        self.conditions = conditions;
+        let factor = conditions.speed_factor(TripMode::Walk);
+        for ped in self.peds.values_mut() {
+            ped.conditions_speed_factor = factor;
         }
     }
 
@@ -70,6 +95,7 @@ impl WalkingSimState {
                 steep_uphill: false,
             },
             speed: params.speed,
+            conditions_speed_factor: self.conditions.speed_factor(TripMode::Walk),
             total_blocked_time: Duration::ZERO,
             started_at: now,
             path: params.path,
@@ -82,12 +108,18 @@ impl WalkingSimState {
             SidewalkPOI::Building(b) | SidewalkPOI::ParkingSpot(ParkingSpot::Offstreet(b, _)) => {
                 PedState::LeavingBuilding(
                     b,
-                    TimeInterval::new(now, now + map.get_b(b).driveway_geom.length() / ped.speed),
+                    TimeInterval::new(
+                        now,
+                        now + map.get_b(b).driveway_geom.length() / ped.walking_speed(),
+                    ),
                 )
             }
             SidewalkPOI::ParkingSpot(ParkingSpot::Lot(pl, _)) => PedState::LeavingParkingLot(
                 pl,
-                TimeInterval::new(now, now + map.get_pl(pl).sidewalk_line.length() / ped.speed),
+                TimeInterval::new(
+                    now,
+                    now + map.get_pl(pl).sidewalk_line.length() / ped.walking_speed(),
+                ),
             ),
             SidewalkPOI::BikeRack(driving_pos) => PedState::FinishingBiking(
                 params.start.clone(),
@@ -127,6 +159,22 @@ impl WalkingSimState {
             .collect()
     }
 
+    /// How fast the pedestrian is moving right now. Zero when they're waiting or not following a
+    /// sidewalk or crossing.
+    pub fn current_speed(&self, id: PedestrianID) -> Option<Speed> {
+        let p = self.peds.get(&id)?;
+        Some(match p.state {
+            PedState::Crossing {
+                ref dist_int,
+                ref time_int,
+                ..
+            } if time_int.end > time_int.start => {
+                Speed::from_dist_time(dist_int.length(), time_int.end - time_int.start)
+            }
+            _ => Speed::ZERO,
+        })
+    }
+
     pub fn update_ped(
         &mut self,
         id: PedestrianID,
@@ -146,7 +194,8 @@ impl WalkingSimState {
                                     pl,
                                     TimeInterval::new(
                                         now,
-                                        now + ctx.map.get_pl(pl).sidewalk_line.length() / ped.speed,
+                                        now + ctx.map.get_pl(pl).sidewalk_line.length()
+                                            / ped.walking_speed(),
                                     ),
                                 );
                                 ctx.scheduler
@@ -170,7 +219,8 @@ impl WalkingSimState {
                                 b,
                                 TimeInterval::new(
                                     now,
-                                    now + ctx.map.get_b(b).driveway_geom.length() / ped.speed,
+                                    now + ctx.map.get_b(b).driveway_geom.length()
+                                        / ped.walking_speed(),
                                 ),
                             );
                             ctx.scheduler
@@ -245,7 +295,9 @@ impl WalkingSimState {
                         ctx.scheduler
                             .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                     } else {
-                        // Must've failed because we can't turn yet. Don't schedule a retry here.
+                        // Must've failed because we can't turn yet. If the intersection didn't
+                        // accept us, it'll wake us up. If the crosswalk is full, maybe_transition
+                        // already scheduled a retry.
                         ped.state = PedState::WaitingToTurn(dist, now);
                     }
                 }
@@ -631,7 +683,10 @@ impl WalkingSimState {
 struct Pedestrian {
     id: PedestrianID,
     state: PedState,
+    /// How fast the person walks in dry daylight
     speed: Speed,
+    /// Scales the speed by the current weather and daylight
+    conditions_speed_factor: f64,
     total_blocked_time: Duration,
     // TODO organize analytics better.
     started_at: Time,
@@ -644,6 +699,10 @@ struct Pedestrian {
 }
 
 impl Pedestrian {
+    fn walking_speed(&self) -> Speed {
+        self.speed * self.conditions_speed_factor
+    }
+
     fn crossing_state(
         &self,
         peds_per_traversable: &MultiMap<Traversable, PedestrianID>,
@@ -664,21 +723,19 @@ impl Pedestrian {
             }
         };
 
-        let speed_penalty = crowdedness_penalty(
-            map,
-            self.path.current_step().as_traversable(),
-            peds_per_traversable,
-        );
-        if speed_penalty != 1.0 {
+        let on = self.path.current_step().as_traversable();
+        let density = pedestrian_density(map, on, peds_per_traversable);
+        if density >= CROWDED_DENSITY {
             events.push(Event::ProblemEncountered(
                 self.trip,
-                Problem::PedestrianOvercrowding(self.path.current_step().as_traversable()),
+                Problem::PedestrianOvercrowding(on),
             ));
         }
+        let speed_penalty = crowdedness_penalty(density);
 
         let dist_int = DistanceInterval::new_walking(start_dist, end_dist);
         let (speed, percent_incline) = self.path.current_step().max_speed_and_incline_along(
-            Some(self.speed),
+            Some(self.walking_speed()),
             PathConstraints::Pedestrian,
             map,
         );
@@ -854,11 +911,23 @@ impl Pedestrian {
         scheduler: &mut Scheduler,
     ) -> bool {
         if let PathStep::Turn(t) | PathStep::ContraflowTurn(t) = self.path.next_step() {
+            if map.get_t(t).turn_type.pedestrian_crossing()
+                && peds_per_traversable.get(Traversable::Turn(t)).len()
+                    >= crosswalk_capacity(map, t)
+            {
+                // Queue at the corner until somebody gets off the crosswalk. The intersection
+                // won't wake us up for this, so check again later.
+                scheduler.update(
+                    now + BLIND_RETRY_TO_ENTER_CROSSWALK,
+                    Command::UpdatePed(self.id),
+                );
+                return false;
+            }
             if !intersections.maybe_start_turn(
                 AgentID::Pedestrian(self.id),
                 t,
                 PathStep::Turn(t).max_speed_along(
-                    Some(self.speed),
+                    Some(self.walking_speed()),
                     PathConstraints::Pedestrian,
                     map,
                 ),
@@ -869,6 +938,9 @@ impl Pedestrian {
             ) {
                 return false;
             }
+            if intersections.is_jaywalking(t, map) {
+                events.push(Event::ProblemEncountered(self.trip, Problem::Jaywalking(t)));
+            }
         }
 
         peds_per_traversable.remove(self.path.current_step().as_traversable(), self.id);
@@ -984,37 +1056,87 @@ impl IndexableKey for PedestrianID {
     }
 }
 
-/// Returns a number in (0, 1] to multiply speed by to account for current crowdedness.
-///
-/// We could get really fancy here and slow people down only when they're part of a crowd, or
-/// passing people going the opposite direction. But start simple -- keep a fixed speed for the
-/// entire time on a sidewalk, and base the decision on how many people are there when entering the
-/// sidewalk.
-fn crowdedness_penalty(
-    map: &Map,
-    traversable: Traversable,
-    peds_per_traversable: &MultiMap<Traversable, PedestrianID>,
-) -> f64 {
-    // 1) How many people are in the space
-    let num_people = peds_per_traversable.get(traversable).len();
-    // 2) The length of the sidewalk or crosswalk
-    let len = traversable.get_polyline(map).length();
-    // 3) The width of the space
+/// The area in square meters that pedestrians on a sidewalk or crosswalk share.
+fn walkable_area(map: &Map, traversable: Traversable) -> f64 {
+    let len = traversable
+        .get_polyline(map)
+        .length()
+        .max(MIN_LENGTH_FOR_DENSITY);
     let width = match traversable {
         // Sidewalk
         Traversable::Lane(l) => map.get_l(l).width,
         // For crosswalks, the thinner of the two sidewalks being connected
         Traversable::Turn(t) => map.get_l(t.src).width.min(map.get_l(t.dst).width),
     };
+    width.inner_meters() * len.inner_meters()
+}
+
+/// People per square meter, assuming everyone's equally spread out.
+fn pedestrian_density(
+    map: &Map,
+    traversable: Traversable,
+    peds_per_traversable: &MultiMap<Traversable, PedestrianID>,
+) -> f64 {
+    let num_people = peds_per_traversable.get(traversable).len();
+    (num_people as f64) / walkable_area(map, traversable)
+}
+
+/// How many people can be on a crosswalk at once. Even the tiniest crosswalk fits one person.
+fn crosswalk_capacity(map: &Map, t: TurnID) -> usize {
+    capacity_for_area(walkable_area(map, Traversable::Turn(t)))
+}
+
+fn capacity_for_area(square_meters: f64) -> usize {
+    ((square_meters * CROSSWALK_CAPACITY_DENSITY).floor() as usize).max(1)
+}
 
-    // Person per area, assuming everyone's equally spread out
-    let people_per_sq_m = (num_people as f64) / (width.inner_meters() * len.inner_meters());
-    // Based on eyeballing images from
-    // https://www.gkstill.com/Support/crowd-density/CrowdDensity-1.html, let's use a fixed
-    // threshold of 1.5 people per square meter as "crowded" and slow them down by half.
-    if people_per_sq_m < 1.5 {
-        // Plenty of room, no penalty
+/// Returns a number in (0, 1] to multiply speed by to account for current crowdedness, using the
+/// fundamental diagram from Weidmann (1993). Speed barely changes until about 1 person per square
+/// meter, then drops off, approaching a standstill at the jam density.
+///
+/// We could get really fancy here and slow people down only when they're part of a crowd, or
+/// passing people going the opposite direction. But start simple -- keep a fixed speed for the
+/// entire time on a sidewalk, and base the decision on how many people are there when entering the
+/// sidewalk.
+fn crowdedness_penalty(density: f64) -> f64 {
+    if density <= 0.0 {
         return 1.0;
     }
-    0.5
+    // Even in a jam, people shuffle forwards eventually
+    let density = density.min(0.95 * JAM_DENSITY);
+    (1.0 - (-1.913 * (1.0 / density - 1.0 / JAM_DENSITY)).exp()).clamp(0.05, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crowdedness_penalty() {
+        assert_eq!(crowdedness_penalty(0.0), 1.0);
+        // Barely noticeable below 1 person per square meter
+        for density in [0.1, 0.3, 0.5] {
+            assert!(crowdedness_penalty(density) > 0.95);
+        }
+        assert!(crowdedness_penalty(1.0) > 0.75);
+
+        // Slower as it gets more crowded
+        let mut last = 1.0;
+        for density in [1.0, 1.5, 2.0, 3.0, 4.0, 5.0] {
+            let penalty = crowdedness_penalty(density);
+            assert!(penalty < last);
+            last = penalty;
+        }
+
+        // Never a total standstill, even past the jam density
+        assert_eq!(crowdedness_penalty(JAM_DENSITY), 0.05);
+        assert_eq!(crowdedness_penalty(2.0 * JAM_DENSITY), 0.05);
+    }
+
+    #[test]
+    fn test_crosswalk_capacity() {
+        assert_eq!(capacity_for_area(0.0), 1);
+        assert_eq!(capacity_for_area(0.1), 1);
+        assert_eq!(capacity_for_area(10.0), 25);
+    }
 }
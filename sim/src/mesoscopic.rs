@@ -0,0 +1,655 @@
+//! A much faster, coarser alternative to the normal simulation, meant for regional studies where
+//! simulating every vehicle's exact position isn't feasible. It reads the same Scenario and Map
+//! and produces the same Analytics, so prebaked results from it work with all of the dashboards.
+//!
+//! Every DirectedRoadID is a link with some storage capacity and a maximum outflow rate. A vehicle
+//! entering a link waits there for its travel time, which grows as the link fills up, following
+//! Greenshields' linear speed-density relationship. Then it joins the queue to leave the link.
+//! Vehicles leave in order, no faster than the link's outflow rate, and only if there's room on
+//! the next link. So queues spill back upstream through intersections. Pedestrians and cyclists
+//! don't interact with anything; they just follow their path at their own speed.
+//!
+//! Some simplifications compared to the normal simulation:
+//!
+//! - Cars appear at the start of their path and vanish at the end; there's no parking.
+//! - Transit trips are walked the whole way.
+//! - Traffic signals and stop signs only reduce a link's outflow rate; there are no conflicts
+//!   between movements.
+//! - Scheduled incidents and live map edits aren't supported.
+//! - Weather and daylight scale each trip's speeds when it starts, but don't change anybody's mode.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+
+use abstutil::Timer;
+use geom::{Distance, Duration, Speed, Time};
+use map_model::{
+    DirectedRoadID, LaneID, Map, Path, PathConstraints, PathStep, Traversable, TurnID,
+};
+use synthpop::{conditions_at, ConditionsChange, CyclistComfort, Scenario, TripEndpoint, TripMode};
+
+use crate::{
+    AgentID, Analytics, CarID, Event, PedestrianID, PersonID, SimOptions, TripID, TripPhaseType,
+    VehicleType,
+};
+
+/// The space one queued vehicle takes up, including the gap in front of it
+const VEHICLE_SPACING: Distance = Distance::const_meters(7.5);
+/// How many vehicles per second a single lane can discharge
+const SATURATION_FLOW_PER_LANE: f64 = 0.5;
+/// Even in the densest traffic, vehicles keep creeping forward at this fraction of free-flow speed
+const MIN_SPEED_FRACTION: f64 = 0.1;
+/// If the next link stays full for this long, the vehicle squeezes in anyway. This avoids
+/// gridlock, like the normal simulation's handling of turn conflict cycles.
+const STUCK_TIME: Duration = Duration::const_seconds(300.0);
+
+/// Runs a Scenario using a mesoscopic traffic model. See the module docs for details.
+pub struct MesoSim {
+    time: Time,
+    trips: Vec<MesoTrip>,
+    links: BTreeMap<DirectedRoadID, Link>,
+    link_stats: BTreeMap<DirectedRoadID, LinkStats>,
+    queue: BinaryHeap<Reverse<(Time, Item)>>,
+    analytics: Analytics,
+    /// Only affects walking routes; signals aren't modelled
+    jaywalking: bool,
+    conditions: Vec<ConditionsChange>,
+}
+
+/// Measurements for one link over the whole simulation.
+#[derive(Clone, Debug, Default)]
+pub struct LinkStats {
+    /// How many vehicles entered the link
+    pub volume: usize,
+    /// Summed over every vehicle that's left the link, including time spent queueing
+    pub total_travel_time: Duration,
+    /// How many vehicles have left the link
+    pub exits: usize,
+}
+
+impl LinkStats {
+    pub fn mean_travel_time(&self) -> Option<Duration> {
+        if self.exits == 0 {
+            None
+        } else {
+            Some(self.total_travel_time / (self.exits as f64))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Item {
+    StartTrip(usize),
+    UpdateLink(DirectedRoadID),
+    Advance(usize),
+}
+
+struct MesoTrip {
+    id: TripID,
+    person: PersonID,
+    agent: AgentID,
+    mode: TripMode,
+    depart: Time,
+    origin: TripEndpoint,
+    destination: TripEndpoint,
+    cancelled: bool,
+    /// For walking and biking
+    max_speed: Option<Speed>,
+    /// For driving
+    speed_factor: f64,
+    /// For biking
+    cyclist_comfort: Option<CyclistComfort>,
+    /// The same person's previous trip
+    prev: Option<usize>,
+
+    legs: Vec<Leg>,
+    /// The index into legs that'll be entered next
+    next_leg: usize,
+    finished: bool,
+    /// Waiting for the previous trip to finish before starting
+    deferred: bool,
+    blocked_time: Duration,
+}
+
+/// The path is grouped into a lane and the turn leading to it, if any.
+struct Leg {
+    turn: Option<TurnID>,
+    lane: LaneID,
+    /// How long it takes to cross the turn and lane with no traffic
+    free_time: Duration,
+}
+
+struct Link {
+    /// How many vehicles fit on the link
+    storage: usize,
+    /// The minimum time between vehicles leaving
+    headway: Duration,
+    /// Vehicles on the link, in the order they entered. (When the vehicle's travel time elapses,
+    /// when it entered, the trip)
+    vehicles: VecDeque<(Time, Time, usize)>,
+    next_departure: Time,
+    /// Upstream links with a vehicle waiting for room here
+    blocked_upstream: BTreeSet<DirectedRoadID>,
+    /// The next UpdateLink event that's actually live. Any others in the queue are stale.
+    scheduled: Option<Time>,
+}
+
+impl Link {
+    fn new(dr: DirectedRoadID, map: &Map) -> Link {
+        let lanes = dr.lanes(PathConstraints::Car, map).len().max(1);
+        let length = map.get_r(dr.road).length();
+        let storage = ((lanes as f64) * (length / VEHICLE_SPACING)).floor() as usize;
+        let mut headway = Duration::seconds(1.0 / (SATURATION_FLOW_PER_LANE * lanes as f64));
+        // Assume roughly half of each cycle is green
+        if map.get_i(dr.dst_i(map)).is_traffic_signal() {
+            headway = 2.0 * headway;
+        }
+        Link {
+            storage: storage.max(1),
+            headway,
+            vehicles: VecDeque::new(),
+            next_departure: Time::START_OF_DAY,
+            blocked_upstream: BTreeSet::new(),
+            scheduled: None,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.vehicles.len() >= self.storage
+    }
+}
+
+impl MesoSim {
+    pub fn new(scenario: &Scenario, opts: &SimOptions) -> MesoSim {
+        let mut analytics = Analytics::new(!opts.skip_analytics, opts.electric_car_share);
+        analytics.disable_conflict_detection();
+
+        let mut conditions = match opts.conditions {
+            Some(conditions) => vec![ConditionsChange {
+                time: Time::START_OF_DAY,
+                conditions,
+            }],
+            None => scenario.conditions.clone(),
+        };
+        conditions.sort_by_key(|change| change.time);
+        for change in &conditions {
+            analytics.record_conditions(change.time, change.conditions);
+        }
+
+        let avg_bike_speed = 0.5 * (Speed::miles_per_hour(8.0) + map_model::MAX_BIKE_SPEED);
+        let avg_walking_speed = 0.5 * (Speed::miles_per_hour(2.0) + map_model::MAX_WALKING_SPEED);
+
+        // People and trips get the same IDs as in the normal simulation, so results can be
+        // compared per trip. Vehicles don't match, though: every driving or biking trip gets a new
+        // CarID here, while the normal simulation gives each person their own vehicles and also
+        // runs buses.
+        let mut trips = Vec::new();
+        let mut queue = BinaryHeap::new();
+        let mut next_car = 0;
+        for (person_idx, person) in scenario.people.iter().enumerate() {
+            let mut prev = None;
+            for trip in &person.trips {
+                let agent = match trip.mode {
+                    TripMode::Drive | TripMode::Bike => {
+                        next_car += 1;
+                        AgentID::Car(CarID {
+                            id: next_car - 1,
+                            vehicle_type: if trip.mode == TripMode::Drive {
+                                VehicleType::Car
+                            } else {
+                                VehicleType::Bike
+                            },
+                        })
+                    }
+                    TripMode::Walk | TripMode::Transit => {
+                        AgentID::Pedestrian(PedestrianID(person_idx))
+                    }
+                };
+                let max_speed = match trip.mode {
+                    TripMode::Drive => None,
+                    TripMode::Bike => Some(avg_bike_speed),
+                    TripMode::Walk | TripMode::Transit => Some(
+                        person
+                            .behavior
+                            .as_ref()
+                            .map(|b| b.walking_speed)
+                            .unwrap_or(avg_walking_speed),
+                    ),
+                };
+
+                let idx = trips.len();
+                queue.push(Reverse((trip.depart, Item::StartTrip(idx))));
+                trips.push(MesoTrip {
+                    id: TripID(idx),
+                    person: PersonID(person_idx),
+                    agent,
+                    mode: trip.mode,
+                    depart: trip.depart,
+                    origin: trip.origin,
+                    destination: trip.destination,
+                    cancelled: trip.cancelled,
+                    max_speed,
+                    speed_factor: person
+                        .behavior
+                        .as_ref()
+                        .map(|b| b.desired_speed_factor)
+                        .unwrap_or(1.0),
+                    cyclist_comfort: person.behavior.as_ref().map(|b| b.cyclist_comfort),
+                    prev,
+
+                    legs: Vec::new(),
+                    next_leg: 0,
+                    finished: false,
+                    deferred: false,
+                    blocked_time: Duration::ZERO,
+                });
+                prev = Some(idx);
+            }
+        }
+
+        MesoSim {
+            time: Time::START_OF_DAY,
+            trips,
+            links: BTreeMap::new(),
+            link_stats: BTreeMap::new(),
+            queue,
+            analytics,
+            jaywalking: opts.jaywalking,
+            conditions,
+        }
+    }
+
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    pub fn get_analytics(&self) -> &Analytics {
+        &self.analytics
+    }
+
+    pub fn link_stats(&self) -> &BTreeMap<DirectedRoadID, LinkStats> {
+        &self.link_stats
+    }
+
+    /// How many trips have finished or been cancelled so far, out of the total
+    pub fn num_trips_done(&self) -> (usize, usize) {
+        (
+            self.trips.iter().filter(|t| t.finished).count(),
+            self.trips.len(),
+        )
+    }
+
+    /// Simulate everything happening up to and including the given time.
+    pub fn run_until(&mut self, map: &Map, until: Time, timer: &mut Timer) {
+        timer.start(format!("simulate until {}", until));
+        while let Some(Reverse((time, item))) = self.queue.peek().cloned() {
+            if time > until {
+                break;
+            }
+            self.queue.pop();
+            self.time = time;
+            match item {
+                Item::StartTrip(idx) => self.start_trip(idx, map),
+                Item::UpdateLink(dr) => self.update_link(dr, map),
+                Item::Advance(idx) => self.advance(idx, map),
+            }
+        }
+        self.time = until;
+        timer.stop(format!("simulate until {}", until));
+    }
+
+    fn event(&mut self, ev: Event, map: &Map) {
+        self.analytics.event(ev, self.time, map);
+    }
+
+    fn start_trip(&mut self, idx: usize, map: &Map) {
+        // Like the normal simulation, a person can only do one trip at a time
+        if let Some(prev) = self.trips[idx].prev {
+            if !self.trips[prev].finished {
+                self.trips[idx].deferred = true;
+                return;
+            }
+        }
+
+        let trip = &self.trips[idx];
+        let (id, mode) = (trip.id, trip.mode);
+        if trip.cancelled {
+            self.cancel_trip(idx, map);
+            return;
+        }
+        let req = match TripEndpoint::path_req(trip.origin, trip.destination, trip.mode, map) {
+            Some(req) => req,
+            None => {
+                warn!("{} has no valid start or end for {:?}", id, mode);
+                self.cancel_trip(idx, map);
+                return;
+            }
+        };
+        let path =
+            match crate::trips::pathfind(req.clone(), trip.cyclist_comfort, self.jaywalking, map) {
+                Ok(path) => path,
+                Err(err) => {
+                    warn!("{} couldn't find a path: {}", id, err);
+                    self.cancel_trip(idx, map);
+                    return;
+                }
+            };
+
+        let conditions = conditions_at(&self.conditions, self.time);
+        let speed_factor = match mode {
+            TripMode::Drive => trip.speed_factor * conditions.speed_factor(mode),
+            TripMode::Bike => conditions.speed_factor(mode),
+            // Transit trips are walked
+            TripMode::Walk | TripMode::Transit => conditions.speed_factor(TripMode::Walk),
+        };
+        let legs = make_legs(&path, trip.max_speed, speed_factor, map);
+        self.analytics.record_demand(&path, map);
+
+        let trip = &mut self.trips[idx];
+        trip.legs = legs;
+        let (person, agent, origin) = (trip.person, trip.agent, trip.origin);
+        if let TripEndpoint::Border(i) = origin {
+            self.event(Event::PersonEntersMap(person, agent, i), map);
+        }
+        let phase = match mode {
+            TripMode::Drive => TripPhaseType::Driving,
+            TripMode::Bike => TripPhaseType::Biking,
+            TripMode::Walk | TripMode::Transit => TripPhaseType::Walking,
+        };
+        self.event(Event::TripPhaseStarting(id, person, Some(req), phase), map);
+
+        if mode == TripMode::Drive {
+            // Vehicles leaving a driveway or border are always let onto their first link, even
+            // if it's full.
+            self.enter_link(idx, map);
+        } else {
+            self.advance(idx, map);
+        }
+    }
+
+    /// Walking and biking trips move to their next leg, or finish.
+    fn advance(&mut self, idx: usize, map: &Map) {
+        let trip = &mut self.trips[idx];
+        let (turn, lane, free_time) = match trip.legs.get(trip.next_leg) {
+            Some(leg) => (leg.turn, leg.lane, leg.free_time),
+            None => {
+                self.finish_trip(idx, map);
+                return;
+            }
+        };
+        trip.next_leg += 1;
+        self.enter_leg(idx, turn, lane, map);
+        self.queue
+            .push(Reverse((self.time + free_time, Item::Advance(idx))));
+    }
+
+    /// A vehicle enters its next leg, which is on a different link.
+    fn enter_link(&mut self, idx: usize, map: &Map) {
+        let trip = &mut self.trips[idx];
+        let leg = &trip.legs[trip.next_leg];
+        let (turn, lane, free_time) = (leg.turn, leg.lane, leg.free_time);
+        trip.next_leg += 1;
+        self.enter_leg(idx, turn, lane, map);
+
+        let dr = map.get_l(lane).get_directed_parent();
+        let now = self.time;
+        let link = self.links.entry(dr).or_insert_with(|| Link::new(dr, map));
+        // Greenshields: speed drops linearly as density approaches the jam density
+        let density = (link.vehicles.len() as f64) / (link.storage as f64);
+        let travel_time = free_time / (1.0 - density).max(MIN_SPEED_FRACTION);
+        link.vehicles.push_back((now + travel_time, now, idx));
+        let first = link.vehicles.len() == 1;
+        let next_departure = link.next_departure;
+        self.link_stats.entry(dr).or_default().volume += 1;
+        if first {
+            self.schedule_link(dr, (now + travel_time).max(next_departure));
+        }
+    }
+
+    fn enter_leg(&mut self, idx: usize, turn: Option<TurnID>, lane: LaneID, map: &Map) {
+        let trip = &self.trips[idx];
+        let (agent, id) = (trip.agent, trip.id);
+        if let Some(t) = turn {
+            self.event(
+                Event::AgentEntersTraversable(agent, Some(id), Traversable::Turn(t), None),
+                map,
+            );
+        }
+        self.event(
+            Event::AgentEntersTraversable(agent, Some(id), Traversable::Lane(lane), None),
+            map,
+        );
+    }
+
+    fn schedule_link(&mut self, dr: DirectedRoadID, time: Time) {
+        let link = self.links.get_mut(&dr).unwrap();
+        if link.scheduled.map(|t| time < t).unwrap_or(true) {
+            link.scheduled = Some(time);
+            self.queue.push(Reverse((time, Item::UpdateLink(dr))));
+        }
+    }
+
+    /// Let as many vehicles as possible leave the link.
+    fn update_link(&mut self, dr: DirectedRoadID, map: &Map) {
+        let now = self.time;
+        let link = self.links.get_mut(&dr).unwrap();
+        if link.scheduled != Some(now) {
+            return;
+        }
+        link.scheduled = None;
+
+        loop {
+            let link = &self.links[&dr];
+            let (ready, entered, idx) = match link.vehicles.front() {
+                Some(x) => *x,
+                None => break,
+            };
+            let wait_until = ready.max(link.next_departure);
+            if wait_until > now {
+                self.schedule_link(dr, wait_until);
+                break;
+            }
+
+            let trip = &self.trips[idx];
+            let next = trip.legs.get(trip.next_leg).map(|leg| (leg.turn, leg.lane));
+            if let Some((_, lane)) = next {
+                let to = map.get_l(lane).get_directed_parent();
+                if let Some(downstream) = self.links.get_mut(&to) {
+                    if downstream.is_full() && now - ready < STUCK_TIME {
+                        downstream.blocked_upstream.insert(dr);
+                        self.schedule_link(dr, ready + STUCK_TIME);
+                        break;
+                    }
+                }
+            }
+
+            let link = self.links.get_mut(&dr).unwrap();
+            link.vehicles.pop_front();
+            link.next_departure = now + link.headway;
+            let upstream = std::mem::take(&mut link.blocked_upstream);
+            for u in upstream {
+                self.schedule_link(u, now);
+            }
+            let stats = self.link_stats.get_mut(&dr).unwrap();
+            stats.exits += 1;
+            stats.total_travel_time += now - entered;
+
+            let delay = now - ready;
+            let trip = &mut self.trips[idx];
+            trip.blocked_time += delay;
+            let (id, agent) = (trip.id, trip.agent);
+            match next {
+                Some((turn, _)) => {
+                    if let Some(t) = turn {
+                        if delay > Duration::ZERO {
+                            self.event(Event::IntersectionDelayMeasured(id, t, agent, delay), map);
+                        }
+                    }
+                    self.enter_link(idx, map);
+                }
+                None => {
+                    self.finish_trip(idx, map);
+                }
+            }
+        }
+    }
+
+    fn finish_trip(&mut self, idx: usize, map: &Map) {
+        let trip = &mut self.trips[idx];
+        trip.finished = true;
+        trip.legs = Vec::new();
+        let (id, person, agent, mode, destination) = (
+            trip.id,
+            trip.person,
+            trip.agent,
+            trip.mode,
+            trip.destination,
+        );
+        let total_time = self.time - trip.depart;
+        let blocked_time = trip.blocked_time;
+        if let TripEndpoint::Border(i) = destination {
+            self.event(Event::PersonLeavesMap(person, Some(agent), i), map);
+        }
+        self.event(
+            Event::TripFinished {
+                trip: id,
+                mode,
+                total_time,
+                blocked_time,
+            },
+            map,
+        );
+        self.start_deferred(idx, map);
+    }
+
+    fn cancel_trip(&mut self, idx: usize, map: &Map) {
+        let trip = &mut self.trips[idx];
+        trip.finished = true;
+        let (id, mode) = (trip.id, trip.mode);
+        self.event(Event::TripCancelled(id, mode), map);
+        self.start_deferred(idx, map);
+    }
+
+    fn start_deferred(&mut self, idx: usize, map: &Map) {
+        if let Some(next) = self.trips.get(idx + 1) {
+            if next.prev == Some(idx) && next.deferred {
+                self.trips[idx + 1].deferred = false;
+                self.start_trip(idx + 1, map);
+            }
+        }
+    }
+}
+
+fn make_legs(path: &Path, max_speed: Option<Speed>, speed_factor: f64, map: &Map) -> Vec<Leg> {
+    let constraints = path.get_req().constraints;
+    let mut legs = Vec::new();
+    let mut turn = None;
+    let mut turn_time = Duration::ZERO;
+    for step in path.get_steps() {
+        let speed = speed_factor * step.max_speed_along(max_speed, constraints, map);
+        let time = path.dist_crossed_from_step(map, step) / speed;
+        match step {
+            PathStep::Turn(t) | PathStep::ContraflowTurn(t) => {
+                turn = Some(*t);
+                turn_time = time;
+            }
+            PathStep::Lane(l) | PathStep::ContraflowLane(l) => {
+                legs.push(Leg {
+                    turn: turn.take(),
+                    lane: *l,
+                    free_time: turn_time + time,
+                });
+                turn_time = Duration::ZERO;
+            }
+        }
+    }
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use synthpop::{IndividTrip, PersonSpec, TripPurpose};
+
+    use super::*;
+    use crate::testing::import_map;
+
+    /// Everybody drives between the same two borders at the same time. Returns the stats for the
+    /// first link on the path, the headway of that link, and when the last trip finished.
+    fn drive_between_borders(map: &Map, num_cars: usize) -> (LinkStats, Duration, Time) {
+        let borders: Vec<TripEndpoint> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| i.is_border())
+            .map(|i| TripEndpoint::Border(i.id))
+            .collect();
+        let (origin, destination, path) = borders
+            .iter()
+            .flat_map(|a| borders.iter().map(move |b| (*a, *b)))
+            .find_map(|(a, b)| {
+                let req = TripEndpoint::path_req(a, b, TripMode::Drive, map)?;
+                let path = map.pathfind(req).ok()?;
+                Some((a, b, path))
+            })
+            .unwrap();
+        let first_link = match path.get_steps()[0] {
+            PathStep::Lane(l) => map.get_l(l).get_directed_parent(),
+            _ => unreachable!(),
+        };
+
+        let mut scenario = Scenario::empty(map, "queueing");
+        for _ in 0..num_cars {
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                trips: vec![IndividTrip::new(
+                    Time::START_OF_DAY,
+                    TripPurpose::Shopping,
+                    origin,
+                    destination,
+                    TripMode::Drive,
+                )],
+                behavior: None,
+            });
+        }
+        let mut sim = MesoSim::new(&scenario, &SimOptions::new("queueing"));
+        sim.run_until(
+            map,
+            Time::START_OF_DAY + Duration::hours(1),
+            &mut Timer::throwaway(),
+        );
+        assert_eq!(sim.num_trips_done(), (num_cars, num_cars));
+        let last_finish = sim
+            .get_analytics()
+            .finished_trips
+            .iter()
+            .map(|(t, _, _, _)| *t)
+            .max()
+            .unwrap();
+        (
+            sim.link_stats()[&first_link].clone(),
+            Link::new(first_link, map).headway,
+            last_finish,
+        )
+    }
+
+    #[test]
+    fn test_link_travel_time() {
+        let map = import_map();
+        let (stats, _, _) = drive_between_borders(&map, 1);
+        assert_eq!(stats.volume, 1);
+        assert_eq!(stats.exits, 1);
+        assert!(stats.mean_travel_time().unwrap() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_capacity_queueing() {
+        let map = import_map();
+        let (alone, _, _) = drive_between_borders(&map, 1);
+        let num_cars = 50;
+        let (crowded, headway, last_finish) = drive_between_borders(&map, num_cars);
+        assert_eq!(crowded.volume, num_cars);
+        // The link fills up, so everyone after the first few is slowed down
+        assert!(crowded.mean_travel_time().unwrap() > alone.mean_travel_time().unwrap());
+        // Vehicles can't leave the link any faster than its outflow rate
+        assert!(last_finish - Time::START_OF_DAY >= (num_cars - 1) as f64 * headway);
+    }
+}
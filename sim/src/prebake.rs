@@ -1,8 +1,10 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
 
-use crate::{AlertHandler, Sim, SimFlags, SimOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::{AlertHandler, Sim, SimFlags, SimOptions, TripID};
 use abstutil::{prettyprint_usize, Timer};
-use geom::{Duration, Time};
+use geom::{Duration, SampleSummary, Time};
 use map_model::Map;
 use synthpop::Scenario;
 
@@ -15,21 +17,7 @@ pub fn prebake(map: &Map, scenario: Scenario, timer: &mut Timer) -> PrebakeSumma
         scenario.scenario_name
     ));
 
-    let mut opts = SimOptions::new("prebaked");
-    opts.alerts = AlertHandler::Silence;
-    let mut sim = Sim::new(map, opts);
-    // Bit of an abuse of this, but just need to fix the rng seed.
-    let mut rng = SimFlags::for_test("prebaked").make_rng();
-    sim.instantiate(&scenario, map, &mut rng, timer);
-
-    // Run until a few hours after the end of the day. Some trips start close to midnight, and we
-    // want prebaked data for them too.
-    sim.timed_step(
-        map,
-        sim.get_end_of_day() - Time::START_OF_DAY + Duration::hours(3),
-        &mut None,
-        timer,
-    );
+    let sim = simulate_full_day(map, &scenario, timer);
     abstio::write_binary(
         abstio::path_prebaked_results(&scenario.map_name, &scenario.scenario_name),
         sim.get_analytics(),
@@ -53,6 +41,127 @@ pub fn prebake(map: &Map, scenario: Scenario, timer: &mut Timer) -> PrebakeSumma
     PrebakeSummary::new(&sim, &scenario)
 }
 
+/// Simulate a scenario the same way prebaked results are produced, with a fixed RNG seed, until a
+/// few hours after the end of the day. Some trips start close to midnight, and we want results for
+/// them too.
+pub fn simulate_full_day(map: &Map, scenario: &Scenario, timer: &mut Timer) -> Sim {
+    simulate_full_day_with_seed(map, scenario, SimFlags::RNG_SEED, timer)
+}
+
+/// Like `simulate_full_day`, but with a different RNG seed.
+pub fn simulate_full_day_with_seed(
+    map: &Map,
+    scenario: &Scenario,
+    rng_seed: u64,
+    timer: &mut Timer,
+) -> Sim {
+    let mut opts = SimOptions::new("prebaked");
+    opts.alerts = AlertHandler::Silence;
+    let mut sim = Sim::new(map, opts);
+    // Bit of an abuse of this, but just need to fix the rng seed.
+    let mut flags = SimFlags::for_test("prebaked");
+    flags.rng_seed = rng_seed;
+    let mut rng = flags.make_rng();
+    sim.instantiate(scenario, map, &mut rng, timer);
+    let dt = sim.get_end_of_day() - Time::START_OF_DAY + Duration::hours(3);
+    if scenario.incidents.is_empty() {
+        sim.timed_step(map, dt, &mut None, timer);
+    } else {
+        // Incidents temporarily edit the map
+        let mut map = map.clone();
+        sim.timed_step_with_incidents(&mut map, dt, &mut None, timer);
+    }
+    sim
+}
+
+/// Simulate a scenario with several RNG seeds in parallel, and save the trip times from each run.
+/// The first seed is the one used for regular prebaked results. Randomness affects things like
+/// vehicle lengths and parking choices, so this shows how much results vary between runs.
+pub fn prebake_multiple_seeds(
+    map: &Map,
+    scenario: &Scenario,
+    num_seeds: usize,
+    timer: &mut Timer,
+) -> MultiSeedResults {
+    let seeds: Vec<u64> = (0..num_seeds as u64)
+        .map(|i| SimFlags::RNG_SEED + i)
+        .collect();
+    let runs = timer.parallelize("simulate with each seed", seeds.clone(), |rng_seed| {
+        let sim = simulate_full_day_with_seed(map, scenario, rng_seed, &mut Timer::throwaway());
+        sim.get_analytics()
+            .finished_trips
+            .iter()
+            .map(|(_, trip, _, maybe_dt)| (*trip, *maybe_dt))
+            .collect::<Vec<_>>()
+    });
+
+    let mut trip_durations = BTreeMap::new();
+    for (idx, run) in runs.into_iter().enumerate() {
+        for (trip, maybe_dt) in run {
+            trip_durations
+                .entry(trip)
+                .or_insert_with(|| vec![None; seeds.len()])[idx] = maybe_dt;
+        }
+    }
+    let results = MultiSeedResults {
+        seeds,
+        trip_durations,
+    };
+    abstio::write_binary(
+        abstio::path_prebaked_multi_seed_results(&scenario.map_name, &scenario.scenario_name),
+        &results,
+    );
+    results
+}
+
+/// Trip times from simulating the same scenario with different RNG seeds. A single run compared
+/// against a single baseline can't tell a real improvement from noise; this can.
+#[derive(Serialize, Deserialize)]
+pub struct MultiSeedResults {
+    pub seeds: Vec<u64>,
+    /// The duration of every trip in each run, in the same order as `seeds`. `None` means the trip
+    /// was cancelled or never finished in that run.
+    pub trip_durations: BTreeMap<TripID, Vec<Option<Duration>>>,
+}
+
+impl MultiSeedResults {
+    /// How long one trip takes, in seconds, across the runs where it finished.
+    pub fn trip_duration(&self, trip: TripID) -> Option<SampleSummary> {
+        let samples: Vec<f64> = self
+            .trip_durations
+            .get(&trip)?
+            .iter()
+            .flatten()
+            .map(|dt| dt.inner_seconds())
+            .collect();
+        SampleSummary::new(&samples)
+    }
+
+    /// The average duration of some trips in seconds, calculated separately for every run and
+    /// then summarized across runs.
+    pub fn mean_duration<I: IntoIterator<Item = TripID>>(&self, trips: I) -> Option<SampleSummary> {
+        let mut sums = vec![0.0; self.seeds.len()];
+        let mut counts = vec![0; self.seeds.len()];
+        for trip in trips {
+            if let Some(durations) = self.trip_durations.get(&trip) {
+                for (idx, maybe_dt) in durations.iter().enumerate() {
+                    if let Some(dt) = maybe_dt {
+                        sums[idx] += dt.inner_seconds();
+                        counts[idx] += 1;
+                    }
+                }
+            }
+        }
+        let samples: Vec<f64> = sums
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|(sum, count)| sum / (count as f64))
+            .collect();
+        SampleSummary::new(&samples)
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct PrebakeSummary {
     pub map: String,
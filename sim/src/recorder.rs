@@ -69,6 +69,7 @@ impl TrafficRecorder {
             people.push(PersonSpec {
                 orig_id: None,
                 trips: vec![trip],
+                behavior: None,
             });
         }
         Scenario {
@@ -76,6 +77,8 @@ impl TrafficRecorder {
             map_name: map.get_name().clone(),
             people,
             only_seed_buses: None,
+            incidents: Vec::new(),
+            conditions: Vec::new(),
         }
         .save();
     }
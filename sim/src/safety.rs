@@ -0,0 +1,370 @@
+//! Surrogate safety measures. Collisions never happen in the simulation, but agents passing
+//! through the same spot in an intersection very close together in time are a predictor of where
+//! real crashes are likely.
+//!
+//! Agents aren't tracked continuously through intersections. Instead, once an agent finishes a
+//! turn, its time entering and leaving the turn is compared against other agents that recently
+//! crossed conflicting turns, assuming everybody moves at a constant speed through the turn.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, PolyLine, Pt2D, Speed, Time};
+use map_model::{IntersectionID, Map, Traversable, TurnID};
+
+use crate::{
+    AgentID, AgentType, TripID, VehicleType, BIKE_LENGTH, BUS_LENGTH, LIGHT_RAIL_LENGTH,
+    MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+};
+
+/// Two agents following each other through a conflict point more closely than this is a conflict.
+const MAX_POST_ENCROACHMENT_TIME: Duration = Duration::const_seconds(2.0);
+/// How long to remember agents that finished a turn. Slow pedestrians might take longer than this
+/// to cross a wide road, but conflicts with them are caught when they finish.
+const LOOKBACK: Duration = Duration::const_seconds(60.0);
+/// The space a pedestrian takes up along their path
+const PEDESTRIAN_LENGTH: Distance = Distance::const_meters(0.5);
+
+/// A near-miss between two agents at an intersection.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SafetyConflict {
+    pub intersection: IntersectionID,
+    /// Where the paths of both agents cross or merge
+    pub pt: Pt2D,
+    /// The agent who reached the conflict point first, then the one who reached it second
+    pub agent_types: (AgentType, AgentType),
+    /// From the first agent clearing the conflict point to the second reaching it
+    pub post_encroachment_time: Duration,
+    /// Only set when the second agent started their turn before the first agent cleared the
+    /// conflict point. How soon they would've collided if the first agent had stopped there.
+    pub time_to_collision: Option<Duration>,
+    pub severity: ConflictSeverity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ConflictSeverity {
+    Minor,
+    Moderate,
+    Serious,
+}
+
+impl ConflictSeverity {
+    fn new(post_encroachment_time: Duration, time_to_collision: Option<Duration>) -> Self {
+        let ttc = time_to_collision.unwrap_or(Duration::hours(1));
+        if post_encroachment_time < Duration::seconds(1.0) || ttc < Duration::seconds(1.5) {
+            ConflictSeverity::Serious
+        } else if post_encroachment_time < Duration::seconds(1.5) || ttc < Duration::seconds(3.0) {
+            ConflictSeverity::Moderate
+        } else {
+            ConflictSeverity::Minor
+        }
+    }
+}
+
+/// Watches agents moving through intersections. This is transient state that Analytics keeps
+/// while the simulation runs.
+#[derive(Clone, Default)]
+pub(crate) struct ConflictDetector {
+    /// Agents in the middle of a turn, since when
+    in_turn: BTreeMap<AgentID, (TurnID, Option<TripID>, Time)>,
+    /// Per intersection, agents that recently finished a turn, oldest first
+    recent: BTreeMap<IntersectionID, VecDeque<Crossing>>,
+}
+
+#[derive(Clone)]
+struct Crossing {
+    agent: AgentID,
+    trip: Option<TripID>,
+    turn: TurnID,
+    entered: Time,
+    exited: Time,
+}
+
+impl ConflictDetector {
+    /// Call whenever an agent enters a lane or turn. Returns any conflicts found, along with the
+    /// trips of both agents involved.
+    pub fn agent_enters(
+        &mut self,
+        agent: AgentID,
+        trip: Option<TripID>,
+        to: Traversable,
+        time: Time,
+        map: &Map,
+    ) -> Vec<(Option<TripID>, Option<TripID>, SafetyConflict)> {
+        let mut conflicts = Vec::new();
+        if let AgentID::BusPassenger(_, _) = agent {
+            return conflicts;
+        }
+
+        if let Some((turn, trip, entered)) = self.in_turn.remove(&agent) {
+            let crossing = Crossing {
+                agent,
+                trip,
+                turn,
+                entered,
+                exited: time,
+            };
+            let recent = self.recent.entry(turn.parent).or_default();
+            while recent
+                .front()
+                .map(|x| time - x.exited > LOOKBACK)
+                .unwrap_or(false)
+            {
+                recent.pop_front();
+            }
+            for other in recent.iter() {
+                if other.agent == agent {
+                    continue;
+                }
+                if let Some((first, second, conflict)) = measure(other, &crossing, map) {
+                    conflicts.push((first.trip, second.trip, conflict));
+                }
+            }
+            recent.push_back(crossing);
+        }
+
+        if let Traversable::Turn(t) = to {
+            self.in_turn.insert(agent, (t, trip, time));
+        }
+        conflicts
+    }
+
+    /// Live map edits might delete turns that agents recently crossed or are in the middle of.
+    pub fn forget_deleted_turns(&mut self, map: &Map) {
+        self.in_turn
+            .retain(|_, (turn, _, _)| map.maybe_get_t(*turn).is_some());
+        for recent in self.recent.values_mut() {
+            recent.retain(|x| map.maybe_get_t(x.turn).is_some());
+        }
+        self.recent.retain(|_, recent| !recent.is_empty());
+    }
+}
+
+/// When an agent was at the conflict point
+struct Occupancy<'a> {
+    crossing: &'a Crossing,
+    arrived: Time,
+    cleared: Time,
+}
+
+/// Returns the crossing that reached the conflict point first, then second, and the conflict, if
+/// their turns conflict and they came close enough. If live edits deleted either turn, there's
+/// nothing to compare.
+fn measure<'a>(
+    c1: &'a Crossing,
+    c2: &'a Crossing,
+    map: &Map,
+) -> Option<(&'a Crossing, &'a Crossing, SafetyConflict)> {
+    let turn1 = map.maybe_get_t(c1.turn)?;
+    let turn2 = map.maybe_get_t(c2.turn)?;
+    if !turn1.conflicts_with(turn2) {
+        return None;
+    }
+    let geom1 = &turn1.geom;
+    let geom2 = &turn2.geom;
+    let (pt, dist1, dist2) = if geom1.last_pt() == geom2.last_pt() {
+        // Merging into the same lane
+        (geom1.last_pt(), geom1.length(), geom2.length())
+    } else {
+        let (pt, _) = geom1.intersection(geom2)?;
+        let dist_along = |geom: &PolyLine| {
+            geom.dist_along_of_point(pt)
+                .map(|(dist, _)| dist)
+                .unwrap_or_else(|| geom.length() / 2.0)
+        };
+        (pt, dist_along(geom1), dist_along(geom2))
+    };
+
+    let mut occupancy = vec![
+        occupy(c1, geom1.length(), dist1),
+        occupy(c2, geom2.length(), dist2),
+    ];
+    occupancy.sort_by_key(|o| o.arrived);
+    let second = occupancy.pop().unwrap();
+    let first = occupancy.pop().unwrap();
+
+    // The simulation shouldn't let agents overlap, but the constant speed assumption might
+    let post_encroachment_time = (second.arrived - first.cleared).max(Duration::ZERO);
+    if post_encroachment_time >= MAX_POST_ENCROACHMENT_TIME {
+        return None;
+    }
+    let time_to_collision = if second.crossing.entered < first.cleared {
+        Some(second.arrived - second.crossing.entered)
+    } else {
+        None
+    };
+
+    Some((
+        first.crossing,
+        second.crossing,
+        SafetyConflict {
+            intersection: c1.turn.parent,
+            pt,
+            agent_types: (
+                first.crossing.agent.to_type(),
+                second.crossing.agent.to_type(),
+            ),
+            post_encroachment_time,
+            time_to_collision,
+            severity: ConflictSeverity::new(post_encroachment_time, time_to_collision),
+        },
+    ))
+}
+
+fn occupy(crossing: &Crossing, turn_length: Distance, dist_to_conflict: Distance) -> Occupancy {
+    let dt = crossing.exited - crossing.entered;
+    if dt == Duration::ZERO || turn_length == Distance::ZERO {
+        return Occupancy {
+            crossing,
+            arrived: crossing.entered,
+            cleared: crossing.entered,
+        };
+    }
+    let speed = Speed::from_dist_time(turn_length, dt);
+    let arrived = crossing.entered + dt * (dist_to_conflict / turn_length);
+    let cleared = if speed == Speed::ZERO {
+        crossing.exited
+    } else {
+        arrived + agent_length(crossing.agent) / speed
+    };
+    Occupancy {
+        crossing,
+        arrived,
+        cleared,
+    }
+}
+
+fn agent_length(agent: AgentID) -> Distance {
+    match agent {
+        AgentID::Car(car) => match car.vehicle_type {
+            // Lengths vary, so take the average
+            VehicleType::Car => (MIN_CAR_LENGTH + MAX_CAR_LENGTH) / 2.0,
+            VehicleType::Bus => BUS_LENGTH,
+            VehicleType::Train => LIGHT_RAIL_LENGTH,
+            VehicleType::Bike => BIKE_LENGTH,
+        },
+        AgentID::Pedestrian(_) | AgentID::BusPassenger(_, _) => PEDESTRIAN_LENGTH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Timer;
+    use map_model::PathStep;
+    use synthpop::{
+        Incident, IndividTrip, PersonSpec, Scenario, ScheduledIncident, TripEndpoint, TripMode,
+        TripPurpose,
+    };
+
+    use super::*;
+    use crate::testing::import_map;
+    use crate::{AlertHandler, PedestrianID, Sim, SimFlags, SimOptions};
+
+    /// Everybody drives between the same two borders, and partway through, a road they use is
+    /// closed. The turns they recently crossed into that road disappear.
+    #[test]
+    fn test_close_road_mid_sim() {
+        let mut map = import_map();
+        let borders: Vec<TripEndpoint> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| i.is_border())
+            .map(|i| TripEndpoint::Border(i.id))
+            .collect();
+        let (origin, destination, closed_road) = borders
+            .iter()
+            .flat_map(|a| borders.iter().map(move |b| (*a, *b)))
+            .find_map(|(a, b)| {
+                let req = TripEndpoint::path_req(a, b, TripMode::Drive, &map)?;
+                let first_road = req.start.lane().road;
+                let path = map.pathfind(req).ok()?;
+                // The road after the first turn
+                let r = path.get_steps().iter().find_map(|step| match step {
+                    PathStep::Lane(l) if l.road != first_road => Some(l.road),
+                    _ => None,
+                })?;
+                Some((a, b, r))
+            })
+            .unwrap();
+        let turns_into_road: Vec<TurnID> = map
+            .all_turns()
+            .filter(|t| t.id.dst.road == closed_road && !t.between_sidewalks())
+            .map(|t| t.id)
+            .collect();
+        assert!(!turns_into_road.is_empty());
+
+        let mut scenario = Scenario::empty(&map, "road_closure");
+        for i in 0..50 {
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                trips: vec![IndividTrip::new(
+                    Time::START_OF_DAY + Duration::seconds(i as f64),
+                    TripPurpose::Shopping,
+                    origin,
+                    destination,
+                    TripMode::Drive,
+                )],
+                behavior: None,
+            });
+        }
+        let start = Time::START_OF_DAY + Duration::seconds(30.0);
+        let end = Time::START_OF_DAY + Duration::minutes(10);
+        scenario.incidents.push(ScheduledIncident {
+            start,
+            end,
+            incident: Incident::RoadClosure(closed_road),
+        });
+
+        let mut opts = SimOptions::new("road_closure");
+        opts.alerts = AlertHandler::Silence;
+        let mut sim = Sim::new(&map, opts);
+        let mut rng = SimFlags::for_test("road_closure").make_rng();
+        let mut timer = Timer::throwaway();
+        sim.instantiate(&scenario, &map, &mut rng, &mut timer);
+
+        sim.timed_step_with_incidents(
+            &mut map,
+            start + Duration::seconds(1.0) - Time::START_OF_DAY,
+            &mut None,
+            &mut timer,
+        );
+        assert!(sim.get_incidents().iter().all(|(_, active)| *active));
+        assert!(turns_into_road
+            .iter()
+            .all(|t| map.maybe_get_t(*t).is_none()));
+
+        // Agents keep finishing turns at the intersections next to the closed road
+        sim.timed_step_with_incidents(&mut map, Duration::hours(1), &mut None, &mut timer);
+        assert!(sim.get_incidents().iter().all(|(_, active)| !*active));
+        assert!(turns_into_road
+            .iter()
+            .all(|t| map.maybe_get_t(*t).is_some()));
+    }
+
+    #[test]
+    fn test_forget_deleted_turns() {
+        let map = import_map();
+        let turn = map.all_turns().next().unwrap().id;
+        let mut missing = turn;
+        missing.src.offset = 100;
+
+        let mut detector = ConflictDetector::default();
+        for (idx, t) in [turn, missing].into_iter().enumerate() {
+            let agent = AgentID::Pedestrian(PedestrianID(idx));
+            let time = Time::START_OF_DAY + Duration::seconds(idx as f64);
+            detector.agent_enters(agent, None, Traversable::Turn(t), time, &map);
+            detector.agent_enters(
+                agent,
+                None,
+                Traversable::Lane(t.dst),
+                time + Duration::seconds(1.0),
+                &map,
+            );
+            detector.agent_enters(agent, None, Traversable::Turn(t), time, &map);
+        }
+        detector.forget_deleted_turns(&map);
+        assert_eq!(detector.in_turn.len(), 1);
+        assert!(detector.recent.values().flatten().all(|x| x.turn == turn));
+    }
+}
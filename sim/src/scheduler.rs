@@ -27,6 +27,13 @@ pub(crate) enum Command {
     Pandemic(pandemic::Cmd),
     /// The Time is redundant, just used to dedupe commands
     StartBus(TransitRouteID, Time),
+    /// Indexes into the incidents scheduled so far
+    StartIncident(usize),
+    EndIncident(usize),
+    /// Indexes into the changes in weather and daylight scheduled so far
+    ChangeConditions(usize),
+    /// Record every agent's position, then repeat after this long
+    SampleTrajectories(Duration),
 }
 
 impl Command {
@@ -50,6 +57,10 @@ impl Command {
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
             Command::StartBus(r, t) => CommandType::StartBus(*r, *t),
+            Command::StartIncident(idx) => CommandType::StartIncident(*idx),
+            Command::EndIncident(idx) => CommandType::EndIncident(*idx),
+            Command::ChangeConditions(idx) => CommandType::ChangeConditions(*idx),
+            Command::SampleTrajectories(_) => CommandType::SampleTrajectories,
         }
     }
 
@@ -65,6 +76,9 @@ impl Command {
             Command::Callback(_) => SimpleCommandType::Callback,
             Command::Pandemic(_) => SimpleCommandType::Pandemic,
             Command::StartBus(_, _) => SimpleCommandType::StartBus,
+            Command::StartIncident(_) | Command::EndIncident(_) => SimpleCommandType::Incident,
+            Command::ChangeConditions(_) => SimpleCommandType::Conditions,
+            Command::SampleTrajectories(_) => SimpleCommandType::SampleTrajectories,
         }
     }
 }
@@ -81,6 +95,10 @@ enum CommandType {
     Callback,
     Pandemic(pandemic::Cmd),
     StartBus(TransitRouteID, Time),
+    StartIncident(usize),
+    EndIncident(usize),
+    ChangeConditions(usize),
+    SampleTrajectories,
 }
 
 /// A more compressed form of CommandType, just used for keeping stats on event processing.
@@ -94,6 +112,9 @@ enum SimpleCommandType {
     Callback,
     Pandemic,
     StartBus,
+    Incident,
+    Conditions,
+    SampleTrajectories,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -13,20 +13,23 @@ use abstio::{CityName, MapName};
 use abstutil::{prettyprint_usize, serialized_size_bytes, Timer};
 use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints, PathRequest,
-    Position, TransitRoute, Traversable,
+    BuildingID, EditCmd, EditEffects, IntersectionID, LaneID, Map, ParkingLotID, Path,
+    PathConstraints, PathRequest, Position, TransitRoute, Traversable,
+};
+use synthpop::{
+    conditions_at, BehaviorProfile, Conditions, ConditionsChange, OrigPersonID, ScheduledIncident,
 };
-use synthpop::OrigPersonID;
 
 pub use self::queries::{AgentProperties, DelayCause};
 // TODO Super weird for both of these to wind up here
 pub use self::scenario::{count_parked_cars_per_bldg, rand_dist};
 use crate::{
     AgentID, AlertLocation, Analytics, CarID, Command, CreateCar, DrivingSimState, Event,
-    IntersectionSimState, PandemicModel, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot,
-    Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs, TrafficRecorder,
-    TransitSimState, TripID, TripInfo, TripManager, TripPhaseType, Vehicle, VehicleSpec,
-    VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
+    IncidentState, IntersectionSimState, PandemicModel, ParkedCar, ParkingSim, ParkingSimState,
+    ParkingSpot, Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs,
+    TrafficRecorder, Trajectories, TrajectorySampling, TransitSimState, TripID, TripInfo,
+    TripManager, TripPhaseType, Vehicle, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH,
+    LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
 };
 
 mod queries;
@@ -48,6 +51,13 @@ pub struct Sim {
     pandemic: Option<PandemicModel>,
     scheduler: Scheduler,
     time: Time,
+    incidents: IncidentState,
+    /// The current weather and daylight
+    conditions: Conditions,
+    /// Every change to the conditions scheduled so far
+    conditions_timeline: Vec<ConditionsChange>,
+    /// If SimOptions sets the conditions, they never change.
+    fixed_conditions: bool,
 
     // These're needed to load from a savestate.
     pub(crate) map_name: MapName,
@@ -62,6 +72,8 @@ pub struct Sim {
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    #[serde(skip_serializing, skip_deserializing)]
+    trajectories: Option<Trajectories>,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
@@ -127,6 +139,42 @@ pub struct SimOptions {
     /// quickly.
     #[structopt(long)]
     pub skip_analytics: bool,
+    /// The fraction of cars, from 0 to 1, that are electric. This only affects the emissions
+    /// estimated in analytics.
+    #[structopt(long, default_value = "0")]
+    pub electric_car_share: f64,
+    /// Let vehicles overtake slower ones by pulling into the oncoming lane. Otherwise, they can
+    /// only pass using a free lane going the same direction.
+    #[structopt(long)]
+    pub overtake_into_oncoming_traffic: bool,
+    /// When passing a cyclist, drivers try to leave at least this many meters of room sideways. If
+    /// their lane isn't wide enough, they wait for a gap in oncoming traffic.
+    #[structopt(long, default_value = "1.5")]
+    pub min_passing_distance_meters: f64,
+    /// The fraction of drivers, from 0 to 1, who squeeze past a cyclist in their own lane instead
+    /// of waiting for a gap in oncoming traffic.
+    #[structopt(long, default_value = "0")]
+    pub impatient_driver_share: f64,
+    /// Make vehicles speed up and brake gradually, using a profile per vehicle type, instead of
+    /// instantly jumping to the speed limit. Queues at signals discharge more realistically, at the
+    /// cost of slightly slower simulations.
+    #[structopt(long)]
+    pub enable_acceleration: bool,
+    /// Prefetch the first path for trips departing soon using many threads, instead of
+    /// calculating it as each trip starts. Results are identical. This isn't a parallel
+    /// simulation; every event is still handled one at a time, in order, and later paths (like
+    /// rerouting around congestion) are still calculated on one thread.
+    #[structopt(long)]
+    pub prefetch_paths: bool,
+    /// Let pedestrians cross slow, local roads against a red light when there's a gap in traffic,
+    /// and prefer unmarked crossings over these roads when walking.
+    #[structopt(long)]
+    pub jaywalking: bool,
+    /// Use this weather and daylight for the whole simulation, like `rain,night`, ignoring any
+    /// changes in the scenario. Options for weather are dry, rain, snow, and fog; for daylight,
+    /// day, twilight, and night.
+    #[structopt(long, parse(try_from_str = Conditions::parse))]
+    pub conditions: Option<Conditions>,
 }
 
 impl SimOptions {
@@ -143,6 +191,14 @@ impl SimOptions {
             infinite_parking: false,
             disable_turn_conflicts: false,
             skip_analytics: false,
+            electric_car_share: 0.0,
+            overtake_into_oncoming_traffic: false,
+            min_passing_distance_meters: 1.5,
+            impatient_driver_share: 0.0,
+            enable_acceleration: false,
+            prefetch_paths: false,
+            jaywalking: false,
+            conditions: None,
         }
     }
 }
@@ -206,16 +262,20 @@ impl Sim {
             opts.allow_block_the_box = true;
         }
 
-        Sim {
+        let mut sim = Sim {
             driving: DrivingSimState::new(map, &opts),
             parking: ParkingSimState::new(map, opts.infinite_parking, &mut timer),
             walking: WalkingSimState::new(),
             intersections: IntersectionSimState::new(map, &mut scheduler, &opts),
             transit: TransitSimState::new(map),
-            trips: TripManager::new(),
+            trips: TripManager::new(opts.prefetch_paths, opts.jaywalking),
             pandemic: opts.enable_pandemic_model.map(PandemicModel::new),
             scheduler,
             time: Time::START_OF_DAY,
+            incidents: IncidentState::default(),
+            conditions: Conditions::DEFAULT,
+            conditions_timeline: Vec::new(),
+            fixed_conditions: false,
 
             map_name: map.get_name().clone(),
             edits_name: map.get_edits().edits_name.clone(),
@@ -224,9 +284,15 @@ impl Sim {
             highlighted_people: None,
             alerts: opts.alerts,
 
-            analytics: Analytics::new(!opts.skip_analytics),
+            analytics: Analytics::new(!opts.skip_analytics, opts.electric_car_share),
             recorder: None,
+            trajectories: None,
+        };
+        if let Some(conditions) = opts.conditions {
+            sim.change_conditions(conditions);
+            sim.fixed_conditions = true;
         }
+        sim
     }
 
     pub(crate) fn spawn_trips(
@@ -244,6 +310,8 @@ impl Sim {
             if let Some(msg) = info.cancellation_reason {
                 self.trips.cancel_unstarted_trip(trip, msg);
             } else {
+                self.trips
+                    .expect_departure(trip, info.departure, args.clone());
                 self.scheduler
                     .push(info.departure, Command::StartTrip(trip, args));
             }
@@ -256,8 +324,8 @@ impl Sim {
         self.dispatch_events(Vec::new(), map);
     }
 
-    pub fn get_free_onstreet_spots(&self, l: LaneID) -> Vec<ParkingSpot> {
-        self.parking.get_free_onstreet_spots(l)
+    pub fn get_free_onstreet_spots(&self, l: LaneID, map: &Map) -> Vec<ParkingSpot> {
+        self.parking.get_free_onstreet_spots(l, map)
     }
 
     pub fn get_free_offstreet_spots(&self, b: BuildingID) -> Vec<ParkingSpot> {
@@ -287,6 +355,8 @@ impl Sim {
             vehicle_type: VehicleType::Car,
             length: MIN_CAR_LENGTH,
             max_speed: None,
+            speed_factor: 1.0,
+            critical_gap: None,
         };
         let driving_lane = map.find_driving_lane_near_building(b);
 
@@ -316,8 +386,10 @@ impl Sim {
         orig_id: Option<OrigPersonID>,
         ped_speed: Speed,
         vehicle_specs: Vec<VehicleSpec>,
+        behavior: Option<BehaviorProfile>,
     ) -> &Person {
-        self.trips.new_person(orig_id, ped_speed, vehicle_specs)
+        self.trips
+            .new_person(orig_id, ped_speed, vehicle_specs, behavior)
     }
     pub(crate) fn seed_parked_car(&mut self, vehicle: Vehicle, spot: ParkingSpot) {
         self.parking.reserve_spot(spot, vehicle.id);
@@ -349,6 +421,8 @@ impl Sim {
             vehicle_type,
             length,
             max_speed: None,
+            speed_factor: 1.0,
+            critical_gap: None,
         }
         .make(
             CarID {
@@ -406,6 +480,7 @@ impl Sim {
             self.time += max_dt;
             return false;
         };
+        self.trips.prefetch_paths(max_time, map);
 
         let mut halt = false;
         while let Some(time) = self.scheduler.peek_next_time() {
@@ -432,6 +507,7 @@ impl Sim {
         maybe_cb: &mut Option<Box<dyn SimCallback>>,
     ) -> bool {
         self.time = time;
+        self.parking.update_time(time);
         let mut events = Vec::new();
         let mut halt = false;
 
@@ -631,8 +707,35 @@ impl Sim {
                     .unwrap()
                     .handle_cmd(self.time, cmd, &mut self.scheduler);
             }
-            Command::StartBus(r, _) => {
-                self.start_bus(map.get_tr(r), map);
+            Command::StartBus(r, t) => {
+                // Live map edits may have deleted the route or changed its schedule
+                if let Some(route) = map.maybe_get_tr(r) {
+                    if route.spawn_times.contains(&t) {
+                        self.start_bus(route, map);
+                    }
+                }
+            }
+            Command::StartIncident(idx) => {
+                // Only the caller can edit the map, so stop here and let them
+                self.incidents.mark_pending(idx, true);
+                halt = true;
+            }
+            Command::EndIncident(idx) => {
+                self.incidents.mark_pending(idx, false);
+                halt = true;
+            }
+            Command::ChangeConditions(idx) => {
+                self.change_conditions(self.conditions_timeline[idx].conditions);
+            }
+            Command::SampleTrajectories(frequency) => {
+                // The recorder isn't kept in savestates, so this might be left over
+                if self.trajectories.is_some() {
+                    self.sample_all_trajectories(map);
+                    self.scheduler.push(
+                        self.time + frequency,
+                        Command::SampleTrajectories(frequency),
+                    );
+                }
             }
         }
 
@@ -656,6 +759,13 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving);
             }
+            if let Event::AgentEntersTraversable(agent, _, _, _) = ev {
+                if self.trajectories.as_ref().map(|t| t.sampling)
+                    == Some(TrajectorySampling::OnEnteringTraversable)
+                {
+                    self.sample_trajectory(agent, map);
+                }
+            }
 
             self.analytics.event(ev, self.time, map);
         }
@@ -839,9 +949,23 @@ impl Sim {
     /// Respond to arbitrary map edits without resetting the simulation. Returns the number of
     /// (trips cancelled, parked cars displaced).
     pub fn handle_live_edits(&mut self, map: &Map, timer: &mut Timer) -> (usize, usize) {
+        self.handle_live_changes(map, BTreeSet::new(), timer)
+    }
+
+    // Besides everything in the map's edits, treat some more lanes as changed. Temporary edits
+    // aren't recorded in the map's edits.
+    fn handle_live_changes(
+        &mut self,
+        map: &Map,
+        also_changed_lanes: BTreeSet<LaneID>,
+        timer: &mut Timer,
+    ) -> (usize, usize) {
         self.edits_name = map.get_edits().edits_name.clone();
+        self.trips.clear_prefetched_paths();
+        self.analytics.handle_live_edits(map);
 
-        let (affected, num_parked_cars) = self.find_trips_affected_by_live_edits(map, timer);
+        let (affected, num_parked_cars) =
+            self.find_trips_affected_by_live_edits(map, also_changed_lanes, timer);
         let num_trips_cancelled = affected.len();
         let affected_agents: BTreeSet<AgentID> = affected.iter().map(|(a, _)| *a).collect();
 
@@ -888,6 +1012,23 @@ impl Sim {
         self.driving.handle_live_edits(map);
         self.intersections.handle_live_edits(map);
 
+        // Spawn vehicles for new or rescheduled routes. Anything already scheduled is just
+        // scheduled again.
+        let edits = map.get_edits();
+        for id in edits
+            .original_transit_routes
+            .keys()
+            .chain(edits.changed_routes.iter())
+        {
+            if let Some(route) = map.maybe_get_tr(*id) {
+                for t in &route.spawn_times {
+                    if *t > self.time {
+                        self.scheduler.update(*t, Command::StartBus(*id, *t));
+                    }
+                }
+            }
+        }
+
         (num_trips_cancelled, num_parked_cars)
     }
 
@@ -895,6 +1036,7 @@ impl Sim {
     fn find_trips_affected_by_live_edits(
         &mut self,
         map: &Map,
+        also_changed_lanes: BTreeSet<LaneID>,
         timer: &mut Timer,
     ) -> (BTreeSet<(AgentID, TripID)>, usize) {
         let mut affected: BTreeSet<(AgentID, TripID)> = BTreeSet::new();
@@ -903,7 +1045,14 @@ impl Sim {
 
         {
             // Find every active trip whose path crosses a modified lane or intersection
-            let (edited_lanes, _) = map.get_edits().changed_lanes(map);
+            let (mut edited_lanes, _) = map.get_edits().changed_lanes(map);
+            edited_lanes.extend(also_changed_lanes);
+            // Splitting a road shortens its lanes
+            for cmd in map.get_edits().created_roads.values() {
+                if let EditCmd::SplitRoad { r, .. } = cmd {
+                    edited_lanes.extend(map.get_r(*r).lanes.iter().map(|l| l.id));
+                }
+            }
             let mut closed_intersections = HashSet::new();
             for i in map.get_edits().original_intersections.keys() {
                 if map.get_i(*i).is_closed() {
@@ -916,9 +1065,13 @@ impl Sim {
                         .get_steps()
                         .iter()
                         .any(|step| match step.as_traversable() {
-                            Traversable::Lane(l) => edited_lanes.contains(&l),
+                            // Undoing edits can remove roads and intersections
+                            Traversable::Lane(l) => {
+                                edited_lanes.contains(&l) || map.maybe_get_l(l).is_none()
+                            }
                             Traversable::Turn(t) => {
-                                closed_intersections.contains(&t.parent)
+                                map.maybe_get_t(t).is_none()
+                                    || closed_intersections.contains(&t.parent)
                                     || edited_lanes.contains(&t.src)
                                     || edited_lanes.contains(&t.dst)
                             }
@@ -935,6 +1088,12 @@ impl Sim {
             );
         }
 
+        // People waiting for a transit route that changed
+        for ped in self.transit.handle_live_edits(map) {
+            let a = AgentID::Pedestrian(ped);
+            affected.insert((a, self.agent_to_trip(a).unwrap()));
+        }
+
         let num_evicted = {
             let (evicted_cars, cars_parking_in_the_void) =
                 self.parking.handle_live_edits(map, timer);
@@ -959,6 +1118,129 @@ impl Sim {
     }
 }
 
+// Incidents
+impl Sim {
+    /// Schedule a temporary disruption to the map. Incidents that already ended are ignored, and
+    /// ones that already started begin immediately.
+    pub fn schedule_incident(&mut self, incident: ScheduledIncident) {
+        if incident.end <= self.time {
+            warn!(
+                "Not scheduling {}, because it ended at {}",
+                incident.incident.describe(),
+                incident.end
+            );
+            return;
+        }
+        let start = incident.start.max(self.time);
+        let end = incident.end;
+        let idx = self.incidents.add(incident);
+        self.scheduler.push(start, Command::StartIncident(idx));
+        self.scheduler.push(end, Command::EndIncident(idx));
+    }
+
+    /// Every incident scheduled so far, and whether it's currently happening.
+    pub fn get_incidents(&self) -> Vec<(&ScheduledIncident, bool)> {
+        self.incidents.all()
+    }
+
+    /// True if an incident just started or ended, and the map hasn't been updated yet. The
+    /// simulation halts whenever this happens, so anything stepping forwards and owning the map
+    /// should check this, then call `apply_incidents`.
+    pub fn has_pending_incidents(&self) -> bool {
+        self.incidents.has_pending()
+    }
+
+    /// Edit the map for any incidents that just started or ended, then update the simulation to
+    /// match. These edits are temporary, so they don't become part of `map.get_edits()`. Returns
+    /// what changed, or None if nothing happened.
+    pub fn apply_incidents(&mut self, map: &mut Map, timer: &mut Timer) -> Option<EditEffects> {
+        if !self.incidents.has_pending() {
+            return None;
+        }
+        let cmds = self.incidents.make_edits(self.time, map);
+        let effects = map.apply_temporary_edits(&cmds, timer);
+        map.recalculate_pathfinding_after_edits(timer);
+        self.handle_live_edited_traffic_signals(map);
+        let changed_lanes = effects
+            .changed_roads
+            .iter()
+            .flat_map(|r| map.get_r(*r).lanes.iter().map(|l| l.id))
+            .collect();
+        self.handle_live_changes(map, changed_lanes, timer);
+        Some(effects)
+    }
+
+    /// Like `timed_step`, but applies incidents to the map as they start and end.
+    pub fn timed_step_with_incidents(
+        &mut self,
+        map: &mut Map,
+        dt: Duration,
+        maybe_cb: &mut Option<Box<dyn SimCallback>>,
+        timer: &mut Timer,
+    ) {
+        let end_time = self.time + dt;
+        while self.time < end_time {
+            self.timed_step(map, end_time - self.time, maybe_cb, timer);
+            // If nothing happened, then the callback or an alert halted the simulation
+            if self.apply_incidents(map, timer).is_none() {
+                break;
+            }
+        }
+    }
+}
+
+// Weather and daylight
+impl Sim {
+    /// Change the weather and daylight at some point. Changes in the past happen immediately. If
+    /// SimOptions fixed the conditions, this does nothing.
+    pub fn schedule_conditions_change(&mut self, change: ConditionsChange) {
+        if self.fixed_conditions {
+            warn!(
+                "Not changing the conditions to {} at {}, because they're fixed",
+                change.conditions, change.time
+            );
+            return;
+        }
+        let time = change.time.max(self.time);
+        self.conditions_timeline.push(change);
+        self.scheduler.push(
+            time,
+            Command::ChangeConditions(self.conditions_timeline.len() - 1),
+        );
+    }
+
+    /// The weather and daylight right now.
+    pub fn get_conditions(&self) -> Conditions {
+        self.conditions
+    }
+
+    /// The weather and daylight expected at some time, based on everything scheduled so far.
+    pub fn conditions_at(&self, time: Time) -> Conditions {
+        if self.fixed_conditions {
+            self.conditions
+        } else {
+            conditions_at(&self.conditions_timeline, time)
+        }
+    }
+
+    /// Every change to the conditions scheduled so far.
+    pub fn get_conditions_timeline(&self) -> &Vec<ConditionsChange> {
+        &self.conditions_timeline
+    }
+
+    fn change_conditions(&mut self, conditions: Conditions) {
+        if conditions == self.conditions {
+            return;
+        }
+        info!("At {}, the conditions change to {}", self.time, conditions);
+        self.conditions = conditions;
+        self.driving.set_conditions(conditions);
+        self.walking.set_conditions(conditions);
+        self.intersections.set_conditions(conditions);
+        self.analytics.record_conditions(self.time, conditions);
+    }
+}
+
 // Invasive debugging
 impl Sim {
     pub fn delete_car(&mut self, id: CarID, map: &Map) {
@@ -1025,6 +1307,79 @@ impl Sim {
     }
 }
 
+// Recording trajectories
+impl Sim {
+    pub fn record_trajectories(&mut self, sampling: TrajectorySampling) {
+        assert!(self.trajectories.is_none());
+        if let TrajectorySampling::Every(frequency) = sampling {
+            self.scheduler
+                .push(self.time, Command::SampleTrajectories(frequency));
+        }
+        self.trajectories = Some(Trajectories::new(sampling));
+    }
+
+    pub fn get_trajectories(&self) -> Option<&Trajectories> {
+        self.trajectories.as_ref()
+    }
+
+    /// Stops recording and returns everything recorded so far.
+    pub fn take_trajectories(&mut self) -> Option<Trajectories> {
+        // Frequency doesn't matter
+        self.scheduler
+            .cancel(Command::SampleTrajectories(Duration::seconds(1.0)));
+        self.trajectories.take()
+    }
+
+    fn sample_all_trajectories(&mut self, map: &Map) {
+        let mut agents: Vec<AgentID> = self
+            .driving
+            .get_all_draw_cars(self.time, map, &self.transit)
+            .into_iter()
+            .map(|car| AgentID::Car(car.id))
+            .collect();
+        agents.extend(
+            self.walking
+                .get_all_draw_peds(self.time, map)
+                .into_iter()
+                .map(|ped| AgentID::Pedestrian(ped.id)),
+        );
+        for agent in agents {
+            self.sample_trajectory(agent, map);
+        }
+    }
+
+    fn sample_trajectory(&mut self, agent: AgentID, map: &Map) {
+        let sample = match agent {
+            AgentID::Car(car) => self
+                .driving
+                .get_single_draw_car(car, self.time, map, &self.transit)
+                .zip(self.driving.current_speed(car, self.time))
+                .map(|(draw, speed)| {
+                    (
+                        draw.body.last_pt(),
+                        draw.body.last_line().angle(),
+                        speed,
+                        draw.on,
+                    )
+                }),
+            AgentID::Pedestrian(ped) => self
+                .walking
+                .get_draw_ped(ped, self.time, map)
+                .zip(self.walking.current_speed(ped))
+                .map(|(draw, speed)| (draw.pos, draw.facing, speed, draw.on)),
+            // Riders are recorded along with their bus
+            AgentID::BusPassenger(_, _) => None,
+        };
+        if let Some((pt, facing, speed, on)) = sample {
+            let trip = self.trips.agent_to_trip(agent);
+            self.trajectories
+                .as_mut()
+                .unwrap()
+                .record(self.time, agent, trip, pt, facing, speed, on, map);
+        }
+    }
+}
+
 // Managing highlighted people
 impl Sim {
     pub fn set_highlighted_people(&mut self, people: BTreeSet<PersonID>) {
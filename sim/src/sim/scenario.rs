@@ -54,19 +54,43 @@ impl Sim {
             }
         }
 
+        for change in &scenario.conditions {
+            self.schedule_conditions_change(change.clone());
+        }
+        // Only fork if the conditions might change anybody's mode, so the RNG is otherwise
+        // untouched.
+        let mut mode_rng = if self.fixed_conditions || !scenario.conditions.is_empty() {
+            Some(fork_rng(rng))
+        } else {
+            None
+        };
+
         timer.start_iter("trips for People", scenario.people.len());
         let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
         let mut schedule_trips = Vec::new();
         for p in &scenario.people {
             timer.next();
 
+            let adjusted;
+            let p = match mode_rng {
+                Some(ref mut mode_rng) => {
+                    adjusted = self.adjust_modes_for_conditions(p, mode_rng);
+                    &adjusted
+                }
+                None => p,
+            };
+
             if let Err(err) = p.check_schedule() {
                 panic!("{}", err);
             }
 
             let (vehicle_specs, cars_initially_parked_at, vehicle_foreach_trip) =
                 get_vehicles(p, rng);
-            let person = self.new_person(p.orig_id, rand_ped_speed(rng), vehicle_specs);
+            let ped_speed = match p.behavior {
+                Some(ref behavior) => behavior.walking_speed,
+                None => rand_ped_speed(rng),
+            };
+            let person = self.new_person(p.orig_id, ped_speed, vehicle_specs, p.behavior.clone());
             for (idx, b) in cars_initially_parked_at {
                 parked_cars.push((person.vehicles[idx].clone(), b));
             }
@@ -99,8 +123,28 @@ impl Sim {
         seed_parked_cars(parked_cars, self, map, rng, timer);
 
         self.spawn_trips(schedule_trips, map, timer);
+
+        for incident in &scenario.incidents {
+            self.schedule_incident(incident.clone());
+        }
         timer.stop(format!("Instantiating {}", scenario.scenario_name));
     }
+
+    /// In bad weather or the dark, some people who'd normally walk or bike take transit instead.
+    fn adjust_modes_for_conditions(
+        &self,
+        person: &PersonSpec,
+        rng: &mut XorShiftRng,
+    ) -> PersonSpec {
+        let mut person = person.clone();
+        for trip in &mut person.trips {
+            let propensity = self.conditions_at(trip.depart).mode_propensity(trip.mode);
+            if propensity < 1.0 && !rng.gen_bool(propensity) {
+                trip.mode = TripMode::Transit;
+            }
+        }
+        person
+    }
 }
 
 fn get_vehicles(
@@ -170,6 +214,13 @@ fn get_vehicles(
         vehicle_foreach_trip.push(use_for_trip);
     }
 
+    if let Some(ref behavior) = person.behavior {
+        for spec in &mut vehicle_specs {
+            spec.speed_factor = behavior.desired_speed_factor;
+            spec.critical_gap = Some(behavior.critical_gap);
+        }
+    }
+
     // For debugging
     if false {
         let mut n = vehicle_specs.len();
@@ -194,6 +245,8 @@ fn rand_car(rng: &mut XorShiftRng) -> VehicleSpec {
         vehicle_type: VehicleType::Car,
         length,
         max_speed: None,
+        speed_factor: 1.0,
+        critical_gap: None,
     }
 }
 
@@ -207,6 +260,8 @@ fn rand_bike(rng: &mut XorShiftRng) -> VehicleSpec {
         vehicle_type: VehicleType::Bike,
         length: BIKE_LENGTH,
         max_speed,
+        speed_factor: 1.0,
+        critical_gap: None,
     }
 }
 
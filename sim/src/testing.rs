@@ -0,0 +1,38 @@
+//! Helpers shared by unit tests across the crate.
+
+use abstio::MapName;
+use abstutil::Timer;
+use geom::Distance;
+use map_model::{DrivingSide, Map, MapConfig, RawToMapOptions};
+
+/// Imports a small map from the tests crate's input.
+pub(crate) fn import_map() -> Map {
+    let mut timer = Timer::throwaway();
+    let raw = convert_osm::convert(
+        "../tests/input/lane_selection.osm".to_string(),
+        MapName::new("zz", "oneshot", "lane_selection"),
+        None,
+        convert_osm::Options {
+            map_config: MapConfig {
+                driving_side: DrivingSide::Right,
+                bikes_can_use_bus_lanes: true,
+                inferred_sidewalks: true,
+                street_parking_spot_length: Distance::meters(8.0),
+                turn_on_red: false,
+            },
+            onstreet_parking: convert_osm::OnstreetParking::JustOSM,
+            public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
+            private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(0),
+            include_railroads: true,
+            extra_buildings: None,
+            skip_local_roads: false,
+            filter_crosswalks: false,
+            separate_sidewalks: false,
+            mid_block_crossings: false,
+            gtfs_url: None,
+            elevation: false,
+        },
+        &mut timer,
+    );
+    Map::create_from_raw(raw, RawToMapOptions::default(), &mut timer)
+}
@@ -0,0 +1,273 @@
+//! Records where agents go over time, so simulations can be visualized and post-processed with
+//! other tools. The recorder only runs when requested, and exports to CSV, GeoJSON-LD, or SUMO's
+//! floating car data (FCD) XML format.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use geom::{Angle, Duration, LonLat, Pt2D, Speed, Time};
+use map_model::{Map, Traversable};
+
+use crate::{AgentID, TripID, VehicleType};
+
+/// When to record an agent's position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrajectorySampling {
+    /// Record every agent moving around the map at a fixed interval
+    Every(Duration),
+    /// Record an agent whenever it enters a lane or turn
+    OnEnteringTraversable,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrajectoryFormat {
+    Csv,
+    GeoJsonLd,
+    /// SUMO's floating car data, using longitude and latitude for x and y
+    SumoFcd,
+}
+
+impl TrajectoryFormat {
+    /// Guess the format from a file's extension.
+    pub fn from_path(path: &str) -> Result<TrajectoryFormat> {
+        if path.ends_with(".csv") {
+            Ok(TrajectoryFormat::Csv)
+        } else if path.ends_with(".geojson") || path.ends_with(".jsonld") {
+            Ok(TrajectoryFormat::GeoJsonLd)
+        } else if path.ends_with(".xml") {
+            Ok(TrajectoryFormat::SumoFcd)
+        } else {
+            bail!(
+                "Don't know what trajectory format to use for {}; try .csv, .geojson, or .xml",
+                path
+            )
+        }
+    }
+
+    pub fn parse(x: &str) -> Result<TrajectoryFormat> {
+        match x {
+            "csv" => Ok(TrajectoryFormat::Csv),
+            "geojson-ld" => Ok(TrajectoryFormat::GeoJsonLd),
+            "sumo-fcd" => Ok(TrajectoryFormat::SumoFcd),
+            _ => bail!(
+                "Unknown trajectory format {}; try csv, geojson-ld, or sumo-fcd",
+                x
+            ),
+        }
+    }
+}
+
+/// One observation of an agent.
+#[derive(Clone, Debug)]
+pub struct TrajectoryPoint {
+    pub time: Time,
+    pub agent: AgentID,
+    /// None for buses and trains
+    pub trip: Option<TripID>,
+    pub pos: LonLat,
+    pub speed: Speed,
+    /// Degrees clockwise from north
+    pub heading: f64,
+    pub on: Traversable,
+}
+
+/// Every recorded observation, in order of time.
+#[derive(Clone)]
+pub struct Trajectories {
+    pub sampling: TrajectorySampling,
+    pub points: Vec<TrajectoryPoint>,
+}
+
+impl Trajectories {
+    pub(crate) fn new(sampling: TrajectorySampling) -> Trajectories {
+        Trajectories {
+            sampling,
+            points: Vec::new(),
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        time: Time,
+        agent: AgentID,
+        trip: Option<TripID>,
+        pt: Pt2D,
+        facing: Angle,
+        speed: Speed,
+        on: Traversable,
+        map: &Map,
+    ) {
+        self.points.push(TrajectoryPoint {
+            time,
+            agent,
+            trip,
+            pos: pt.to_gps(map.get_gps_bounds()),
+            speed,
+            // Angles in map-space go clockwise from east, since the Y axis points south.
+            heading: facing.rotate_degs(90.0).normalized_degrees(),
+            on,
+        });
+    }
+
+    pub fn export(&self, format: TrajectoryFormat) -> String {
+        match format {
+            TrajectoryFormat::Csv => self.to_csv(),
+            TrajectoryFormat::GeoJsonLd => self.to_geojson_ld(),
+            TrajectoryFormat::SumoFcd => self.to_sumo_fcd(),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out =
+            "time,agent,type,trip,longitude,latitude,speed_mps,heading_degrees,lane\n".to_string();
+        for pt in &self.points {
+            writeln!(
+                out,
+                "{:.1},{},{},{},{:.7},{:.7},{:.2},{:.1},{}",
+                pt.time.inner_seconds(),
+                agent_id(pt.agent),
+                agent_type(pt.agent),
+                pt.trip.map(|t| t.0.to_string()).unwrap_or_default(),
+                pt.pos.x(),
+                pt.pos.y(),
+                pt.speed.inner_meters_per_second(),
+                pt.heading,
+                lane_id(pt.on)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    fn to_geojson_ld(&self) -> String {
+        let features: Vec<serde_json::Value> = self
+            .points
+            .iter()
+            .map(|pt| {
+                serde_json::json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Point",
+                        "coordinates": [pt.pos.x(), pt.pos.y()],
+                    },
+                    "properties": {
+                        "time": pt.time.inner_seconds(),
+                        "agent": agent_id(pt.agent),
+                        "type": agent_type(pt.agent),
+                        "trip": pt.trip.map(|t| t.0),
+                        "speed_mps": pt.speed.inner_meters_per_second(),
+                        "heading_degrees": pt.heading,
+                        "lane": lane_id(pt.on),
+                    },
+                })
+            })
+            .collect();
+        serde_json::to_string(&serde_json::json!({
+            "@context": "https://geojson.org/geojson-ld/geojson-context.jsonld",
+            "type": "FeatureCollection",
+            "features": features,
+        }))
+        .unwrap()
+    }
+
+    fn to_sumo_fcd(&self) -> String {
+        let mut out = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<fcd-export>\n".to_string();
+        let mut current_time = None;
+        for pt in &self.points {
+            if current_time != Some(pt.time) {
+                if current_time.is_some() {
+                    out.push_str("    </timestep>\n");
+                }
+                writeln!(
+                    out,
+                    "    <timestep time=\"{:.2}\">",
+                    pt.time.inner_seconds()
+                )
+                .unwrap();
+                current_time = Some(pt.time);
+            }
+            let trip = pt
+                .trip
+                .map(|t| format!(" trip=\"{}\"", t.0))
+                .unwrap_or_default();
+            // SUMO tracks pedestrians along edges, not lanes
+            if let AgentID::Pedestrian(_) = pt.agent {
+                writeln!(
+                    out,
+                    "        <person id=\"{}\" x=\"{:.7}\" y=\"{:.7}\" angle=\"{:.2}\" speed=\"{:.2}\" edge=\"{}\"{}/>",
+                    agent_id(pt.agent),
+                    pt.pos.x(),
+                    pt.pos.y(),
+                    pt.heading,
+                    pt.speed.inner_meters_per_second(),
+                    edge_id(pt.on),
+                    trip
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "        <vehicle id=\"{}\" x=\"{:.7}\" y=\"{:.7}\" angle=\"{:.2}\" type=\"{}\" speed=\"{:.2}\" lane=\"{}\"{}/>",
+                    agent_id(pt.agent),
+                    pt.pos.x(),
+                    pt.pos.y(),
+                    pt.heading,
+                    agent_type(pt.agent),
+                    pt.speed.inner_meters_per_second(),
+                    lane_id(pt.on),
+                    trip
+                )
+                .unwrap();
+            }
+        }
+        if current_time.is_some() {
+            out.push_str("    </timestep>\n");
+        }
+        out.push_str("</fcd-export>\n");
+        out
+    }
+}
+
+fn agent_type(agent: AgentID) -> &'static str {
+    match agent {
+        AgentID::Car(car) => match car.vehicle_type {
+            VehicleType::Car => "car",
+            VehicleType::Bus => "bus",
+            VehicleType::Train => "train",
+            VehicleType::Bike => "bike",
+        },
+        AgentID::Pedestrian(_) => "pedestrian",
+        AgentID::BusPassenger(_, _) => "passenger",
+    }
+}
+
+/// IDs without spaces, unique across all agents
+fn agent_id(agent: AgentID) -> String {
+    match agent {
+        AgentID::Car(car) => format!("{}{}", agent_type(agent), car.id),
+        AgentID::Pedestrian(ped) => format!("ped{}", ped.0),
+        AgentID::BusPassenger(person, _) => format!("passenger{}", person.0),
+    }
+}
+
+/// Loosely follows SUMO's naming: lanes are "road_index", and lanes inside intersections start
+/// with a colon.
+fn lane_id(on: Traversable) -> String {
+    match on {
+        Traversable::Lane(l) => format!("{}_{}", l.road.0, l.offset),
+        Traversable::Turn(t) => format!(
+            ":{}_{}_{}",
+            t.parent.0,
+            lane_id(Traversable::Lane(t.src)),
+            lane_id(Traversable::Lane(t.dst))
+        ),
+    }
+}
+
+fn edge_id(on: Traversable) -> String {
+    match on {
+        Traversable::Lane(l) => l.road.0.to_string(),
+        Traversable::Turn(t) => format!(":{}", t.parent.0),
+    }
+}
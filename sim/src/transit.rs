@@ -63,6 +63,13 @@ pub(crate) struct TransitSimState {
     )]
     peds_waiting:
         BTreeMap<TransitStopID, Vec<(PedestrianID, TransitRouteID, Option<TransitStopID>, Time)>>,
+    /// When a route is changed by live map edits, vehicles already serving it finish the old
+    /// version. Each of them keeps a copy here.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    retired_routes: BTreeMap<CarID, Route>,
 
     events: Vec<Event>,
 }
@@ -79,6 +86,7 @@ impl TransitSimState {
             buses: BTreeMap::new(),
             routes: BTreeMap::new(),
             peds_waiting,
+            retired_routes: BTreeMap::new(),
             events: Vec::new(),
         }
     }
@@ -135,7 +143,12 @@ impl TransitSimState {
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
                 bus.state = BusState::AtStop(stop_idx);
-                let stop1 = self.routes[&bus.route].stops[stop_idx];
+                let route = self
+                    .retired_routes
+                    .get(&id)
+                    .unwrap_or_else(|| &self.routes[&bus.route]);
+                let stop1 = route.stops[stop_idx];
+                let remaining_stops = &route.stops[stop_idx + 1..];
                 self.events
                     .push(Event::BusArrivedAtStop(id, bus.route, stop1));
 
@@ -158,7 +171,13 @@ impl TransitSimState {
                 for (ped, route, maybe_stop2, started_waiting) in
                     self.peds_waiting.remove(&stop1).unwrap()
                 {
-                    if bus.route == route {
+                    // If the route was changed by live edits, this vehicle might not go where the
+                    // passenger wants.
+                    if bus.route == route
+                        && maybe_stop2
+                            .map(|stop2| remaining_stops.contains(&stop2))
+                            .unwrap_or(true)
+                    {
                         let (trip, person) = trips.ped_boarded_bus(
                             now,
                             ped,
@@ -192,11 +211,13 @@ impl TransitSimState {
                 true
             }
             BusState::DrivingOffMap => {
-                self.routes
-                    .get_mut(&bus.route)
-                    .unwrap()
-                    .active_vehicles
-                    .remove(&id);
+                if self.retired_routes.remove(&id).is_none() {
+                    self.routes
+                        .get_mut(&bus.route)
+                        .unwrap()
+                        .active_vehicles
+                        .remove(&id);
+                }
                 for (person, maybe_stop2) in bus.passengers.drain(..) {
                     if let Some(stop2) = maybe_stop2 {
                         panic!(
@@ -216,7 +237,10 @@ impl TransitSimState {
 
     pub fn bus_departed_from_stop(&mut self, id: CarID, _: &Map) -> Router {
         let mut bus = self.buses.get_mut(&id).unwrap();
-        let route = self.routes.get_mut(&bus.route).unwrap();
+        let route = self
+            .retired_routes
+            .get(&id)
+            .unwrap_or_else(|| &self.routes[&bus.route]);
         match bus.state {
             BusState::AtStop(stop_idx) => {
                 self.events.push(Event::BusDepartedFromStop(
@@ -327,7 +351,7 @@ impl TransitSimState {
     pub fn active_vehicles(&self) -> (usize, usize) {
         let mut buses = 0;
         let mut trains = 0;
-        for r in self.routes.values() {
+        for r in self.routes.values().chain(self.retired_routes.values()) {
             let len = r.active_vehicles.len();
             if len > 0 {
                 if r.active_vehicles.iter().next().unwrap().vehicle_type == VehicleType::Bus {
@@ -340,6 +364,73 @@ impl TransitSimState {
         (buses, trains)
     }
 
+    /// Respond to transit stops and routes being created, changed, or deleted by live map edits.
+    /// Vehicles already serving a changed route finish the old version of it, and the next vehicle
+    /// spawned follows the new version. Returns people waiting for a route that won't take them
+    /// where they want to go anymore; the caller should cancel their trips.
+    pub fn handle_live_edits(&mut self, map: &Map) -> Vec<PedestrianID> {
+        for ts in map.all_transit_stops().keys() {
+            self.peds_waiting.entry(*ts).or_insert_with(Vec::new);
+        }
+
+        let mut changed_routes = BTreeSet::new();
+        for (id, route) in &self.routes {
+            let changed = match map.maybe_get_tr(*id) {
+                Some(tr) => {
+                    let end_lane = tr.end_border.unwrap_or_else(|| {
+                        map.get_ts(*tr.stops.last().unwrap()).driving_pos.lane()
+                    });
+                    route.stops != tr.stops
+                        || route.paths[0].get_req().start.lane() != tr.start
+                        || route.paths.last().unwrap().get_req().end.lane() != end_lane
+                }
+                None => true,
+            };
+            if changed {
+                changed_routes.insert(*id);
+            }
+        }
+
+        for id in &changed_routes {
+            let route = self.routes.remove(id).unwrap();
+            for bus in &route.active_vehicles {
+                self.retired_routes.insert(
+                    *bus,
+                    Route {
+                        paths: route.paths.clone(),
+                        stops: route.stops.clone(),
+                        active_vehicles: std::iter::once(*bus).collect(),
+                    },
+                );
+            }
+        }
+
+        let mut cancelled = Vec::new();
+        for (stop1, waiting) in &mut self.peds_waiting {
+            waiting.retain(|(ped, route, maybe_stop2, _)| {
+                if !changed_routes.contains(route) {
+                    return true;
+                }
+                let still_served = map
+                    .maybe_get_tr(*route)
+                    .and_then(|tr| {
+                        let idx = tr.stops.iter().position(|ts| ts == stop1)?;
+                        Some(
+                            maybe_stop2
+                                .map(|stop2| tr.stops[idx + 1..].contains(&stop2))
+                                .unwrap_or(true),
+                        )
+                    })
+                    .unwrap_or(false);
+                if !still_served {
+                    cancelled.push(*ped);
+                }
+                still_served
+            });
+        }
+        cancelled
+    }
+
     pub fn get_people_waiting_at_stop(
         &self,
         at: TransitStopID,
@@ -1,15 +1,17 @@
 use std::collections::{BTreeMap, VecDeque};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Counter, Timer};
 use geom::{Distance, Duration, Speed, Time};
 use map_model::{
-    BuildingID, IntersectionID, Map, PathConstraints, PathRequest, Position, TransitRouteID,
-    TransitStopID,
+    BuildingID, IntersectionID, Map, Path, PathConstraints, PathRequest, PathfinderCaching,
+    Position, TransitRouteID, TransitStopID,
 };
 use synthpop::{
-    IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose,
+    BehaviorProfile, CyclistComfort, IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint,
+    TripMode, TripPurpose,
 };
 
 use crate::sim::Ctx;
@@ -20,6 +22,10 @@ use crate::{
     VehicleType, WalkingSimState,
 };
 
+/// When calculating paths ahead of time, handle everybody departing within this long in one
+/// batch.
+const PREFETCH_PATHS_WINDOW: Duration = Duration::const_seconds(60.0);
+
 /// Manages people, each of which executes some trips through the day. Each trip is further broken
 /// down into legs -- for example, a driving trip might start with somebody walking to their car,
 /// driving somewhere, parking, and then walking to their final destination.
@@ -41,11 +47,25 @@ pub(crate) struct TripManager {
     car_id_counter: usize,
 
     events: Vec<Event>,
+
+    /// Trips that haven't started yet, by departure time. Only tracked when calculating paths
+    /// ahead of time.
+    #[serde(skip_serializing, skip_deserializing)]
+    upcoming_departures: BTreeMap<(Time, TripID), StartTripArgs>,
+    /// The first path of upcoming trips, calculated in parallel. These're only used if the
+    /// request still matches when the trip actually starts, so results are the same as
+    /// calculating them one at a time. Entries are removed when the trip tries to start or gets
+    /// cancelled.
+    #[serde(skip_serializing, skip_deserializing)]
+    prefetched_paths: BTreeMap<TripID, (PathRequest, Result<Path, String>)>,
+    #[serde(skip_serializing, skip_deserializing)]
+    prefetch_paths: bool,
+    jaywalking: bool,
 }
 
 // Initialization
 impl TripManager {
-    pub fn new() -> TripManager {
+    pub fn new(prefetch_paths: bool, jaywalking: bool) -> TripManager {
         TripManager {
             trips: Vec::new(),
             people: Vec::new(),
@@ -53,6 +73,10 @@ impl TripManager {
             unfinished_trips: 0,
             car_id_counter: 0,
             events: Vec::new(),
+            upcoming_departures: BTreeMap::new(),
+            prefetched_paths: BTreeMap::new(),
+            prefetch_paths,
+            jaywalking,
         }
     }
 
@@ -62,6 +86,7 @@ impl TripManager {
         orig_id: Option<OrigPersonID>,
         ped_speed: Speed,
         vehicle_specs: Vec<VehicleSpec>,
+        behavior: Option<BehaviorProfile>,
     ) -> &Person {
         let id = PersonID(self.people.len());
         let vehicles = vehicle_specs
@@ -83,6 +108,7 @@ impl TripManager {
             ped: PedestrianID(id.0),
             ped_speed,
             vehicles,
+            behavior,
             delayed_trips: Vec::new(),
             on_bus: None,
         });
@@ -135,6 +161,8 @@ impl TripManager {
 
     pub fn start_trip(&mut self, now: Time, trip: TripID, args: StartTripArgs, ctx: &mut Ctx) {
         assert!(self.trips[trip.0].info.cancellation_reason.is_none());
+        // A path calculated ahead of time is only good for this attempt to start the trip
+        let prefetched = self.prefetched_paths.remove(&trip);
 
         let person = &mut self.people[self.trips[trip.0].person.0];
         if let PersonState::Trip(_) = person.state {
@@ -179,6 +207,15 @@ impl TripManager {
         assert!(self.trips[trip.0].legs.is_empty());
         self.trips[trip.0].legs.extend(legs);
 
+        let first_req = first_path_request(&spec, ctx.map);
+        let prefetched = prefetched.and_then(|(req, result)| {
+            if Some(&req) == first_req.as_ref() {
+                Some(result.map_err(|err| anyhow!(err)))
+            } else {
+                None
+            }
+        });
+
         match spec {
             TripSpec::VehicleAppearing {
                 start_pos,
@@ -196,19 +233,11 @@ impl TripManager {
 
                 let vehicle = person.get_vehicle(use_vehicle);
                 assert!(ctx.parking.lookup_parked_car(vehicle.id).is_none());
-                let constraints = if use_vehicle.vehicle_type == VehicleType::Bike {
-                    PathConstraints::Bike
-                } else {
-                    PathConstraints::Car
-                };
-                let req = PathRequest::vehicle(
-                    start_pos,
-                    goal.goal_pos(constraints, ctx.map).unwrap(),
-                    constraints,
-                );
+                let req = first_req.unwrap();
+                let maybe_path = prefetched.unwrap_or_else(|| person.pathfind(req, ctx.map));
                 let person = person.id;
 
-                match ctx.map.pathfind(req) {
+                match maybe_path {
                     Ok(path) => {
                         let router = goal.make_router(vehicle.id, path, ctx.map);
                         ctx.scheduler.push(
@@ -241,7 +270,7 @@ impl TripManager {
                     let walking_goal =
                         SidewalkSpot::parking_spot(parked_car.spot, ctx.map, ctx.parking);
                     let req = PathRequest::walking(start.sidewalk_pos, walking_goal.sidewalk_pos);
-                    match ctx.map.pathfind(req) {
+                    match pathfind(req, None, self.jaywalking, ctx.map) {
                         Ok(path) => {
                             ctx.scheduler.push(
                                 now,
@@ -308,8 +337,8 @@ impl TripManager {
                 );
                 person.state = PersonState::Trip(trip);
 
-                let req = PathRequest::walking(start.sidewalk_pos, goal.sidewalk_pos);
-                match ctx.map.pathfind(req) {
+                let req = first_req.unwrap();
+                match prefetched.unwrap_or_else(|| pathfind(req, None, self.jaywalking, ctx.map)) {
                     Ok(path) => {
                         ctx.scheduler.push(
                             now,
@@ -334,11 +363,10 @@ impl TripManager {
                 person.state = PersonState::Trip(trip);
 
                 if let Some(walk_to) = SidewalkSpot::bike_rack(start, ctx.map) {
-                    let req = PathRequest::walking(
-                        SidewalkSpot::building(start, ctx.map).sidewalk_pos,
-                        walk_to.sidewalk_pos,
-                    );
-                    match ctx.map.pathfind(req) {
+                    let req = first_req.unwrap();
+                    match prefetched
+                        .unwrap_or_else(|| pathfind(req, None, self.jaywalking, ctx.map))
+                    {
                         Ok(path) => {
                             // Where we start biking may have slightly changed due to live map
                             // edits!
@@ -411,8 +439,8 @@ impl TripManager {
                 person.state = PersonState::Trip(trip);
 
                 let walk_to = SidewalkSpot::bus_stop(stop1, ctx.map);
-                let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
-                match ctx.map.pathfind(req) {
+                let req = first_req.unwrap();
+                match prefetched.unwrap_or_else(|| pathfind(req, None, self.jaywalking, ctx.map)) {
                     Ok(path) => {
                         ctx.scheduler.push(
                             now,
@@ -615,8 +643,8 @@ impl TripManager {
                 req.start.lane()
             ))
         } else {
-            ctx.map
-                .pathfind(req)
+            self.people[trip.person.0]
+                .pathfind(req, ctx.map)
                 .map(|path| drive_to.make_router(bike, path, ctx.map))
         };
         match maybe_router {
@@ -937,7 +965,7 @@ impl TripManager {
         };
 
         let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
-        match ctx.map.pathfind(req) {
+        match pathfind(req, None, self.jaywalking, ctx.map) {
             Ok(path) => {
                 let person = &self.people[trip.person.0];
                 ctx.scheduler.push(
@@ -960,10 +988,79 @@ impl TripManager {
     }
 }
 
+// Calculating paths ahead of time
+impl TripManager {
+    pub fn expect_departure(&mut self, trip: TripID, departure: Time, args: StartTripArgs) {
+        if self.prefetch_paths {
+            self.upcoming_departures.insert((departure, trip), args);
+        }
+    }
+
+    /// If any trips are starting now, calculate the first path for all of them and everything
+    /// else departing soon, using many threads.
+    pub fn prefetch_paths(&mut self, now: Time, map: &Map) {
+        match self.upcoming_departures.keys().next() {
+            Some((time, _)) if *time <= now => {}
+            _ => {
+                return;
+            }
+        }
+
+        let mut requests = Vec::new();
+        while let Some((time, trip)) = self.upcoming_departures.keys().next().cloned() {
+            if time >= now + PREFETCH_PATHS_WINDOW {
+                break;
+            }
+            let args = self.upcoming_departures.remove(&(time, trip)).unwrap();
+            let info = &self.trips[trip.0].info;
+            if info.cancellation_reason.is_some() {
+                continue;
+            }
+            let spec = match TripSpec::maybe_new(
+                info.start,
+                info.end,
+                info.mode,
+                args.use_vehicle,
+                args.retry_if_no_room,
+                map,
+            ) {
+                Ok(spec) => spec.into_plan(map).0,
+                Err(_) => {
+                    continue;
+                }
+            };
+            if let Some(req) = first_path_request(&spec, map) {
+                let comfort = self.people[self.trips[trip.0].person.0]
+                    .behavior
+                    .as_ref()
+                    .map(|b| b.cyclist_comfort);
+                requests.push((trip, req, comfort));
+            }
+        }
+
+        let jaywalking = self.jaywalking;
+        let results =
+            Timer::throwaway().parallelize("prefetch paths", requests, |(trip, req, comfort)| {
+                let result =
+                    pathfind(req.clone(), comfort, jaywalking, map).map_err(|err| err.to_string());
+                (trip, req, result)
+            });
+        for (trip, req, result) in results {
+            self.prefetched_paths.insert(trip, (req, result));
+        }
+    }
+
+    /// After the map changes, paths calculated ahead of time might be wrong.
+    pub fn clear_prefetched_paths(&mut self) {
+        self.prefetched_paths.clear();
+    }
+}
+
 // Cancelling trips
 impl TripManager {
     /// Cancel a trip before it's started. The person will stay where they are.
     pub fn cancel_unstarted_trip(&mut self, id: TripID, reason: String) {
+        self.prefetched_paths.remove(&id);
         let trip = &mut self.trips[id.0];
         self.unfinished_trips -= 1;
         trip.info.cancellation_reason = Some(reason);
@@ -1300,12 +1397,64 @@ impl TripManager {
                         )
                     })
                     .collect(),
+                behavior: p.behavior.clone(),
             });
         }
         scenario
     }
 }
 
+/// Bike routes depend on how comfortable the person is riding in traffic. Walking routes depend on
+/// whether people jaywalk.
+pub(crate) fn pathfind(
+    req: PathRequest,
+    comfort: Option<CyclistComfort>,
+    jaywalking: bool,
+    map: &Map,
+) -> Result<Path> {
+    if let (PathConstraints::Bike, Some(comfort)) = (req.constraints, comfort) {
+        let params = comfort.routing_params(map.routing_params());
+        return map.pathfind_with_params(req, &params, PathfinderCaching::CacheDijkstra);
+    }
+    if req.constraints == PathConstraints::Pedestrian && jaywalking {
+        let mut params = map.routing_params().clone();
+        params.jaywalking = true;
+        return map.pathfind_with_params(req, &params, PathfinderCaching::CacheDijkstra);
+    }
+    map.pathfind(req)
+}
+
+/// The first path a trip needs when it starts. Walking to a parked car depends on where the car is
+/// by then, so that's not included.
+fn first_path_request(spec: &TripSpec, map: &Map) -> Option<PathRequest> {
+    match spec {
+        TripSpec::VehicleAppearing {
+            start_pos,
+            goal,
+            use_vehicle,
+            ..
+        } => {
+            let constraints = use_vehicle.vehicle_type.to_constraints();
+            goal.goal_pos(constraints, map)
+                .map(|end| PathRequest::vehicle(*start_pos, end, constraints))
+        }
+        TripSpec::JustWalking { start, goal } => {
+            Some(PathRequest::walking(start.sidewalk_pos, goal.sidewalk_pos))
+        }
+        TripSpec::UsingBike { start, .. } => SidewalkSpot::bike_rack(*start, map).map(|walk_to| {
+            PathRequest::walking(
+                SidewalkSpot::building(*start, map).sidewalk_pos,
+                walk_to.sidewalk_pos,
+            )
+        }),
+        TripSpec::UsingTransit { start, stop1, .. } => Some(PathRequest::walking(
+            start.sidewalk_pos,
+            SidewalkSpot::bus_stop(*stop1, map).sidewalk_pos,
+        )),
+        TripSpec::UsingParkedCar { .. } | TripSpec::SpawningFailure { .. } => None,
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Trip {
     id: TripID,
@@ -1394,6 +1543,8 @@ pub struct Person {
     pub ped_speed: Speed,
     /// Both cars and bikes
     pub vehicles: Vec<Vehicle>,
+    /// If the scenario didn't specify this, the person behaves like everybody else.
+    pub behavior: Option<BehaviorProfile>,
 
     delayed_trips: Vec<(TripID, StartTripArgs)>,
     on_bus: Option<CarID>,
@@ -1403,6 +1554,16 @@ impl Person {
     fn get_vehicle(&self, id: CarID) -> Vehicle {
         self.vehicles.iter().find(|v| v.id == id).unwrap().clone()
     }
+
+    /// Bike routes depend on how comfortable the person is riding in traffic.
+    fn pathfind(&self, req: PathRequest, map: &Map) -> Result<Path> {
+        pathfind(
+            req,
+            self.behavior.as_ref().map(|b| b.cyclist_comfort),
+            false,
+            map,
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
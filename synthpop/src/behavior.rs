@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Duration, Speed};
+use map_model::RoutingParams;
+
+/// How one person tends to move around. Without this, everybody using the same mode behaves
+/// identically, apart from some random variation in vehicle length and top speed.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BehaviorProfile {
+    /// How fast the person likes to drive, relative to the speed limit. 1.1 means 10% over the
+    /// limit.
+    pub desired_speed_factor: f64,
+    /// Before making an unprotected turn, the smallest gap in traffic with priority that this
+    /// person accepts.
+    pub critical_gap: Duration,
+    /// Affects the routes chosen when biking.
+    pub cyclist_comfort: CyclistComfort,
+    /// Usually depends on age.
+    pub walking_speed: Speed,
+}
+
+/// Roughly based on Portland's "four types of cyclists." People who won't bike at all just don't
+/// have bike trips.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CyclistComfort {
+    /// Rides on any road, regardless of traffic
+    StrongAndFearless,
+    /// Comfortable on most roads, but prefers bike lanes. This matches the map's default routing.
+    EnthusedAndConfident,
+    /// Goes out of the way to avoid busy roads and steep hills
+    InterestedButConcerned,
+}
+
+impl CyclistComfort {
+    /// Adjusts the map's default routing parameters for bike trips.
+    pub fn routing_params(self, base: &RoutingParams) -> RoutingParams {
+        let mut params = base.clone();
+        match self {
+            CyclistComfort::StrongAndFearless => {
+                params.driving_lane_penalty = 1.0;
+                params.avoid_high_stress = 1.0;
+                params.avoid_steep_incline_penalty = 1.0;
+            }
+            CyclistComfort::EnthusedAndConfident => {}
+            CyclistComfort::InterestedButConcerned => {
+                params.driving_lane_penalty *= 2.0;
+                params.avoid_high_stress = params.avoid_high_stress.max(2.0);
+                params.avoid_steep_incline_penalty = params.avoid_steep_incline_penalty.max(2.0);
+            }
+        }
+        params
+    }
+}
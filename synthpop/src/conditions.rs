@@ -0,0 +1,164 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+
+use crate::TripMode;
+
+/// The weather and daylight at some point during the simulation. These slow everybody down, make
+/// some people avoid walking and biking, and make traffic signals worse at noticing vehicles.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Conditions {
+    pub weather: Weather,
+    pub daylight: Daylight,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Weather {
+    Dry,
+    Rain,
+    Snow,
+    Fog,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Daylight {
+    Day,
+    /// Dawn or dusk
+    Twilight,
+    Night,
+}
+
+/// From this time until the next change, these conditions hold.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ConditionsChange {
+    pub time: Time,
+    pub conditions: Conditions,
+}
+
+impl Conditions {
+    /// Dry and daytime. Nothing is affected.
+    pub const DEFAULT: Conditions = Conditions {
+        weather: Weather::Dry,
+        daylight: Daylight::Day,
+    };
+
+    /// Parses something like "rain,night".
+    pub fn parse(x: &str) -> Result<Conditions> {
+        let (weather, daylight) = match x.split_once(',') {
+            Some(pair) => pair,
+            None => bail!("Conditions should look like weather,daylight -- rain,night"),
+        };
+        let weather = match weather {
+            "dry" => Weather::Dry,
+            "rain" => Weather::Rain,
+            "snow" => Weather::Snow,
+            "fog" => Weather::Fog,
+            _ => bail!("Unknown weather {}; try dry, rain, snow, or fog", weather),
+        };
+        let daylight = match daylight {
+            "day" => Daylight::Day,
+            "twilight" => Daylight::Twilight,
+            "night" => Daylight::Night,
+            _ => bail!("Unknown daylight {}; try day, twilight, or night", daylight),
+        };
+        Ok(Conditions { weather, daylight })
+    }
+
+    /// How fast people go using this mode, relative to dry daylight. Transit uses the driving
+    /// factor.
+    pub fn speed_factor(self, mode: TripMode) -> f64 {
+        let weather = match (self.weather, mode) {
+            (Weather::Dry, _) => 1.0,
+            (Weather::Rain, TripMode::Walk) => 1.0,
+            (Weather::Rain, TripMode::Bike) => 0.85,
+            (Weather::Rain, _) => 0.9,
+            (Weather::Snow, TripMode::Walk) => 0.8,
+            (Weather::Snow, TripMode::Bike) => 0.6,
+            (Weather::Snow, _) => 0.7,
+            (Weather::Fog, TripMode::Walk) => 1.0,
+            (Weather::Fog, TripMode::Bike) => 0.9,
+            (Weather::Fog, _) => 0.8,
+        };
+        let daylight = match (self.daylight, mode) {
+            (Daylight::Day, _) | (_, TripMode::Walk) => 1.0,
+            (Daylight::Twilight, _) => 0.97,
+            (Daylight::Night, TripMode::Bike) => 0.9,
+            (Daylight::Night, _) => 0.95,
+        };
+        weather * daylight
+    }
+
+    /// The chance, from 0 to 1, that somebody who'd use this mode in dry daylight still does.
+    /// Walking and biking trips that don't happen switch to transit; driving and transit trips
+    /// are unaffected.
+    pub fn mode_propensity(self, mode: TripMode) -> f64 {
+        let weather = match (self.weather, mode) {
+            (_, TripMode::Drive) | (_, TripMode::Transit) | (Weather::Dry, _) => 1.0,
+            (Weather::Rain, TripMode::Walk) => 0.85,
+            (Weather::Rain, TripMode::Bike) => 0.6,
+            (Weather::Snow, TripMode::Walk) => 0.7,
+            (Weather::Snow, TripMode::Bike) => 0.3,
+            (Weather::Fog, TripMode::Walk) => 1.0,
+            (Weather::Fog, TripMode::Bike) => 0.9,
+        };
+        let daylight = match (self.daylight, mode) {
+            (_, TripMode::Drive) | (_, TripMode::Transit) | (Daylight::Day, _) => 1.0,
+            (Daylight::Twilight, _) => 0.95,
+            (Daylight::Night, _) => 0.8,
+        };
+        weather * daylight
+    }
+
+    /// How well traffic signal detectors notice waiting vehicles, from 0 to 1.
+    pub fn detection_sensitivity(self) -> f64 {
+        let weather = match self.weather {
+            Weather::Dry => 1.0,
+            Weather::Rain => 0.9,
+            Weather::Snow => 0.75,
+            Weather::Fog => 0.8,
+        };
+        let daylight = match self.daylight {
+            Daylight::Day => 1.0,
+            Daylight::Twilight => 0.95,
+            Daylight::Night => 0.9,
+        };
+        weather * daylight
+    }
+}
+
+impl Default for Conditions {
+    fn default() -> Conditions {
+        Conditions::DEFAULT
+    }
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let weather = match self.weather {
+            Weather::Dry => "dry",
+            Weather::Rain => "rain",
+            Weather::Snow => "snow",
+            Weather::Fog => "fog",
+        };
+        let daylight = match self.daylight {
+            Daylight::Day => "day",
+            Daylight::Twilight => "twilight",
+            Daylight::Night => "night",
+        };
+        write!(f, "{},{}", weather, daylight)
+    }
+}
+
+/// The conditions at some time, given a list of changes. Before the first change, it's dry
+/// daylight.
+pub fn conditions_at(timeline: &[ConditionsChange], time: Time) -> Conditions {
+    timeline
+        .iter()
+        .filter(|change| change.time <= time)
+        .max_by_key(|change| change.time)
+        .map(|change| change.conditions)
+        .unwrap_or_default()
+}
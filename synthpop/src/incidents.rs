@@ -0,0 +1,85 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+use map_model::{IntersectionID, LaneID, Map, RoadID};
+
+/// Something disrupting part of the map for a while. The simulation applies these as temporary
+/// map edits when they start, and reverts them when they end.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ScheduledIncident {
+    pub start: Time,
+    pub end: Time,
+    pub incident: Incident,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Incident {
+    /// A crash or stalled vehicle blocks these lanes to all traffic.
+    LaneBlockage(Vec<LaneID>),
+    /// Every lane for vehicles along the road is closed. Sidewalks stay open.
+    RoadClosure(RoadID),
+    /// A traffic signal stops working, so drivers treat it as an all-way stop.
+    SignalFailure(IntersectionID),
+    /// Roads are closed to vehicles for something like a parade or street fair, so traffic has to
+    /// detour around them. Sidewalks stay open.
+    SpecialEvent { name: String, roads: Vec<RoadID> },
+}
+
+impl ScheduledIncident {
+    /// Make sure the incident refers to things that exist in the map and ends after it starts.
+    pub fn check(&self, map: &Map) -> Result<()> {
+        if self.end <= self.start {
+            bail!(
+                "{} starts at {}, but ends at {}",
+                self.incident.describe(),
+                self.start,
+                self.end
+            );
+        }
+        match self.incident {
+            Incident::LaneBlockage(ref lanes) => {
+                if lanes.is_empty() {
+                    bail!("A lane blockage has no lanes");
+                }
+                for l in lanes {
+                    if map.maybe_get_l(*l).is_none() {
+                        bail!("{} doesn't exist", l);
+                    }
+                }
+            }
+            Incident::RoadClosure(r) => {
+                if map.maybe_get_r(r).is_none() {
+                    bail!("{} doesn't exist", r);
+                }
+            }
+            Incident::SignalFailure(i) => {
+                if map.maybe_get_traffic_signal(i).is_none() {
+                    bail!("{} isn't a traffic signal", i);
+                }
+            }
+            Incident::SpecialEvent { ref roads, .. } => {
+                if roads.is_empty() {
+                    bail!("{} doesn't close any roads", self.incident.describe());
+                }
+                for r in roads {
+                    if map.maybe_get_r(*r).is_none() {
+                        bail!("{} doesn't exist", r);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Incident {
+    pub fn describe(&self) -> String {
+        match self {
+            Incident::LaneBlockage(lanes) => format!("blockage of {} lanes", lanes.len()),
+            Incident::RoadClosure(r) => format!("closure of {}", r),
+            Incident::SignalFailure(i) => format!("signal failure at {}", i),
+            Incident::SpecialEvent { name, .. } => format!("special event {}", name),
+        }
+    }
+}
@@ -15,18 +15,26 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_usize, serialize_usize};
 use map_model::PathConstraints;
 
+pub use self::behavior::{BehaviorProfile, CyclistComfort};
 pub use self::borders::{MapBorder, MapBorders};
+pub use self::conditions::{conditions_at, Conditions, ConditionsChange, Daylight, Weather};
 pub use self::counts::TrafficCounts;
 pub use self::endpoint::TripEndpoint;
 pub use self::external::{ExternalPerson, ExternalTrip, ExternalTripEndpoint};
+pub use self::incidents::{Incident, ScheduledIncident};
 pub use self::modifier::ScenarioModifier;
+pub use self::noise::{lden_band, DailyFlow, HourlyFlow, NoiseModel, LDEN_BANDS};
 pub use self::scenario::{IndividTrip, PersonSpec, Scenario, TripPurpose};
 
+mod behavior;
 mod borders;
+mod conditions;
 mod counts;
 mod endpoint;
 mod external;
+mod incidents;
 mod modifier;
+mod noise;
 mod scenario;
 
 /// How does a trip primarily happen?
@@ -0,0 +1,284 @@
+//! Estimates road traffic noise at buildings, loosely following the German RLS-90 guidelines. The
+//! emission of each road depends on its hourly volume, share of heavy vehicles, speed limit, and
+//! grade. That spreads out over distance to the facade of each building nearby. Shielding by other
+//! buildings and reflections aren't modelled, so buildings far behind the first row of a busy road
+//! are overestimated.
+
+use std::collections::BTreeMap;
+
+use geom::{Distance, FindClosest, Pt2D};
+use map_model::{BuildingID, Map, RoadID};
+
+use crate::TrafficCounts;
+
+/// Roads further than this from a building are ignored.
+const MAX_DISTANCE: Distance = Distance::const_meters(300.0);
+/// Even tiny buildings right on the road are at least this far from the center of the traffic.
+const MIN_DISTANCE: f64 = 5.0;
+
+/// The bands used to report Lden under the EU Environmental Noise Directive, from quietest to
+/// loudest.
+pub const LDEN_BANDS: [&str; 6] = [
+    "under 55 dB",
+    "55-60 dB",
+    "60-65 dB",
+    "65-70 dB",
+    "70-75 dB",
+    "over 75 dB",
+];
+
+/// Which of `LDEN_BANDS` a level falls into.
+pub fn lden_band(db: f64) -> &'static str {
+    let idx = ((db - 50.0) / 5.0).floor().clamp(0.0, 5.0) as usize;
+    LDEN_BANDS[idx]
+}
+
+/// How many vehicles pass along a road per hour, averaged over one period of the day.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HourlyFlow {
+    /// Cars and other light vehicles
+    pub light: f64,
+    /// Buses, trucks, and trains
+    pub heavy: f64,
+}
+
+/// Traffic along one road for the three periods of the day used for Lden: day (7am to 7pm),
+/// evening (7pm to 11pm), and night (11pm to 7am).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DailyFlow {
+    pub day: HourlyFlow,
+    pub evening: HourlyFlow,
+    pub night: HourlyFlow,
+}
+
+impl DailyFlow {
+    /// Spread a count of light vehicles over a whole day using a typical urban profile, for
+    /// sources without any time of day.
+    pub fn from_daily_total(light: usize) -> DailyFlow {
+        let light = light as f64;
+        DailyFlow {
+            day: HourlyFlow {
+                light: 0.75 * light / 12.0,
+                heavy: 0.0,
+            },
+            evening: HourlyFlow {
+                light: 0.15 * light / 4.0,
+                heavy: 0.0,
+            },
+            night: HourlyFlow {
+                light: 0.1 * light / 8.0,
+                heavy: 0.0,
+            },
+        }
+    }
+
+    /// `per_hour` has the number of (light, heavy) vehicles counted in each hour, starting from
+    /// midnight. It may cover less than a full day; each period is only averaged over the hours
+    /// present.
+    pub fn from_hourly_counts(per_hour: &[(usize, usize)]) -> DailyFlow {
+        let average = |hours: &[usize]| {
+            let mut flow = HourlyFlow::default();
+            let mut num_hours = 0.0;
+            for hour in hours {
+                if let Some((light, heavy)) = per_hour.get(*hour) {
+                    flow.light += *light as f64;
+                    flow.heavy += *heavy as f64;
+                    num_hours += 1.0;
+                }
+            }
+            if num_hours > 0.0 {
+                flow.light /= num_hours;
+                flow.heavy /= num_hours;
+            }
+            flow
+        };
+        DailyFlow {
+            day: average(&[7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18]),
+            evening: average(&[19, 20, 21, 22]),
+            night: average(&[23, 0, 1, 2, 3, 4, 5, 6]),
+        }
+    }
+}
+
+/// The sound emitted by every road with traffic, ready to be evaluated at any building.
+pub struct NoiseModel {
+    /// Per road, the emission level for the day, evening, and night, as sound intensity relative
+    /// to the reference level (so 10^(dB / 10)). This way, contributions from different roads can
+    /// just be added.
+    emissions: BTreeMap<RoadID, [f64; 3]>,
+    closest: FindClosest<RoadID>,
+}
+
+impl NoiseModel {
+    pub fn new(map: &Map, flows: &BTreeMap<RoadID, DailyFlow>) -> NoiseModel {
+        let mut emissions = BTreeMap::new();
+        let mut closest = FindClosest::new(map.get_bounds());
+        for (r, flow) in flows {
+            let road = map.get_r(*r);
+            let speed = road.speed_limit.inner_meters_per_second() * 3.6;
+            let grade = road.percent_incline.abs() * 100.0;
+            let levels = [flow.day, flow.evening, flow.night]
+                .map(|hourly| emission_intensity(hourly, speed, grade));
+            if levels.iter().all(|x| *x == 0.0) {
+                continue;
+            }
+            emissions.insert(*r, levels);
+            closest.add(*r, road.center_pts.points());
+        }
+        NoiseModel { emissions, closest }
+    }
+
+    /// Only uses the total daily count per road, assuming all light vehicles and a typical
+    /// distribution over the day.
+    pub fn from_counts(map: &Map, counts: &TrafficCounts) -> NoiseModel {
+        let flows = counts
+            .per_road
+            .borrow()
+            .iter()
+            .map(|(r, cnt)| (*r, DailyFlow::from_daily_total(*cnt)))
+            .collect();
+        NoiseModel::new(map, &flows)
+    }
+
+    /// The day-evening-night level (Lden, in dB(A)) at the most exposed facade of a building.
+    /// Evening noise is penalized by 5dB and night noise by 10dB. None if there's no traffic
+    /// nearby.
+    pub fn building_lden(&self, map: &Map, b: BuildingID) -> Option<f64> {
+        let bldg = map.get_b(b);
+        let mut total = [0.0; 3];
+        for (r, road_pt, _) in self
+            .closest
+            .all_close_pts(bldg.polygon.center(), MAX_DISTANCE)
+        {
+            // The center of the building could be far from the facade facing the road
+            let facade = bldg
+                .polygon
+                .points()
+                .iter()
+                .min_by_key(|pt| pt.dist_to(road_pt))
+                .cloned()
+                .unwrap_or(road_pt);
+            let dist = facade.dist_to(road_pt).inner_meters().max(MIN_DISTANCE);
+
+            // Treat each road as a straight line, and scale by how much of the view from the
+            // facade it takes up.
+            let center_pts = &map.get_r(r).center_pts;
+            let angle = view_angle(facade, center_pts.first_pt(), center_pts.last_pt());
+            let attenuation = propagation(dist) + 10.0 * (angle / std::f64::consts::PI).log10();
+            let factor = 10.0_f64.powf(attenuation / 10.0);
+            for (sum, emission) in total.iter_mut().zip(self.emissions[&r].iter()) {
+                *sum += emission * factor;
+            }
+        }
+
+        if total.iter().all(|x| *x == 0.0) {
+            return None;
+        }
+        let [day, evening, night] = total;
+        // Each intensity already corresponds to 10^(L / 10), so the penalties are multipliers
+        Some(
+            10.0 * ((12.0 * day + 4.0 * evening * 10.0_f64.powf(0.5) + 8.0 * night * 10.0) / 24.0)
+                .log10(),
+        )
+    }
+
+    /// Lden for every building with traffic nearby.
+    pub fn all_buildings(&self, map: &Map) -> BTreeMap<BuildingID, f64> {
+        map.all_buildings()
+            .iter()
+            .filter_map(|b| self.building_lden(map, b.id).map(|db| (b.id, db)))
+            .collect()
+    }
+}
+
+/// The emission level (L_m,E in RLS-90) as an intensity. Speed is in km/h, and grade in percent.
+fn emission_intensity(flow: HourlyFlow, speed: f64, grade: f64) -> f64 {
+    let total = flow.light + flow.heavy;
+    if total == 0.0 {
+        return 0.0;
+    }
+    let pct_heavy = 100.0 * flow.heavy / total;
+    let base = 37.3 + 10.0 * (total * (1.0 + 0.082 * pct_heavy)).log10();
+
+    let car = 27.7 + 10.0 * (1.0 + (0.02 * speed.clamp(30.0, 130.0)).powi(3)).log10();
+    let truck = 23.1 + 12.5 * speed.clamp(30.0, 80.0).log10();
+    let speed_correction = car - 37.3
+        + 10.0
+            * ((100.0 + (10.0_f64.powf(0.1 * (truck - car)) - 1.0) * pct_heavy)
+                / (100.0 + 8.23 * pct_heavy))
+                .log10();
+    let grade_correction = (0.6 * grade - 3.0).max(0.0);
+
+    10.0_f64.powf((base + speed_correction + grade_correction) / 10.0)
+}
+
+/// How the level changes from the emission level over some distance in meters, including ground
+/// absorption.
+fn propagation(dist: f64) -> f64 {
+    let distance = 15.8 - 10.0 * dist.log10() - 0.0142 * dist.powf(0.9);
+    // Assume the sound travels about 2.25m above the ground on average
+    let ground = ((2.25 / dist) * (34.0 + 600.0 / dist) - 4.8).min(0.0);
+    distance + ground
+}
+
+/// The angle in radians between the two ends of a road, as seen from some point. Very short or
+/// distant roads still contribute a little.
+fn view_angle(from: Pt2D, pt1: Pt2D, pt2: Pt2D) -> f64 {
+    let (x1, y1) = (pt1.x() - from.x(), pt1.y() - from.y());
+    let (x2, y2) = (pt2.x() - from.x(), pt2.y() - from.y());
+    let angle = (x1 * y2 - y1 * x2).abs().atan2(x1 * x2 + y1 * y2);
+    angle.max(0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(intensity: f64) -> f64 {
+        10.0 * intensity.log10()
+    }
+
+    #[test]
+    fn test_emission_level() {
+        // 1000 vehicles per hour with 10% heavy vehicles is 69.9 dB(A) at 25m in RLS-90, with
+        // almost no correction at the reference speed of 100km/h
+        let flow = HourlyFlow {
+            light: 900.0,
+            heavy: 100.0,
+        };
+        assert!((db(emission_intensity(flow, 100.0, 0.0)) - 69.9).abs() < 0.1);
+        // Slower traffic is quieter
+        assert!((db(emission_intensity(flow, 50.0, 0.0)) - 65.8).abs() < 0.1);
+        // Grades only matter past 5%
+        assert_eq!(
+            emission_intensity(flow, 100.0, 4.0),
+            emission_intensity(flow, 100.0, 0.0)
+        );
+        assert!(
+            (db(emission_intensity(flow, 100.0, 7.0))
+                - db(emission_intensity(flow, 100.0, 0.0))
+                - 1.2)
+                .abs()
+                < 0.01
+        );
+        assert_eq!(emission_intensity(HourlyFlow::default(), 50.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_propagation() {
+        // No ground absorption yet at 25m
+        assert!((propagation(25.0) - 1.56).abs() < 0.01);
+        assert!((propagation(100.0) - -9.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_lden_band() {
+        assert_eq!(lden_band(30.0), "under 55 dB");
+        assert_eq!(lden_band(54.9), "under 55 dB");
+        assert_eq!(lden_band(55.0), "55-60 dB");
+        assert_eq!(lden_band(64.9), "60-65 dB");
+        assert_eq!(lden_band(74.9), "70-75 dB");
+        assert_eq!(lden_band(75.0), "over 75 dB");
+        assert_eq!(lden_band(120.0), "over 75 dB");
+    }
+}
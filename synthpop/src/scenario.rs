@@ -9,9 +9,17 @@ use abstutil::prettyprint_usize;
 use geom::Time;
 use map_model::Map;
 
-use crate::{OrigPersonID, TripEndpoint, TripMode};
+use crate::{
+    BehaviorProfile, ConditionsChange, OrigPersonID, ScheduledIncident, TripEndpoint, TripMode,
+};
 
 /// A Scenario describes all the input to a simulation. Usually a scenario covers one day.
+///
+/// New fields go at the end of this struct and `PersonSpec`, with `#[serde(default)]`, so older
+/// JSON scenarios still load. Binary scenarios don't get that benefit -- bincode has no field
+/// names to notice what's missing -- so they have to be regenerated whenever a field is added.
+/// Binary scenarios from before `PersonSpec::behavior`, `incidents`, and `conditions` existed
+/// won't load anymore.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Scenario {
     pub scenario_name: String,
@@ -20,6 +28,13 @@ pub struct Scenario {
     pub people: Vec<PersonSpec>,
     /// None means seed all buses. Otherwise the route name must be present here.
     pub only_seed_buses: Option<BTreeSet<String>>,
+    /// Temporary disruptions to the map, like crashes and closures
+    #[serde(default)]
+    pub incidents: Vec<ScheduledIncident>,
+    /// How the weather and daylight change over the day. Before the first change, it's dry
+    /// daylight.
+    #[serde(default)]
+    pub conditions: Vec<ConditionsChange>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -30,6 +45,10 @@ pub struct PersonSpec {
     /// trip. In the case of borders, the outbound and inbound border may be different. This means
     /// that there was some sort of "remote" trip happening outside the map that we don't simulate.
     pub trips: Vec<IndividTrip>,
+    /// If this is missing, the person behaves like everybody else using the same mode, except for
+    /// a random walking speed.
+    #[serde(default)]
+    pub behavior: Option<BehaviorProfile>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -117,6 +136,8 @@ impl Scenario {
             map_name: map.get_name().clone(),
             people: Vec::new(),
             only_seed_buses: Some(BTreeSet::new()),
+            incidents: Vec::new(),
+            conditions: Vec::new(),
         }
     }
 
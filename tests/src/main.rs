@@ -74,6 +74,8 @@ fn import_map(path: String) -> Map {
             extra_buildings: None,
             skip_local_roads: false,
             filter_crosswalks: false,
+            separate_sidewalks: false,
+            mid_block_crossings: false,
             gtfs_url: None,
             elevation: false,
         },
@@ -128,7 +130,7 @@ fn check_proposals() -> Result<()> {
                 if let Err(err) = perma.clone().into_edits(&map) {
                     abstio::write_json(
                         "repair_attempt.json".to_string(),
-                        &perma.into_edits_permissive(&map).to_permanent(&map),
+                        &perma.into_edits_permissive(&map).to_permanent(&map)?,
                     );
                     anyhow::bail!("{} is out-of-date: {}", name, err);
                 }
@@ -184,6 +186,7 @@ fn test_lane_changing(map: &Map) -> Result<()> {
                     TripMode::Bike
                 },
             )],
+            behavior: None,
         });
     }
     // Enable to manually watch the scenario